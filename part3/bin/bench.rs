@@ -31,6 +31,21 @@ fn tokenize_iter(c: &mut Criterion) {
     }
 }
 
+fn tokenize_fast(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tokenize [fast] Vec");
+    for size in SIZES {
+        let input = lib::gen_input(size);
+        group.throughput(Throughput::BytesDecimal(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("size", size), &input, |b, input| {
+            b.iter(|| {
+                lib::tokenize_fast(input)
+                    .map(|ev| ev.unwrap().1)
+                    .collect::<Vec<lib::Token>>()
+            })
+        });
+    }
+}
+
 fn tokenize_push(c: &mut Criterion) {
     let mut group = c.benchmark_group("Tokenize [push] Vec");
     for size in SIZES {
@@ -97,6 +112,73 @@ fn parse_events_direct_iter(c: &mut Criterion) {
     }
 }
 
+fn structural_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Structural index");
+    for size in SIZES {
+        let input = lib::gen_input(size);
+        group.throughput(Throughput::BytesDecimal(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("size", size), &input, |b, input| {
+            b.iter(|| lib::StructuralIndex::new(input.as_bytes()));
+        });
+    }
+}
+
+fn parse_events_indexed_iter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Parse events [indexed] Vec");
+    for size in SIZES {
+        let input = lib::gen_input(size);
+        group.throughput(Throughput::BytesDecimal(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("size", size), &input, |b, input| {
+            b.iter(|| {
+                lib::parse_events_iter_indexed(input)
+                    .map(|ev| ev.unwrap())
+                    .collect::<Vec<lib::ParseEvent>>()
+            });
+        });
+    }
+}
+
+/// [lib::gen_input] with each array element and object member on a separate line, for
+/// benchmarking skipping whitespace. The generated input is nested too deeply to be indented.
+fn gen_input_lines(size: usize) -> String {
+    let json = lib::parse_ast_recursive(&lib::gen_input(size)).unwrap();
+    let config = lib::WriterConfig {
+        indent: Some(0),
+        ..lib::WriterConfig::default()
+    };
+    lib::write_json_with_config(&json, config)
+}
+
+fn parse_events_direct_iter_lines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Parse events [iter] Vec (lines)");
+    for size in SIZES {
+        let input = gen_input_lines(size);
+        group.throughput(Throughput::BytesDecimal(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("size", size), &input, |b, input| {
+            b.iter(|| {
+                lib::parse_events_iter(input)
+                    .map(|ev| ev.unwrap())
+                    .collect::<Vec<lib::ParseEvent>>()
+            });
+        });
+    }
+}
+
+fn parse_events_indexed_iter_lines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Parse events [indexed] Vec (lines)");
+    for size in SIZES {
+        let input = gen_input_lines(size);
+        group.throughput(Throughput::BytesDecimal(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("size", size), &input, |b, input| {
+            b.iter(|| {
+                lib::parse_events_iter_indexed(input)
+                    .map(|ev| ev.unwrap())
+                    .collect::<Vec<lib::ParseEvent>>()
+            });
+        });
+    }
+}
+
 fn parse_events_direct_push(c: &mut Criterion) {
     let mut group = c.benchmark_group("Parse events [push] Vec");
     for size in SIZES {
@@ -123,7 +205,7 @@ fn tokenize_list_events_iter(c: &mut Criterion) {
         group.bench_with_input(BenchmarkId::new("size", size), &input, |b, input| {
             b.iter(|| {
                 lib::parse_events_iter_using_lexer_iter(
-                    lib::tokenize_list(&input)
+                    lib::tokenize_list(input)
                         .unwrap()
                         .into_iter()
                         .map(Result::Ok),
@@ -168,7 +250,22 @@ fn tokenize_iter_events_iter(c: &mut Criterion) {
         group.throughput(Throughput::BytesDecimal(input.len() as u64));
         group.bench_with_input(BenchmarkId::new("size", size), &input, |b, input| {
             b.iter(|| {
-                lib::parse_events_iter_using_lexer_iter(lib::tokenize_iter(&input), input.len())
+                lib::parse_events_iter_using_lexer_iter(lib::tokenize_iter(input), input.len())
+                    .map(|ev| ev.unwrap())
+                    .collect::<Vec<lib::ParseEvent>>()
+            });
+        });
+    }
+}
+
+fn tokenize_fast_events_iter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tokenize [fast] events [iter] Vec");
+    for size in SIZES {
+        let input = lib::gen_input(size);
+        group.throughput(Throughput::BytesDecimal(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("size", size), &input, |b, input| {
+            b.iter(|| {
+                lib::parse_events_iter_using_lexer_iter(lib::tokenize_fast(input), input.len())
                     .map(|ev| ev.unwrap())
                     .collect::<Vec<lib::ParseEvent>>()
             });
@@ -332,6 +429,21 @@ fn tokenize_iter_events_iter_ast(c: &mut Criterion) {
     }
 }
 
+fn tokenize_fast_events_iter_ast(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tokenize [fast] events [iter] AST");
+    for size in SIZES {
+        let input = lib::gen_input(size);
+        group.throughput(Throughput::BytesDecimal(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("size", size), &input, |b, input| {
+            b.iter(|| {
+                let mut event_parser =
+                    lib::parse_events_iter_using_lexer_iter(lib::tokenize_fast(input), input.len());
+                lib::event_to_tree(&mut event_parser, input).unwrap();
+            });
+        });
+    }
+}
+
 fn tokenize_iter_events_push_ast(c: &mut Criterion) {
     let mut group = c.benchmark_group("Tokenize [iter] events [push] AST");
     for size in SIZES {
@@ -378,6 +490,7 @@ fn tokenize_push_events_push_ast(c: &mut Criterion) {
 criterion_group!(
     benches,
     // tokenize_iter,
+    // tokenize_fast,
     // tokenize_push,
     // tokenize_list,
 
@@ -386,6 +499,7 @@ criterion_group!(
     // tokenize_list_events_iter,
     // tokenize_list_events_push,
     // tokenize_iter_events_iter,
+    // tokenize_fast_events_iter,
     // tokenize_iter_events_push,
     // tokenize_iter_events_push_non_recursive,
     // tokenize_push_events_push,
@@ -397,7 +511,20 @@ criterion_group!(
     // tokenize_list_events_iter_ast,
     // tokenize_list_events_push_ast,
     tokenize_iter_events_iter_ast,
+    tokenize_fast_events_iter_ast,
     // tokenize_iter_events_push_ast,
     tokenize_push_events_push_ast,
 );
-criterion_main!(benches);
+// The two-stage parser, compared with the lexer and the event parser it's based on.
+#[rustfmt::skip]
+criterion_group!(
+    structural_index_benches,
+    tokenize_list,
+    parse_events_direct_iter,
+    structural_index,
+    parse_events_indexed_iter,
+    parse_events_direct_iter_lines,
+    parse_events_indexed_iter_lines,
+);
+
+criterion_main!(benches, structural_index_benches);
@@ -1,9 +1,18 @@
 use parsing_post as lib;
 
+use std::io::IsTerminal;
+
 fn main() {
     let file = std::env::args().nth(1).unwrap();
     let contents = std::fs::read_to_string(file).unwrap();
     let mut event_parser =
         lib::parse_events_iter_using_lexer_iter(lib::tokenize_iter(&contents), contents.len());
-    lib::event_to_tree(&mut event_parser, &contents).unwrap();
+    if let Err(error) = lib::event_to_tree(&mut event_parser, &contents) {
+        let options = lib::RenderOptions {
+            ansi: std::io::stderr().is_terminal(),
+            ..Default::default()
+        };
+        eprint!("{}", lib::render_error(&error, &contents, options));
+        std::process::exit(1);
+    }
 }
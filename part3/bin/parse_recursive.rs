@@ -1,7 +1,16 @@
 use parsing_post as lib;
 
+use std::io::IsTerminal;
+
 fn main() {
     let file = std::env::args().nth(1).unwrap();
     let contents = std::fs::read_to_string(file).unwrap();
-    lib::parse_ast_recursive(&contents).unwrap();
+    if let Err(error) = lib::parse_ast_recursive(&contents) {
+        let options = lib::RenderOptions {
+            ansi: std::io::stderr().is_terminal(),
+            ..Default::default()
+        };
+        eprint!("{}", lib::render_error(&error, &contents, options));
+        std::process::exit(1);
+    }
 }
@@ -1,123 +1,293 @@
-use crate::event_to_tree::Container;
-use crate::{EventListener, Json, ParseError};
-
-/// An [EventListener] that builds [Json].
-pub struct AstBuilderListener<'a> {
-    input: &'a str,
-    container_stack: Vec<Container>,
-    current_container: Option<Container>,
-    parsed_object: Option<Json>,
+use crate::event_to_tree::{Container, Tree, TreeInput};
+use crate::limits::LimitChecker;
+use crate::{
+    EventListener, Json, ListenerAction, ParseError, ParseEventKind, ParserConfig, SpannedJson,
+};
+
+use std::convert::Infallible;
+
+/// An [EventListener] that builds [Json], or [SpannedJson] when created with
+/// [AstBuilderListener::new_spanned].
+pub struct AstBuilderListener<'a, T: Tree = Json> {
+    input: TreeInput<'a>,
+    container_stack: Vec<Container<T>>,
+    current_container: Option<Container<T>>,
+    parsed_object: Option<T>,
     error: Option<ParseError>,
+
+    /// Checks the resource limits in the configuration, when it has limits other than the nesting
+    /// depth.
+    limits: Option<LimitChecker>,
+
+    config: ParserConfig,
+
+    /// Whether a limit was exceeded, or a duplicate key was rejected. Later events are ignored.
+    stopped: bool,
 }
 
 impl<'a> AstBuilderListener<'a> {
     pub fn new(input: &'a str) -> AstBuilderListener<'a> {
-        AstBuilderListener {
+        AstBuilderListener::new_generic(TreeInput::Str(input), ParserConfig::default())
+    }
+
+    /// Same as [AstBuilderListener::new], but checks the resource limits in the given
+    /// configuration, other than [ParserConfig::max_depth] which is checked by the parsers, and
+    /// handles duplicate object keys as configured in [ParserConfig::duplicate_keys]. Other
+    /// configuration is used by the parsers, and ignored.
+    pub fn with_config(input: &'a str, config: ParserConfig) -> AstBuilderListener<'a> {
+        AstBuilderListener::new_generic(TreeInput::Str(input), config)
+    }
+
+    /// Same as [AstBuilderListener::new], for events parsed from byte input, for example with
+    /// [parse_events_push_bytes][crate::parse_events_push_bytes]. The parser validates UTF-8 of
+    /// the strings.
+    pub fn from_bytes(input: &'a [u8]) -> AstBuilderListener<'a> {
+        AstBuilderListener::new_generic(TreeInput::Bytes(input), ParserConfig::default())
+    }
+
+    /// Same as [AstBuilderListener::from_bytes], but with the given configuration, see
+    /// [AstBuilderListener::with_config].
+    pub fn from_bytes_with_config(input: &'a [u8], config: ParserConfig) -> AstBuilderListener<'a> {
+        AstBuilderListener::new_generic(TreeInput::Bytes(input), config)
+    }
+}
+
+impl<'a> AstBuilderListener<'a, SpannedJson> {
+    pub fn new_spanned(input: &'a str) -> AstBuilderListener<'a, SpannedJson> {
+        AstBuilderListener::new_generic(TreeInput::Str(input), ParserConfig::default())
+    }
+
+    /// Same as [AstBuilderListener::new_spanned], but checks the resource limits and handles
+    /// duplicate object keys as configured, see [AstBuilderListener::with_config].
+    pub fn new_spanned_with_config(
+        input: &'a str,
+        config: ParserConfig,
+    ) -> AstBuilderListener<'a, SpannedJson> {
+        AstBuilderListener::new_generic(TreeInput::Str(input), config)
+    }
+
+    /// Same as [AstBuilderListener::new_spanned], for events parsed from byte input, see
+    /// [AstBuilderListener::from_bytes].
+    pub fn new_spanned_from_bytes(input: &'a [u8]) -> AstBuilderListener<'a, SpannedJson> {
+        AstBuilderListener::new_generic(TreeInput::Bytes(input), ParserConfig::default())
+    }
+
+    /// Same as [AstBuilderListener::new_spanned_from_bytes], but with the given configuration, see
+    /// [AstBuilderListener::with_config].
+    pub fn new_spanned_from_bytes_with_config(
+        input: &'a [u8],
+        config: ParserConfig,
+    ) -> AstBuilderListener<'a, SpannedJson> {
+        AstBuilderListener::new_generic(TreeInput::Bytes(input), config)
+    }
+}
+
+impl<'a, T: Tree> AstBuilderListener<'a, T> {
+    fn new_generic(input: TreeInput<'a>, config: ParserConfig) -> AstBuilderListener<'a, T> {
+        let mut listener = AstBuilderListener {
             input,
             container_stack: vec![],
             current_container: None,
             parsed_object: None,
             error: None,
+            limits: None,
+            config,
+            stopped: false,
+        };
+
+        if config.has_limits() {
+            let limits = LimitChecker::new(config);
+            if let Err(err) = limits.check_input(input.as_bytes()) {
+                listener.error = Some(err);
+                listener.stopped = true;
+            }
+            listener.limits = Some(limits);
         }
-    }
 
-    pub fn into_ast(self) -> (Option<Json>, Option<ParseError>) {
-        (self.parsed_object, self.error)
+        listener
     }
-}
 
-impl<'a> EventListener for AstBuilderListener<'a> {
-    fn handle_start_object(&mut self, _byte_offset: usize) -> bool {
-        if let Some(container) = self.current_container.take() {
-            self.container_stack.push(container);
+    /// Checks the limits on the next event. Returns whether to handle the event.
+    fn check_limits(&mut self, byte_offset: usize, kind: &ParseEventKind) -> bool {
+        if self.stopped {
+            return false;
         }
-        self.current_container = Some(Container::new_map());
-        true
-    }
-
-    fn handle_end_object(&mut self, _byte_offset: usize) -> bool {
-        let map = self.current_container.take().unwrap().into_map().finish();
-        match self.container_stack.pop() {
-            Some(mut container) => {
-                container.add_object(map);
-                self.current_container = Some(container)
-            }
-            None => {
-                self.parsed_object = Some(map);
+        let limits = match &mut self.limits {
+            Some(limits) => limits,
+            None => return true,
+        };
+        match limits.check(byte_offset, kind) {
+            Ok(()) => true,
+            Err(err) => {
+                self.error.get_or_insert(err);
+                self.stopped = true;
+                false
             }
         }
-        true
     }
 
-    fn handle_start_array(&mut self, _byte_offset: usize) -> bool {
+    pub fn into_ast(self) -> (Option<T>, Option<ParseError>) {
+        (self.parsed_object, self.error)
+    }
+
+    /// Whether the top-level value is built.
+    pub(crate) fn has_ast(&self) -> bool {
+        self.parsed_object.is_some()
+    }
+
+    fn start_container(&mut self, container: Container<T>) {
         if let Some(container) = self.current_container.take() {
             self.container_stack.push(container);
         }
-        self.current_container = Some(Container::new_array());
-        true
+        self.current_container = Some(container);
     }
 
-    fn handle_end_array(&mut self, _byte_offset: usize) -> bool {
-        let array = self.current_container.take().unwrap().into_array();
+    fn end_container(&mut self, byte_offset: usize) {
+        let object = self
+            .current_container
+            .take()
+            .unwrap()
+            .finish(byte_offset + 1);
         match self.container_stack.pop() {
             Some(mut container) => {
-                container.add_object(Json::Array(array));
+                // Arrays and objects are never keys, so this can't fail with a duplicate key.
+                container.add_object(object, byte_offset).unwrap();
                 self.current_container = Some(container)
             }
             None => {
-                self.parsed_object = Some(Json::Array(array));
+                self.parsed_object = Some(object);
             }
         }
-        true
     }
 
-    fn handle_int(&mut self, _byte_offset: usize, i: u64) -> bool {
-        let object = Json::Int(i);
+    fn add_leaf(&mut self, byte_offset: usize, kind: ParseEventKind) -> bool {
+        if !self.check_limits(byte_offset, &kind) {
+            return false;
+        }
+        let (object, keep_parsing) = match T::leaf(self.input, byte_offset, kind) {
+            Ok(object) => (object, true),
+            Err(err) => {
+                // Only strings can fail to decode. We may still get events after returning
+                // `Stop`, so add a placeholder string to keep the containers consistent.
+                self.error.get_or_insert(err);
+                let placeholder = ParseEventKind::Str {
+                    size_in_bytes: 0,
+                    has_escapes: false,
+                };
+                (
+                    T::leaf(self.input, byte_offset, placeholder).unwrap(),
+                    false,
+                )
+            }
+        };
         match self.current_container.as_mut() {
-            Some(container) => container.add_object(object),
+            Some(container) => {
+                if let Err(err) = container.add_object(object, byte_offset) {
+                    self.error.get_or_insert(err);
+                    self.stopped = true;
+                    return false;
+                }
+            }
             None => {
                 self.parsed_object = Some(object);
             }
         }
-        true
+        keep_parsing
     }
+}
 
-    fn handle_str(&mut self, byte_offset: usize, size_in_bytes: usize) -> bool {
-        let string = self.input[byte_offset..byte_offset + size_in_bytes].to_string();
-        let object = Json::String(string);
-        match self.current_container.as_mut() {
-            Some(container) => container.add_object(object),
-            None => {
-                self.parsed_object = Some(object);
-            }
+impl<'a, T: Tree> EventListener for AstBuilderListener<'a, T> {
+    type Error = Infallible;
+
+    fn handle_start_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        if !self.check_limits(byte_offset, &ParseEventKind::StartObject) {
+            return ListenerAction::Stop;
         }
-        true
+        self.start_container(Container::new_map(byte_offset, self.config));
+        ListenerAction::Continue
     }
 
-    fn handle_bool(&mut self, _byte_offset: usize, b: bool) -> bool {
-        let object = Json::Bool(b);
-        match self.current_container.as_mut() {
-            Some(container) => container.add_object(object),
-            None => {
-                self.parsed_object = Some(object);
-            }
+    fn handle_end_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        if !self.check_limits(byte_offset, &ParseEventKind::EndObject) {
+            return ListenerAction::Stop;
         }
-        true
+        self.end_container(byte_offset);
+        ListenerAction::Continue
     }
 
-    fn handle_null(&mut self, _byte_offset: usize) -> bool {
-        let object = Json::Null;
-        match self.current_container.as_mut() {
-            Some(container) => container.add_object(object),
-            None => {
-                self.parsed_object = Some(object);
-            }
+    fn handle_start_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        if !self.check_limits(byte_offset, &ParseEventKind::StartArray) {
+            return ListenerAction::Stop;
+        }
+        self.start_container(Container::new_array(byte_offset));
+        ListenerAction::Continue
+    }
+
+    fn handle_end_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        if !self.check_limits(byte_offset, &ParseEventKind::EndArray) {
+            return ListenerAction::Stop;
         }
-        true
+        self.end_container(byte_offset);
+        ListenerAction::Continue
+    }
+
+    fn handle_int(&mut self, byte_offset: usize, i: u64) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.add_leaf(byte_offset, ParseEventKind::Int(i)))
+    }
+
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.add_leaf(byte_offset, ParseEventKind::NegInt(i)))
+    }
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.add_leaf(byte_offset, ParseEventKind::Float(f)))
+    }
+
+    fn handle_raw_number(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(
+            self.add_leaf(byte_offset, ParseEventKind::RawNumber { size_in_bytes }),
+        )
+    }
+
+    fn handle_str(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+        has_escapes: bool,
+    ) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.add_leaf(
+            byte_offset,
+            ParseEventKind::Str {
+                size_in_bytes,
+                has_escapes,
+            },
+        ))
+    }
+
+    fn handle_bool(&mut self, byte_offset: usize, b: bool) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.add_leaf(byte_offset, ParseEventKind::Bool(b)))
+    }
+
+    fn handle_null(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.add_leaf(byte_offset, ParseEventKind::Null))
+    }
+
+    fn handle_comment(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(
+            self.check_limits(byte_offset, &ParseEventKind::Comment { size_in_bytes }),
+        )
     }
 
     fn handle_error(&mut self, error: crate::ParseError) {
-        self.error = Some(error);
+        self.error.get_or_insert(error);
     }
 }
 
@@ -141,3 +311,54 @@ fn test_push_parser() {
         assert_eq!(parse(&str).unwrap(), ast);
     }
 }
+
+#[test]
+fn surrogate_error_tests() {
+    for (str, byte_offset) in crate::test_common::surrogate_error_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse(&str).unwrap_err().byte_offset, byte_offset);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse(&str), Err(error));
+    }
+}
+
+#[test]
+fn limit_tests() {
+    let tests = crate::test_common::limit_tests()
+        .into_iter()
+        .chain(crate::test_common::non_ascii_input_size_tests());
+    for (str, config, error) in tests {
+        println!("Parsing {:?}", str);
+        let mut listener = AstBuilderListener::with_config(&str, config);
+        crate::event_push_parser::parse(&str, &mut listener);
+        assert_eq!(listener.into_ast().1, error);
+        let mut listener = AstBuilderListener::new_spanned_with_config(&str, config);
+        crate::event_push_parser_2::parse(&str, &mut listener);
+        assert_eq!(listener.into_ast().1, error);
+    }
+}
+
+#[test]
+fn duplicate_key_tests() {
+    for (str, config, result) in crate::test_common::duplicate_key_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = AstBuilderListener::with_config(&str, config);
+        crate::event_push_parser::parse(&str, &mut listener);
+        match listener.into_ast() {
+            (_, Some(err)) => assert_eq!(Err(err), result),
+            (ast, None) => assert_eq!(Ok(ast.unwrap()), result),
+        }
+        let mut listener = AstBuilderListener::new_spanned_with_config(&str, config);
+        crate::event_push_parser_2::parse(&str, &mut listener);
+        match listener.into_ast() {
+            (_, Some(err)) => assert_eq!(Err(err), result),
+            (ast, None) => assert_eq!(Ok(ast.unwrap().into_json()), result),
+        }
+    }
+}
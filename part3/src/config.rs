@@ -0,0 +1,104 @@
+/// Configuration for the lexers and parsers.
+///
+/// Entry points without a `config` argument use [ParserConfig::default].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserConfig {
+    /// What to do when an integer does not fit into `u64` (or `i64`, when negative).
+    pub int_overflow: IntOverflow,
+
+    /// Maximum number of nested arrays and objects. Opening an array or object at this depth fails
+    /// with a [NestingTooDeep][crate::ParseErrorKind::NestingTooDeep] error.
+    ///
+    /// Recursive parsers use stack space proportional to the nesting depth, so this also bounds
+    /// their stack usage. The default is [DEFAULT_MAX_DEPTH].
+    pub max_depth: usize,
+
+    /// Maximum size of the input in bytes. Larger inputs fail with an
+    /// [InputTooLarge][crate::ParseErrorKind::InputTooLarge] error.
+    ///
+    /// This and the other resource limits below are checked by the event parsers and the AST
+    /// builders ([event_to_tree][crate::event_to_tree] and
+    /// [AstBuilderListener][crate::AstBuilderListener]). Parsing stops after a limit is exceeded,
+    /// also in [parse_events_iter_recovering][crate::parse_events_iter_recovering]. The default is
+    /// no limit.
+    pub max_input_size: usize,
+
+    /// Maximum size of a string or object key in bytes, not including the double quotes, before
+    /// decoding escape sequences. Longer strings fail with a
+    /// [StringTooLong][crate::ParseErrorKind::StringTooLong] error.
+    pub max_string_size: usize,
+
+    /// Maximum number of elements in an array, or members in an object. Larger arrays and objects
+    /// fail with a [ContainerTooLarge][crate::ParseErrorKind::ContainerTooLarge] error.
+    pub max_container_size: usize,
+
+    /// Maximum number of events, including comment and end events. Inputs with more events fail
+    /// with a [TooManyEvents][crate::ParseErrorKind::TooManyEvents] error.
+    pub max_events: usize,
+
+    /// What to do with duplicate keys in objects. Only used by the parsers that build ASTs:
+    /// [event_to_tree][crate::event_to_tree], [AstBuilderListener][crate::AstBuilderListener] and
+    /// the recursive descent parsers. The default is [DuplicateKeys::KeepAll].
+    pub duplicate_keys: DuplicateKeys,
+}
+
+/// Default value of [ParserConfig::max_depth]. The recursive parsers can parse input nested this
+/// deep with a 2 MiB stack, the default for spawned threads, also in debug builds.
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        ParserConfig {
+            int_overflow: IntOverflow::Error,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_input_size: usize::MAX,
+            max_string_size: usize::MAX,
+            max_container_size: usize::MAX,
+            max_events: usize::MAX,
+            duplicate_keys: DuplicateKeys::KeepAll,
+        }
+    }
+}
+
+impl ParserConfig {
+    /// Whether any of the resource limits other than [ParserConfig::max_depth] are set.
+    pub(crate) fn has_limits(&self) -> bool {
+        self.max_input_size != usize::MAX
+            || self.max_string_size != usize::MAX
+            || self.max_container_size != usize::MAX
+            || self.max_events != usize::MAX
+    }
+}
+
+/// How to handle integers that don't fit into `u64` or `i64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntOverflow {
+    /// Fail with an [IntegerOverflow][crate::ParseErrorKind::IntegerOverflow] error at the
+    /// number's location.
+    Error,
+
+    /// Parse the number as `f64`, losing precision.
+    Float,
+
+    /// Generate a raw number token or event, with the location and size of the number in the
+    /// input.
+    Raw,
+}
+
+/// How to handle duplicate keys in objects when building ASTs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Keep all members, in the order they appear in the input.
+    #[default]
+    KeepAll,
+
+    /// Keep the first member with a key, ignore the later members with the same key.
+    KeepFirst,
+
+    /// Keep the value of the last member with a key, at the position of the first member with the
+    /// key.
+    KeepLast,
+
+    /// Fail with a [DuplicateKey][crate::ParseErrorKind::DuplicateKey] error at the duplicate key.
+    Error,
+}
@@ -0,0 +1,593 @@
+use crate::number::scan_number;
+use crate::{Expected, IntOverflow, Lexer, ParseError, ParseErrorKind, ParserConfig, Token};
+
+use std::fmt;
+
+/// A lossless concrete syntax tree. Printing it with [Display][fmt::Display] gives back the
+/// original input, byte for byte.
+///
+/// Whitespace and comments are attached to the tokens that follow them, as
+/// [leading trivia][CstToken::leading_trivia]. Whitespace and comments after the last token are in
+/// [Cst::trailing_trivia].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst {
+    pub value: CstValue,
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstValue {
+    /// A number, string, `true`, `false`, or `null`.
+    Scalar(CstToken),
+
+    Array {
+        open: CstToken,
+        elements: Vec<CstElement>,
+        close: CstToken,
+    },
+
+    Object {
+        open: CstToken,
+        members: Vec<CstMember>,
+        close: CstToken,
+    },
+}
+
+/// An array element, with the `,` after it. Only the last element does not have a `,`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstElement {
+    pub value: CstValue,
+    pub comma: Option<CstToken>,
+}
+
+/// An object member, with the `,` after it. Only the last member does not have a `,`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstMember {
+    pub key: CstToken,
+    pub colon: CstToken,
+    pub value: CstValue,
+    pub comma: Option<CstToken>,
+}
+
+/// A token, with the whitespace and comments before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstToken {
+    pub leading_trivia: Vec<Trivia>,
+    pub token: Token,
+
+    /// Source text of the token. For strings this includes the double quotes.
+    pub text: String,
+}
+
+/// Whitespace and comments between tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trivia {
+    Whitespace(String),
+
+    /// A comment, including the "//" at the beginning and the newline at the end.
+    Comment(String),
+}
+
+/// Parses input to a [Cst].
+pub fn parse_cst(input: &str) -> Result<Cst, ParseError> {
+    parse_cst_with_config(input, ParserConfig::default())
+}
+
+/// Same as [parse_cst], but with the given configuration.
+pub fn parse_cst_with_config(input: &str, config: ParserConfig) -> Result<Cst, ParseError> {
+    let mut parser = CstParser {
+        input,
+        lexer: Lexer::with_config(input, config),
+        last_token_end: 0,
+        trivia: vec![],
+        max_depth: config.max_depth,
+    };
+
+    let value = parser.parse_value(Expected::Value)?;
+
+    // Same as the other parsers: anything other than trivia after the value is trailing
+    // characters, even when it's not a valid token.
+    match parser.next_token() {
+        Ok(None) => {}
+        Ok(Some((byte_offset, _))) => {
+            return Err(ParseError::new(
+                byte_offset,
+                ParseErrorKind::TrailingCharacters,
+            ))
+        }
+        Err(err) => {
+            return Err(ParseError::new(
+                err.byte_offset,
+                ParseErrorKind::TrailingCharacters,
+            ))
+        }
+    }
+
+    Ok(Cst {
+        value,
+        trailing_trivia: parser.trivia,
+    })
+}
+
+struct CstParser<'a> {
+    input: &'a str,
+    lexer: Lexer<'a>,
+
+    /// Byte offset after the last token, which is where the whitespace before the next token
+    /// starts.
+    last_token_end: usize,
+
+    /// Trivia collected since the last token.
+    trivia: Vec<Trivia>,
+
+    /// See [ParserConfig::max_depth].
+    max_depth: usize,
+}
+
+/// An array or object being parsed by [CstParser::parse_value].
+enum Container {
+    Array {
+        open: CstToken,
+        elements: Vec<CstElement>,
+    },
+
+    /// An object, with the key and `:` of the member whose value is being parsed.
+    Object {
+        open: CstToken,
+        members: Vec<CstMember>,
+        key: CstToken,
+        colon: CstToken,
+    },
+}
+
+impl<'a> CstParser<'a> {
+    /// Returns the next non-comment token and its byte offset. Comments and whitespace are
+    /// collected as the token's leading trivia. At the end of the input, the trivia after the last
+    /// token is left in `self.trivia`.
+    fn next_token(&mut self) -> Result<Option<(usize, CstToken)>, ParseError> {
+        loop {
+            let (byte_offset, token) = match self.lexer.next() {
+                Some(token) => token?,
+                None => {
+                    self.add_whitespace(self.input.len());
+                    return Ok(None);
+                }
+            };
+
+            let start = token.start_offset(byte_offset);
+            let end = token_end(self.input, byte_offset, &token);
+            self.add_whitespace(start);
+            self.last_token_end = end;
+            let text = self.input[start..end].to_owned();
+
+            if let Token::Comment { .. } = token {
+                self.trivia.push(Trivia::Comment(text));
+                continue;
+            }
+
+            let leading_trivia = std::mem::take(&mut self.trivia);
+            return Ok(Some((
+                start,
+                CstToken {
+                    leading_trivia,
+                    token,
+                    text,
+                },
+            )));
+        }
+    }
+
+    /// Adds the whitespace between the last token and `byte_offset` to the trivia.
+    fn add_whitespace(&mut self, byte_offset: usize) {
+        if byte_offset > self.last_token_end {
+            let whitespace = &self.input[self.last_token_end..byte_offset];
+            self.trivia.push(Trivia::Whitespace(whitespace.to_owned()));
+        }
+    }
+
+    /// Same as [CstParser::next_token], but fails at the end of the input. `expected` is used in
+    /// the error.
+    fn expect_token(&mut self, expected: Expected) -> Result<(usize, CstToken), ParseError> {
+        match self.next_token()? {
+            Some(token) => Ok(token),
+            None => Err(ParseError::unexpected_end_of_input(
+                self.input.len(),
+                expected,
+            )),
+        }
+    }
+
+    /// Parses a value. `expected` is used in errors.
+    ///
+    /// Arrays and objects are parsed with an explicit stack rather than recursively, so deeply
+    /// nested input doesn't overflow the stack.
+    fn parse_value(&mut self, mut expected: Expected) -> Result<CstValue, ParseError> {
+        let mut container_stack: Vec<Container> = vec![];
+
+        loop {
+            // Parse a scalar or an empty array or object, or start a non-empty array or object.
+            let (byte_offset, token) = self.expect_token(expected)?;
+            let mut value = match token.token {
+                Token::LBracket | Token::LBrace if container_stack.len() == self.max_depth => {
+                    return Err(ParseError::new(byte_offset, ParseErrorKind::NestingTooDeep));
+                }
+
+                Token::LBracket => {
+                    container_stack.push(Container::Array {
+                        open: token,
+                        elements: vec![],
+                    });
+                    expected = Expected::ValueOrEndArray;
+                    continue;
+                }
+
+                Token::RBracket if expected == Expected::ValueOrEndArray => {
+                    match container_stack.pop() {
+                        Some(Container::Array { open, elements }) => CstValue::Array {
+                            open,
+                            elements,
+                            close: token,
+                        },
+                        _ => unreachable!(),
+                    }
+                }
+
+                Token::LBrace => {
+                    let (key_byte_offset, key) = self.expect_token(Expected::KeyOrEndObject)?;
+                    if let Token::RBrace = key.token {
+                        CstValue::Object {
+                            open: token,
+                            members: vec![],
+                            close: key,
+                        }
+                    } else {
+                        let colon =
+                            self.expect_colon(key_byte_offset, &key, Expected::KeyOrEndObject)?;
+                        container_stack.push(Container::Object {
+                            open: token,
+                            members: vec![],
+                            key,
+                            colon,
+                        });
+                        expected = Expected::Value;
+                        continue;
+                    }
+                }
+
+                Token::Int(_)
+                | Token::NegInt(_)
+                | Token::Float(_)
+                | Token::RawNumber { .. }
+                | Token::Str { .. }
+                | Token::True
+                | Token::False
+                | Token::Null => CstValue::Scalar(token),
+
+                other => {
+                    return Err(ParseError::unexpected_token(
+                        byte_offset,
+                        expected,
+                        other.kind(),
+                    ))
+                }
+            };
+
+            // Add the value to its container, and finish the containers that end after it.
+            loop {
+                match container_stack.pop() {
+                    None => return Ok(value),
+
+                    Some(Container::Array { open, mut elements }) => {
+                        let (byte_offset, token) = self.expect_token(Expected::CommaOrEndArray)?;
+                        match token.token {
+                            Token::Comma => {
+                                elements.push(CstElement {
+                                    value,
+                                    comma: Some(token),
+                                });
+                                container_stack.push(Container::Array { open, elements });
+                                expected = Expected::Value;
+                                break;
+                            }
+                            Token::RBracket => {
+                                elements.push(CstElement { value, comma: None });
+                                value = CstValue::Array {
+                                    open,
+                                    elements,
+                                    close: token,
+                                };
+                            }
+                            other => {
+                                return Err(ParseError::unexpected_token(
+                                    byte_offset,
+                                    Expected::CommaOrEndArray,
+                                    other.kind(),
+                                ))
+                            }
+                        }
+                    }
+
+                    Some(Container::Object {
+                        open,
+                        mut members,
+                        key,
+                        colon,
+                    }) => {
+                        let (byte_offset, token) = self.expect_token(Expected::CommaOrEndObject)?;
+                        match token.token {
+                            Token::Comma => {
+                                members.push(CstMember {
+                                    key,
+                                    colon,
+                                    value,
+                                    comma: Some(token),
+                                });
+                                let (key_byte_offset, key) = self.expect_token(Expected::Key)?;
+                                let colon =
+                                    self.expect_colon(key_byte_offset, &key, Expected::Key)?;
+                                container_stack.push(Container::Object {
+                                    open,
+                                    members,
+                                    key,
+                                    colon,
+                                });
+                                expected = Expected::Value;
+                                break;
+                            }
+                            Token::RBrace => {
+                                members.push(CstMember {
+                                    key,
+                                    colon,
+                                    value,
+                                    comma: None,
+                                });
+                                value = CstValue::Object {
+                                    open,
+                                    members,
+                                    close: token,
+                                };
+                            }
+                            other => {
+                                return Err(ParseError::unexpected_token(
+                                    byte_offset,
+                                    Expected::CommaOrEndObject,
+                                    other.kind(),
+                                ))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks that the object key `key` at `byte_offset` is a string, and parses the `:` after it.
+    /// `expected` is used in the error when the key is not a string.
+    fn expect_colon(
+        &mut self,
+        byte_offset: usize,
+        key: &CstToken,
+        expected: Expected,
+    ) -> Result<CstToken, ParseError> {
+        if !matches!(key.token, Token::Str { .. }) {
+            return Err(ParseError::unexpected_token(
+                byte_offset,
+                expected,
+                key.token.kind(),
+            ));
+        }
+
+        let (byte_offset, colon) = self.expect_token(Expected::Colon)?;
+        if !matches!(colon.token, Token::Colon) {
+            return Err(ParseError::unexpected_token(
+                byte_offset,
+                Expected::Colon,
+                colon.token.kind(),
+            ));
+        }
+        Ok(colon)
+    }
+}
+
+/// Byte offset after the end of `token`. `byte_offset` is the location of the token as reported
+/// by the lexer.
+fn token_end(input: &str, byte_offset: usize, token: &Token) -> usize {
+    match token {
+        Token::Int(_) | Token::NegInt(_) | Token::Float(_) => {
+            // The lexer already scanned the number, so this can't fail.
+            scan_number(input.as_bytes(), byte_offset, IntOverflow::Raw)
+                .unwrap()
+                .1
+        }
+        Token::RawNumber { size_in_bytes } | Token::Comment { size_in_bytes } => {
+            byte_offset + size_in_bytes
+        }
+        // Lexer reports strings after the opening double quote.
+        Token::Str { size_in_bytes, .. } => byte_offset + size_in_bytes + 1,
+        Token::True | Token::Null => byte_offset + 4,
+        Token::False => byte_offset + 5,
+        Token::LBracket
+        | Token::RBracket
+        | Token::LBrace
+        | Token::RBrace
+        | Token::Colon
+        | Token::Comma => byte_offset + 1,
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)?;
+        for trivia in &self.trailing_trivia {
+            write!(f, "{}", trivia)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CstValue::Scalar(token) => write!(f, "{}", token),
+
+            CstValue::Array {
+                open,
+                elements,
+                close,
+            } => {
+                write!(f, "{}", open)?;
+                for CstElement { value, comma } in elements {
+                    write!(f, "{}", value)?;
+                    if let Some(comma) = comma {
+                        write!(f, "{}", comma)?;
+                    }
+                }
+                write!(f, "{}", close)
+            }
+
+            CstValue::Object {
+                open,
+                members,
+                close,
+            } => {
+                write!(f, "{}", open)?;
+                for CstMember {
+                    key,
+                    colon,
+                    value,
+                    comma,
+                } in members
+                {
+                    write!(f, "{}{}{}", key, colon, value)?;
+                    if let Some(comma) = comma {
+                        write!(f, "{}", comma)?;
+                    }
+                }
+                write!(f, "{}", close)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CstToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.leading_trivia {
+            write!(f, "{}", trivia)?;
+        }
+        f.write_str(&self.text)
+    }
+}
+
+impl fmt::Display for Trivia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trivia::Whitespace(text) | Trivia::Comment(text) => f.write_str(text),
+        }
+    }
+}
+
+#[test]
+fn round_trip_tests() {
+    for (str, _) in crate::test_common::ast_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse_cst(&str).unwrap().to_string(), str);
+    }
+
+    for (str, _) in crate::test_common::event_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse_cst(&str).unwrap().to_string(), str);
+    }
+
+    let str =
+        "\r\n// config\r\n{\t\"a\" :[ 1.50 ,-0, 1e3 ] , // after a\n \"é\":\"\\u00e9\" }\n// end";
+    assert_eq!(parse_cst(str).unwrap().to_string(), str);
+
+    let str = "null // é";
+    assert_eq!(parse_cst(str).unwrap().to_string(), str);
+}
+
+#[test]
+fn round_trip_random_tests() {
+    for input_size in [10, 100, 1_000, 10_000] {
+        let input = crate::gen_input(input_size);
+        assert_eq!(parse_cst(&input).unwrap().to_string(), input);
+    }
+}
+
+#[test]
+fn trivia_test() {
+    let cst = parse_cst(" // a\n[1, // b\n  2] ").unwrap();
+
+    let whitespace = |str: &str| Trivia::Whitespace(str.to_owned());
+    let comment = |str: &str| Trivia::Comment(str.to_owned());
+    let token = |leading_trivia: Vec<Trivia>, token: Token, text: &str| CstToken {
+        leading_trivia,
+        token,
+        text: text.to_owned(),
+    };
+
+    assert_eq!(
+        cst,
+        Cst {
+            value: CstValue::Array {
+                open: token(
+                    vec![whitespace(" "), comment("// a\n")],
+                    Token::LBracket,
+                    "["
+                ),
+                elements: vec![
+                    CstElement {
+                        value: CstValue::Scalar(token(vec![], Token::Int(1), "1")),
+                        comma: Some(token(vec![], Token::Comma, ",")),
+                    },
+                    CstElement {
+                        value: CstValue::Scalar(token(
+                            vec![whitespace(" "), comment("// b\n"), whitespace("  ")],
+                            Token::Int(2),
+                            "2"
+                        )),
+                        comma: None,
+                    },
+                ],
+                close: token(vec![], Token::RBracket, "]"),
+            },
+            trailing_trivia: vec![whitespace(" ")],
+        }
+    );
+}
+
+#[test]
+fn edit_test() {
+    let input = "{\n  // port to listen on\n  \"port\": 8080,\n  \"debug\": false // for now\n}\n";
+    let mut cst = parse_cst(input).unwrap();
+
+    let members = match &mut cst.value {
+        CstValue::Object { members, .. } => members,
+        _ => panic!(),
+    };
+
+    if let CstValue::Scalar(token) = &mut members[0].value {
+        token.token = Token::Int(9090);
+        token.text = "9090".to_owned();
+    }
+
+    assert_eq!(
+        cst.to_string(),
+        "{\n  // port to listen on\n  \"port\": 9090,\n  \"debug\": false // for now\n}\n"
+    );
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse_cst(&str), Err(error));
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        assert_eq!(parse_cst_with_config(&str, config).err(), error);
+    }
+}
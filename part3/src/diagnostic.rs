@@ -0,0 +1,348 @@
+use crate::{Expected, ParseError, ParseErrorKind, SourceMap, TokenKind};
+
+use std::fmt::Write;
+
+/// Options for [render_error].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Color the output with ANSI escape sequences, for terminals.
+    pub ansi: bool,
+
+    /// Add a note with a suggestion to fix the error, when there is one.
+    pub help: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            ansi: false,
+            help: true,
+        }
+    }
+}
+
+/// Renders `error` with the line of `input` it's in and a caret under the error location:
+///
+/// ```text
+/// error: unexpected number, expected ',' or ']'
+///  --> 1:4
+///   |
+/// 1 | [1 2]
+///   |    ^
+///   = help: did you forget a comma?
+/// ```
+///
+/// Columns are in UTF-16 code units, as in editors. The output ends with a newline.
+pub fn render_error(error: &ParseError, input: &str, options: RenderOptions) -> String {
+    let style = if options.ansi {
+        Style::ANSI
+    } else {
+        Style::PLAIN
+    };
+
+    let source_map = SourceMap::new(input);
+    let position = error.position(&source_map);
+
+    // Errors in byte inputs, or errors that the parser reports without the input (for example
+    // InputTooLarge in parsers that take a lexer), can be in the middle of a character.
+    let byte_offset = source_map.char_boundary(error.byte_offset);
+    let line_text = source_map.line_text(position.line);
+
+    let line_number = position.line.to_string();
+    let gutter = " ".repeat(line_number.len());
+
+    // Characters before the error location, with everything other than tabs replaced with spaces,
+    // so that the caret is aligned with the error location when the line has tabs.
+    let caret_indent: String = line_text
+        .char_indices()
+        .take_while(|(byte_idx, _)| *byte_idx < position.column - 1)
+        .map(|(_, char)| if char == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{}error{}: {}{}{}",
+        style.error, style.reset, style.bold, error.kind, style.reset
+    );
+    let _ = writeln!(
+        out,
+        "{}{}-->{} {}:{}",
+        gutter, style.gutter, style.reset, position.line, position.utf16_column
+    );
+    let _ = writeln!(out, "{} {}|{}", gutter, style.gutter, style.reset);
+    let _ = writeln!(
+        out,
+        "{}{} |{}{}{}",
+        style.gutter,
+        line_number,
+        style.reset,
+        if line_text.is_empty() { "" } else { " " },
+        line_text
+    );
+    let _ = writeln!(
+        out,
+        "{} {}|{} {}{}^{}",
+        gutter, style.gutter, style.reset, caret_indent, style.error, style.reset
+    );
+
+    if options.help {
+        if let Some(help) = help(error.kind, byte_offset, input) {
+            let _ = writeln!(
+                out,
+                "{} {}={} {}help{}: {}",
+                gutter, style.gutter, style.reset, style.help, style.reset, help
+            );
+        }
+    }
+
+    out
+}
+
+/// A suggestion to fix the error `kind` at `byte_offset` in `input`. `byte_offset` should be at a
+/// character boundary.
+fn help(kind: ParseErrorKind, byte_offset: usize, input: &str) -> Option<&'static str> {
+    use ParseErrorKind::*;
+
+    let previous_char = input[..byte_offset]
+        .chars()
+        .rev()
+        .find(|char| !char.is_ascii_whitespace());
+
+    let next_char = input[byte_offset..].chars().next();
+
+    match kind {
+        UnexpectedToken {
+            expected: Expected::Value | Expected::Key,
+            found: TokenKind::RBracket | TokenKind::RBrace,
+        } if previous_char == Some(',') => Some("trailing commas are not allowed"),
+
+        UnexpectedToken {
+            expected: Expected::Key | Expected::KeyOrEndObject,
+            found,
+        } if found != TokenKind::Comma && found != TokenKind::RBrace => {
+            Some("object keys must be strings")
+        }
+
+        UnexpectedToken {
+            expected: Expected::CommaOrEndArray | Expected::CommaOrEndObject,
+            found:
+                TokenKind::String
+                | TokenKind::Number
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Null
+                | TokenKind::LBracket
+                | TokenKind::LBrace,
+        } => Some("did you forget a comma?"),
+
+        UnexpectedToken {
+            expected: Expected::Colon,
+            ..
+        } => Some("did you forget a ':' after the key?"),
+
+        InvalidToken if next_char == Some('\'') => Some("strings must use double quotes"),
+
+        UnterminatedString => Some("add a '\"' at the end of the string"),
+
+        InvalidEscape => Some(r#"valid escapes are \", \\, \/, \b, \f, \n, \r, \t, and \uXXXX"#),
+
+        UnpairedSurrogate => {
+            Some(r"a \uD800-\uDBFF escape must be followed by a \uDC00-\uDFFF escape")
+        }
+
+        IntegerOverflow => {
+            Some("use IntOverflow::Float or IntOverflow::Raw to parse large integers")
+        }
+
+        TrailingCharacters => Some("a JSON document can only have one value at the top level"),
+
+        _ => None,
+    }
+}
+
+/// ANSI escape sequences to use in the output, empty when not coloring.
+struct Style {
+    error: &'static str,
+    gutter: &'static str,
+    help: &'static str,
+    bold: &'static str,
+    reset: &'static str,
+}
+
+impl Style {
+    const PLAIN: Style = Style {
+        error: "",
+        gutter: "",
+        help: "",
+        bold: "",
+        reset: "",
+    };
+
+    const ANSI: Style = Style {
+        error: "\x1b[1;31m",
+        gutter: "\x1b[1;34m",
+        help: "\x1b[1;36m",
+        bold: "\x1b[1m",
+        reset: "\x1b[0m",
+    };
+}
+
+#[cfg(test)]
+fn render(input: &str) -> String {
+    let error = crate::parse_ast_recursive(input).unwrap_err();
+    render_error(&error, input, RenderOptions::default())
+}
+
+#[test]
+fn render_test() {
+    assert_eq!(
+        render("[1 2]"),
+        "\
+error: unexpected number, expected ',' or ']'
+ --> 1:4
+  |
+1 | [1 2]
+  |    ^
+  = help: did you forget a comma?
+"
+    );
+
+    // Error on a later line, after non-ASCII characters and tabs.
+    let input = "{\n\t\"ключ\": 1,\n\t\"b\":\ttru\n}";
+    assert_eq!(
+        render(input),
+        "\
+error: invalid token
+ --> 3:7
+  |
+3 | \t\"b\":\ttru
+  | \t    \t^
+"
+    );
+
+    // End of input.
+    assert_eq!(
+        render("[1,\r\n"),
+        "\
+error: unexpected end of input, expected a value
+ --> 2:1
+  |
+2 |
+  | ^
+"
+    );
+
+    // Line numbers wider than one digit.
+    let input = format!("{}[1,]", "\n".repeat(9));
+    assert_eq!(
+        render(&input),
+        "\
+error: unexpected ']', expected a value
+  --> 10:4
+   |
+10 | [1,]
+   |    ^
+   = help: trailing commas are not allowed
+"
+    );
+}
+
+#[test]
+fn help_test() {
+    let help = |input: &str| {
+        let error = crate::parse_ast_recursive(input).unwrap_err();
+        help(error.kind, error.byte_offset, input)
+    };
+    assert_eq!(help(r#"{"a":1,}"#), Some("trailing commas are not allowed"));
+    assert_eq!(help(r#"{"a":}"#), None);
+    assert_eq!(help("{true:1}"), Some("object keys must be strings"));
+    assert_eq!(help("{1:1}"), Some("object keys must be strings"));
+    assert_eq!(help(r#"{"a":1 "b":2}"#), Some("did you forget a comma?"));
+    assert_eq!(
+        help(r#"{"a" 1}"#),
+        Some("did you forget a ':' after the key?")
+    );
+    assert_eq!(help("['a']"), Some("strings must use double quotes"));
+    assert_eq!(help("[1 }"), None);
+}
+
+#[test]
+fn render_options_test() {
+    let input = "[1 2]";
+    let error = crate::parse_ast_recursive(input).unwrap_err();
+
+    let no_help = render_error(
+        &error,
+        input,
+        RenderOptions {
+            ansi: false,
+            help: false,
+        },
+    );
+    assert!(!no_help.contains("help"));
+
+    let ansi = render_error(
+        &error,
+        input,
+        RenderOptions {
+            ansi: true,
+            help: true,
+        },
+    );
+    assert!(ansi.starts_with("\x1b[1;31merror\x1b[0m: \x1b[1munexpected number"));
+
+    // Removing the escape sequences gives the plain output.
+    let mut stripped = String::new();
+    let mut chars = ansi.chars();
+    while let Some(char) = chars.next() {
+        if char == '\x1b' {
+            chars.by_ref().find(|char| *char == 'm');
+        } else {
+            stripped.push(char);
+        }
+    }
+    assert_eq!(
+        stripped,
+        render_error(&error, input, RenderOptions::default())
+    );
+}
+
+#[test]
+fn render_error_in_character_test() {
+    // Parsers that take a lexer report InputTooLarge at the limit, here in the middle of 'é'.
+    let input = r#"["é"]"#;
+    let config = crate::ParserConfig {
+        max_input_size: 3,
+        ..crate::ParserConfig::default()
+    };
+    let error = crate::parse_events_iter_using_lexer_iter_with_config(
+        crate::tokenize_iter(input),
+        input.len(),
+        config,
+    )
+    .find_map(Result::err)
+    .unwrap();
+    assert_eq!(error, ParseError::new(3, ParseErrorKind::InputTooLarge));
+
+    // Rendered at the start of the character.
+    let source_map = SourceMap::new(input);
+    assert_eq!(
+        error.position(&source_map),
+        crate::Position {
+            line: 1,
+            column: 3,
+            utf16_column: 3
+        }
+    );
+    assert_eq!(
+        render_error(&error, input, RenderOptions::default()),
+        "\
+error: input too large
+ --> 1:3
+  |
+1 | [\"é\"]
+  |   ^
+"
+    );
+}
@@ -1,29 +1,67 @@
-use crate::recursive_descent::{next_char, skip_trivia};
-use crate::{Json, ParseError};
+use crate::lexer::invalid_token;
+use crate::object_members::{MemberSlot, ObjectMembers};
+use crate::recursive_descent::{next_char, parse_number, parse_string, skip_trivia};
+use crate::{
+    AstBuilderListener, Expected, Json, ParseError, ParseErrorKind, ParserConfig, TokenKind,
+};
 
 use std::iter::Peekable;
 use std::str::CharIndices;
 
 pub fn parse(input: &str) -> Result<Json, ParseError> {
+    parse_with_config(input, ParserConfig::default())
+}
+
+pub fn parse_with_config(input: &str, config: ParserConfig) -> Result<Json, ParseError> {
     let mut iter = input.char_indices().peekable();
-    let json = parse_single(&mut iter, input)?;
+    let json = parse_single(&mut iter, input, config)?;
     skip_trivia(&mut iter)?;
     if let Some((byte_offset, _)) = iter.next() {
         // We should return the parsed object with this error, but it's OK for the purposes of this
         // post.
-        return Err(ParseError {
+        return Err(ParseError::new(
             byte_offset,
-            reason: "trailing characters after paring",
-        });
+            ParseErrorKind::TrailingCharacters,
+        ));
     }
     Ok(json)
 }
 
+/// Same as [parse], but the input does not have to be valid UTF-8. UTF-8 is validated in strings
+/// and comments.
+pub fn parse_bytes(input: &[u8]) -> Result<Json, ParseError> {
+    parse_bytes_with_config(input, ParserConfig::default())
+}
+
+/// Same as [parse_bytes], but with the given configuration.
+///
+/// This parser scans `char`s, so byte input is parsed with the non-recursive push parser on the
+/// UTF-8-validating [Lexer][crate::Lexer], building the [Json] with
+/// [AstBuilderListener][crate::AstBuilderListener]. Unlike [parse_with_config], this also checks
+/// the resource limits in the configuration.
+pub fn parse_bytes_with_config(input: &[u8], config: ParserConfig) -> Result<Json, ParseError> {
+    let mut listener = AstBuilderListener::from_bytes_with_config(input, config);
+    let Ok(()) = crate::event_push_parser_3_non_recursive::parse_bytes_with_config(
+        input,
+        &mut listener,
+        config,
+    );
+    match listener.into_ast() {
+        (_, Some(err)) => Err(err),
+        (Some(json), None) => Ok(json),
+        // The parser reports an error when it doesn't parse a complete value.
+        (None, None) => unreachable!(),
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum ParserState {
     /// Parse any kind of object, update state based on the current container.
     TopLevel,
 
+    /// Parsing an array and we've just parsed '[', parse an element, or finish the array on ']'.
+    ArrayStart,
+
     /// Parsing a container, parse another element on ',', or finish the
     /// container on ']' or '}'.
     ExpectComma,
@@ -38,53 +76,66 @@ pub(crate) enum ParserState {
     ObjectExpectColon,
 }
 
-fn parse_single(iter: &mut Peekable<CharIndices>, input: &str) -> Result<Json, ParseError> {
+impl ParserState {
+    /// What is expected in this state, for errors. `in_object` should be true when the innermost
+    /// container is an object.
+    pub(crate) fn expected(self, in_object: bool) -> Expected {
+        match self {
+            ParserState::TopLevel => Expected::Value,
+            ParserState::ArrayStart => Expected::ValueOrEndArray,
+            ParserState::ExpectComma if in_object => Expected::CommaOrEndObject,
+            ParserState::ExpectComma => Expected::CommaOrEndArray,
+            ParserState::ObjectExpectKeyValue => Expected::Key,
+            ParserState::ObjectExpectKeyValueTerminate => Expected::KeyOrEndObject,
+            ParserState::ObjectExpectColon => Expected::Colon,
+        }
+    }
+}
+
+fn parse_single(
+    iter: &mut Peekable<CharIndices>,
+    input: &str,
+    config: ParserConfig,
+) -> Result<Json, ParseError> {
     let mut container_stack: Vec<Container> = vec![];
     let mut state = ParserState::TopLevel;
 
     loop {
         skip_trivia(iter)?;
 
+        let expected = state.expected(matches!(container_stack.last(), Some(Container::Map(_))));
+
         let (byte_offset, char) = match iter.next() {
             Some(next) => next,
-            None => {
-                return Err(ParseError {
-                    byte_offset: input.len(),
-                    reason: "unexpected end of input",
-                })
-            }
+            None => return Err(ParseError::unexpected_end_of_input(input.len(), expected)),
         };
 
         match state {
-            ParserState::TopLevel => match char {
+            ParserState::TopLevel | ParserState::ArrayStart => match char {
+                '{' | '[' if container_stack.len() == config.max_depth => {
+                    return Err(ParseError::new(byte_offset, ParseErrorKind::NestingTooDeep));
+                }
+
                 '{' => {
-                    container_stack.push(Container::new_map());
+                    container_stack.push(Container::new_map(config));
                     state = ParserState::ObjectExpectKeyValueTerminate;
                 }
 
                 '[' => {
                     container_stack.push(Container::new_array());
-                    state = ParserState::TopLevel;
+                    state = ParserState::ArrayStart;
                 }
 
-                ']' => match container_stack.pop() {
-                    Some(Container::Array(elems)) => {
-                        let object = Json::Array(elems);
-                        match container_stack.last_mut() {
-                            Some(container) => {
-                                container.add_json(object);
-                                state = ParserState::ExpectComma;
-                            }
-                            None => return Ok(object),
+                ']' if matches!(state, ParserState::ArrayStart) => {
+                    let object = Json::Array(container_stack.pop().unwrap().into_array());
+                    match container_stack.last_mut() {
+                        Some(container) => {
+                            container.add_json(object);
+                            state = ParserState::ExpectComma;
                         }
+                        None => return Ok(object),
                     }
-                    Some(Container::Map(_)) | None => {
-                        return Err(ParseError {
-                            byte_offset,
-                            reason: "unexpected character",
-                        })
-                    }
-                },
+                }
 
                 't' if next_char(iter) == Some('r')
                     && next_char(iter) == Some('u')
@@ -129,23 +180,8 @@ fn parse_single(iter: &mut Peekable<CharIndices>, input: &str) -> Result<Json, P
                     }
                 }
 
-                c if c.is_ascii_digit() => {
-                    let mut i: u64 = u64::from((c as u8) - b'0');
-
-                    while let Some((_, next)) = iter.peek().copied() {
-                        if !next.is_ascii_digit() {
-                            break;
-                        }
-
-                        // Consume the digit.
-                        iter.next();
-
-                        // Ignore overflows for the purposes of this post.
-                        i *= 10;
-                        i += u64::from((next as u8) - b'0');
-                    }
-
-                    let object = Json::Int(i);
+                c if c == '-' || c.is_ascii_digit() => {
+                    let object = parse_number(input, byte_offset, config.int_overflow, iter)?;
                     match container_stack.last_mut() {
                         Some(container) => container.add_json(object),
                         None => return Ok(object),
@@ -165,12 +201,9 @@ fn parse_single(iter: &mut Peekable<CharIndices>, input: &str) -> Result<Json, P
                     }
                 }
 
-                _ => {
-                    return Err(ParseError {
-                        byte_offset,
-                        reason: "unexpected character",
-                    })
-                }
+                't' | 'f' | 'n' => return Err(invalid_token(byte_offset)),
+
+                _ => return Err(ParseError::unexpected_char(byte_offset, expected, char)),
             },
 
             ParserState::ExpectComma => match char {
@@ -185,15 +218,16 @@ fn parse_single(iter: &mut Peekable<CharIndices>, input: &str) -> Result<Json, P
 
                     let map = match container {
                         Container::Array(_) => {
-                            return Err(ParseError {
+                            return Err(ParseError::unexpected_token(
                                 byte_offset,
-                                reason: "unexpected '}'",
-                            })
+                                expected,
+                                TokenKind::RBrace,
+                            ))
                         }
                         Container::Map(map) => map,
                     };
 
-                    let object = map.finish(byte_offset)?;
+                    let object = map.finish();
 
                     match container_stack.last_mut() {
                         Some(container) => {
@@ -219,52 +253,43 @@ fn parse_single(iter: &mut Peekable<CharIndices>, input: &str) -> Result<Json, P
                             }
                         }
                         Container::Map(_) => {
-                            return Err(ParseError {
+                            return Err(ParseError::unexpected_token(
                                 byte_offset,
-                                reason: "unexpected '}'",
-                            })
+                                expected,
+                                TokenKind::RBracket,
+                            ))
                         }
                     }
                 }
 
-                _ => {
-                    return Err(ParseError {
-                        byte_offset,
-                        reason: "unexpected character",
-                    })
-                }
+                _ => return Err(ParseError::unexpected_char(byte_offset, expected, char)),
             },
 
             ParserState::ObjectExpectKeyValue => match char {
                 '"' => {
-                    let string = parse_string(input, byte_offset, iter)?;
-                    let object = Json::String(string);
-                    container_stack.last_mut().unwrap().add_json(object);
+                    let key = parse_string(input, byte_offset, iter)?;
+                    container_stack
+                        .last_mut()
+                        .unwrap()
+                        .add_key(key, byte_offset)?;
                     state = ParserState::ObjectExpectColon;
                 }
 
-                _ => {
-                    return Err(ParseError {
-                        byte_offset,
-                        reason: "unexpected character",
-                    })
-                }
+                _ => return Err(ParseError::unexpected_char(byte_offset, expected, char)),
             },
 
             ParserState::ObjectExpectKeyValueTerminate => match char {
                 '"' => {
-                    let string = parse_string(input, byte_offset, iter)?;
-                    let object = Json::String(string);
-                    container_stack.last_mut().unwrap().add_json(object);
+                    let key = parse_string(input, byte_offset, iter)?;
+                    container_stack
+                        .last_mut()
+                        .unwrap()
+                        .add_key(key, byte_offset)?;
                     state = ParserState::ObjectExpectColon;
                 }
 
                 '}' => {
-                    let object = container_stack
-                        .pop()
-                        .unwrap()
-                        .into_map()
-                        .finish(byte_offset)?;
+                    let object = container_stack.pop().unwrap().into_map().finish();
                     match container_stack.last_mut() {
                         Some(container) => {
                             container.add_json(object);
@@ -274,12 +299,7 @@ fn parse_single(iter: &mut Peekable<CharIndices>, input: &str) -> Result<Json, P
                     }
                 }
 
-                _ => {
-                    return Err(ParseError {
-                        byte_offset,
-                        reason: "unexpected character",
-                    })
-                }
+                _ => return Err(ParseError::unexpected_char(byte_offset, expected, char)),
             },
 
             ParserState::ObjectExpectColon => match char {
@@ -287,51 +307,28 @@ fn parse_single(iter: &mut Peekable<CharIndices>, input: &str) -> Result<Json, P
                     state = ParserState::TopLevel;
                 }
 
-                _ => {
-                    return Err(ParseError {
-                        byte_offset,
-                        reason: "unexpected character",
-                    })
-                }
+                _ => return Err(ParseError::unexpected_char(byte_offset, expected, char)),
             },
         }
     }
 }
 
-// NB. Initial double quote should be consumed in `iter`, but not in
-// `byte_offset`.
-fn parse_string(
-    input: &str,
-    byte_offset: usize,
-    iter: &mut Peekable<CharIndices>,
-) -> Result<String, ParseError> {
-    for (next_byte_offset, next) in iter.by_ref() {
-        if next == '"' {
-            let string = input[byte_offset + 1..next_byte_offset].to_string();
-            return Ok(string);
-        }
-    }
-
-    return Err(ParseError {
-        byte_offset: input.len(),
-        reason: "unexpected end of input while parsing string",
-    });
-}
-
 enum Container {
     Array(Vec<Json>),
     Map(MapInProgress),
 }
 
 struct MapInProgress {
-    built: Vec<(String, Json)>,
-    next: Option<String>,
+    built: ObjectMembers<String, Json>,
+
+    /// The key of the member being parsed, and where to add the member.
+    next: Option<(String, MemberSlot)>,
 }
 
 impl Container {
-    fn new_map() -> Container {
+    fn new_map(config: ParserConfig) -> Container {
         Container::Map(MapInProgress {
-            built: vec![],
+            built: ObjectMembers::new(config.duplicate_keys),
             next: None,
         })
     }
@@ -340,6 +337,13 @@ impl Container {
         Container::Array(vec![])
     }
 
+    fn into_array(self) -> Vec<Json> {
+        match self {
+            Container::Array(array) => array,
+            Container::Map(_) => panic!(),
+        }
+    }
+
     fn into_map(self) -> MapInProgress {
         match self {
             Container::Array(_) => panic!(),
@@ -353,30 +357,35 @@ impl Container {
             Container::Map(map) => map.add_json(object),
         }
     }
+
+    /// Adds an object key. `quote_offset` is the byte offset of the key's opening double quote.
+    fn add_key(&mut self, key: String, quote_offset: usize) -> Result<(), ParseError> {
+        match self {
+            Container::Array(_) => panic!(),
+            Container::Map(map) => map.add_key(key, quote_offset),
+        }
+    }
 }
 
 impl MapInProgress {
     fn add_json(&mut self, object: Json) {
-        match self.next.take() {
-            Some(key) => {
-                self.built.push((key, object));
-            }
-            None => {
-                self.next = Some(object.into_string());
-            }
-        }
+        // Parser states only allow values after a key.
+        let (key, slot) = self.next.take().unwrap();
+        self.built.push(key, object, slot);
+    }
+
+    fn add_key(&mut self, key: String, quote_offset: usize) -> Result<(), ParseError> {
+        debug_assert!(self.next.is_none());
+        let slot = self.built.check_key(&key, quote_offset)?;
+        self.next = Some((key, slot));
+        Ok(())
     }
 
-    fn finish(self, byte_offset: usize) -> Result<Json, ParseError> {
+    fn finish(self) -> Json {
         let MapInProgress { built, next } = self;
-        if next.is_some() {
-            Err(ParseError {
-                byte_offset,
-                reason: "unexpected '}'",
-            })
-        } else {
-            Ok(Json::Object(built))
-        }
+        // Parser states only allow '}' after a value or '{'.
+        debug_assert!(next.is_none());
+        Json::Object(built.into_vec())
     }
 }
 
@@ -388,6 +397,14 @@ fn ast_tests() {
     }
 }
 
+#[test]
+fn surrogate_error_tests() {
+    for (str, byte_offset) in crate::test_common::surrogate_error_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse(&str).unwrap_err().byte_offset, byte_offset);
+    }
+}
+
 #[test]
 fn event_to_tree_random_tests() {
     for input_size in [10, 100, 1_000, 2_000, 5_000, 10_000, 10_000_000] {
@@ -397,3 +414,72 @@ fn event_to_tree_random_tests() {
         assert_eq!(ast_1, ast_2);
     }
 }
+
+#[test]
+fn int_overflow_tests() {
+    for (str, byte_offset, ast) in crate::test_common::int_overflow_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(
+            parse(&str),
+            Err(ParseError::new(
+                byte_offset,
+                crate::ParseErrorKind::IntegerOverflow
+            ))
+        );
+
+        let config = crate::test_common::raw_int_config();
+        assert_eq!(parse_with_config(&str, config).unwrap(), ast);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse(&str), Err(error));
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        assert_eq!(parse_with_config(&str, config).err(), error);
+    }
+}
+
+#[test]
+fn duplicate_key_tests() {
+    for (str, config, result) in crate::test_common::duplicate_key_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse_with_config(&str, config), result);
+    }
+}
+
+#[test]
+fn bytes_tests() {
+    // Valid UTF-8 is parsed the same as `&str` input.
+    for (str, _) in crate::test_common::ast_tests() {
+        assert_eq!(parse_bytes(str.as_bytes()), parse(&str), "{:?}", str);
+    }
+    for (str, _) in crate::test_common::error_tests() {
+        assert_eq!(parse_bytes(str.as_bytes()), parse(&str), "{:?}", str);
+    }
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        assert_eq!(parse_bytes_with_config(str.as_bytes(), config).err(), error);
+    }
+    for (str, config, result) in crate::test_common::duplicate_key_tests() {
+        assert_eq!(parse_bytes_with_config(str.as_bytes(), config), result);
+    }
+
+    // Unlike `parse_with_config`, the byte parser checks the resource limits.
+    let tests = crate::test_common::limit_tests()
+        .into_iter()
+        .chain(crate::test_common::non_ascii_input_size_tests());
+    for (str, config, error) in tests {
+        assert_eq!(parse_bytes_with_config(str.as_bytes(), config).err(), error);
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(parse_bytes(&bytes), Err(error), "{:?}", bytes);
+    }
+}
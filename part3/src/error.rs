@@ -0,0 +1,306 @@
+use crate::Token;
+
+use std::fmt;
+
+/// A parse error, common for both event and AST parsers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset of the parse error in the input.
+    pub byte_offset: usize,
+
+    /// What went wrong.
+    pub kind: ParseErrorKind,
+}
+
+/// Kinds of parse errors. All parsers report the same kind for the same input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// Input ended before the value was complete. Reported at the end of the input.
+    UnexpectedEndOfInput { expected: Expected },
+
+    /// A token that is not valid at its location. Reported at the token.
+    UnexpectedToken {
+        expected: Expected,
+        found: TokenKind,
+    },
+
+    /// Characters that don't form a token, for example `tru` or `@`.
+    InvalidToken,
+
+    /// A string without a closing double quote. Reported at the opening double quote.
+    UnterminatedString,
+
+    /// A backslash in a string not followed by a valid escape sequence. Reported at the backslash.
+    InvalidEscape,
+
+    /// A `\uXXXX` escape of a surrogate that is not a part of a surrogate pair. Reported at the
+    /// backslash.
+    UnpairedSurrogate,
+
+    /// A number that doesn't follow the JSON number syntax. Reported at the first unexpected
+    /// character.
+    InvalidNumber,
+
+    /// An integer that does not fit into `u64` or `i64`. Reported at the number.
+    IntegerOverflow,
+
+    /// Non-whitespace, non-comment characters after the top-level value.
+    TrailingCharacters,
+
+    /// Bytes in a string or comment that are not valid UTF-8, in parsers of byte input. Reported at
+    /// the first byte of the invalid sequence.
+    InvalidUtf8,
+
+    /// An array or object nested deeper than
+    /// [ParserConfig::max_depth][crate::ParserConfig::max_depth]. Reported at the `[` or `{` that
+    /// exceeds the limit.
+    NestingTooDeep,
+
+    /// Input larger than [ParserConfig::max_input_size][crate::ParserConfig::max_input_size].
+    /// Reported at the first byte after the limit, or at the start of the character when the limit
+    /// is in the middle of a UTF-8 encoded character.
+    ///
+    /// Parsers that take a lexer and the input size instead of the input report the error at the
+    /// limit, which can be in the middle of a character. [SourceMap][crate::SourceMap] and
+    /// [render_error][crate::render_error] move such offsets to the start of the character.
+    InputTooLarge,
+
+    /// A string or object key longer than
+    /// [ParserConfig::max_string_size][crate::ParserConfig::max_string_size]. Reported at the
+    /// opening double quote.
+    StringTooLong,
+
+    /// An array or object with more elements than
+    /// [ParserConfig::max_container_size][crate::ParserConfig::max_container_size]. Reported at
+    /// the element (or key, in objects) that exceeds the limit.
+    ContainerTooLarge,
+
+    /// More events than [ParserConfig::max_events][crate::ParserConfig::max_events]. Reported at
+    /// the token of the event that exceeds the limit.
+    TooManyEvents,
+
+    /// An object key that appeared before in the same object, with
+    /// [DuplicateKeys::Error][crate::DuplicateKeys::Error]. Reported at the opening double quote of
+    /// the duplicate key.
+    DuplicateKey,
+}
+
+/// What a parser expected at the location of an [UnexpectedToken][ParseErrorKind::UnexpectedToken]
+/// or [UnexpectedEndOfInput][ParseErrorKind::UnexpectedEndOfInput] error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    /// A value: at the top level, after `:` in an object, or after `,` in an array.
+    Value,
+
+    /// A value or `]`, after `[`.
+    ValueOrEndArray,
+
+    /// `,` or `]`, after an array element.
+    CommaOrEndArray,
+
+    /// A string key, after `,` in an object.
+    Key,
+
+    /// A string key or `}`, after `{`.
+    KeyOrEndObject,
+
+    /// `:`, after an object key.
+    Colon,
+
+    /// `,` or `}`, after an object value.
+    CommaOrEndObject,
+}
+
+/// Kinds of tokens, without the token details. Used in [ParseErrorKind::UnexpectedToken].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Colon,
+    Comma,
+    String,
+    Number,
+    True,
+    False,
+    Null,
+}
+
+impl ParseError {
+    pub(crate) fn new(byte_offset: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError { byte_offset, kind }
+    }
+
+    pub(crate) fn unexpected_end_of_input(byte_offset: usize, expected: Expected) -> ParseError {
+        ParseError::new(
+            byte_offset,
+            ParseErrorKind::UnexpectedEndOfInput { expected },
+        )
+    }
+
+    pub(crate) fn unexpected_token(
+        byte_offset: usize,
+        expected: Expected,
+        found: TokenKind,
+    ) -> ParseError {
+        ParseError::new(
+            byte_offset,
+            ParseErrorKind::UnexpectedToken { expected, found },
+        )
+    }
+
+    /// Error for an unexpected character in parsers that scan characters instead of tokens.
+    /// `char` should be the first character of the unexpected token.
+    pub(crate) fn unexpected_char(
+        byte_offset: usize,
+        expected: Expected,
+        char: char,
+    ) -> ParseError {
+        match TokenKind::from_first_char(char) {
+            Some(found) => ParseError::unexpected_token(byte_offset, expected, found),
+            None => ParseError::new(byte_offset, ParseErrorKind::InvalidToken),
+        }
+    }
+}
+
+impl TokenKind {
+    /// The kind of the token that starts with `char`. Keywords are not checked, so `t` is
+    /// [TokenKind::True].
+    pub(crate) fn from_first_char(char: char) -> Option<TokenKind> {
+        Some(match char {
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
+            '{' => TokenKind::LBrace,
+            '}' => TokenKind::RBrace,
+            ':' => TokenKind::Colon,
+            ',' => TokenKind::Comma,
+            '"' => TokenKind::String,
+            '-' | '0'..='9' => TokenKind::Number,
+            't' => TokenKind::True,
+            'f' => TokenKind::False,
+            'n' => TokenKind::Null,
+            _ => return None,
+        })
+    }
+}
+
+impl Token {
+    /// Kind of the token. Comments don't have a [TokenKind] and should be skipped before calling
+    /// this.
+    pub(crate) fn kind(&self) -> TokenKind {
+        match self {
+            Token::Int(_) | Token::NegInt(_) | Token::Float(_) | Token::RawNumber { .. } => {
+                TokenKind::Number
+            }
+            Token::Str { .. } => TokenKind::String,
+            Token::True => TokenKind::True,
+            Token::False => TokenKind::False,
+            Token::Null => TokenKind::Null,
+            Token::LBracket => TokenKind::LBracket,
+            Token::RBracket => TokenKind::RBracket,
+            Token::LBrace => TokenKind::LBrace,
+            Token::RBrace => TokenKind::RBrace,
+            Token::Colon => TokenKind::Colon,
+            Token::Comma => TokenKind::Comma,
+            Token::Comment { .. } => panic!("Token::kind called on a comment"),
+        }
+    }
+
+    /// Byte offset of the first character of the token, given the location reported by the
+    /// lexers. Strings are reported after the opening double quote, other tokens at their first
+    /// character.
+    pub(crate) fn start_offset(&self, byte_offset: usize) -> usize {
+        match self {
+            Token::Str { .. } => byte_offset - 1,
+            _ => byte_offset,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte offset {}", self.kind, self.byte_offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEndOfInput { expected } => {
+                write!(f, "unexpected end of input, expected {}", expected)
+            }
+            ParseErrorKind::UnexpectedToken { expected, found } => {
+                write!(f, "unexpected {}, expected {}", found, expected)
+            }
+            ParseErrorKind::InvalidToken => f.write_str("invalid token"),
+            ParseErrorKind::UnterminatedString => f.write_str("unterminated string"),
+            ParseErrorKind::InvalidEscape => f.write_str("invalid escape sequence"),
+            ParseErrorKind::UnpairedSurrogate => {
+                f.write_str("unpaired surrogate in escape sequence")
+            }
+            ParseErrorKind::InvalidNumber => f.write_str("invalid number"),
+            ParseErrorKind::IntegerOverflow => f.write_str("integer overflow"),
+            ParseErrorKind::TrailingCharacters => f.write_str("trailing characters after value"),
+            ParseErrorKind::InvalidUtf8 => f.write_str("invalid UTF-8"),
+            ParseErrorKind::NestingTooDeep => f.write_str("arrays and objects nested too deep"),
+            ParseErrorKind::InputTooLarge => f.write_str("input too large"),
+            ParseErrorKind::StringTooLong => f.write_str("string too long"),
+            ParseErrorKind::ContainerTooLarge => {
+                f.write_str("too many elements in array or object")
+            }
+            ParseErrorKind::TooManyEvents => f.write_str("too many events"),
+            ParseErrorKind::DuplicateKey => f.write_str("duplicate object key"),
+        }
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Expected::Value => "a value",
+            Expected::ValueOrEndArray => "a value or ']'",
+            Expected::CommaOrEndArray => "',' or ']'",
+            Expected::Key => "an object key",
+            Expected::KeyOrEndObject => "an object key or '}'",
+            Expected::Colon => "':'",
+            Expected::CommaOrEndObject => "',' or '}'",
+        })
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TokenKind::LBracket => "'['",
+            TokenKind::RBracket => "']'",
+            TokenKind::LBrace => "'{'",
+            TokenKind::RBrace => "'}'",
+            TokenKind::Colon => "':'",
+            TokenKind::Comma => "','",
+            TokenKind::String => "string",
+            TokenKind::Number => "number",
+            TokenKind::True => "'true'",
+            TokenKind::False => "'false'",
+            TokenKind::Null => "'null'",
+        })
+    }
+}
+
+#[test]
+fn display_test() {
+    assert_eq!(
+        ParseError::unexpected_token(3, Expected::CommaOrEndArray, TokenKind::RBrace).to_string(),
+        "unexpected '}', expected ',' or ']' at byte offset 3"
+    );
+    assert_eq!(
+        ParseError::unexpected_end_of_input(5, Expected::Colon).to_string(),
+        "unexpected end of input, expected ':' at byte offset 5"
+    );
+    assert_eq!(
+        ParseError::new(0, ParseErrorKind::IntegerOverflow).to_string(),
+        "integer overflow at byte offset 0"
+    );
+}
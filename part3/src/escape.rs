@@ -0,0 +1,314 @@
+use crate::utf8::Utf8Validator;
+use crate::{ParseError, ParseErrorKind};
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Errors reported by [scan_string].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScanStringError {
+    /// Input ended before the closing double quote.
+    Unterminated,
+
+    /// Invalid escape sequence. The byte offset is the offset of the backslash.
+    InvalidEscape { byte_offset: usize },
+
+    /// Invalid UTF-8, when scanning bytes. The byte offset is the offset of the first byte of the
+    /// invalid sequence.
+    InvalidUtf8 { byte_offset: usize },
+}
+
+/// Skip until the end of a string. Expects the opening double quote to be consumed.
+///
+/// Returns byte offset of the closing double quote, and whether the string has any escape
+/// sequences.
+pub(crate) fn scan_string(
+    iter: &mut Peekable<CharIndices>,
+) -> Result<(usize, bool), ScanStringError> {
+    let mut has_escapes = false;
+
+    while let Some((byte_offset, char)) = iter.next() {
+        match char {
+            '"' => return Ok((byte_offset, has_escapes)),
+
+            '\\' => {
+                has_escapes = true;
+                match iter.next() {
+                    Some((_, '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't')) => {}
+
+                    Some((_, 'u')) => {
+                        for _ in 0..4 {
+                            match iter.next() {
+                                Some((_, c)) if c.is_ascii_hexdigit() => {}
+                                Some(_) => {
+                                    return Err(ScanStringError::InvalidEscape { byte_offset })
+                                }
+                                None => return Err(ScanStringError::Unterminated),
+                            }
+                        }
+                    }
+
+                    Some(_) => return Err(ScanStringError::InvalidEscape { byte_offset }),
+
+                    None => return Err(ScanStringError::Unterminated),
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    Err(ScanStringError::Unterminated)
+}
+
+/// Same as [scan_string], but scans bytes starting at `byte_offset`, and validates UTF-8 of the
+/// string. Expects the opening double quote to be before `byte_offset`.
+pub(crate) fn scan_string_bytes(
+    input: &[u8],
+    mut byte_offset: usize,
+) -> Result<(usize, bool), ScanStringError> {
+    let mut has_escapes = false;
+    let mut utf8 = Utf8Validator::default();
+
+    while let Some(&byte) = input.get(byte_offset) {
+        if let Err(err) = utf8.feed(byte, byte_offset) {
+            return Err(ScanStringError::InvalidUtf8 {
+                byte_offset: err.byte_offset,
+            });
+        }
+
+        match byte {
+            b'"' => return Ok((byte_offset, has_escapes)),
+
+            b'\\' => {
+                has_escapes = true;
+                let backslash_offset = byte_offset;
+                byte_offset += 1;
+                match input.get(byte_offset) {
+                    Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {}
+
+                    Some(b'u') => {
+                        for _ in 0..4 {
+                            byte_offset += 1;
+                            match input.get(byte_offset) {
+                                Some(byte) if byte.is_ascii_hexdigit() => {}
+                                Some(_) => {
+                                    return Err(ScanStringError::InvalidEscape {
+                                        byte_offset: backslash_offset,
+                                    })
+                                }
+                                None => return Err(ScanStringError::Unterminated),
+                            }
+                        }
+                    }
+
+                    Some(_) => {
+                        return Err(ScanStringError::InvalidEscape {
+                            byte_offset: backslash_offset,
+                        })
+                    }
+
+                    None => return Err(ScanStringError::Unterminated),
+                }
+            }
+
+            _ => {}
+        }
+
+        byte_offset += 1;
+    }
+
+    Err(ScanStringError::Unterminated)
+}
+
+/// Decodes escape sequences in a string.
+///
+/// `str` should be the contents of a string, without the double quotes. `byte_offset` is the
+/// location of `str` in the input, used in errors.
+///
+/// `\uXXXX` escapes of a high surrogate followed by a low surrogate are decoded as one character.
+/// Surrogates that are not a part of such a pair are reported as errors.
+pub fn unescape(str: &str, byte_offset: usize) -> Result<String, ParseError> {
+    let mut decoded = String::with_capacity(str.len());
+    let mut chars = str.char_indices();
+
+    while let Some((escape_byte_idx, char)) = chars.next() {
+        if char != '\\' {
+            decoded.push(char);
+            continue;
+        }
+
+        let escape_byte_offset = byte_offset + escape_byte_idx;
+
+        let decoded_char = match chars.next() {
+            Some((_, '"')) => '"',
+            Some((_, '\\')) => '\\',
+            Some((_, '/')) => '/',
+            Some((_, 'b')) => '\u{8}',
+            Some((_, 'f')) => '\u{c}',
+            Some((_, 'n')) => '\n',
+            Some((_, 'r')) => '\r',
+            Some((_, 't')) => '\t',
+            Some((_, 'u')) => {
+                let code = parse_hex4(&mut chars, escape_byte_offset)?;
+                match code {
+                    0xD800..=0xDBFF => {
+                        // High surrogate, should be followed by a low surrogate.
+                        let low = match chars.as_str().strip_prefix("\\u") {
+                            Some(_) => {
+                                let (low_byte_idx, _) = chars.next().unwrap(); // consume '\'
+                                chars.next(); // consume 'u'
+                                parse_hex4(&mut chars, byte_offset + low_byte_idx)?
+                            }
+                            None => return Err(unpaired_surrogate(escape_byte_offset)),
+                        };
+
+                        if !(0xDC00..=0xDFFF).contains(&low) {
+                            return Err(unpaired_surrogate(escape_byte_offset));
+                        }
+
+                        char::from_u32(0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)).unwrap()
+                    }
+
+                    0xDC00..=0xDFFF => {
+                        // Low surrogate without a high surrogate before it.
+                        return Err(unpaired_surrogate(escape_byte_offset));
+                    }
+
+                    _ => char::from_u32(code).unwrap(),
+                }
+            }
+            _ => {
+                return Err(ParseError::new(
+                    escape_byte_offset,
+                    ParseErrorKind::InvalidEscape,
+                ))
+            }
+        };
+
+        decoded.push(decoded_char);
+    }
+
+    Ok(decoded)
+}
+
+/// Parses the four hex digits of a `\uXXXX` escape. `escape_byte_offset` is the location of the
+/// backslash, used in errors.
+fn parse_hex4(chars: &mut CharIndices, escape_byte_offset: usize) -> Result<u32, ParseError> {
+    let mut code: u32 = 0;
+    for _ in 0..4 {
+        match chars.next().and_then(|(_, c)| c.to_digit(16)) {
+            Some(digit) => code = code * 16 + digit,
+            None => {
+                return Err(ParseError::new(
+                    escape_byte_offset,
+                    ParseErrorKind::InvalidEscape,
+                ))
+            }
+        }
+    }
+    Ok(code)
+}
+
+fn unpaired_surrogate(byte_offset: usize) -> ParseError {
+    ParseError::new(byte_offset, ParseErrorKind::UnpairedSurrogate)
+}
+
+#[test]
+fn scan_string_test() {
+    fn scan(input: &str) -> Result<(usize, bool), ScanStringError> {
+        let mut iter = input.char_indices().peekable();
+        scan_string(&mut iter)
+    }
+
+    assert_eq!(scan(r#"""#), Ok((0, false)));
+    assert_eq!(scan(r#"abc""#), Ok((3, false)));
+    assert_eq!(scan(r#"a\"b""#), Ok((4, true)));
+    assert_eq!(scan(r#"\\""#), Ok((2, true)));
+    assert_eq!(scan(r#"\u00e9""#), Ok((6, true)));
+    assert_eq!(
+        scan(r#"ab\x""#),
+        Err(ScanStringError::InvalidEscape { byte_offset: 2 })
+    );
+    assert_eq!(
+        scan(r#"\u00g0""#),
+        Err(ScanStringError::InvalidEscape { byte_offset: 0 })
+    );
+    assert_eq!(scan(r#"abc"#), Err(ScanStringError::Unterminated));
+    assert_eq!(scan(r#"abc\""#), Err(ScanStringError::Unterminated));
+}
+
+#[test]
+fn scan_string_bytes_test() {
+    fn scan(input: &[u8]) -> Result<(usize, bool), ScanStringError> {
+        scan_string_bytes(input, 1)
+    }
+
+    assert_eq!(scan(br#""""#), Ok((1, false)));
+    assert_eq!(scan(br#""abc""#), Ok((4, false)));
+    assert_eq!(scan(br#""a\"b""#), Ok((5, true)));
+    assert_eq!(scan(br#""\u00e9""#), Ok((7, true)));
+    assert_eq!(scan("\"é😀\"".as_bytes()), Ok((7, false)));
+    assert_eq!(
+        scan(br#""ab\x""#),
+        Err(ScanStringError::InvalidEscape { byte_offset: 3 })
+    );
+    assert_eq!(
+        scan(br#""\u00g0""#),
+        Err(ScanStringError::InvalidEscape { byte_offset: 1 })
+    );
+    assert_eq!(scan(br#""abc"#), Err(ScanStringError::Unterminated));
+    assert_eq!(scan(br#""abc\""#), Err(ScanStringError::Unterminated));
+    assert_eq!(
+        scan(b"\"a\xff\""),
+        Err(ScanStringError::InvalidUtf8 { byte_offset: 2 })
+    );
+    assert_eq!(
+        scan(b"\"a\xc3\""),
+        Err(ScanStringError::InvalidUtf8 { byte_offset: 2 })
+    );
+}
+
+#[test]
+fn unescape_test() {
+    fn unescape_(str: &str) -> String {
+        unescape(str, 0).unwrap()
+    }
+
+    assert_eq!(unescape_("abc"), "abc");
+    assert_eq!(unescape_(r#"a\"b"#), "a\"b");
+    assert_eq!(unescape_(r#"C:\\Users\\x"#), "C:\\Users\\x");
+    assert_eq!(unescape_(r#"\/\b\f\n\r\t"#), "/\u{8}\u{c}\n\r\t");
+    assert_eq!(unescape_(r#"\u00e9\u0041"#), "éA");
+    assert_eq!(unescape_(r#"\uD83D\uDE00!"#), "😀!");
+    assert_eq!(unescape_(r#"\ud834\udd1e"#), "𝄞");
+}
+
+#[test]
+fn unescape_surrogate_errors() {
+    let error = |byte_offset| {
+        Err(ParseError::new(
+            byte_offset,
+            ParseErrorKind::UnpairedSurrogate,
+        ))
+    };
+
+    // Lone high surrogate.
+    assert_eq!(unescape(r#"ab\uD83D"#, 10), error(12));
+    assert_eq!(unescape(r#"\uD83Dx"#, 0), error(0));
+
+    // High surrogate followed by a non-surrogate.
+    assert_eq!(unescape(r#"\uD83D\u0041"#, 0), error(0));
+
+    // Lone low surrogate.
+    assert_eq!(unescape(r#"x\uDE00"#, 0), error(1));
+
+    // Reversed pair.
+    assert_eq!(unescape(r#"\uDE00\uD83D"#, 0), error(0));
+
+    // Invalid hex digit in the low surrogate, reported at the low surrogate's escape.
+    assert_eq!(
+        unescape(r#"\ud83d\u12G4"#, 10),
+        Err(ParseError::new(16, ParseErrorKind::InvalidEscape))
+    );
+}
@@ -1,21 +1,42 @@
 /// A parse event, with location of the event in the input.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq)]
 pub struct ParseEvent {
     pub kind: ParseEventKind,
     pub byte_offset: usize,
 }
 
 /// Details of a parse event.
-#[derive(Debug, PartialEq, Eq)]
+///
+/// Source text of numbers can be obtained with [number_text][crate::number_text].
+#[derive(Debug, PartialEq)]
 pub enum ParseEventKind {
     StartObject,
     EndObject,
     StartArray,
     EndArray,
+    /// A non-negative integer.
     Int(u64),
+
+    /// A negative integer.
+    NegInt(i64),
+
+    /// A number with a fraction or exponent, or `-0`.
+    Float(f64),
+
+    /// An integer that does not fit into `u64` or `i64`. Generated when
+    /// [IntOverflow::Raw][crate::IntOverflow::Raw] is used.
+    RawNumber {
+        /// Size of the number in the input.
+        size_in_bytes: usize,
+    },
+
     Str {
         /// Size of the string, not including the double quotes.
         size_in_bytes: usize,
+
+        /// Whether the string has escape sequences. When this is `true` the string should be
+        /// decoded with [unescape][crate::unescape].
+        has_escapes: bool,
     },
     Bool(bool),
     Null,
@@ -1,47 +1,220 @@
-use crate::ParseError;
+use crate::{ParseError, ParseEventKind};
+
+/// What a push parser does after an [EventListener] method returns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenerAction<E> {
+    /// Keep parsing.
+    Continue,
+
+    /// When returned for the start of an array or object, skip the contents of the array or
+    /// object, without generating events. The next event is the end of the array or object.
+    ///
+    /// [parse_events_push][crate::event_push_parser::parse] only checks the skipped input for
+    /// balanced brackets, strings, and comments. Parsers with a lexer check the tokens in the
+    /// skipped input, but not the syntax.
+    ///
+    /// Same as [ListenerAction::Continue] for other events.
+    SkipChildren,
+
+    /// Stop parsing, without an error. The parser doesn't call the listener again.
+    Stop,
+
+    /// Stop parsing with an error. The parser doesn't call the listener again, and returns the
+    /// error.
+    Abort(E),
+}
+
+impl<E> ListenerAction<E> {
+    /// [ListenerAction::Continue] when `keep_parsing` is `true`, [ListenerAction::Stop]
+    /// otherwise.
+    pub(crate) fn continue_if(keep_parsing: bool) -> ListenerAction<E> {
+        if keep_parsing {
+            ListenerAction::Continue
+        } else {
+            ListenerAction::Stop
+        }
+    }
+
+    /// Whether the parser keeps parsing after the action.
+    pub(crate) fn keep_parsing(&self) -> bool {
+        matches!(
+            self,
+            ListenerAction::Continue | ListenerAction::SkipChildren
+        )
+    }
+}
 
 /// The trait for push-based event parsing. Used by
-/// [parse_events_push][crate::event_push_parser::parse].
+/// [parse_events_push][crate::event_push_parser::parse] and the other push parsers.
 ///
-/// Methods return a `bool` for whether to keep parsing.
+/// Methods return a [ListenerAction] for how to continue parsing. A listener can stop parsing, skip
+/// the contents of an array or object, or abort parsing with its own error, which the parser then
+/// returns.
 ///
-/// Default implementations of the methods return `true` to continue parsing.
+/// Default implementations of the methods return [ListenerAction::Continue].
 pub trait EventListener {
-    fn handle_start_object(&mut self, _byte_offset: usize) -> bool {
-        true
+    /// The error type of [ListenerAction::Abort]. Listeners that don't abort can use
+    /// [std::convert::Infallible].
+    type Error;
+
+    fn handle_start_object(&mut self, _byte_offset: usize) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
+    }
+
+    fn handle_end_object(&mut self, _byte_offset: usize) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
+    }
+
+    fn handle_start_array(&mut self, _byte_offset: usize) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
     }
 
-    fn handle_end_object(&mut self, _byte_offset: usize) -> bool {
-        true
+    fn handle_end_array(&mut self, _byte_offset: usize) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
     }
 
-    fn handle_start_array(&mut self, _byte_offset: usize) -> bool {
-        true
+    fn handle_int(&mut self, _byte_offset: usize, _i: u64) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
     }
 
-    fn handle_end_array(&mut self, _byte_offset: usize) -> bool {
-        true
+    fn handle_neg_int(&mut self, _byte_offset: usize, _i: i64) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
     }
 
-    fn handle_int(&mut self, _byte_offset: usize, _i: u64) -> bool {
-        true
+    fn handle_float(&mut self, _byte_offset: usize, _f: f64) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
     }
 
-    fn handle_str(&mut self, _byte_offset: usize, _size_in_bytes: usize) -> bool {
-        true
+    fn handle_raw_number(
+        &mut self,
+        _byte_offset: usize,
+        _size_in_bytes: usize,
+    ) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
     }
 
-    fn handle_bool(&mut self, _byte_offset: usize, _b: bool) -> bool {
-        true
+    fn handle_str(
+        &mut self,
+        _byte_offset: usize,
+        _size_in_bytes: usize,
+        _has_escapes: bool,
+    ) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
     }
 
-    fn handle_null(&mut self, _byte_offset: usize) -> bool {
-        true
+    fn handle_bool(&mut self, _byte_offset: usize, _b: bool) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
     }
 
-    fn handle_comment(&mut self, _byte_offset: usize, _size_in_bytes: usize) -> bool {
-        true
+    fn handle_null(&mut self, _byte_offset: usize) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
+    }
+
+    fn handle_comment(
+        &mut self,
+        _byte_offset: usize,
+        _size_in_bytes: usize,
+    ) -> ListenerAction<Self::Error> {
+        ListenerAction::Continue
     }
 
     fn handle_error(&mut self, _error: ParseError);
 }
+
+impl<L: EventListener + ?Sized> EventListener for &mut L {
+    type Error = L::Error;
+
+    fn handle_start_object(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        (**self).handle_start_object(byte_offset)
+    }
+
+    fn handle_end_object(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        (**self).handle_end_object(byte_offset)
+    }
+
+    fn handle_start_array(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        (**self).handle_start_array(byte_offset)
+    }
+
+    fn handle_end_array(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        (**self).handle_end_array(byte_offset)
+    }
+
+    fn handle_int(&mut self, byte_offset: usize, i: u64) -> ListenerAction<L::Error> {
+        (**self).handle_int(byte_offset, i)
+    }
+
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64) -> ListenerAction<L::Error> {
+        (**self).handle_neg_int(byte_offset, i)
+    }
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64) -> ListenerAction<L::Error> {
+        (**self).handle_float(byte_offset, f)
+    }
+
+    fn handle_raw_number(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<L::Error> {
+        (**self).handle_raw_number(byte_offset, size_in_bytes)
+    }
+
+    fn handle_str(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+        has_escapes: bool,
+    ) -> ListenerAction<L::Error> {
+        (**self).handle_str(byte_offset, size_in_bytes, has_escapes)
+    }
+
+    fn handle_bool(&mut self, byte_offset: usize, b: bool) -> ListenerAction<L::Error> {
+        (**self).handle_bool(byte_offset, b)
+    }
+
+    fn handle_null(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        (**self).handle_null(byte_offset)
+    }
+
+    fn handle_comment(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<L::Error> {
+        (**self).handle_comment(byte_offset, size_in_bytes)
+    }
+
+    fn handle_error(&mut self, error: ParseError) {
+        (**self).handle_error(error)
+    }
+}
+
+/// Calls the listener method for an event. Returns the listener's return value.
+pub(crate) fn send_event<L: EventListener>(
+    listener: &mut L,
+    byte_offset: usize,
+    kind: &ParseEventKind,
+) -> ListenerAction<L::Error> {
+    match *kind {
+        ParseEventKind::StartObject => listener.handle_start_object(byte_offset),
+        ParseEventKind::EndObject => listener.handle_end_object(byte_offset),
+        ParseEventKind::StartArray => listener.handle_start_array(byte_offset),
+        ParseEventKind::EndArray => listener.handle_end_array(byte_offset),
+        ParseEventKind::Int(i) => listener.handle_int(byte_offset, i),
+        ParseEventKind::NegInt(i) => listener.handle_neg_int(byte_offset, i),
+        ParseEventKind::Float(f) => listener.handle_float(byte_offset, f),
+        ParseEventKind::RawNumber { size_in_bytes } => {
+            listener.handle_raw_number(byte_offset, size_in_bytes)
+        }
+        ParseEventKind::Str {
+            size_in_bytes,
+            has_escapes,
+        } => listener.handle_str(byte_offset, size_in_bytes, has_escapes),
+        ParseEventKind::Bool(b) => listener.handle_bool(byte_offset, b),
+        ParseEventKind::Null => listener.handle_null(byte_offset),
+        ParseEventKind::Comment { size_in_bytes } => {
+            listener.handle_comment(byte_offset, size_in_bytes)
+        }
+    }
+}
@@ -1,26 +1,163 @@
-use crate::{ParseError, ParseEvent, ParseEventKind};
+use crate::escape::scan_string_bytes;
+use crate::lexer::{scan_comment, scan_keyword, scan_string_error, skip_container};
+use crate::limits::IterLimits;
+use crate::number::{scan_number, Number};
+use crate::structural_index::IndexBuilder;
+use crate::{
+    Expected, ParseError, ParseErrorKind, ParseEvent, ParseEventKind, ParserConfig, Token,
+    TokenKind,
+};
 
 /// Parses input to [ParseEvent]s.
-pub fn parse_events_iter(input: &str) -> EventParser {
-    EventParser::new(input)
+pub fn parse_events_iter(input: &str) -> EventParser<'_> {
+    EventParser::new(input.as_bytes(), ParserConfig::default(), false)
+}
+
+/// Same as [parse_events_iter], but with the given configuration.
+pub fn parse_events_iter_with_config(input: &str, config: ParserConfig) -> EventParser<'_> {
+    EventParser::new(input.as_bytes(), config, false)
+}
+
+/// Same as [parse_events_iter], but the input does not have to be valid UTF-8. UTF-8 is validated
+/// in strings and comments, the rest of the JSON syntax is ASCII.
+///
+/// String and comment events can be converted to `&str` after validation, with
+/// [std::str::from_utf8].
+pub fn parse_events_iter_bytes(input: &[u8]) -> EventParser<'_> {
+    EventParser::new(input, ParserConfig::default(), false)
+}
+
+/// Same as [parse_events_iter_bytes], but with the given configuration.
+pub fn parse_events_iter_bytes_with_config(input: &[u8], config: ParserConfig) -> EventParser<'_> {
+    EventParser::new(input, config, false)
+}
+
+/// Same as [parse_events_iter], but indexes the input as in
+/// [StructuralIndex](crate::StructuralIndex), and uses the index to find the next token after
+/// whitespace, and the ends of strings.
+///
+/// Numbers and keywords are still scanned byte by byte. The input is indexed a few blocks at a time
+/// as the parser advances, rather than all at once. On the inputs in the benchmarks this is not
+/// faster than [parse_events_iter].
+///
+/// This is the second stage of the two-stage parsing in
+/// [simdjson](https://arxiv.org/abs/1902.08318). The events and errors are the same as the ones
+/// generated by [parse_events_iter].
+pub fn parse_events_iter_indexed(input: &str) -> EventParser<'_> {
+    parse_events_iter_indexed_with_config(input, ParserConfig::default())
+}
+
+/// Same as [parse_events_iter_indexed], but with the given configuration.
+pub fn parse_events_iter_indexed_with_config(input: &str, config: ParserConfig) -> EventParser<'_> {
+    let mut parser = EventParser::new(input.as_bytes(), config, false);
+    parser.index = Some(IndexCursor {
+        builder: IndexBuilder::new(input.as_bytes()),
+        offsets: Vec::with_capacity(INDEX_WINDOW_BLOCKS * 64),
+        next: 0,
+    });
+    parser
+}
+
+/// Parses input to [ParseEvent]s, recovering from errors.
+///
+/// Errors are generated inline with the events, and parsing continues after an error:
+///
+/// - A missing `,` between values and a missing `:` after a key are reported, and parsing
+///   continues as if they were there.
+///
+/// - Other errors are recovered by skipping the input until the next `,`, `]`, or `}` in the
+///   current array or object. `]` and `}` close the innermost array or object, generating end
+///   events for any containers nested in it.
+///
+/// - At the end of the input, end events are generated for all of the unclosed arrays and objects,
+///   at the end of the input.
+///
+/// Start and end events are always balanced, but an object key may not be followed by a value
+/// after an error.
+pub fn parse_events_iter_recovering(input: &str) -> EventParser<'_> {
+    EventParser::new(input.as_bytes(), ParserConfig::default(), true)
+}
+
+/// Same as [parse_events_iter_recovering], but with the given configuration.
+pub fn parse_events_iter_recovering_with_config(
+    input: &str,
+    config: ParserConfig,
+) -> EventParser<'_> {
+    EventParser::new(input.as_bytes(), config, true)
 }
 
 /// A parser that generates [ParseEvent]s.
 #[derive(Debug)]
 pub struct EventParser<'a> {
-    input: &'a str,
+    input: &'a [u8],
+    config: ParserConfig,
     byte_offset: usize,
     container_stack: Vec<Container>,
     state: ParserState,
+
+    /// Whether to recover from errors, see [parse_events_iter_recovering].
+    recover: bool,
+
+    /// Whether we're skipping the input after an error, to the next `,`, `]`, or `}`.
+    recovering: bool,
+
+    /// When available, used to skip whitespace and strings. See [parse_events_iter_indexed].
+    index: Option<IndexCursor<'a>>,
+
+    /// Checks the resource limits in `config`, when it has limits other than the nesting depth.
+    limits: Option<IterLimits>,
+}
+
+/// Number of 64-byte blocks indexed at a time by [IndexCursor].
+const INDEX_WINDOW_BLOCKS: usize = 16;
+
+#[derive(Debug)]
+struct IndexCursor<'a> {
+    builder: IndexBuilder<'a>,
+
+    /// Offsets of the tokens in the current window of the input.
+    offsets: Vec<usize>,
+
+    /// Index of the first offset in `offsets` that may be after the current byte offset.
+    next: usize,
+}
+
+impl IndexCursor<'_> {
+    /// Returns the offset of the next token after `byte_offset`, or `None` if there are no more
+    /// tokens.
+    fn next_token(&mut self, byte_offset: usize) -> Option<usize> {
+        loop {
+            while let Some(offset) = self.offsets.get(self.next).copied() {
+                if offset > byte_offset {
+                    return Some(offset);
+                }
+                self.next += 1;
+            }
+
+            self.offsets.clear();
+            self.next = 0;
+            if !self
+                .builder
+                .index_blocks(INDEX_WINDOW_BLOCKS, &mut self.offsets)
+            {
+                return None;
+            }
+        }
+    }
 }
 
 impl<'a> EventParser<'a> {
-    fn new(input: &'a str) -> EventParser<'a> {
+    fn new(input: &'a [u8], config: ParserConfig, recover: bool) -> EventParser<'a> {
         EventParser {
             input,
+            config,
             byte_offset: 0,
             container_stack: vec![],
             state: ParserState::TopLevel,
+            recover,
+            recovering: false,
+            index: None,
+            limits: IterLimits::with_input(config, input),
         }
     }
 }
@@ -31,20 +168,36 @@ pub(crate) enum Container {
     Object,
 }
 
+impl Container {
+    fn end_event(self) -> ParseEventKind {
+        match self {
+            Container::Array => ParseEventKind::EndArray,
+            Container::Object => ParseEventKind::EndObject,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum ParserState {
     /// Parse any kind of object, update state based on the current container.
     TopLevel,
 
+    /// Parsing an array and we've just parsed '[', parse the first element, or finish the array on
+    /// ']'.
+    ArrayStart,
+
     /// Finished parsing a top-level object, expect end-of-input.
     Done,
 
-    /// Parsing an object, parse another element on ',', or finish the array on '}'.
+    /// Parsing an object, parse another element on ',', or finish the object on '}'.
     ObjectExpectComma,
 
-    /// Parsing an object, parse the first element, or finish the array on ']'.
+    /// Parsing an object, parse the first element, or finish the object on '}'.
     ObjectExpectKeyValue,
 
+    /// Parsing an object and we've just parsed ',', parse a key.
+    ObjectExpectKey,
+
     /// Parsing an object and we've just parsed a key, expect ':'.
     ObjectExpectColon,
 
@@ -56,11 +209,80 @@ impl<'a> Iterator for EventParser<'a> {
     type Item = Result<ParseEvent, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        match self.limits.take() {
+            None => self.next_unchecked(),
+            Some(mut limits) => {
+                let event = limits.next(|| self.next_unchecked());
+                self.limits = Some(limits);
+                event
+            }
+        }
+    }
+}
+
+impl<'a> EventParser<'a> {
+    /// Skips the next value without generating events for it.
+    ///
+    /// Arrays and objects are skipped without checking their contents other than the balancing
+    /// of brackets, and the strings and comments in them. Values other than arrays and objects
+    /// are checked as usual. Limits in the configuration, including the nesting depth, are not
+    /// checked in the skipped input, but the skipped value counts as an element of its array or
+    /// object for [ParserConfig::max_container_size].
+    ///
+    /// Call after a key event to skip the key's value, or in an array to skip the next element.
+    /// When the next event is not a value, e.g. at the end of an array or when expecting an
+    /// object key, this does nothing, and any errors are generated by the next call to
+    /// [Iterator::next]. Also does nothing when recovering from an error, see
+    /// [parse_events_iter_recovering].
+    pub fn skip_value(&mut self) -> Result<(), ParseError> {
+        if self.recovering {
+            return Ok(());
+        }
+
+        let result = match self.limits.take() {
+            None => self.skip_next_value().map(|_| ()),
+            Some(mut limits) => {
+                let result = limits.skip(|| self.skip_next_value());
+                self.limits = Some(limits);
+                result
+            }
+        };
+
+        if self.recover {
+            if let Err(err) = &result {
+                self.start_recovery(err);
+            }
+        }
+
+        result
+    }
+
+    fn next_unchecked(&mut self) -> Option<Result<ParseEvent, ParseError>> {
+        if self.recovering {
+            return self.recover();
+        }
+
+        let event = self.parse_next();
+
+        if self.recover {
+            if let Some(Err(err)) = &event {
+                self.start_recovery(err);
+            }
+        }
+
+        event
+    }
+}
+
+impl<'a> EventParser<'a> {
+    fn parse_next(&mut self) -> Option<Result<ParseEvent, ParseError>> {
         match self.state {
-            ParserState::TopLevel => self.top_level(),
+            ParserState::TopLevel => self.top_level(Expected::Value),
+            ParserState::ArrayStart => self.array_start(),
             ParserState::Done => self.done(),
             ParserState::ObjectExpectComma => self.object_expect_comma(),
-            ParserState::ObjectExpectKeyValue => self.object_expect_key_value(),
+            ParserState::ObjectExpectKeyValue => self.object_expect_key(Expected::KeyOrEndObject),
+            ParserState::ObjectExpectKey => self.object_expect_key(Expected::Key),
             ParserState::ObjectExpectColon => self.object_expect_colon(),
             ParserState::ArrayExpectComma => self.array_expect_comma(),
         }
@@ -78,131 +300,87 @@ macro_rules! skip_trivia {
 }
 
 impl<'a> EventParser<'a> {
-    fn top_level(&mut self) -> Option<Result<ParseEvent, ParseError>> {
+    /// Parses a value. `expected` is used in errors.
+    fn top_level(&mut self, expected: Expected) -> Option<Result<ParseEvent, ParseError>> {
         debug_assert!(self.byte_offset <= self.input.len());
         skip_trivia!(self);
-        let mut input = self.input[self.byte_offset..].chars().peekable();
-        match input.peek().copied() {
-            Some('[') => {
+        match self.input.get(self.byte_offset).copied() {
+            Some(b'[') => {
                 // Consume '['
                 let loc = self.byte_offset;
+                if self.container_stack.len() == self.config.max_depth {
+                    return Some(Err(ParseError::new(loc, ParseErrorKind::NestingTooDeep)));
+                }
                 self.byte_offset += 1;
-                self.state = ParserState::TopLevel;
+                self.state = ParserState::ArrayStart;
                 self.container_stack.push(Container::Array);
                 Some(Ok(ParseEvent::new(loc, ParseEventKind::StartArray)))
             }
 
-            Some(']') => {
-                if let Err(err) = self.pop_array() {
-                    return Some(Err(err));
-                }
-                let loc = self.byte_offset;
-                self.byte_offset += 1;
-                self.update_state();
-                Some(Ok(ParseEvent::new(loc, ParseEventKind::EndArray)))
-            }
-
-            Some('{') => {
+            Some(b'{') => {
                 // Consume '{'
                 let loc = self.byte_offset;
+                if self.container_stack.len() == self.config.max_depth {
+                    return Some(Err(ParseError::new(loc, ParseErrorKind::NestingTooDeep)));
+                }
                 self.byte_offset += 1;
                 self.state = ParserState::ObjectExpectKeyValue;
                 self.container_stack.push(Container::Object);
                 Some(Ok(ParseEvent::new(loc, ParseEventKind::StartObject)))
             }
 
-            Some('t') => {
-                input.next();
-                if input.next() == Some('r')
-                    && input.next() == Some('u')
-                    && input.next() == Some('e')
-                {
-                    let loc = self.byte_offset;
-                    self.byte_offset += 4;
-                    self.update_state();
-                    return Some(Ok(ParseEvent::new(loc, ParseEventKind::Bool(true))));
-                }
-
-                Some(Err(ParseError {
-                    byte_offset: self.byte_offset,
-                    reason: "unexpected keyword",
-                }))
-            }
-
-            Some('f') => {
-                input.next();
-                if input.next() == Some('a')
-                    && input.next() == Some('l')
-                    && input.next() == Some('s')
-                    && input.next() == Some('e')
-                {
-                    let loc = self.byte_offset;
-                    self.byte_offset += 5;
-                    self.update_state();
-                    return Some(Ok(ParseEvent::new(loc, ParseEventKind::Bool(false))));
-                }
+            Some(b't' | b'f' | b'n') => {
+                let loc = self.byte_offset;
+                let (token, end_offset) = match scan_keyword(self.input, loc) {
+                    Ok(keyword) => keyword,
+                    Err(err) => return Some(Err(err)),
+                };
 
-                Some(Err(ParseError {
-                    byte_offset: self.byte_offset,
-                    reason: "unexpected keyword",
-                }))
-            }
+                self.byte_offset = end_offset;
+                self.update_state();
 
-            Some('n') => {
-                input.next();
-                if input.next() == Some('u')
-                    && input.next() == Some('l')
-                    && input.next() == Some('l')
-                {
-                    let loc = self.byte_offset;
-                    self.byte_offset += 4;
-                    self.update_state();
-                    return Some(Ok(ParseEvent::new(loc, ParseEventKind::Null)));
-                }
+                let kind = match token {
+                    Token::True => ParseEventKind::Bool(true),
+                    Token::False => ParseEventKind::Bool(false),
+                    _ => ParseEventKind::Null,
+                };
 
-                Some(Err(ParseError {
-                    byte_offset: self.byte_offset,
-                    reason: "unexpected keyword",
-                }))
+                Some(Ok(ParseEvent::new(loc, kind)))
             }
 
-            Some(c) if c.is_ascii_digit() => {
+            Some(b'-' | b'0'..=b'9') => {
                 let loc = self.byte_offset;
-                input.next();
-                self.byte_offset += 1;
-
-                let mut i: u64 = u64::from((c as u8) - b'0');
-
-                while let Some(next) = input.peek().copied() {
-                    if !next.is_ascii_digit() {
-                        break;
-                    }
-
-                    // Consume the digit.
-                    self.byte_offset += 1;
-                    input.next();
-
-                    // Ignore overflows for the purposes of this post.
-                    i *= 10;
-                    i += u64::from((next as u8) - b'0');
-                }
+                let (number, end_offset) =
+                    match scan_number(self.input, loc, self.config.int_overflow) {
+                        Ok(number) => number,
+                        Err(err) => return Some(Err(err)),
+                    };
 
+                self.byte_offset = end_offset;
                 self.update_state();
 
-                Some(Ok(ParseEvent::new(loc, ParseEventKind::Int(i))))
+                let kind = match number {
+                    Number::Int(i) => ParseEventKind::Int(i),
+                    Number::NegInt(i) => ParseEventKind::NegInt(i),
+                    Number::Float(f) => ParseEventKind::Float(f),
+                    Number::Raw { size_in_bytes } => ParseEventKind::RawNumber { size_in_bytes },
+                };
+
+                Some(Ok(ParseEvent::new(loc, kind)))
             }
 
-            Some('"') => {
+            Some(b'"') => {
                 self.byte_offset += 1;
                 let loc = self.byte_offset;
                 self.update_state();
                 match self.skip_string() {
-                    Ok(()) => {
+                    Ok(has_escapes) => {
                         let after_string = self.byte_offset;
                         Some(Ok(ParseEvent::new(
                             loc,
                             ParseEventKind::Str {
                                 size_in_bytes: after_string - loc - 1,
+                                has_escapes,
                             },
                         )))
                     }
@@ -210,15 +388,24 @@ impl<'a> EventParser<'a> {
                 }
             }
 
-            Some(_) => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected character",
-            })),
+            Some(c) => Some(Err(ParseError::unexpected_char(
+                self.byte_offset,
+                expected,
+                char::from(c),
+            ))),
+
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.byte_offset,
+                expected,
+            ))),
+        }
+    }
 
-            None => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected end of input",
-            })),
+    fn array_start(&mut self) -> Option<Result<ParseEvent, ParseError>> {
+        skip_trivia!(self);
+        match self.input.get(self.byte_offset).copied() {
+            Some(b']') => self.end_array(),
+            _ => self.top_level(Expected::ValueOrEndArray),
         }
     }
 
@@ -227,68 +414,56 @@ impl<'a> EventParser<'a> {
         if self.byte_offset == self.input.len() {
             None
         } else {
-            Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "trailing characters",
-            }))
+            Some(Err(ParseError::new(
+                self.byte_offset,
+                ParseErrorKind::TrailingCharacters,
+            )))
         }
     }
 
     fn array_expect_comma(&mut self) -> Option<Result<ParseEvent, ParseError>> {
         skip_trivia!(self);
-        match self.input[self.byte_offset..].chars().next() {
-            Some(',') => {
+        match self.input.get(self.byte_offset).copied() {
+            Some(b',') => {
                 self.byte_offset += 1;
                 self.state = ParserState::TopLevel;
-                self.next()
+                self.parse_next()
             }
 
-            Some(']') => {
-                if let Err(err) = self.pop_array() {
-                    return Some(Err(err));
-                }
-                let loc = self.byte_offset;
-                self.byte_offset += 1;
-                self.update_state();
-                Some(Ok(ParseEvent::new(loc, ParseEventKind::EndArray)))
-            }
+            Some(b']') => self.end_array(),
 
-            Some(_) => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected character while parsing array",
-            })),
+            Some(c) => Some(Err(ParseError::unexpected_char(
+                self.byte_offset,
+                Expected::CommaOrEndArray,
+                char::from(c),
+            ))),
 
-            None => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected end of input while parsing array",
-            })),
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.byte_offset,
+                Expected::CommaOrEndArray,
+            ))),
         }
     }
 
-    fn object_expect_key_value(&mut self) -> Option<Result<ParseEvent, ParseError>> {
+    /// Parses an object key. `expected` is [Expected::KeyOrEndObject] when the object can be
+    /// finished with a '}', [Expected::Key] otherwise.
+    fn object_expect_key(&mut self, expected: Expected) -> Option<Result<ParseEvent, ParseError>> {
         skip_trivia!(self);
-        match self.input[self.byte_offset..].chars().next() {
-            Some('}') => {
-                if let Err(err) = self.pop_map() {
-                    return Some(Err(err));
-                }
-                let loc = self.byte_offset;
-                self.byte_offset += 1;
-                self.update_state();
-                Some(Ok(ParseEvent::new(loc, ParseEventKind::EndObject)))
-            }
+        match self.input.get(self.byte_offset).copied() {
+            Some(b'}') if expected == Expected::KeyOrEndObject => self.end_object(),
 
-            Some('"') => {
+            Some(b'"') => {
                 self.byte_offset += 1;
                 let loc = self.byte_offset;
                 match self.skip_string() {
-                    Ok(()) => {
+                    Ok(has_escapes) => {
                         let after_string = self.byte_offset;
                         self.state = ParserState::ObjectExpectColon;
                         Some(Ok(ParseEvent::new(
                             loc,
                             ParseEventKind::Str {
                                 size_in_bytes: after_string - loc - 1,
+                                has_escapes,
                             },
                         )))
                     }
@@ -296,168 +471,363 @@ impl<'a> EventParser<'a> {
                 }
             }
 
-            Some(_) => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected character while parsing object",
-            })),
+            Some(c) => Some(Err(ParseError::unexpected_char(
+                self.byte_offset,
+                expected,
+                char::from(c),
+            ))),
 
-            None => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected end of input while parsing object",
-            })),
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.byte_offset,
+                expected,
+            ))),
         }
     }
 
     fn object_expect_colon(&mut self) -> Option<Result<ParseEvent, ParseError>> {
         skip_trivia!(self);
-        match self.input[self.byte_offset..].chars().next() {
-            Some(':') => {
+        match self.input.get(self.byte_offset).copied() {
+            Some(b':') => {
                 self.byte_offset += 1;
                 self.state = ParserState::TopLevel;
-                self.next()
+                self.parse_next()
             }
 
-            Some(_) => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected character while parsing object",
-            })),
+            Some(c) => Some(Err(ParseError::unexpected_char(
+                self.byte_offset,
+                Expected::Colon,
+                char::from(c),
+            ))),
 
-            None => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected end of input while parsing object",
-            })),
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.byte_offset,
+                Expected::Colon,
+            ))),
         }
     }
 
     fn object_expect_comma(&mut self) -> Option<Result<ParseEvent, ParseError>> {
         skip_trivia!(self);
-        match self.input[self.byte_offset..].chars().next() {
-            Some(',') => {
+        match self.input.get(self.byte_offset).copied() {
+            Some(b',') => {
                 self.byte_offset += 1;
-                self.state = ParserState::ObjectExpectKeyValue;
-                self.next()
+                self.state = ParserState::ObjectExpectKey;
+                self.parse_next()
             }
 
-            Some('}') => {
-                if let Err(err) = self.pop_map() {
-                    return Some(Err(err));
-                }
-                let loc = self.byte_offset;
-                self.byte_offset += 1;
-                self.update_state();
-                Some(Ok(ParseEvent::new(loc, ParseEventKind::EndObject)))
-            }
+            Some(b'}') => self.end_object(),
 
-            Some(_) => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected character while parsing object",
-            })),
+            Some(c) => Some(Err(ParseError::unexpected_char(
+                self.byte_offset,
+                Expected::CommaOrEndObject,
+                char::from(c),
+            ))),
 
-            None => Some(Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected end of input while parsing object",
-            })),
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.byte_offset,
+                Expected::CommaOrEndObject,
+            ))),
         }
     }
 
-    /// Skip until after the end of a string. Expects the opening double colon to be consumed.
-    fn skip_string(&mut self) -> Result<(), ParseError> {
-        self.skip_trivia()?;
-        for char in self.input[self.byte_offset..].chars() {
-            self.byte_offset += 1;
-            if char == '"' {
-                return Ok(());
+    /// Consumes the ']' at the current location and finishes the array.
+    fn end_array(&mut self) -> Option<Result<ParseEvent, ParseError>> {
+        let container = self.container_stack.pop();
+        debug_assert_eq!(container, Some(Container::Array));
+        let loc = self.byte_offset;
+        self.byte_offset += 1;
+        self.update_state();
+        Some(Ok(ParseEvent::new(loc, ParseEventKind::EndArray)))
+    }
+
+    /// Consumes the '}' at the current location and finishes the object.
+    fn end_object(&mut self) -> Option<Result<ParseEvent, ParseError>> {
+        let container = self.container_stack.pop();
+        debug_assert_eq!(container, Some(Container::Object));
+        let loc = self.byte_offset;
+        self.byte_offset += 1;
+        self.update_state();
+        Some(Ok(ParseEvent::new(loc, ParseEventKind::EndObject)))
+    }
+
+    /// Skip until after the end of a string. Expects the opening double quote to be consumed.
+    /// Returns whether the string has escape sequences.
+    fn skip_string(&mut self) -> Result<bool, ParseError> {
+        // With an index the closing double quote is the next offset in the index. Only `&str`
+        // inputs are indexed, so UTF-8 doesn't need to be validated, and strings without
+        // backslashes don't need to be scanned. Strings with escape sequences, and unterminated
+        // strings, are scanned as usual to check the escapes and generate the same errors.
+        if let Some(index) = &mut self.index {
+            if let Some(quote_byte_offset) = index.next_token(self.byte_offset - 1) {
+                if self.input[quote_byte_offset] == b'"'
+                    && !self.input[self.byte_offset..quote_byte_offset].contains(&b'\\')
+                {
+                    self.byte_offset = quote_byte_offset + 1;
+                    return Ok(false);
+                }
+            }
+        }
+
+        match scan_string_bytes(self.input, self.byte_offset) {
+            Ok((quote_byte_offset, has_escapes)) => {
+                self.byte_offset = quote_byte_offset + 1;
+                Ok(has_escapes)
             }
+
+            Err(err) => Err(scan_string_error(err, self.byte_offset - 1)),
         }
-        Err(ParseError {
-            byte_offset: self.byte_offset,
-            reason: "unexpected end of input while parsing string",
-        })
     }
 
-    /// After parsing a value, update the parser state based on the current container.
-    fn update_state(&mut self) {
-        self.state = match self.container_stack.last() {
-            Some(Container::Array) => ParserState::ArrayExpectComma,
-            Some(Container::Object) => ParserState::ObjectExpectComma,
-            None => ParserState::Done,
+    /// Called after generating `error` in recovery mode. Updates the state to continue parsing
+    /// after the error.
+    fn start_recovery(&mut self, error: &ParseError) {
+        let is_value = |token: TokenKind| {
+            !matches!(
+                token,
+                TokenKind::RBracket | TokenKind::RBrace | TokenKind::Colon | TokenKind::Comma
+            )
         };
-    }
 
-    fn pop_map(&mut self) -> Result<(), ParseError> {
-        match self.container_stack.pop() {
-            Some(Container::Object) => Ok(()),
+        match error.kind {
+            // Missing ',' in an array: parse the next element.
+            ParseErrorKind::UnexpectedToken {
+                expected: Expected::CommaOrEndArray,
+                found,
+            } if is_value(found) => {
+                self.state = ParserState::TopLevel;
+            }
+
+            // Missing ',' in an object: parse the next key.
+            ParseErrorKind::UnexpectedToken {
+                expected: Expected::CommaOrEndObject,
+                found: TokenKind::String,
+            } => {
+                self.state = ParserState::ObjectExpectKey;
+            }
+
+            // Missing ':' after a key: parse the value.
+            ParseErrorKind::UnexpectedToken {
+                expected: Expected::Colon,
+                found,
+            } if is_value(found) => {
+                self.state = ParserState::TopLevel;
+            }
 
-            _ => Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected '}'",
-            }),
+            // Skip the rest of the string, to avoid parsing the closing double quote as the start
+            // of a string.
+            ParseErrorKind::InvalidEscape => {
+                self.byte_offset = error.byte_offset;
+                self.skip_string_lenient();
+                self.recovering = true;
+            }
+
+            _ => {
+                self.recovering = true;
+            }
         }
     }
 
-    fn pop_array(&mut self) -> Result<(), ParseError> {
-        match self.container_stack.pop() {
-            Some(Container::Array) => Ok(()),
+    /// Skips the input until the next `,`, `]`, or `}` at the current nesting level, and continues
+    /// parsing from there. See [parse_events_iter_recovering].
+    fn recover(&mut self) -> Option<Result<ParseEvent, ParseError>> {
+        // Number of arrays and objects opened in the skipped input.
+        let mut depth: usize = 0;
+
+        while let Some(c) = self.input.get(self.byte_offset).copied() {
+            match c {
+                b'"' => {
+                    self.byte_offset += 1;
+                    self.skip_string_lenient();
+                    continue;
+                }
+
+                b'/' if self.input[self.byte_offset..].starts_with(b"//") => {
+                    self.byte_offset = match self.input[self.byte_offset..]
+                        .iter()
+                        .position(|c| *c == b'\n')
+                    {
+                        Some(newline_byte_idx) => self.byte_offset + newline_byte_idx + 1,
+                        None => self.input.len(),
+                    };
+                    continue;
+                }
+
+                b'[' | b'{' => depth += 1,
+
+                b']' | b'}' if depth != 0 => depth -= 1,
+
+                b',' if depth == 0 => {
+                    if let Some(container) = self.container_stack.last() {
+                        self.byte_offset += 1;
+                        self.recovering = false;
+                        self.state = match container {
+                            Container::Array => ParserState::TopLevel,
+                            Container::Object => ParserState::ObjectExpectKey,
+                        };
+                        return self.next();
+                    }
+                }
+
+                b']' if self.container_stack.contains(&Container::Array) => {
+                    return self.recover_end_container(Container::Array);
+                }
+
+                b'}' if self.container_stack.contains(&Container::Object) => {
+                    return self.recover_end_container(Container::Object);
+                }
 
-            _ => Err(ParseError {
-                byte_offset: self.byte_offset,
-                reason: "unexpected ']'",
-            }),
+                _ => {}
+            }
+
+            self.byte_offset += 1;
+        }
+
+        // End of input, close the containers.
+        match self.container_stack.pop() {
+            Some(container) => Some(Ok(ParseEvent::new(self.input.len(), container.end_event()))),
+            None => {
+                self.recovering = false;
+                self.state = ParserState::Done;
+                None
+            }
         }
     }
 
-    fn skip_trivia(&mut self) -> Result<Option<ParseEvent>, ParseError> {
-        if self.byte_offset == self.input.len() {
-            return Ok(None);
+    /// Generates an end event when recovering at a `]` or `}` that ends a `container`. If there
+    /// are other containers nested in the `container` they are closed first, one at a time,
+    /// without consuming the `]` or `}`.
+    fn recover_end_container(
+        &mut self,
+        container: Container,
+    ) -> Option<Result<ParseEvent, ParseError>> {
+        let innermost = *self.container_stack.last().unwrap();
+        if innermost == container {
+            self.recovering = false;
+            match container {
+                Container::Array => self.end_array(),
+                Container::Object => self.end_object(),
+            }
+        } else {
+            self.container_stack.pop();
+            Some(Ok(ParseEvent::new(self.byte_offset, innermost.end_event())))
         }
-        let mut chars = self.input[self.byte_offset..].char_indices().peekable();
-        loop {
-            match chars.peek().copied() {
-                Some((byte_idx, '/')) => {
-                    chars.next(); // consume peeked '/'
-                    match chars.next() {
-                        Some((_, '/')) => loop {
-                            match chars.next() {
-                                Some((newline_byte_idx, '\n')) => {
-                                    self.byte_offset += newline_byte_idx;
-                                    return Ok(Some(ParseEvent {
-                                        kind: ParseEventKind::Comment {
-                                            size_in_bytes: newline_byte_idx - byte_idx + 1,
-                                        },
-                                        byte_offset: byte_idx,
-                                    }));
-                                }
-                                Some(_) => {}
-                                None => {
-                                    return Err(ParseError {
-                                        byte_offset: byte_idx,
-                                        reason: "unterminated comment",
-                                    });
-                                }
-                            }
-                        },
-                        _ => {
-                            return Err(ParseError {
-                                byte_offset: byte_idx,
-                                reason: "unexpected '/'",
-                            });
-                        }
-                    }
+    }
+
+    /// Skips until after the closing double quote of a string, or to the end of input, without
+    /// checking escape sequences. Expects the opening double quote to be consumed.
+    fn skip_string_lenient(&mut self) {
+        let mut bytes = self.input[self.byte_offset..].iter().enumerate();
+        while let Some((byte_idx, c)) = bytes.next() {
+            match c {
+                b'\\' => {
+                    bytes.next();
                 }
+                b'"' => {
+                    self.byte_offset += byte_idx + 1;
+                    return;
+                }
+                _ => {}
+            }
+        }
+        self.byte_offset = self.input.len();
+    }
+
+    /// Skips the next value, if the next event is a value. See [EventParser::skip_value].
+    ///
+    /// Returns the byte offset of the skipped value, or `None` if the next event is not a value.
+    fn skip_next_value(&mut self) -> Result<Option<usize>, ParseError> {
+        let expected = match self.state {
+            ParserState::TopLevel => Expected::Value,
 
-                Some((_, c)) if c.is_ascii_whitespace() => {
-                    chars.next(); // consume peeked whitespace
+            ParserState::ArrayStart => {
+                self.skip_comments()?;
+                if self.input.get(self.byte_offset) == Some(&b']') {
+                    return Ok(None);
                 }
+                Expected::ValueOrEndArray
+            }
 
-                Some((byte_idx, _)) => {
-                    self.byte_offset += byte_idx;
+            ParserState::ArrayExpectComma => {
+                self.skip_comments()?;
+                if self.input.get(self.byte_offset) != Some(&b',') {
                     return Ok(None);
                 }
+                self.byte_offset += 1;
+                self.state = ParserState::TopLevel;
+                Expected::Value
+            }
 
-                None => {
-                    self.byte_offset = self.input.len();
+            ParserState::ObjectExpectColon => {
+                self.skip_comments()?;
+                if self.input.get(self.byte_offset) != Some(&b':') {
                     return Ok(None);
                 }
+                self.byte_offset += 1;
+                self.state = ParserState::TopLevel;
+                Expected::Value
+            }
+
+            ParserState::Done
+            | ParserState::ObjectExpectComma
+            | ParserState::ObjectExpectKeyValue
+            | ParserState::ObjectExpectKey => return Ok(None),
+        };
+
+        self.skip_comments()?;
+        let value_offset = self.byte_offset;
+        match self.input.get(value_offset) {
+            Some(b'[' | b'{') => {
+                self.byte_offset = skip_container(self.input, value_offset)? + 1;
+                self.update_state();
+            }
+            _ => {
+                if let Some(Err(err)) = self.top_level(expected) {
+                    return Err(err);
+                }
+            }
+        }
+        Ok(Some(value_offset))
+    }
+
+    /// Skips whitespace and comments, without generating comment events.
+    fn skip_comments(&mut self) -> Result<(), ParseError> {
+        while self.skip_trivia()?.is_some() {}
+        Ok(())
+    }
+
+    /// After parsing a value, update the parser state based on the current container.
+    fn update_state(&mut self) {
+        self.state = match self.container_stack.last() {
+            Some(Container::Array) => ParserState::ArrayExpectComma,
+            Some(Container::Object) => ParserState::ObjectExpectComma,
+            None => ParserState::Done,
+        };
+    }
+
+    fn skip_trivia(&mut self) -> Result<Option<ParseEvent>, ParseError> {
+        loop {
+            match self.input.get(self.byte_offset) {
+                Some(b'/') => {
+                    let start = self.byte_offset;
+                    self.byte_offset = scan_comment(self.input, start)?;
+                    return Ok(Some(ParseEvent {
+                        kind: ParseEventKind::Comment {
+                            size_in_bytes: self.byte_offset - start,
+                        },
+                        byte_offset: start,
+                    }));
+                }
+
+                Some(c) if c.is_ascii_whitespace() => {
+                    self.byte_offset = match &mut self.index {
+                        Some(index) => index
+                            .next_token(self.byte_offset)
+                            .unwrap_or(self.input.len()),
+                        None => self.byte_offset + 1,
+                    };
+                }
+
+                _ => return Ok(None),
             }
         }
     }
@@ -466,7 +836,7 @@ impl<'a> EventParser<'a> {
 #[cfg(test)]
 fn collect_events(input: &str) -> (Vec<ParseEventKind>, Option<ParseError>) {
     let mut events: Vec<ParseEventKind> = vec![];
-    for event in EventParser::new(input) {
+    for event in parse_events_iter(input) {
         match event {
             Ok(event) => events.push(event.kind),
             Err(err) => return (events, Some(err)),
@@ -494,6 +864,16 @@ fn event_to_tree_tests() {
     }
 }
 
+#[test]
+fn event_to_tree_surrogate_error_tests() {
+    for (str, byte_offset) in crate::test_common::surrogate_error_tests() {
+        println!("Parsing {:?}", str);
+        let mut parser = parse_events_iter(&str);
+        let err = crate::event_to_tree(&mut parser, &str).unwrap_err();
+        assert_eq!(err.byte_offset, byte_offset);
+    }
+}
+
 #[test]
 fn event_to_tree_random_tests() {
     for input_size in [10, 100, 1_000, 2_000, 5_000, 10_000, 10_000_000] {
@@ -503,3 +883,465 @@ fn event_to_tree_random_tests() {
         assert_eq!(event_ast, ast);
     }
 }
+
+#[test]
+fn string_tests() {
+    // Comment characters in strings.
+    let (events, error) = collect_events(r#"["// hi"]"#);
+    assert_eq!(
+        events,
+        vec![
+            ParseEventKind::StartArray,
+            ParseEventKind::Str {
+                size_in_bytes: 5,
+                has_escapes: false
+            },
+            ParseEventKind::EndArray
+        ]
+    );
+    assert_eq!(error, None);
+
+    let (_, error) = collect_events(r#"["a\x"]"#);
+    assert_eq!(
+        error,
+        Some(ParseError::new(3, crate::ParseErrorKind::InvalidEscape))
+    );
+}
+
+#[test]
+fn int_overflow_tests() {
+    for (str, byte_offset, ast) in crate::test_common::int_overflow_tests() {
+        println!("Parsing {:?}", str);
+        let err = crate::event_to_tree(&mut parse_events_iter(&str), &str).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(byte_offset, crate::ParseErrorKind::IntegerOverflow)
+        );
+
+        let config = crate::test_common::raw_int_config();
+        let mut parser = parse_events_iter_with_config(&str, config);
+        assert_eq!(crate::event_to_tree(&mut parser, &str).unwrap(), ast);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        let (_, error_) = collect_events(&str);
+        assert_eq!(error_, Some(error));
+    }
+}
+
+#[cfg(test)]
+fn collect_events_recovering(input: &str) -> Vec<Result<ParseEventKind, ParseError>> {
+    parse_events_iter_recovering(input)
+        .map(|event| event.map(|event| event.kind))
+        .collect()
+}
+
+#[test]
+fn recovery_tests() {
+    use crate::ParseEventKind::*;
+
+    let unexpected = ParseError::unexpected_token;
+    let string = |size_in_bytes| Str {
+        size_in_bytes,
+        has_escapes: false,
+    };
+
+    // Missing comma, bad keyword.
+    assert_eq!(
+        collect_events_recovering("[1 2, tru, 3]"),
+        vec![
+            Ok(StartArray),
+            Ok(Int(1)),
+            Err(unexpected(3, Expected::CommaOrEndArray, TokenKind::Number)),
+            Ok(Int(2)),
+            Err(ParseError::new(6, ParseErrorKind::InvalidToken)),
+            Ok(Int(3)),
+            Ok(EndArray),
+        ]
+    );
+
+    // Missing colon and comma in an object, trailing comma.
+    assert_eq!(
+        collect_events_recovering(r#"{"a" 1 "b": 2,}"#),
+        vec![
+            Ok(StartObject),
+            Ok(string(1)),
+            Err(unexpected(5, Expected::Colon, TokenKind::Number)),
+            Ok(Int(1)),
+            Err(unexpected(7, Expected::CommaOrEndObject, TokenKind::String)),
+            Ok(string(1)),
+            Ok(Int(2)),
+            Err(unexpected(14, Expected::Key, TokenKind::RBrace)),
+            Ok(EndObject),
+        ]
+    );
+
+    // '}' closes the array nested in the object.
+    assert_eq!(
+        collect_events_recovering(r#"{"a": [1, 2}"#),
+        vec![
+            Ok(StartObject),
+            Ok(string(1)),
+            Ok(StartArray),
+            Ok(Int(1)),
+            Ok(Int(2)),
+            Err(unexpected(11, Expected::CommaOrEndArray, TokenKind::RBrace)),
+            Ok(EndArray),
+            Ok(EndObject),
+        ]
+    );
+
+    // Skipped input with nested containers, strings, and comments.
+    assert_eq!(
+        collect_events_recovering("[@ [1, \"]\"] // ,\n, 2]"),
+        vec![
+            Ok(StartArray),
+            Err(ParseError::new(1, ParseErrorKind::InvalidToken)),
+            Ok(Int(2)),
+            Ok(EndArray),
+        ]
+    );
+
+    // Invalid escape: the rest of the string is skipped.
+    assert_eq!(
+        collect_events_recovering(r#"["\x,", 1]"#),
+        vec![
+            Ok(StartArray),
+            Err(ParseError::new(2, ParseErrorKind::InvalidEscape)),
+            Ok(Int(1)),
+            Ok(EndArray),
+        ]
+    );
+
+    // Unclosed containers are closed at the end of input.
+    assert_eq!(
+        collect_events_recovering(r#"[{"a": 1"#),
+        vec![
+            Ok(StartArray),
+            Ok(StartObject),
+            Ok(string(1)),
+            Ok(Int(1)),
+            Err(ParseError::unexpected_end_of_input(
+                8,
+                Expected::CommaOrEndObject
+            )),
+            Ok(EndObject),
+            Ok(EndArray),
+        ]
+    );
+
+    // Trailing characters.
+    assert_eq!(
+        collect_events_recovering("1 2 3"),
+        vec![
+            Ok(Int(1)),
+            Err(ParseError::new(2, ParseErrorKind::TrailingCharacters)),
+        ]
+    );
+}
+
+#[test]
+fn recovery_error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        let events = collect_events_recovering(&str);
+
+        // First error is the same as the one without recovery.
+        assert_eq!(
+            events.iter().find_map(|event| event.as_ref().err()),
+            Some(&error)
+        );
+
+        // Start and end events are balanced.
+        let mut depth: i32 = 0;
+        for event in events.iter().flatten() {
+            match event {
+                ParseEventKind::StartArray | ParseEventKind::StartObject => depth += 1,
+                ParseEventKind::EndArray | ParseEventKind::EndObject => depth -= 1,
+                _ => {}
+            }
+            assert!(depth >= 0);
+        }
+        assert_eq!(depth, 0);
+    }
+
+    // Events of valid inputs are the same as without recovery.
+    for (str, events) in crate::test_common::event_tests() {
+        let events_: Vec<Result<ParseEventKind, ParseError>> = events.into_iter().map(Ok).collect();
+        assert_eq!(collect_events_recovering(&str), events_);
+    }
+}
+
+#[test]
+fn recovery_event_to_tree_test() {
+    // Events after recovery points can be used to build a tree.
+    let input = r#"{"a": [1 2, tru, 3], "b": {"c" 4} "d": [5,]"#;
+    let mut events = parse_events_iter_recovering(input).filter(Result::is_ok);
+    assert_eq!(
+        crate::event_to_tree(&mut events, input),
+        crate::parse_ast_recursive(r#"{"a": [1, 2, 3], "b": {"c": 4}, "d": [5]}"#)
+    );
+}
+
+#[test]
+fn bytes_tests() {
+    // Parsers don't stop after errors, collect until the first error.
+    let events = |iter: EventParser| -> Vec<Result<ParseEvent, ParseError>> {
+        let mut events = vec![];
+        for event in iter {
+            let is_error = event.is_err();
+            events.push(event);
+            if is_error {
+                break;
+            }
+        }
+        events
+    };
+
+    // Valid UTF-8 is parsed the same as `&str` input.
+    let strs = crate::test_common::ast_tests()
+        .into_iter()
+        .map(|(str, _)| str)
+        .chain(
+            crate::test_common::error_tests()
+                .into_iter()
+                .map(|(str, _)| str),
+        );
+    for str in strs {
+        assert_eq!(
+            events(parse_events_iter_bytes(str.as_bytes())),
+            events(parse_events_iter(&str))
+        );
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(
+            parse_events_iter_bytes(&bytes).find_map(Result::err),
+            Some(error),
+            "{:?}",
+            bytes
+        );
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        let error_ = parse_events_iter_with_config(&str, config).find_map(Result::err);
+        assert_eq!(error_, error);
+        let error_ = parse_events_iter_indexed_with_config(&str, config).find_map(Result::err);
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn limit_tests() {
+    let tests = crate::test_common::limit_tests()
+        .into_iter()
+        .chain(crate::test_common::non_ascii_input_size_tests());
+    for (str, config, error) in tests {
+        println!("Parsing {:?}", str);
+        let error_ = parse_events_iter_with_config(&str, config).find_map(Result::err);
+        assert_eq!(error_, error);
+        let error_ = parse_events_iter_indexed_with_config(&str, config).find_map(Result::err);
+        assert_eq!(error_, error);
+        let error_ = parse_events_iter_recovering_with_config(&str, config).find_map(Result::err);
+        assert_eq!(error_, error);
+    }
+
+    // Parsing stops after exceeding a limit, also when recovering from errors.
+    let config = ParserConfig {
+        max_events: 2,
+        ..ParserConfig::default()
+    };
+    assert_eq!(
+        parse_events_iter_recovering_with_config("[1, 2, 3]", config).count(),
+        3
+    );
+}
+
+/// Collects the events of `str`, skipping the values after the keys `skip`, and the array elements
+/// after `skip` strings.
+#[cfg(test)]
+fn collect_skip_events(
+    str: &str,
+    config: ParserConfig,
+) -> (Vec<ParseEventKind>, Option<ParseError>) {
+    let mut parser = parse_events_iter_with_config(str, config);
+    let mut events: Vec<ParseEventKind> = vec![];
+    loop {
+        match parser.next() {
+            None => return (events, None),
+            Some(Err(err)) => return (events, Some(err)),
+            Some(Ok(event)) => {
+                let skip = match event.kind {
+                    ParseEventKind::Str { size_in_bytes, .. } => {
+                        &str.as_bytes()[event.byte_offset..event.byte_offset + size_in_bytes]
+                            == b"skip"
+                    }
+                    _ => false,
+                };
+                events.push(event.kind);
+                if skip {
+                    if let Err(err) = parser.skip_value() {
+                        return (events, Some(err));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn skip_value_tests() {
+    let skip_events = |str: &str| collect_skip_events(str, ParserConfig::default());
+
+    let skip = || ParseEventKind::Str {
+        size_in_bytes: 4,
+        has_escapes: false,
+    };
+
+    let (events, error) = skip_events(r#"{"skip": {"a": [1, "]}", {}]}, "b": true}"#);
+    assert_eq!(
+        events,
+        vec![
+            ParseEventKind::StartObject,
+            skip(),
+            ParseEventKind::Str {
+                size_in_bytes: 1,
+                has_escapes: false,
+            },
+            ParseEventKind::Bool(true),
+            ParseEventKind::EndObject,
+        ]
+    );
+    assert_eq!(error, None);
+
+    let (events, error) = skip_events("[\"skip\" , [[tru], // ]\n], 1, \"skip\"]");
+    assert_eq!(
+        events,
+        vec![
+            ParseEventKind::StartArray,
+            skip(),
+            ParseEventKind::Int(1),
+            skip(),
+            ParseEventKind::EndArray,
+        ]
+    );
+    assert_eq!(error, None);
+
+    // Skipping scalars and comments before the value.
+    let (events, error) = skip_events("{\"skip\" // :\n: \"x\", \"a\": null}");
+    assert_eq!(events.len(), 5);
+    assert_eq!(events[3], ParseEventKind::Null);
+    assert_eq!(error, None);
+
+    // Errors in skipped values.
+    for (str, byte_offset) in [
+        (r#"{"skip": [1, 2}"#, 14),
+        (r#"{"skip": [1, "2]}"#, 13),
+        (r#"{"skip": [1, 2"#, 14),
+        (r#"{"skip": tru}"#, 9),
+        (r#"{"skip": }"#, 9),
+        (r#"["skip", ]"#, 9),
+    ] {
+        println!("Parsing {:?}", str);
+        let (_, error) = skip_events(str);
+        assert_eq!(error.unwrap().byte_offset, byte_offset);
+    }
+
+    // Errors after skipped values are generated as usual.
+    let (_, error) = skip_events(r#"{"skip" 1}"#);
+    assert_eq!(error.unwrap().byte_offset, 8);
+    let (_, error) = skip_events(r#"["skip" {}]"#);
+    assert_eq!(error.unwrap().byte_offset, 8);
+
+    // Skipping at the top level and at the end of a container.
+    let mut parser = parse_events_iter("[] ");
+    parser.skip_value().unwrap();
+    assert!(parser.next().is_none());
+    let mut parser = parse_events_iter("[]");
+    parser.next();
+    parser.skip_value().unwrap();
+    assert_eq!(
+        parser.next().unwrap().unwrap().kind,
+        ParseEventKind::EndArray
+    );
+}
+
+#[test]
+fn skip_value_utf8_tests() {
+    // Invalid UTF-8 in a skipped string or comment is reported, same as when it's not skipped.
+    for (bytes, byte_offset) in [
+        (&b"[[\"a\xff\"], 1]"[..], 4),
+        (b"[{\"a\": \"\\n\xc3\xa9\xe9\"}]", 12),
+        (b"[[// \xff\n]]", 5),
+    ] {
+        let error = Some(ParseError::new(byte_offset, ParseErrorKind::InvalidUtf8));
+        assert_eq!(
+            parse_events_iter_bytes(bytes).find_map(Result::err),
+            error,
+            "{:?}",
+            bytes
+        );
+
+        let mut parser = parse_events_iter_bytes(bytes);
+        assert_eq!(
+            parser.next().unwrap().unwrap().kind,
+            ParseEventKind::StartArray
+        );
+        assert_eq!(parser.skip_value().err(), error, "{:?}", bytes);
+    }
+
+    // An escaped non-ASCII character is valid UTF-8, even though it's not a valid escape.
+    let mut parser = parse_events_iter_bytes("[[\"\\é\"], 1]".as_bytes());
+    parser.next().unwrap().unwrap();
+    assert_eq!(parser.skip_value(), Ok(()));
+    assert_eq!(parser.next().unwrap().unwrap().kind, ParseEventKind::Int(1));
+}
+
+#[test]
+fn skip_value_limit_tests() {
+    let config = ParserConfig {
+        max_container_size: 2,
+        ..ParserConfig::default()
+    };
+
+    // Skipped values are not checked.
+    let (events, error) = collect_skip_events(r#"{"skip": [1, 2, 3], "a": 1}"#, config);
+    assert_eq!(events.len(), 5);
+    assert_eq!(error, None);
+
+    // Skipped values count as elements of their containers.
+    for (str, byte_offset) in [
+        (r#"{"skip": [], "a": 1, "b": 2}"#, 21),
+        (r#"["skip", 1, 2]"#, 12),
+        (r#"[1, "skip", 2]"#, 12),
+    ] {
+        println!("Parsing {:?}", str);
+        let (_, error) = collect_skip_events(str, config);
+        assert_eq!(
+            error,
+            Some(ParseError::new(
+                byte_offset,
+                ParseErrorKind::ContainerTooLarge
+            ))
+        );
+    }
+
+    // The input size is checked before skipping.
+    let config = ParserConfig {
+        max_input_size: 2,
+        ..ParserConfig::default()
+    };
+    let mut parser = parse_events_iter_with_config("[1, 2]", config);
+    assert_eq!(
+        parser.skip_value(),
+        Err(ParseError::new(2, ParseErrorKind::InputTooLarge))
+    );
+    assert!(parser.next().is_none());
+}
@@ -1,14 +1,29 @@
 use crate::event_parser::{Container, ParserState};
-use crate::{ParseError, ParseEvent, ParseEventKind, Token};
+use crate::limits::IterLimits;
+use crate::{
+    Expected, ParseError, ParseErrorKind, ParseEvent, ParseEventKind, ParserConfig, Token,
+};
 
-type Item = Result<(usize, Token), usize>;
+type Item = Result<(usize, Token), ParseError>;
 
 /// Parses input to [ParseEvent]s.
 pub fn parse_events_iter_using_lexer_iter<I: Iterator<Item = Item>>(
     lexer: I,
     input_size: usize,
 ) -> EventParser<I> {
-    EventParser::new(lexer, input_size)
+    EventParser::new(lexer, input_size, ParserConfig::default())
+}
+
+/// Same as [parse_events_iter_using_lexer_iter], but with the given configuration.
+///
+/// Numbers are scanned by the lexer, so [ParserConfig::int_overflow] should be passed to the
+/// lexer.
+pub fn parse_events_iter_using_lexer_iter_with_config<I: Iterator<Item = Item>>(
+    lexer: I,
+    input_size: usize,
+    config: ParserConfig,
+) -> EventParser<I> {
+    EventParser::new(lexer, input_size, config)
 }
 
 pub struct EventParser<I: Iterator<Item = Item>> {
@@ -16,15 +31,22 @@ pub struct EventParser<I: Iterator<Item = Item>> {
     container_stack: Vec<Container>,
     state: ParserState,
     input_size: usize,
+    max_depth: usize,
+
+    /// Checks the resource limits in the configuration, when it has limits other than the nesting
+    /// depth.
+    limits: Option<IterLimits>,
 }
 
 impl<I: Iterator<Item = Item>> EventParser<I> {
-    fn new(lexer: I, input_size: usize) -> EventParser<I> {
+    fn new(lexer: I, input_size: usize, config: ParserConfig) -> EventParser<I> {
         EventParser {
             lexer,
             container_stack: vec![],
             state: ParserState::TopLevel,
             input_size,
+            max_depth: config.max_depth,
+            limits: IterLimits::new(config, input_size),
         }
     }
 }
@@ -33,11 +55,26 @@ impl<I: Iterator<Item = Item>> Iterator for EventParser<I> {
     type Item = Result<ParseEvent, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        match self.limits.take() {
+            None => self.next_unchecked(),
+            Some(mut limits) => {
+                let event = limits.next(|| self.next_unchecked());
+                self.limits = Some(limits);
+                event
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = Item>> EventParser<I> {
+    fn next_unchecked(&mut self) -> Option<Result<ParseEvent, ParseError>> {
         match self.state {
-            ParserState::TopLevel => self.top_level(),
+            ParserState::TopLevel => self.top_level(Expected::Value),
+            ParserState::ArrayStart => self.top_level(Expected::ValueOrEndArray),
             ParserState::Done => self.done(),
             ParserState::ObjectExpectComma => self.object_expect_comma(),
-            ParserState::ObjectExpectKeyValue => self.object_expect_key_value(),
+            ParserState::ObjectExpectKeyValue => self.object_expect_key(Expected::KeyOrEndObject),
+            ParserState::ObjectExpectKey => self.object_expect_key(Expected::Key),
             ParserState::ObjectExpectColon => self.object_expect_colon(),
             ParserState::ArrayExpectComma => self.array_expect_comma(),
         }
@@ -45,21 +82,27 @@ impl<I: Iterator<Item = Item>> Iterator for EventParser<I> {
 }
 
 impl<I: Iterator<Item = Item>> EventParser<I> {
-    fn top_level(&mut self) -> Option<Result<ParseEvent, ParseError>> {
+    /// Parses a value. `expected` is used in errors, and when it's [Expected::ValueOrEndArray] a
+    /// ']' finishes the current array.
+    fn top_level(&mut self, expected: Expected) -> Option<Result<ParseEvent, ParseError>> {
         match self.lexer.next() {
             Some(Ok((byte_offset, t))) => match t {
+                Token::LBracket | Token::LBrace if self.container_stack.len() == self.max_depth => {
+                    Some(Err(ParseError::new(
+                        byte_offset,
+                        ParseErrorKind::NestingTooDeep,
+                    )))
+                }
+
                 Token::LBracket => {
                     self.container_stack.push(Container::Array);
+                    self.state = ParserState::ArrayStart;
                     Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::StartArray)))
                 }
 
-                Token::RBracket => match self.pop_array(byte_offset) {
-                    Ok(()) => {
-                        self.update_state();
-                        Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::EndArray)))
-                    }
-                    Err(err) => Some(Err(err)),
-                },
+                Token::RBracket if expected == Expected::ValueOrEndArray => {
+                    self.end_array(byte_offset)
+                }
 
                 Token::LBrace => {
                     self.container_stack.push(Container::Object);
@@ -93,12 +136,36 @@ impl<I: Iterator<Item = Item>> EventParser<I> {
                     Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::Int(i))))
                 }
 
-                Token::Str { size_in_bytes } => {
+                Token::NegInt(i) => {
+                    self.update_state();
+                    Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::NegInt(i))))
+                }
+
+                Token::Float(f) => {
                     self.update_state();
+                    Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::Float(f))))
+                }
 
+                Token::RawNumber { size_in_bytes } => {
+                    self.update_state();
                     Some(Ok(ParseEvent::new(
                         byte_offset,
-                        ParseEventKind::Str { size_in_bytes },
+                        ParseEventKind::RawNumber { size_in_bytes },
+                    )))
+                }
+
+                Token::Str {
+                    size_in_bytes,
+                    has_escapes,
+                } => {
+                    self.update_state();
+
+                    Some(Ok(ParseEvent::new(
+                        byte_offset,
+                        ParseEventKind::Str {
+                            size_in_bytes,
+                            has_escapes,
+                        },
                     )))
                 }
 
@@ -107,21 +174,17 @@ impl<I: Iterator<Item = Item>> EventParser<I> {
                     ParseEventKind::Comment { size_in_bytes },
                 ))),
 
-                Token::RBrace | Token::Colon | Token::Comma => Some(Err(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                })),
+                Token::RBracket | Token::RBrace | Token::Colon | Token::Comma => Some(Err(
+                    ParseError::unexpected_token(t.start_offset(byte_offset), expected, t.kind()),
+                )),
             },
 
-            Some(Err(byte_offset)) => Some(Err(ParseError {
-                byte_offset,
-                reason: "invalid token",
-            })),
+            Some(Err(err)) => Some(Err(err)),
 
-            None => Some(Err(ParseError {
-                byte_offset: self.input_size,
-                reason: "unexpected end of input",
-            })),
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.input_size,
+                expected,
+            ))),
         }
     }
 
@@ -132,15 +195,17 @@ impl<I: Iterator<Item = Item>> EventParser<I> {
                     byte_offset,
                     ParseEventKind::Comment { size_in_bytes },
                 ))),
-                _ => Some(Err(ParseError {
-                    byte_offset,
-                    reason: "trailing tokens",
-                })),
+                _ => Some(Err(ParseError::new(
+                    t.start_offset(byte_offset),
+                    ParseErrorKind::TrailingCharacters,
+                ))),
             },
-            Some(Err(byte_offset)) => Some(Err(ParseError {
-                byte_offset,
-                reason: "invalid token",
-            })),
+            // Same as the parsers that don't use a lexer: anything other than trivia after the
+            // value is trailing characters, even when it's not a valid token.
+            Some(Err(err)) => Some(Err(ParseError::new(
+                err.byte_offset,
+                ParseErrorKind::TrailingCharacters,
+            ))),
             None => None,
         }
     }
@@ -153,53 +218,49 @@ impl<I: Iterator<Item = Item>> EventParser<I> {
                     self.next()
                 }
 
-                Token::RBracket => match self.pop_array(byte_offset) {
-                    Ok(()) => {
-                        self.update_state();
-                        Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::EndArray)))
-                    }
-                    Err(err) => Some(Err(err)),
-                },
+                Token::RBracket => self.end_array(byte_offset),
 
                 Token::Comment { size_in_bytes } => Some(Ok(ParseEvent::new(
                     byte_offset,
                     ParseEventKind::Comment { size_in_bytes },
                 ))),
 
-                _ => Some(Err(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                })),
+                _ => Some(Err(ParseError::unexpected_token(
+                    t.start_offset(byte_offset),
+                    Expected::CommaOrEndArray,
+                    t.kind(),
+                ))),
             },
 
-            Some(Err(byte_offset)) => Some(Err(ParseError {
-                byte_offset,
-                reason: "invalid token",
-            })),
+            Some(Err(err)) => Some(Err(err)),
 
-            None => Some(Err(ParseError {
-                byte_offset: self.input_size,
-                reason: "unexpected end of input",
-            })),
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.input_size,
+                Expected::CommaOrEndArray,
+            ))),
         }
     }
 
-    fn object_expect_key_value(&mut self) -> Option<Result<ParseEvent, ParseError>> {
+    /// Parses an object key. `expected` is [Expected::KeyOrEndObject] when the object can be
+    /// finished with a '}', [Expected::Key] otherwise.
+    fn object_expect_key(&mut self, expected: Expected) -> Option<Result<ParseEvent, ParseError>> {
         match self.lexer.next() {
             Some(Ok((byte_offset, t))) => match t {
-                Token::RBrace => match self.pop_object(byte_offset) {
-                    Ok(()) => {
-                        self.update_state();
-                        Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::EndObject)))
-                    }
-                    Err(err) => Some(Err(err)),
-                },
-
-                Token::Str { size_in_bytes } => {
+                Token::RBrace if expected == Expected::KeyOrEndObject => {
+                    self.end_object(byte_offset)
+                }
+
+                Token::Str {
+                    size_in_bytes,
+                    has_escapes,
+                } => {
                     self.state = ParserState::ObjectExpectColon;
                     Some(Ok(ParseEvent::new(
                         byte_offset,
-                        ParseEventKind::Str { size_in_bytes },
+                        ParseEventKind::Str {
+                            size_in_bytes,
+                            has_escapes,
+                        },
                     )))
                 }
 
@@ -208,21 +269,19 @@ impl<I: Iterator<Item = Item>> EventParser<I> {
                     ParseEventKind::Comment { size_in_bytes },
                 ))),
 
-                _ => Some(Err(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                })),
+                _ => Some(Err(ParseError::unexpected_token(
+                    t.start_offset(byte_offset),
+                    expected,
+                    t.kind(),
+                ))),
             },
 
-            Some(Err(byte_offset)) => Some(Err(ParseError {
-                byte_offset,
-                reason: "invalid token",
-            })),
+            Some(Err(err)) => Some(Err(err)),
 
-            None => Some(Err(ParseError {
-                byte_offset: self.input_size,
-                reason: "unexpected end of input",
-            })),
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.input_size,
+                expected,
+            ))),
         }
     }
 
@@ -239,21 +298,19 @@ impl<I: Iterator<Item = Item>> EventParser<I> {
                     ParseEventKind::Comment { size_in_bytes },
                 ))),
 
-                _ => Some(Err(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                })),
+                _ => Some(Err(ParseError::unexpected_token(
+                    t.start_offset(byte_offset),
+                    Expected::Colon,
+                    t.kind(),
+                ))),
             },
 
-            Some(Err(byte_offset)) => Some(Err(ParseError {
-                byte_offset,
-                reason: "invalid token",
-            })),
+            Some(Err(err)) => Some(Err(err)),
 
-            None => Some(Err(ParseError {
-                byte_offset: self.input_size,
-                reason: "unexpected end of input",
-            })),
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.input_size,
+                Expected::Colon,
+            ))),
         }
     }
 
@@ -261,38 +318,30 @@ impl<I: Iterator<Item = Item>> EventParser<I> {
         match self.lexer.next() {
             Some(Ok((byte_offset, t))) => match t {
                 Token::Comma => {
-                    self.state = ParserState::ObjectExpectKeyValue;
+                    self.state = ParserState::ObjectExpectKey;
                     self.next()
                 }
 
-                Token::RBrace => match self.pop_object(byte_offset) {
-                    Ok(()) => {
-                        self.update_state();
-                        Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::EndObject)))
-                    }
-                    Err(err) => Some(Err(err)),
-                },
+                Token::RBrace => self.end_object(byte_offset),
 
                 Token::Comment { size_in_bytes } => Some(Ok(ParseEvent::new(
                     byte_offset,
                     ParseEventKind::Comment { size_in_bytes },
                 ))),
 
-                _ => Some(Err(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                })),
+                _ => Some(Err(ParseError::unexpected_token(
+                    t.start_offset(byte_offset),
+                    Expected::CommaOrEndObject,
+                    t.kind(),
+                ))),
             },
 
-            Some(Err(byte_offset)) => Some(Err(ParseError {
-                byte_offset,
-                reason: "invalid token",
-            })),
+            Some(Err(err)) => Some(Err(err)),
 
-            None => Some(Err(ParseError {
-                byte_offset: self.input_size,
-                reason: "unexpected end of input",
-            })),
+            None => Some(Err(ParseError::unexpected_end_of_input(
+                self.input_size,
+                Expected::CommaOrEndObject,
+            ))),
         }
     }
 
@@ -304,26 +353,18 @@ impl<I: Iterator<Item = Item>> EventParser<I> {
         };
     }
 
-    fn pop_array(&mut self, byte_offset: usize) -> Result<(), ParseError> {
-        match self.container_stack.pop() {
-            Some(Container::Array) => Ok(()),
-
-            _ => Err(ParseError {
-                byte_offset,
-                reason: "unexpected ']'",
-            }),
-        }
+    fn end_array(&mut self, byte_offset: usize) -> Option<Result<ParseEvent, ParseError>> {
+        let container = self.container_stack.pop();
+        debug_assert_eq!(container, Some(Container::Array));
+        self.update_state();
+        Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::EndArray)))
     }
 
-    fn pop_object(&mut self, byte_offset: usize) -> Result<(), ParseError> {
-        match self.container_stack.pop() {
-            Some(Container::Object) => Ok(()),
-
-            _ => Err(ParseError {
-                byte_offset,
-                reason: "unexpected '}'",
-            }),
-        }
+    fn end_object(&mut self, byte_offset: usize) -> Option<Result<ParseEvent, ParseError>> {
+        let container = self.container_stack.pop();
+        debug_assert_eq!(container, Some(Container::Object));
+        self.update_state();
+        Some(Ok(ParseEvent::new(byte_offset, ParseEventKind::EndObject)))
     }
 }
 
@@ -365,3 +406,58 @@ fn event_to_tree_random_tests() {
         assert_eq!(event_ast, ast);
     }
 }
+
+#[test]
+fn int_overflow_tests() {
+    for (str, byte_offset, ast) in crate::test_common::int_overflow_tests() {
+        println!("Parsing {:?}", str);
+        let mut parser = parse_events_iter_using_lexer_iter(crate::tokenize_iter(&str), str.len());
+        let err = crate::event_to_tree(&mut parser, &str).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::new(byte_offset, crate::ParseErrorKind::IntegerOverflow)
+        );
+
+        let config = crate::test_common::raw_int_config();
+        let lexer = crate::Lexer::with_config(&str, config);
+        let mut parser = parse_events_iter_using_lexer_iter(lexer, str.len());
+        assert_eq!(crate::event_to_tree(&mut parser, &str).unwrap(), ast);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        let error_ = parse_events_iter_using_lexer_iter(crate::tokenize_iter(&str), str.len())
+            .find_map(Result::err);
+        assert_eq!(error_, Some(error));
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        let error_ = parse_events_iter_using_lexer_iter_with_config(
+            crate::tokenize_iter(&str),
+            str.len(),
+            config,
+        )
+        .find_map(Result::err);
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn limit_tests() {
+    for (str, config, error) in crate::test_common::limit_tests() {
+        println!("Parsing {:?}", str);
+        let error_ = parse_events_iter_using_lexer_iter_with_config(
+            crate::tokenize_iter(&str),
+            str.len(),
+            config,
+        )
+        .find_map(Result::err);
+        assert_eq!(error_, error);
+    }
+}
@@ -0,0 +1,559 @@
+use crate::event_push_parser_chunked::ChunkedPushParser;
+use crate::{unescape, ParseError, ParseEvent, ParseEventKind, ParserConfig, PushToEvents};
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+
+/// Size of the buffer used by [parse_events_read] and [parse_events_read_with_config].
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 64 * 1024;
+
+pub fn parse_events_read<R: io::Read>(reader: R) -> ReadEventParser<R> {
+    ReadEventParser::new(reader, ParserConfig::default(), DEFAULT_READ_BUFFER_SIZE)
+}
+
+pub fn parse_events_read_with_config<R: io::Read>(
+    reader: R,
+    config: ParserConfig,
+) -> ReadEventParser<R> {
+    ReadEventParser::new(reader, config, DEFAULT_READ_BUFFER_SIZE)
+}
+
+/// An event parser that reads the input from a [io::Read].
+///
+/// The input is read in fixed-size chunks, and only the chunk being parsed is kept in memory,
+/// plus the token at the end of the previous chunk when the token continues in the next chunk.
+///
+/// The events have the text of the tokens, so a token is kept whole, and the buffer grows with the
+/// longest string, number, or comment in the input. Set [ParserConfig::max_string_size] to bound
+/// the memory used for strings: it's checked as the string is read, and parsing fails as soon as a
+/// string is longer than the limit. Numbers and comments don't have a size limit, other than
+/// [ParserConfig::max_input_size].
+///
+/// Since the input is not available after parsing, events have the text of the strings, numbers
+/// and comments instead of their sizes. [ReadEventParser::next_event] returns the text borrowed
+/// from the buffer when possible. The [Iterator] implementation returns owned text.
+///
+/// After an error the parser returns `None`.
+pub struct ReadEventParser<R: io::Read> {
+    reader: R,
+
+    parser: ChunkedPushParser<PushToEvents>,
+
+    /// Events parsed from the last read, to be returned.
+    events: std::vec::IntoIter<ParseEvent>,
+
+    /// Error to return after `events`.
+    error: Option<ParseError>,
+
+    /// Number of bytes to read at once.
+    read_size: usize,
+
+    /// Input starting at `buffer_offset`. Has the bytes of the last read, and the bytes of the
+    /// token that was not complete at the end of the previous read.
+    buffer: Vec<u8>,
+
+    /// Byte offset of the first byte in `buffer`, in the whole input.
+    buffer_offset: usize,
+
+    /// Whether we've read the whole input or failed.
+    done: bool,
+}
+
+/// A parse event read by [ReadEventParser].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadEvent<'a> {
+    pub kind: ReadEventKind<'a>,
+    pub byte_offset: usize,
+}
+
+/// Details of a [ReadEvent]. Same as [ParseEventKind], but with the text of strings, raw numbers
+/// and comments instead of their sizes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadEventKind<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Int(u64),
+    NegInt(i64),
+    Float(f64),
+    RawNumber(Cow<'a, str>),
+
+    /// A string, with the escape sequences decoded.
+    Str(Cow<'a, str>),
+
+    Bool(bool),
+    Null,
+
+    /// A comment, including the "//" at the beginning and newline at the end.
+    Comment(Cow<'a, str>),
+}
+
+/// Errors reported by [ReadEventParser].
+#[derive(Debug)]
+pub enum ReadError {
+    /// The input could not be parsed.
+    Parse(ParseError),
+
+    /// The input could not be read.
+    Io(io::Error),
+}
+
+impl<R: io::Read> ReadEventParser<R> {
+    /// Creates a parser that reads `read_size` bytes at once.
+    ///
+    /// Panics if `read_size` is 0.
+    pub fn new(reader: R, config: ParserConfig, read_size: usize) -> ReadEventParser<R> {
+        assert!(read_size != 0);
+        ReadEventParser {
+            reader,
+            parser: ChunkedPushParser::with_config(PushToEvents::new(), config),
+            events: vec![].into_iter(),
+            error: None,
+            read_size,
+            buffer: Vec::with_capacity(read_size),
+            buffer_offset: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the next event, with the text borrowed from the parser's buffer when the text does
+    /// not have escape sequences.
+    pub fn next_event(&mut self) -> Option<Result<ReadEvent<'_>, ReadError>> {
+        loop {
+            if let Some(event) = self.events.next() {
+                return Some(self.read_event(event));
+            }
+
+            if let Some(error) = self.error.take() {
+                self.done = true;
+                return Some(Err(ReadError::Parse(error)));
+            }
+
+            if self.done {
+                return None;
+            }
+
+            if let Err(error) = self.read() {
+                self.done = true;
+                return Some(Err(ReadError::Io(error)));
+            }
+        }
+    }
+
+    /// Reads and parses the next chunk of the input.
+    fn read(&mut self) -> io::Result<()> {
+        // Events of the previous read are returned, only keep the incomplete token.
+        let buffer_end = self.buffer_offset + self.buffer.len();
+        let keep_from = self.parser.token_start().unwrap_or(buffer_end);
+        self.buffer.drain(..keep_from - self.buffer_offset);
+        self.buffer_offset = keep_from;
+
+        let read_start = self.buffer.len();
+        self.buffer.resize(read_start + self.read_size, 0);
+
+        let result = loop {
+            match self.reader.read(&mut self.buffer[read_start..]) {
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+
+        match result {
+            Ok(0) => {
+                self.buffer.truncate(read_start);
+                let Ok(()) = self.parser.end_input();
+                self.done = true;
+            }
+            Ok(n) => {
+                self.buffer.truncate(read_start + n);
+                let Ok(()) = self.parser.feed(&self.buffer[read_start..]);
+            }
+            Err(error) => {
+                self.buffer.truncate(read_start);
+                return Err(error);
+            }
+        }
+
+        let (events, error) = self.parser.listener_mut().take_events();
+        self.events = events.into_iter();
+        self.error = error;
+        Ok(())
+    }
+
+    /// Text of the input at `byte_offset`. The text should be in the buffer.
+    fn text(&self, byte_offset: usize, size_in_bytes: usize) -> &str {
+        let start = byte_offset - self.buffer_offset;
+        // The parser validates UTF-8 of strings and comments, and numbers are ASCII.
+        std::str::from_utf8(&self.buffer[start..start + size_in_bytes]).unwrap()
+    }
+
+    fn read_event(&mut self, event: ParseEvent) -> Result<ReadEvent<'_>, ReadError> {
+        let ParseEvent { kind, byte_offset } = event;
+        let kind = match kind {
+            ParseEventKind::StartObject => ReadEventKind::StartObject,
+            ParseEventKind::EndObject => ReadEventKind::EndObject,
+            ParseEventKind::StartArray => ReadEventKind::StartArray,
+            ParseEventKind::EndArray => ReadEventKind::EndArray,
+            ParseEventKind::Int(i) => ReadEventKind::Int(i),
+            ParseEventKind::NegInt(i) => ReadEventKind::NegInt(i),
+            ParseEventKind::Float(f) => ReadEventKind::Float(f),
+            ParseEventKind::RawNumber { size_in_bytes } => {
+                ReadEventKind::RawNumber(Cow::Borrowed(self.text(byte_offset, size_in_bytes)))
+            }
+            ParseEventKind::Str {
+                size_in_bytes,
+                has_escapes,
+            } => {
+                if has_escapes {
+                    match unescape(self.text(byte_offset, size_in_bytes), byte_offset) {
+                        Ok(str) => ReadEventKind::Str(Cow::Owned(str)),
+                        Err(error) => {
+                            // Stop after the error, same as parse errors.
+                            self.events = vec![].into_iter();
+                            self.error = None;
+                            self.done = true;
+                            return Err(ReadError::Parse(error));
+                        }
+                    }
+                } else {
+                    ReadEventKind::Str(Cow::Borrowed(self.text(byte_offset, size_in_bytes)))
+                }
+            }
+            ParseEventKind::Bool(b) => ReadEventKind::Bool(b),
+            ParseEventKind::Null => ReadEventKind::Null,
+            ParseEventKind::Comment { size_in_bytes } => {
+                ReadEventKind::Comment(Cow::Borrowed(self.text(byte_offset, size_in_bytes)))
+            }
+        };
+        Ok(ReadEvent { kind, byte_offset })
+    }
+}
+
+impl<R: io::Read> Iterator for ReadEventParser<R> {
+    type Item = Result<ReadEvent<'static>, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event()
+            .map(|result| result.map(ReadEvent::into_owned))
+    }
+}
+
+impl<'a> ReadEvent<'a> {
+    /// Copies the borrowed text, if there is any.
+    pub fn into_owned(self) -> ReadEvent<'static> {
+        let owned = |str: Cow<'a, str>| Cow::Owned(str.into_owned());
+        let kind = match self.kind {
+            ReadEventKind::StartObject => ReadEventKind::StartObject,
+            ReadEventKind::EndObject => ReadEventKind::EndObject,
+            ReadEventKind::StartArray => ReadEventKind::StartArray,
+            ReadEventKind::EndArray => ReadEventKind::EndArray,
+            ReadEventKind::Int(i) => ReadEventKind::Int(i),
+            ReadEventKind::NegInt(i) => ReadEventKind::NegInt(i),
+            ReadEventKind::Float(f) => ReadEventKind::Float(f),
+            ReadEventKind::RawNumber(str) => ReadEventKind::RawNumber(owned(str)),
+            ReadEventKind::Str(str) => ReadEventKind::Str(owned(str)),
+            ReadEventKind::Bool(b) => ReadEventKind::Bool(b),
+            ReadEventKind::Null => ReadEventKind::Null,
+            ReadEventKind::Comment(str) => ReadEventKind::Comment(owned(str)),
+        };
+        ReadEvent {
+            kind,
+            byte_offset: self.byte_offset,
+        }
+    }
+}
+
+impl From<ParseError> for ReadError {
+    fn from(error: ParseError) -> Self {
+        ReadError::Parse(error)
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(error: io::Error) -> Self {
+        ReadError::Io(error)
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Parse(error) => write!(f, "{}", error),
+            ReadError::Io(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Parse(error) => Some(error),
+            ReadError::Io(error) => Some(error),
+        }
+    }
+}
+
+/// A reader that returns at most `max_read` bytes at once, and fails with `Interrupted` before
+/// every read.
+#[cfg(test)]
+struct SlowReader<'a> {
+    input: &'a [u8],
+    max_read: usize,
+    interrupt: bool,
+}
+
+#[cfg(test)]
+impl<'a> io::Read for SlowReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let n = buf.len().min(self.max_read).min(self.input.len());
+        buf[..n].copy_from_slice(&self.input[..n]);
+        self.input = &self.input[n..];
+        Ok(n)
+    }
+}
+
+/// Events of [parse_events_iter][crate::parse_events_iter], converted to [ReadEvent]s.
+#[cfg(test)]
+fn expected_events(input: &str) -> Vec<Result<ReadEvent<'static>, ParseError>> {
+    let mut events = vec![];
+    for event in crate::parse_events_iter(input) {
+        let ParseEvent { kind, byte_offset } = match event {
+            Ok(event) => event,
+            Err(error) => {
+                events.push(Err(error));
+                break;
+            }
+        };
+        let text =
+            |size_in_bytes: usize| input[byte_offset..byte_offset + size_in_bytes].to_owned();
+        let kind = match kind {
+            ParseEventKind::StartObject => ReadEventKind::StartObject,
+            ParseEventKind::EndObject => ReadEventKind::EndObject,
+            ParseEventKind::StartArray => ReadEventKind::StartArray,
+            ParseEventKind::EndArray => ReadEventKind::EndArray,
+            ParseEventKind::Int(i) => ReadEventKind::Int(i),
+            ParseEventKind::NegInt(i) => ReadEventKind::NegInt(i),
+            ParseEventKind::Float(f) => ReadEventKind::Float(f),
+            ParseEventKind::RawNumber { size_in_bytes } => {
+                ReadEventKind::RawNumber(Cow::Owned(text(size_in_bytes)))
+            }
+            ParseEventKind::Str { size_in_bytes, .. } => ReadEventKind::Str(Cow::Owned(
+                unescape(&text(size_in_bytes), byte_offset).unwrap(),
+            )),
+            ParseEventKind::Bool(b) => ReadEventKind::Bool(b),
+            ParseEventKind::Null => ReadEventKind::Null,
+            ParseEventKind::Comment { size_in_bytes } => {
+                ReadEventKind::Comment(Cow::Owned(text(size_in_bytes)))
+            }
+        };
+        events.push(Ok(ReadEvent { kind, byte_offset }));
+    }
+    events
+}
+
+#[cfg(test)]
+fn read_events(
+    input: &str,
+    read_size: usize,
+    max_read: usize,
+) -> Vec<Result<ReadEvent<'static>, ParseError>> {
+    let reader = SlowReader {
+        input: input.as_bytes(),
+        max_read,
+        interrupt: false,
+    };
+    ReadEventParser::new(reader, ParserConfig::default(), read_size)
+        .map(|result| {
+            result.map_err(|error| match error {
+                ReadError::Parse(error) => error,
+                ReadError::Io(error) => panic!("{}", error),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn check_read(input: &str) {
+    let expected = expected_events(input);
+    for (read_size, max_read) in [(1, 1), (2, 2), (7, 3), (4096, 5), (4096, 4096)] {
+        assert_eq!(
+            read_events(input, read_size, max_read),
+            expected,
+            "{:?} {} {}",
+            input,
+            read_size,
+            max_read
+        );
+    }
+}
+
+#[test]
+fn ast_tests() {
+    for (str, _) in crate::test_common::ast_tests() {
+        check_read(&str);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, _) in crate::test_common::error_tests() {
+        check_read(&str);
+    }
+}
+
+#[test]
+fn random_tests() {
+    for input_size in [10, 100, 1_000, 10_000, 100_000] {
+        check_read(&crate::gen_input(input_size));
+    }
+}
+
+#[test]
+fn borrowed_text_test() {
+    let input = r#"["abc", "a\nb", 123456789012345678901234567890] // end"#;
+    let config = ParserConfig {
+        int_overflow: crate::IntOverflow::Raw,
+        ..crate::ParserConfig::default()
+    };
+    let mut parser = parse_events_read_with_config(input.as_bytes(), config);
+    let mut kinds = vec![];
+    while let Some(event) = parser.next_event() {
+        let kind = event.unwrap().kind;
+        let borrowed = match &kind {
+            ReadEventKind::Str(str)
+            | ReadEventKind::RawNumber(str)
+            | ReadEventKind::Comment(str) => Some(matches!(str, Cow::Borrowed(_))),
+            _ => None,
+        };
+        kinds.push((kind.clone().into_owned_kind(), borrowed));
+    }
+    assert_eq!(
+        kinds,
+        vec![
+            (ReadEventKind::StartArray, None),
+            (ReadEventKind::Str(Cow::Borrowed("abc")), Some(true)),
+            (ReadEventKind::Str(Cow::Borrowed("a\nb")), Some(false)),
+            (
+                ReadEventKind::RawNumber(Cow::Borrowed("123456789012345678901234567890")),
+                Some(true)
+            ),
+            (ReadEventKind::EndArray, None),
+            (ReadEventKind::Comment(Cow::Borrowed("// end")), Some(true)),
+        ]
+    );
+}
+
+#[cfg(test)]
+impl ReadEventKind<'_> {
+    fn into_owned_kind(self) -> ReadEventKind<'static> {
+        ReadEvent {
+            kind: self,
+            byte_offset: 0,
+        }
+        .into_owned()
+        .kind
+    }
+}
+
+#[test]
+fn io_error_test() {
+    /// Returns the input, then fails.
+    struct FailingReader<'a>(&'a [u8]);
+
+    impl<'a> io::Read for FailingReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::other("connection reset"));
+            }
+            let n = buf.len().min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let mut parser = ReadEventParser::new(FailingReader(b"[1, 2"), ParserConfig::default(), 4);
+    assert_eq!(
+        parser.next().unwrap().unwrap().kind,
+        ReadEventKind::StartArray
+    );
+    assert_eq!(parser.next().unwrap().unwrap().kind, ReadEventKind::Int(1));
+    // "2" may continue in the next read, so it's not reported.
+    assert!(matches!(parser.next(), Some(Err(ReadError::Io(_)))));
+    assert!(parser.next().is_none());
+}
+
+#[test]
+fn buffer_size_test() {
+    // Buffer only grows for long tokens.
+    let input = format!("[{}\"{}\"]", "1, ".repeat(10_000), "a".repeat(1_000));
+    let mut parser = ReadEventParser::new(input.as_bytes(), ParserConfig::default(), 16);
+    let mut max_buffer_size = 0;
+    while let Some(event) = parser.next_event() {
+        event.unwrap();
+        max_buffer_size = max_buffer_size.max(parser.buffer.len());
+    }
+    assert!(max_buffer_size <= 1_000 + 2 + 16, "{}", max_buffer_size);
+}
+
+#[test]
+fn long_string_limit_test() {
+    // An unterminated string much longer than the limit fails without reading the whole string.
+    let input = format!("[\"{}", "a".repeat(1_000_000));
+    let config = ParserConfig {
+        max_string_size: 10,
+        ..ParserConfig::default()
+    };
+    let mut parser = ReadEventParser::new(input.as_bytes(), config, 4096);
+    assert_eq!(
+        parser.next_event().unwrap().unwrap().kind,
+        ReadEventKind::StartArray
+    );
+    match parser.next_event() {
+        Some(Err(ReadError::Parse(error))) => {
+            assert_eq!(
+                error,
+                ParseError::new(1, crate::ParseErrorKind::StringTooLong)
+            )
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(parser.next_event().is_none());
+    assert!(parser.buffer.len() <= 4096, "{}", parser.buffer.len());
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        let error_ =
+            parse_events_read_with_config(str.as_bytes(), config).find_map(|event| match event {
+                Ok(_) => None,
+                Err(ReadError::Parse(err)) => Some(err),
+                Err(ReadError::Io(err)) => panic!("{}", err),
+            });
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn limit_tests() {
+    let tests = crate::test_common::limit_tests()
+        .into_iter()
+        .chain(crate::test_common::non_ascii_input_size_tests());
+    for (str, config, error) in tests {
+        println!("Parsing {:?}", str);
+        let error_ =
+            parse_events_read_with_config(str.as_bytes(), config).find_map(|event| match event {
+                Ok(_) => None,
+                Err(ReadError::Parse(err)) => Some(err),
+                Err(ReadError::Io(err)) => panic!("{}", err),
+            });
+        assert_eq!(error_, error);
+    }
+}
@@ -1,26 +1,78 @@
-use crate::{EventListener, ParseError};
+use crate::escape::scan_string;
+use crate::lexer::{invalid_token, scan_string_error, skip_container};
+use crate::limits::LimitListener;
+use crate::number::{scan_number, skip_until, Number};
+use crate::{
+    EventListener, Expected, ListenerAction, ParseError, ParseErrorKind, ParserConfig, TokenKind,
+};
 
 use std::iter::Peekable;
 use std::str::CharIndices;
 
 /// Parse input to events, call [EventListener] callbacks with the events.
-pub fn parse<L: EventListener>(input: &str, listener: &mut L) {
+///
+/// Parse errors are passed to [EventListener::handle_error]. Returns the error of the listener's
+/// [ListenerAction::Abort], if the listener aborts parsing.
+pub fn parse<L: EventListener>(input: &str, listener: &mut L) -> Result<(), L::Error> {
+    parse_with_config(input, ParserConfig::default(), listener)
+}
+
+/// Same as [parse], but with the given configuration.
+pub fn parse_with_config<L: EventListener>(
+    input: &str,
+    config: ParserConfig,
+    listener: &mut L,
+) -> Result<(), L::Error> {
+    let listener = &mut LimitListener::new(listener, config);
+    parse_value(input, config, listener);
+    listener.take_result()
+}
+
+/// Same as [parse], but the input does not have to be valid UTF-8. UTF-8 is validated in strings
+/// and comments.
+pub fn parse_bytes<L: EventListener>(input: &[u8], listener: &mut L) -> Result<(), L::Error> {
+    parse_bytes_with_config(input, ParserConfig::default(), listener)
+}
+
+/// Same as [parse_bytes], but with the given configuration.
+///
+/// This parser scans `char`s, so byte input is parsed with
+/// [parse_events_push_using_lexer_iter_bytes][crate::parse_events_push_using_lexer_iter_bytes],
+/// which tokenizes with the UTF-8-validating [Lexer][crate::Lexer].
+pub fn parse_bytes_with_config<L: EventListener>(
+    input: &[u8],
+    config: ParserConfig,
+    listener: &mut L,
+) -> Result<(), L::Error> {
+    crate::event_push_parser_3::parse_bytes_with_config(input, listener, config)
+}
+
+fn parse_value<L: EventListener>(
+    input: &str,
+    config: ParserConfig,
+    listener: &mut LimitListener<L>,
+) {
+    if !listener.check_input(input.as_bytes()) {
+        return;
+    }
+
     let mut iter = input.char_indices().peekable();
-    let input_size = input.len();
 
-    if !parse_single(&mut iter, input_size, listener) {
+    if !parse_single(&mut iter, input, config, 0, Expected::Value, listener) {
         return;
     }
 
-    skip_trivia(&mut iter, listener);
+    if !skip_trivia(&mut iter, listener) {
+        return;
+    }
 
     if let Some((byte_offset, _)) = iter.next() {
         // We should return the parsed object with this error, but it's OK for the purposes of this
         // post.
-        listener.handle_error(ParseError {
+        listener.handle_error(ParseError::new(
             byte_offset,
-            reason: "trailing characters after parsing",
-        });
+            ParseErrorKind::TrailingCharacters,
+        ));
     }
 }
 
@@ -32,9 +84,14 @@ macro_rules! skip_trivia {
     };
 }
 
+/// Parses a value. `depth` is the number of arrays and objects the value is in. `expected` is used
+/// in errors when the next token is not a value.
 fn parse_single<L: EventListener>(
     iter: &mut Peekable<CharIndices>,
-    input_size: usize,
+    input: &str,
+    config: ParserConfig,
+    depth: usize,
+    expected: Expected,
     listener: &mut L,
 ) -> bool {
     skip_trivia!(iter, listener);
@@ -42,55 +99,70 @@ fn parse_single<L: EventListener>(
     let (byte_offset, char) = match iter.next() {
         Some(next) => next,
         None => {
-            listener.handle_error(ParseError {
-                byte_offset: input_size,
-                reason: "unexpected end of input",
-            });
+            listener.handle_error(ParseError::unexpected_end_of_input(input.len(), expected));
             return false;
         }
     };
 
+    if (char == '[' || char == '{') && depth == config.max_depth {
+        listener.handle_error(ParseError::new(byte_offset, ParseErrorKind::NestingTooDeep));
+        return false;
+    }
+
     if char == '[' {
-        listener.handle_start_array(byte_offset);
+        match listener.handle_start_array(byte_offset) {
+            ListenerAction::Continue => {}
+            ListenerAction::SkipChildren => {
+                return skip_children(iter, input, byte_offset, listener);
+            }
+            ListenerAction::Stop | ListenerAction::Abort(_) => return false,
+        }
         let mut array_is_empty = true;
         loop {
             skip_trivia!(iter, listener);
 
+            let expected = if array_is_empty {
+                Expected::ValueOrEndArray
+            } else {
+                Expected::CommaOrEndArray
+            };
+
             match iter.peek().copied() {
                 Some((comma_byte_offset, ',')) => {
                     if array_is_empty {
-                        listener.handle_error(ParseError {
-                            byte_offset: comma_byte_offset,
-                            reason: "unexpected character while parsing array",
-                        });
+                        listener.handle_error(ParseError::unexpected_token(
+                            comma_byte_offset,
+                            expected,
+                            TokenKind::Comma,
+                        ));
                         return false;
                     }
 
                     // Consume ','
                     iter.next();
-                    if !parse_single(iter, input_size, listener) {
+                    if !parse_single(iter, input, config, depth + 1, Expected::Value, listener) {
                         return false;
                     }
                 }
 
-                Some((_, ']')) => {
+                Some((end_offset, ']')) => {
                     // Consume ']'
                     iter.next();
-                    listener.handle_end_array(byte_offset);
-                    return true;
+                    return listener.handle_end_array(end_offset).keep_parsing();
                 }
 
-                Some((byte_offset, _)) => {
+                Some((byte_offset, char)) => {
                     if !array_is_empty {
                         // Need to see a ',' before the next element.
-                        listener.handle_error(ParseError {
+                        listener.handle_error(ParseError::unexpected_char(
                             byte_offset,
-                            reason: "unexpected character while parsing array",
-                        });
+                            expected,
+                            char,
+                        ));
                         return false;
                     }
 
-                    if !parse_single(iter, byte_offset, listener) {
+                    if !parse_single(iter, input, config, depth + 1, expected, listener) {
                         return false;
                     }
 
@@ -98,10 +170,8 @@ fn parse_single<L: EventListener>(
                 }
 
                 None => {
-                    listener.handle_error(ParseError {
-                        byte_offset: input_size,
-                        reason: "end of input while parsing array",
-                    });
+                    listener
+                        .handle_error(ParseError::unexpected_end_of_input(input.len(), expected));
                     return false;
                 }
             }
@@ -109,7 +179,13 @@ fn parse_single<L: EventListener>(
     }
 
     if char == '{' {
-        listener.handle_start_object(byte_offset);
+        match listener.handle_start_object(byte_offset) {
+            ListenerAction::Continue => {}
+            ListenerAction::SkipChildren => {
+                return skip_children(iter, input, byte_offset, listener);
+            }
+            ListenerAction::Stop | ListenerAction::Abort(_) => return false,
+        }
         let mut object_is_empty = true;
 
         enum State {
@@ -126,52 +202,48 @@ fn parse_single<L: EventListener>(
 
             match std::mem::replace(&mut state, State::Done) {
                 State::Done => {
-                    match iter.peek().copied() {
-                        Some((byte_offset, ',')) => {
-                            if object_is_empty {
-                                listener.handle_error(ParseError {
-                                    byte_offset,
-                                    reason: "unexpected comma while parsing object",
-                                });
-                                return false;
-                            }
-                            iter.next(); // consume ','
+                    let expected = if object_is_empty {
+                        Expected::KeyOrEndObject
+                    } else {
+                        Expected::CommaOrEndObject
+                    };
+                    match iter.next() {
+                        Some((_, ',')) if !object_is_empty => {
                             state = State::ExpectKey;
                         }
 
-                        Some((_, '}')) => {
-                            iter.next(); // consume '}'
-                            listener.handle_end_object(byte_offset);
-                            return true;
+                        Some((end_offset, '}')) => {
+                            return listener.handle_end_object(end_offset).keep_parsing();
                         }
 
-                        Some((_, '"')) => {
-                            if !parse_single(iter, byte_offset, listener) {
+                        Some((byte_offset, '"')) if object_is_empty => {
+                            if !parse_string_body(iter, byte_offset, listener) {
                                 return false;
                             }
                             state = State::ExpectColon;
                         }
 
-                        Some((byte_offset, _)) => {
-                            listener.handle_error(ParseError {
+                        Some((byte_offset, char)) => {
+                            listener.handle_error(ParseError::unexpected_char(
                                 byte_offset,
-                                reason: "unexpected char while parsing object",
-                            });
+                                expected,
+                                char,
+                            ));
                             return false;
                         }
 
                         None => {
-                            listener.handle_error(ParseError {
-                                byte_offset: input_size,
-                                reason: "unexpected end of input while parsing object",
-                            });
+                            listener.handle_error(ParseError::unexpected_end_of_input(
+                                input.len(),
+                                expected,
+                            ));
                             return false;
                         }
                     }
                 }
 
                 State::ExpectKey => {
-                    if !parse_string(iter, input_size, listener) {
+                    if !parse_string(iter, input.len(), listener) {
                         return false;
                     }
                     state = State::ExpectColon;
@@ -182,25 +254,26 @@ fn parse_single<L: EventListener>(
                         state = State::ExpectValue;
                     }
 
-                    Some((byte_offset, _)) => {
-                        listener.handle_error(ParseError {
+                    Some((byte_offset, char)) => {
+                        listener.handle_error(ParseError::unexpected_char(
                             byte_offset,
-                            reason: "unexpected char while parsing object",
-                        });
+                            Expected::Colon,
+                            char,
+                        ));
                         return false;
                     }
 
                     None => {
-                        listener.handle_error(ParseError {
-                            byte_offset: input_size,
-                            reason: "unexpected end of input while parsing object",
-                        });
+                        listener.handle_error(ParseError::unexpected_end_of_input(
+                            input.len(),
+                            Expected::Colon,
+                        ));
                         return false;
                     }
                 },
 
                 State::ExpectValue => {
-                    if !parse_single(iter, input_size, listener) {
+                    if !parse_single(iter, input, config, depth + 1, Expected::Value, listener) {
                         return false;
                     }
                     object_is_empty = false;
@@ -215,13 +288,9 @@ fn parse_single<L: EventListener>(
             && next_char(iter) == Some('u')
             && next_char(iter) == Some('e')
         {
-            listener.handle_bool(byte_offset, true);
-            return true;
+            return listener.handle_bool(byte_offset, true).keep_parsing();
         }
-        listener.handle_error(ParseError {
-            byte_offset,
-            reason: "unexpected keyword",
-        });
+        listener.handle_error(invalid_token(byte_offset));
         return false;
     }
 
@@ -231,13 +300,9 @@ fn parse_single<L: EventListener>(
             && next_char(iter) == Some('s')
             && next_char(iter) == Some('e')
         {
-            listener.handle_bool(byte_offset, false);
-            return true;
+            return listener.handle_bool(byte_offset, false).keep_parsing();
         }
-        listener.handle_error(ParseError {
-            byte_offset,
-            reason: "unexpected keyword",
-        });
+        listener.handle_error(invalid_token(byte_offset));
         return false;
     }
 
@@ -246,58 +311,70 @@ fn parse_single<L: EventListener>(
             && next_char(iter) == Some('l')
             && next_char(iter) == Some('l')
         {
-            listener.handle_null(byte_offset);
-            return true;
+            return listener.handle_null(byte_offset).keep_parsing();
         }
-        listener.handle_error(ParseError {
-            byte_offset,
-            reason: "unexpected keyword",
-        });
+        listener.handle_error(invalid_token(byte_offset));
         return false;
     }
 
-    if char.is_ascii_digit() {
-        let mut i: u64 = u64::from((char as u8) - b'0');
-
-        while let Some((_, next)) = iter.peek().copied() {
-            if !next.is_ascii_digit() {
-                break;
+    if char == '-' || char.is_ascii_digit() {
+        match scan_number(input.as_bytes(), byte_offset, config.int_overflow) {
+            Ok((number, end_offset)) => {
+                skip_until(iter, end_offset);
+                let action = match number {
+                    Number::Int(i) => listener.handle_int(byte_offset, i),
+                    Number::NegInt(i) => listener.handle_neg_int(byte_offset, i),
+                    Number::Float(f) => listener.handle_float(byte_offset, f),
+                    Number::Raw { size_in_bytes } => {
+                        listener.handle_raw_number(byte_offset, size_in_bytes)
+                    }
+                };
+                return action.keep_parsing();
+            }
+            Err(err) => {
+                listener.handle_error(err);
+                return false;
             }
-
-            // Consume the digit.
-            iter.next();
-
-            // Ignore overflows for the purposes of this post.
-            i *= 10;
-            i += u64::from((next as u8) - b'0');
         }
-
-        listener.handle_int(byte_offset, i);
-        return true;
     }
 
     if char == '"' {
-        for (byte_offset_, next) in iter.by_ref() {
-            if next == '"' {
-                listener.handle_str(byte_offset + 1, byte_offset_ - byte_offset - 1);
-                return true;
-            }
-        }
-
-        listener.handle_error(ParseError {
-            byte_offset: input_size,
-            reason: "unexpected end of input while parsing string",
-        });
-        return false;
+        return parse_string_body(iter, byte_offset, listener);
     }
 
-    listener.handle_error(ParseError {
-        byte_offset,
-        reason: "unexpected character",
-    });
+    listener.handle_error(ParseError::unexpected_char(byte_offset, expected, char));
     false
 }
 
+/// Skips the contents of the array or object started at `byte_offset`, and generates the end
+/// event. See [ListenerAction::SkipChildren].
+fn skip_children<L: EventListener>(
+    iter: &mut Peekable<CharIndices>,
+    input: &str,
+    byte_offset: usize,
+    listener: &mut L,
+) -> bool {
+    let end_offset = match skip_container(input.as_bytes(), byte_offset) {
+        Ok(end_offset) => end_offset,
+        Err(err) => {
+            listener.handle_error(err);
+            return false;
+        }
+    };
+
+    while iter
+        .next_if(|(char_offset, _)| *char_offset <= end_offset)
+        .is_some()
+    {}
+
+    if input.as_bytes()[byte_offset] == b'[' {
+        listener.handle_end_array(end_offset).keep_parsing()
+    } else {
+        listener.handle_end_object(end_offset).keep_parsing()
+    }
+}
+
+/// Parses an object key.
 fn parse_string<L: EventListener>(
     iter: &mut Peekable<CharIndices>,
     input_size: usize,
@@ -306,30 +383,45 @@ fn parse_string<L: EventListener>(
     let (byte_offset, char) = match iter.next() {
         Some(next) => next,
         None => {
-            listener.handle_error(ParseError {
-                byte_offset: input_size,
-                reason: "unexpected end of input",
-            });
+            listener.handle_error(ParseError::unexpected_end_of_input(
+                input_size,
+                Expected::Key,
+            ));
             return false;
         }
     };
 
     if char == '"' {
-        for (byte_offset_, next) in iter.by_ref() {
-            if next == '"' {
-                listener.handle_str(byte_offset + 1, byte_offset_ - byte_offset - 1);
-                return true;
-            }
-        }
+        return parse_string_body(iter, byte_offset, listener);
     }
 
-    listener.handle_error(ParseError {
-        byte_offset: input_size,
-        reason: "unexpected end of input while parsing string",
-    });
+    listener.handle_error(ParseError::unexpected_char(
+        byte_offset,
+        Expected::Key,
+        char,
+    ));
     false
 }
 
+// NB. Initial double quote should be consumed in `iter`, but not in
+// `byte_offset`.
+fn parse_string_body<L: EventListener>(
+    iter: &mut Peekable<CharIndices>,
+    byte_offset: usize,
+    listener: &mut L,
+) -> bool {
+    match scan_string(iter) {
+        Ok((end_offset, has_escapes)) => listener
+            .handle_str(byte_offset + 1, end_offset - byte_offset - 1, has_escapes)
+            .keep_parsing(),
+
+        Err(err) => {
+            listener.handle_error(scan_string_error(err, byte_offset));
+            false
+        }
+    }
+}
+
 fn next_char(iter: &mut Peekable<CharIndices>) -> Option<char> {
     iter.next().map(|(_, char)| char)
 }
@@ -344,28 +436,29 @@ fn skip_trivia<L: EventListener>(iter: &mut Peekable<CharIndices>, listener: &mu
         if char == '/' {
             iter.next(); // consume peeked '/'
             match iter.next() {
-                Some((_, '/')) => {
+                Some((slash_byte_offset, '/')) => {
+                    let mut end_offset = slash_byte_offset + 1;
                     for (byte_offset_, char) in iter.by_ref() {
+                        end_offset = byte_offset_ + char.len_utf8();
                         if char == '\n' {
-                            listener.handle_comment(byte_offset, byte_offset_ - byte_offset + 1);
+                            if !listener
+                                .handle_comment(byte_offset, end_offset - byte_offset)
+                                .keep_parsing()
+                            {
+                                return false;
+                            }
                             continue 'outer;
                         }
                     }
-                }
 
-                Some(_) => {
-                    listener.handle_error(ParseError {
-                        byte_offset,
-                        reason: "unexpected '/'",
-                    });
-                    return false;
+                    // Comment at the end of input.
+                    return listener
+                        .handle_comment(byte_offset, end_offset - byte_offset)
+                        .keep_parsing();
                 }
 
-                None => {
-                    listener.handle_error(ParseError {
-                        byte_offset,
-                        reason: "unexpected end of input",
-                    });
+                _ => {
+                    listener.handle_error(invalid_token(byte_offset));
                     return false;
                 }
             }
@@ -403,6 +496,18 @@ fn event_tests() {
     }
 }
 
+#[test]
+fn event_offset_tests() {
+    // Same events, with the same locations, as the iterator parser.
+    for input_size in [10, 100, 1_000] {
+        let input = crate::gen_input(input_size);
+        let iter_events: Vec<crate::ParseEvent> = crate::parse_events_iter(&input)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(collect_events(&input), iter_events);
+    }
+}
+
 #[test]
 fn event_to_tree_tests() {
     for (str, ast) in crate::test_common::ast_tests() {
@@ -423,3 +528,103 @@ fn event_to_tree_random_tests() {
         assert_eq!(event_ast, ast);
     }
 }
+
+#[test]
+fn int_overflow_tests() {
+    for (str, byte_offset, ast) in crate::test_common::int_overflow_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse(&str, &mut listener);
+        let (_, error) = listener.into_events();
+        assert_eq!(
+            error,
+            Some(ParseError::new(
+                byte_offset,
+                crate::ParseErrorKind::IntegerOverflow
+            ))
+        );
+
+        let config = crate::test_common::raw_int_config();
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(&str, config, &mut listener);
+        let (events, error) = listener.into_events();
+        assert_eq!(error, None);
+        let ast_ = crate::event_to_tree(&mut events.into_iter().map(Result::Ok), &str).unwrap();
+        assert_eq!(ast_, ast);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse(&str, &mut listener);
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, Some(error));
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(&str, config, &mut listener);
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn limit_tests() {
+    let tests = crate::test_common::limit_tests()
+        .into_iter()
+        .chain(crate::test_common::non_ascii_input_size_tests());
+    for (str, config, error) in tests {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(&str, config, &mut listener);
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn listener_action_tests() {
+    for (action, str, events, result, error) in crate::test_common::listener_action_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::test_common::ActionListener::new(action);
+        assert_eq!(parse(&str, &mut listener), result);
+        assert_eq!(listener.events, events);
+        assert_eq!(listener.error, error);
+    }
+}
+
+#[test]
+fn bytes_tests() {
+    let events = |input: &[u8]| {
+        let mut listener = crate::PushToEvents::new();
+        parse_bytes(input, &mut listener);
+        listener.into_events()
+    };
+
+    // Valid UTF-8 is parsed the same as `&str` input.
+    for (str, _) in crate::test_common::error_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse(&str, &mut listener);
+        assert_eq!(events(str.as_bytes()), listener.into_events());
+    }
+
+    let tests = crate::test_common::limit_tests()
+        .into_iter()
+        .chain(crate::test_common::non_ascii_input_size_tests());
+    for (str, config, error) in tests {
+        let mut listener = crate::PushToEvents::new();
+        parse_bytes_with_config(str.as_bytes(), config, &mut listener);
+        assert_eq!(listener.into_events().1, error);
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(events(&bytes).1, Some(error), "{:?}", bytes);
+    }
+}
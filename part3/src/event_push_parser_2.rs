@@ -1,22 +1,126 @@
 use crate::event_parser::{Container, ParserState};
-use crate::{tokenize_push, EventListener, LexerEventListener, ParseError};
+use crate::lexer_push::tokenize_push_bytes_with_config;
+use crate::limits::LimitListener;
+use crate::{
+    EventListener, Expected, LexerEventListener, ListenerAction, ParseError, ParseErrorKind,
+    ParserConfig, TokenKind,
+};
 
-pub fn parse<L: EventListener>(input: &str, listener: &mut L) {
-    let mut lexer_event_listener = LexerEventListenerImpl {
-        listener,
-        container_stack: vec![],
-        state: ParserState::TopLevel,
-    };
-    tokenize_push(input, &mut lexer_event_listener);
+pub fn parse<L: EventListener>(input: &str, listener: &mut L) -> Result<(), L::Error> {
+    parse_with_config(input, ParserConfig::default(), listener)
+}
+
+pub fn parse_with_config<L: EventListener>(
+    input: &str,
+    config: ParserConfig,
+    listener: &mut L,
+) -> Result<(), L::Error> {
+    parse_bytes_with_config(input.as_bytes(), config, listener)
+}
+
+/// Same as [parse], but the input does not have to be valid UTF-8. UTF-8 is validated in strings
+/// and comments.
+pub fn parse_bytes<L: EventListener>(input: &[u8], listener: &mut L) -> Result<(), L::Error> {
+    parse_bytes_with_config(input, ParserConfig::default(), listener)
 }
 
-struct LexerEventListenerImpl<'a, L: EventListener> {
-    listener: &'a mut L,
+pub fn parse_bytes_with_config<L: EventListener>(
+    input: &[u8],
+    config: ParserConfig,
+    listener: &mut L,
+) -> Result<(), L::Error> {
+    let mut lexer_event_listener = LexerEventListenerImpl::new(listener, config);
+    if lexer_event_listener.check_input(input) {
+        tokenize_push_bytes_with_config(input, config, &mut lexer_event_listener);
+        lexer_event_listener.end_of_input(input.len());
+    }
+    lexer_event_listener.take_result()
+}
+
+/// A [LexerEventListener] that parses the tokens and passes the events to an [EventListener].
+pub(crate) struct LexerEventListenerImpl<L: EventListener> {
+    listener: LimitListener<L>,
     container_stack: Vec<Container>,
     state: ParserState,
+
+    /// See [ParserConfig::max_depth].
+    max_depth: usize,
+
+    /// Whether we've reported an error. The push lexer doesn't stop on parse errors, so we ignore
+    /// the rest of the tokens after an error.
+    failed: bool,
+
+    /// Number of arrays and objects the next token is in, in the contents of an array or object
+    /// that the listener skips. See [ListenerAction::SkipChildren].
+    skip_depth: usize,
 }
 
-impl<'a, L: EventListener> LexerEventListenerImpl<'a, L> {
+impl<L: EventListener> LexerEventListenerImpl<L> {
+    pub(crate) fn new(listener: L, config: ParserConfig) -> LexerEventListenerImpl<L> {
+        LexerEventListenerImpl {
+            listener: LimitListener::new(listener, config),
+            container_stack: vec![],
+            state: ParserState::TopLevel,
+            max_depth: config.max_depth,
+            failed: false,
+            skip_depth: 0,
+        }
+    }
+
+    pub(crate) fn listener(&self) -> &L {
+        self.listener.listener()
+    }
+
+    pub(crate) fn listener_mut(&mut self) -> &mut L {
+        self.listener.listener_mut()
+    }
+
+    pub(crate) fn into_listener(self) -> L {
+        self.listener.into_listener()
+    }
+
+    /// Whether an error was reported, or the listener stopped parsing. Tokens after that are
+    /// ignored.
+    pub(crate) fn failed(&self) -> bool {
+        self.failed || self.listener.failed()
+    }
+
+    /// Whether the tokens are ignored, because the listener skipped the array or object they're
+    /// in.
+    pub(crate) fn skipping(&self) -> bool {
+        self.skip_depth != 0
+    }
+
+    /// Returns the error of the listener's [ListenerAction::Abort], if it aborted parsing.
+    pub(crate) fn take_result(&mut self) -> Result<(), L::Error> {
+        self.listener.take_result()
+    }
+
+    /// Checks the size of the input, see [ParserConfig::max_input_size]. Returns whether to keep
+    /// parsing.
+    pub(crate) fn check_input_size(
+        &mut self,
+        input_size: usize,
+        char_start: impl FnOnce(usize) -> usize,
+    ) -> bool {
+        self.listener.check_input_size(input_size, char_start)
+    }
+
+    /// [LexerEventListenerImpl::check_input_size] for the whole input.
+    pub(crate) fn check_input(&mut self, input: &[u8]) -> bool {
+        self.listener.check_input(input)
+    }
+
+    /// Reports an error if the input ended before the value was complete. `input_size` is the
+    /// size of the whole input.
+    pub(crate) fn end_of_input(&mut self, input_size: usize) {
+        if !self.failed() {
+            if let Some(expected) = self.expected() {
+                self.error(ParseError::unexpected_end_of_input(input_size, expected));
+            }
+        }
+    }
+
     fn update_state(&mut self) {
         self.state = match self.container_stack.last() {
             Some(Container::Array) => ParserState::ArrayExpectComma,
@@ -25,225 +129,299 @@ impl<'a, L: EventListener> LexerEventListenerImpl<'a, L> {
         };
     }
 
-    fn pop_array(&mut self, byte_offset: usize) -> Result<(), ParseError> {
-        match self.container_stack.pop() {
-            Some(Container::Array) => Ok(()),
+    /// What we expect in the current state. `None` when we're done parsing.
+    fn expected(&self) -> Option<Expected> {
+        Some(match self.state {
+            ParserState::TopLevel => Expected::Value,
+            ParserState::ArrayStart => Expected::ValueOrEndArray,
+            ParserState::Done => return None,
+            ParserState::ObjectExpectComma => Expected::CommaOrEndObject,
+            ParserState::ObjectExpectKeyValue => Expected::KeyOrEndObject,
+            ParserState::ObjectExpectKey => Expected::Key,
+            ParserState::ObjectExpectColon => Expected::Colon,
+            ParserState::ArrayExpectComma => Expected::CommaOrEndArray,
+        })
+    }
 
-            _ => Err(ParseError {
-                byte_offset,
-                reason: "unexpected ']'",
-            }),
-        }
+    fn error(&mut self, error: ParseError) {
+        self.failed = true;
+        self.listener.handle_error(error);
     }
 
-    fn pop_object(&mut self, byte_offset: usize) -> Result<(), ParseError> {
-        match self.container_stack.pop() {
-            Some(Container::Object) => Ok(()),
+    fn unexpected_token(&mut self, byte_offset: usize, found: TokenKind) {
+        let error = match self.expected() {
+            Some(expected) => ParseError::unexpected_token(byte_offset, expected, found),
+            None => ParseError::new(byte_offset, ParseErrorKind::TrailingCharacters),
+        };
+        self.error(error);
+    }
 
-            _ => Err(ParseError {
-                byte_offset,
-                reason: "unexpected '}'",
-            }),
-        }
+    /// Whether we're expecting a value.
+    fn expect_value(&self) -> bool {
+        matches!(self.state, ParserState::TopLevel | ParserState::ArrayStart)
     }
 }
 
-impl<'a, L: EventListener> LexerEventListener for LexerEventListenerImpl<'a, L> {
+impl<L: EventListener> LexerEventListener for LexerEventListenerImpl<L> {
     fn handle_int(&mut self, byte_offset: usize, i: u64) {
-        match self.state {
-            ParserState::TopLevel => {
-                self.listener.handle_int(byte_offset, i);
-                self.update_state();
-            }
-            _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
-            }
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
+        if self.expect_value() {
+            self.listener.handle_int(byte_offset, i);
+            self.update_state();
+        } else {
+            self.unexpected_token(byte_offset, TokenKind::Number);
+        }
+    }
+
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64) {
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
+        if self.expect_value() {
+            self.listener.handle_neg_int(byte_offset, i);
+            self.update_state();
+        } else {
+            self.unexpected_token(byte_offset, TokenKind::Number);
+        }
+    }
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64) {
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
+        if self.expect_value() {
+            self.listener.handle_float(byte_offset, f);
+            self.update_state();
+        } else {
+            self.unexpected_token(byte_offset, TokenKind::Number);
+        }
+    }
+
+    fn handle_raw_number(&mut self, byte_offset: usize, size_in_bytes: usize) {
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
+        if self.expect_value() {
+            self.listener.handle_raw_number(byte_offset, size_in_bytes);
+            self.update_state();
+        } else {
+            self.unexpected_token(byte_offset, TokenKind::Number);
         }
     }
 
-    fn handle_str(&mut self, byte_offset: usize, size_in_bytes: usize) {
+    fn handle_str(&mut self, byte_offset: usize, size_in_bytes: usize, has_escapes: bool) {
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
         match self.state {
-            ParserState::TopLevel => {
-                self.listener.handle_str(byte_offset, size_in_bytes);
+            ParserState::TopLevel | ParserState::ArrayStart => {
+                self.listener
+                    .handle_str(byte_offset, size_in_bytes, has_escapes);
                 self.update_state();
             }
-            ParserState::ObjectExpectKeyValue => {
-                self.listener.handle_str(byte_offset, size_in_bytes);
+            ParserState::ObjectExpectKeyValue | ParserState::ObjectExpectKey => {
+                self.listener
+                    .handle_str(byte_offset, size_in_bytes, has_escapes);
                 self.state = ParserState::ObjectExpectColon;
             }
             _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
+                // String tokens start at the opening quote.
+                self.unexpected_token(byte_offset - 1, TokenKind::String);
             }
         }
     }
 
     fn handle_true(&mut self, byte_offset: usize) {
-        match self.state {
-            ParserState::TopLevel => {
-                self.listener.handle_bool(byte_offset, true);
-                self.update_state();
-            }
-            _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
-            }
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
+        if self.expect_value() {
+            self.listener.handle_bool(byte_offset, true);
+            self.update_state();
+        } else {
+            self.unexpected_token(byte_offset, TokenKind::True);
         }
     }
 
     fn handle_false(&mut self, byte_offset: usize) {
-        match self.state {
-            ParserState::TopLevel => {
-                self.listener.handle_bool(byte_offset, false);
-                self.update_state();
-            }
-            _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
-            }
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
+        if self.expect_value() {
+            self.listener.handle_bool(byte_offset, false);
+            self.update_state();
+        } else {
+            self.unexpected_token(byte_offset, TokenKind::False);
         }
     }
 
     fn handle_null(&mut self, byte_offset: usize) {
-        match self.state {
-            ParserState::TopLevel => {
-                self.listener.handle_null(byte_offset);
-                self.update_state();
-            }
-            _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
-            }
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
+        if self.expect_value() {
+            self.listener.handle_null(byte_offset);
+            self.update_state();
+        } else {
+            self.unexpected_token(byte_offset, TokenKind::Null);
         }
     }
 
     fn handle_lbracket(&mut self, byte_offset: usize) {
-        match self.state {
-            ParserState::TopLevel => {
-                self.container_stack.push(Container::Array);
-                self.listener.handle_start_array(byte_offset);
-                // state stays at TopLevel
-            }
-            _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
+        if self.failed() {
+            return;
+        }
+        if self.skip_depth != 0 {
+            self.skip_depth += 1;
+            return;
+        }
+        if self.expect_value() {
+            if self.container_stack.len() == self.max_depth {
+                self.error(ParseError::new(byte_offset, ParseErrorKind::NestingTooDeep));
+                return;
             }
+            self.container_stack.push(Container::Array);
+            self.state = match self.listener.handle_start_array(byte_offset) {
+                ListenerAction::SkipChildren => {
+                    self.skip_depth = 1;
+                    ParserState::ArrayExpectComma
+                }
+                _ => ParserState::ArrayStart,
+            };
+        } else {
+            self.unexpected_token(byte_offset, TokenKind::LBracket);
         }
     }
 
     fn handle_rbracket(&mut self, byte_offset: usize) {
+        if self.failed() {
+            return;
+        }
+        if self.skip_depth != 0 {
+            self.skip_depth -= 1;
+            if self.skip_depth != 0 {
+                return;
+            }
+            // End of the skipped contents. The state is the state after the contents, so the
+            // bracket has to end the skipped array or object.
+        }
         match self.state {
-            ParserState::TopLevel | ParserState::ArrayExpectComma => {
-                match self.pop_array(byte_offset) {
-                    Ok(()) => {
-                        self.listener.handle_end_array(byte_offset);
-                        self.update_state();
-                    }
-                    Err(err) => {
-                        self.listener.handle_error(err);
-                    }
-                }
+            ParserState::ArrayStart | ParserState::ArrayExpectComma => {
+                let container = self.container_stack.pop();
+                debug_assert_eq!(container, Some(Container::Array));
+                self.listener.handle_end_array(byte_offset);
+                self.update_state();
             }
             _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
+                self.unexpected_token(byte_offset, TokenKind::RBracket);
             }
         }
     }
 
     fn handle_lbrace(&mut self, byte_offset: usize) {
-        match self.state {
-            ParserState::TopLevel => {
-                self.container_stack.push(Container::Object);
-                self.listener.handle_start_object(byte_offset);
-                self.state = ParserState::ObjectExpectKeyValue;
-            }
-            _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
+        if self.failed() {
+            return;
+        }
+        if self.skip_depth != 0 {
+            self.skip_depth += 1;
+            return;
+        }
+        if self.expect_value() {
+            if self.container_stack.len() == self.max_depth {
+                self.error(ParseError::new(byte_offset, ParseErrorKind::NestingTooDeep));
+                return;
             }
+            self.container_stack.push(Container::Object);
+            self.state = match self.listener.handle_start_object(byte_offset) {
+                ListenerAction::SkipChildren => {
+                    self.skip_depth = 1;
+                    ParserState::ObjectExpectComma
+                }
+                _ => ParserState::ObjectExpectKeyValue,
+            };
+        } else {
+            self.unexpected_token(byte_offset, TokenKind::LBrace);
         }
     }
 
     fn handle_rbrace(&mut self, byte_offset: usize) {
+        if self.failed() {
+            return;
+        }
+        if self.skip_depth != 0 {
+            self.skip_depth -= 1;
+            if self.skip_depth != 0 {
+                return;
+            }
+            // End of the skipped contents. The state is the state after the contents, so the
+            // bracket has to end the skipped array or object.
+        }
         match self.state {
             ParserState::ObjectExpectKeyValue | ParserState::ObjectExpectComma => {
-                match self.pop_object(byte_offset) {
-                    Ok(()) => {
-                        self.listener.handle_end_object(byte_offset);
-                        self.update_state();
-                    }
-                    Err(err) => {
-                        self.listener.handle_error(err);
-                    }
-                }
+                let container = self.container_stack.pop();
+                debug_assert_eq!(container, Some(Container::Object));
+                self.listener.handle_end_object(byte_offset);
+                self.update_state();
             }
             _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
+                self.unexpected_token(byte_offset, TokenKind::RBrace);
             }
         }
     }
 
     fn handle_colon(&mut self, byte_offset: usize) {
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
         match self.state {
             ParserState::ObjectExpectColon => {
                 self.state = ParserState::TopLevel;
             }
             _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
+                self.unexpected_token(byte_offset, TokenKind::Colon);
             }
         }
     }
 
     fn handle_comma(&mut self, byte_offset: usize) {
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
         match self.state {
             ParserState::ObjectExpectComma => {
-                self.state = ParserState::ObjectExpectKeyValue;
+                self.state = ParserState::ObjectExpectKey;
             }
             ParserState::ArrayExpectComma => {
                 self.state = ParserState::TopLevel;
             }
             _ => {
-                self.listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "unexpected token",
-                });
+                self.unexpected_token(byte_offset, TokenKind::Comma);
             }
         }
     }
 
     fn handle_comment(&mut self, byte_offset: usize, size_in_bytes: usize) {
+        if self.failed() || self.skip_depth != 0 {
+            return;
+        }
         self.listener.handle_comment(byte_offset, size_in_bytes);
     }
 
-    fn handle_error(&mut self, byte_offset: usize) {
-        self.listener.handle_error(ParseError {
-            byte_offset,
-            reason: "invalid token",
-        });
+    fn handle_error(&mut self, error: ParseError) {
+        if self.failed() {
+            return;
+        }
+        match self.state {
+            // Same as the parsers that don't use a lexer: anything other than trivia after the
+            // value is trailing characters, even when it's not a valid token.
+            ParserState::Done => self.error(ParseError::new(
+                error.byte_offset,
+                ParseErrorKind::TrailingCharacters,
+            )),
+            _ => self.error(error),
+        }
     }
 }
 
@@ -293,3 +471,94 @@ fn event_to_tree_random_tests() {
         assert_eq!(event_ast, ast);
     }
 }
+
+#[test]
+fn int_overflow_tests() {
+    for (str, byte_offset, ast) in crate::test_common::int_overflow_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse(&str, &mut listener);
+        let (_, error) = listener.into_events();
+        assert_eq!(
+            error,
+            Some(ParseError::new(
+                byte_offset,
+                crate::ParseErrorKind::IntegerOverflow
+            ))
+        );
+
+        let config = crate::test_common::raw_int_config();
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(&str, config, &mut listener);
+        let (events, error) = listener.into_events();
+        assert_eq!(error, None);
+        let ast_ = crate::event_to_tree(&mut events.into_iter().map(Result::Ok), &str).unwrap();
+        assert_eq!(ast_, ast);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse(&str, &mut listener);
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, Some(error));
+    }
+}
+
+#[test]
+fn bytes_tests() {
+    let events = |input: &[u8]| {
+        let mut listener = crate::PushToEvents::new();
+        parse_bytes(input, &mut listener);
+        listener.into_events()
+    };
+
+    // Valid UTF-8 is parsed the same as `&str` input.
+    for (str, _) in crate::test_common::error_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse(&str, &mut listener);
+        assert_eq!(events(str.as_bytes()), listener.into_events());
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(events(&bytes).1, Some(error), "{:?}", bytes);
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(&str, config, &mut listener);
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn limit_tests() {
+    let tests = crate::test_common::limit_tests()
+        .into_iter()
+        .chain(crate::test_common::non_ascii_input_size_tests());
+    for (str, config, error) in tests {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(&str, config, &mut listener);
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn listener_action_tests() {
+    for (action, str, events, result, error) in crate::test_common::listener_action_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::test_common::ActionListener::new(action);
+        assert_eq!(parse(&str, &mut listener), result);
+        assert_eq!(listener.events, events);
+        assert_eq!(listener.error, error);
+    }
+}
@@ -1,17 +1,74 @@
-use crate::{EventListener, ParseError, Token};
+use crate::event_parser::Container;
+use crate::limits::LimitListener;
+use crate::{
+    EventListener, Expected, ListenerAction, ParseError, ParseErrorKind, ParserConfig, Token,
+};
 
 use std::iter::Peekable;
 
-type Item = Result<(usize, Token), usize>;
+type Item = Result<(usize, Token), ParseError>;
 
 pub fn parse<L: EventListener, I: Iterator<Item = Item>>(
     lexer: I,
     listener: &mut L,
     input_size: usize,
+) -> Result<(), L::Error> {
+    parse_with_config(lexer, listener, input_size, ParserConfig::default())
+}
+
+/// Same as [parse], but with the given configuration.
+///
+/// Numbers are scanned by the lexer, so [ParserConfig::int_overflow] should be passed to the
+/// lexer.
+pub fn parse_with_config<L: EventListener, I: Iterator<Item = Item>>(
+    lexer: I,
+    listener: &mut L,
+    input_size: usize,
+    config: ParserConfig,
+) -> Result<(), L::Error> {
+    let listener = &mut LimitListener::new(listener, config);
+    parse_tokens(lexer, listener, input_size, config);
+    listener.take_result()
+}
+
+/// Same as [parse], but tokenizes `input` with [Lexer::from_bytes][crate::Lexer::from_bytes],
+/// which validates UTF-8 in strings and comments.
+pub fn parse_bytes<L: EventListener>(input: &[u8], listener: &mut L) -> Result<(), L::Error> {
+    parse_bytes_with_config(input, listener, ParserConfig::default())
+}
+
+/// Same as [parse_bytes], but with the given configuration.
+pub fn parse_bytes_with_config<L: EventListener>(
+    input: &[u8],
+    listener: &mut L,
+    config: ParserConfig,
+) -> Result<(), L::Error> {
+    let listener = &mut LimitListener::new(listener, config);
+    // Check the size before the lexer, so that the error is at a character boundary.
+    if listener.check_input(input) {
+        parse_tokens(
+            crate::Lexer::from_bytes_with_config(input, config),
+            listener,
+            input.len(),
+            config,
+        );
+    }
+    listener.take_result()
+}
+
+fn parse_tokens<L: EventListener, I: Iterator<Item = Item>>(
+    lexer: I,
+    listener: &mut LimitListener<L>,
+    input_size: usize,
+    config: ParserConfig,
 ) {
+    if !listener.check_input_size(input_size, |byte_offset| byte_offset) {
+        return;
+    }
+
     let mut lexer = lexer.peekable();
 
-    if !parse_single(&mut lexer, input_size, listener) {
+    if !parse_single(&mut lexer, input_size, config, 0, Expected::Value, listener) {
         return;
     }
 
@@ -20,94 +77,194 @@ pub fn parse<L: EventListener, I: Iterator<Item = Item>>(
         match token {
             Ok((byte_offset, t)) => match t {
                 Token::Comment { size_in_bytes } => {
-                    listener.handle_comment(byte_offset, size_in_bytes);
+                    if !listener
+                        .handle_comment(byte_offset, size_in_bytes)
+                        .keep_parsing()
+                    {
+                        break;
+                    }
                 }
                 _ => {
-                    listener.handle_error(ParseError {
-                        byte_offset,
-                        reason: "trailing token",
-                    });
+                    listener.handle_error(ParseError::new(
+                        t.start_offset(byte_offset),
+                        ParseErrorKind::TrailingCharacters,
+                    ));
                     break;
                 }
             },
-            Err(byte_offset) => {
-                listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "invalid token",
-                });
+            Err(err) => {
+                // Same as the parsers that don't use a lexer: anything other than trivia after
+                // the value is trailing characters, even when it's not a valid token.
+                listener.handle_error(ParseError::new(
+                    err.byte_offset,
+                    ParseErrorKind::TrailingCharacters,
+                ));
                 break;
             }
         }
     }
 }
 
+/// Skips the tokens in the contents of an array or object, after the listener returns
+/// [ListenerAction::SkipChildren] for its start. The tokens are not checked other than for the
+/// brackets of the `container`.
+///
+/// Returns the byte offset of the closing bracket, or `None` after reporting an error.
+pub(crate) fn skip_tokens<L: EventListener, I: Iterator<Item = Item>>(
+    lexer: &mut I,
+    input_size: usize,
+    container: Container,
+    listener: &mut L,
+) -> Option<usize> {
+    let expected = match container {
+        Container::Array => Expected::CommaOrEndArray,
+        Container::Object => Expected::CommaOrEndObject,
+    };
+
+    // Number of arrays and objects opened in the skipped tokens.
+    let mut depth: usize = 0;
+
+    loop {
+        match lexer.next() {
+            Some(Ok((byte_offset, token))) => match token {
+                Token::LBracket | Token::LBrace => depth += 1,
+
+                Token::RBracket | Token::RBrace if depth != 0 => depth -= 1,
+
+                Token::RBracket if container == Container::Array => return Some(byte_offset),
+
+                Token::RBrace if container == Container::Object => return Some(byte_offset),
+
+                Token::RBracket | Token::RBrace => {
+                    listener.handle_error(ParseError::unexpected_token(
+                        byte_offset,
+                        expected,
+                        token.kind(),
+                    ));
+                    return None;
+                }
+
+                _ => {}
+            },
+
+            Some(Err(err)) => {
+                listener.handle_error(err);
+                return None;
+            }
+
+            None => {
+                listener.handle_error(ParseError::unexpected_end_of_input(input_size, expected));
+                return None;
+            }
+        }
+    }
+}
+
+/// Parses a value. `depth` is the number of arrays and objects the value is in. `expected` is used
+/// in errors.
 fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
     lexer: &mut Peekable<I>,
     input_size: usize,
+    config: ParserConfig,
+    depth: usize,
+    expected: Expected,
     listener: &mut L,
 ) -> bool {
-    while let Some(token) = lexer.next() {
-        let (byte_offset, token) = match token {
-            Ok(next) => next,
-            Err(byte_offset) => {
-                listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "invalid token",
-                });
+    loop {
+        let (byte_offset, token) = match lexer.next() {
+            Some(Ok(next)) => next,
+            Some(Err(err)) => {
+                listener.handle_error(err);
+                return false;
+            }
+            None => {
+                listener.handle_error(ParseError::unexpected_end_of_input(input_size, expected));
                 return false;
             }
         };
 
         match token {
             Token::Comment { size_in_bytes } => {
-                listener.handle_comment(byte_offset, size_in_bytes);
+                if !listener
+                    .handle_comment(byte_offset, size_in_bytes)
+                    .keep_parsing()
+                {
+                    return false;
+                }
+            }
+
+            Token::LBracket | Token::LBrace if depth == config.max_depth => {
+                listener.handle_error(ParseError::new(byte_offset, ParseErrorKind::NestingTooDeep));
+                return false;
             }
 
             Token::LBracket => {
-                listener.handle_start_array(byte_offset);
+                match listener.handle_start_array(byte_offset) {
+                    ListenerAction::Continue => {}
+                    ListenerAction::SkipChildren => {
+                        return match skip_tokens(lexer, input_size, Container::Array, listener) {
+                            Some(end_offset) => {
+                                listener.handle_end_array(end_offset).keep_parsing()
+                            }
+                            None => false,
+                        };
+                    }
+                    ListenerAction::Stop | ListenerAction::Abort(_) => return false,
+                }
                 let mut array_is_empty = true;
 
                 loop {
-                    match lexer.peek().copied() {
+                    match lexer.peek().cloned() {
                         Some(Ok((byte_offset, t))) => match t {
                             Token::Comment { size_in_bytes } => {
-                                listener.handle_comment(byte_offset, size_in_bytes);
+                                if !listener
+                                    .handle_comment(byte_offset, size_in_bytes)
+                                    .keep_parsing()
+                                {
+                                    return false;
+                                }
                                 lexer.next(); // consume comment
                                 continue;
                             }
 
-                            Token::Comma => {
-                                if array_is_empty {
-                                    listener.handle_error(ParseError {
-                                        byte_offset,
-                                        reason: "unexpected character while parsing array",
-                                    });
-                                    return false;
-                                }
-
+                            Token::Comma if !array_is_empty => {
                                 lexer.next(); // consume comma
-                                if !parse_single(lexer, input_size, listener) {
+                                if !parse_single(
+                                    lexer,
+                                    input_size,
+                                    config,
+                                    depth + 1,
+                                    Expected::Value,
+                                    listener,
+                                ) {
                                     return false;
                                 }
                             }
 
                             Token::RBracket => {
-                                listener.handle_end_array(byte_offset);
                                 lexer.next(); // consume bracket
-                                return true;
+                                return listener.handle_end_array(byte_offset).keep_parsing();
                             }
 
                             _ => {
                                 if !array_is_empty {
                                     // Need to see a ',' before the next element.
-                                    listener.handle_error(ParseError {
-                                        byte_offset,
-                                        reason: "unexpected character while parsing array",
-                                    });
+                                    listener.handle_error(ParseError::unexpected_token(
+                                        t.start_offset(byte_offset),
+                                        Expected::CommaOrEndArray,
+                                        t.kind(),
+                                    ));
                                     return false;
                                 }
 
-                                if !parse_single(lexer, byte_offset, listener) {
+                                if !parse_single(
+                                    lexer,
+                                    input_size,
+                                    config,
+                                    depth + 1,
+                                    Expected::ValueOrEndArray,
+                                    listener,
+                                ) {
                                     return false;
                                 }
 
@@ -115,19 +272,20 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                             }
                         },
 
-                        Some(Err(byte_offset)) => {
-                            listener.handle_error(ParseError {
-                                byte_offset,
-                                reason: "invalid token",
-                            });
+                        Some(Err(err)) => {
+                            listener.handle_error(err);
                             return false;
                         }
 
                         None => {
-                            listener.handle_error(ParseError {
-                                byte_offset: input_size,
-                                reason: "unexpected end of input",
-                            });
+                            let expected = if array_is_empty {
+                                Expected::ValueOrEndArray
+                            } else {
+                                Expected::CommaOrEndArray
+                            };
+                            listener.handle_error(ParseError::unexpected_end_of_input(
+                                input_size, expected,
+                            ));
                             return false;
                         }
                     }
@@ -135,7 +293,18 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
             }
 
             Token::LBrace => {
-                listener.handle_start_object(byte_offset);
+                match listener.handle_start_object(byte_offset) {
+                    ListenerAction::Continue => {}
+                    ListenerAction::SkipChildren => {
+                        return match skip_tokens(lexer, input_size, Container::Object, listener) {
+                            Some(end_offset) => {
+                                listener.handle_end_object(end_offset).keep_parsing()
+                            }
+                            None => false,
+                        };
+                    }
+                    ListenerAction::Stop | ListenerAction::Abort(_) => return false,
+                }
                 let mut object_is_empty = true;
 
                 enum State {
@@ -150,107 +319,137 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                 loop {
                     match state {
                         State::Done => {
-                            match lexer.peek().copied() {
+                            let expected = if object_is_empty {
+                                Expected::KeyOrEndObject
+                            } else {
+                                Expected::CommaOrEndObject
+                            };
+
+                            match lexer.peek().cloned() {
                                 Some(Ok((byte_offset, Token::Comment { size_in_bytes }))) => {
-                                    listener.handle_comment(byte_offset, size_in_bytes);
+                                    if !listener
+                                        .handle_comment(byte_offset, size_in_bytes)
+                                        .keep_parsing()
+                                    {
+                                        return false;
+                                    }
                                     lexer.next(); // consume comment
                                     continue;
                                 }
 
-                                Some(Ok((byte_offset, Token::Comma))) => {
-                                    if object_is_empty {
-                                        listener.handle_error(ParseError {
-                                            byte_offset,
-                                            reason: "unexpected comma while parsing object",
-                                        });
-                                        return false;
-                                    }
+                                Some(Ok((_, Token::Comma))) if !object_is_empty => {
                                     lexer.next(); // consume ','
                                     state = State::ExpectKey;
                                 }
 
-                                Some(Ok((_, Token::RBrace))) => {
+                                Some(Ok((byte_offset, Token::RBrace))) => {
                                     lexer.next(); // consume '}'
-                                    listener.handle_end_object(byte_offset);
-                                    return true;
+                                    return listener.handle_end_object(byte_offset).keep_parsing();
                                 }
 
-                                Some(Ok((byte_offset, Token::Str { size_in_bytes }))) => {
+                                Some(Ok((
+                                    byte_offset,
+                                    Token::Str {
+                                        size_in_bytes,
+                                        has_escapes,
+                                    },
+                                ))) if object_is_empty => {
                                     lexer.next(); // consume string
-                                    listener.handle_str(byte_offset, size_in_bytes);
+                                    if !listener
+                                        .handle_str(byte_offset, size_in_bytes, has_escapes)
+                                        .keep_parsing()
+                                    {
+                                        return false;
+                                    }
                                     state = State::ExpectColon;
                                 }
 
-                                Some(Ok((byte_offset, _))) => {
-                                    listener.handle_error(ParseError {
-                                        byte_offset,
-                                        reason: "unexpected token while parsing object",
-                                    });
+                                Some(Ok((byte_offset, t))) => {
+                                    listener.handle_error(ParseError::unexpected_token(
+                                        t.start_offset(byte_offset),
+                                        expected,
+                                        t.kind(),
+                                    ));
                                     return false;
                                 }
 
-                                Some(Err(byte_offset)) => {
-                                    listener.handle_error(ParseError {
-                                        byte_offset,
-                                        reason: "invalid token",
-                                    });
+                                Some(Err(err)) => {
+                                    listener.handle_error(err);
                                     return false;
                                 }
 
                                 None => {
-                                    listener.handle_error(ParseError {
-                                        byte_offset: input_size,
-                                        reason: "unexpected end of input while parsing object",
-                                    });
+                                    listener.handle_error(ParseError::unexpected_end_of_input(
+                                        input_size, expected,
+                                    ));
                                     return false;
                                 }
                             }
                         }
 
                         State::ExpectKey => {
-                            match lexer.peek().copied() {
+                            match lexer.peek().cloned() {
                                 Some(Ok((byte_offset, Token::Comment { size_in_bytes }))) => {
-                                    listener.handle_comment(byte_offset, size_in_bytes);
+                                    if !listener
+                                        .handle_comment(byte_offset, size_in_bytes)
+                                        .keep_parsing()
+                                    {
+                                        return false;
+                                    }
                                     lexer.next(); // consume comment
                                     continue;
                                 }
 
-                                Some(Ok((byte_offset, Token::Str { size_in_bytes }))) => {
-                                    listener.handle_str(byte_offset, size_in_bytes);
+                                Some(Ok((
+                                    byte_offset,
+                                    Token::Str {
+                                        size_in_bytes,
+                                        has_escapes,
+                                    },
+                                ))) => {
+                                    if !listener
+                                        .handle_str(byte_offset, size_in_bytes, has_escapes)
+                                        .keep_parsing()
+                                    {
+                                        return false;
+                                    }
                                     lexer.next(); // consume string
                                     state = State::ExpectColon;
                                 }
 
-                                Some(Ok((byte_offset, _))) => {
-                                    listener.handle_error(ParseError {
-                                        byte_offset,
-                                        reason: "unexpected token",
-                                    });
+                                Some(Ok((byte_offset, t))) => {
+                                    listener.handle_error(ParseError::unexpected_token(
+                                        t.start_offset(byte_offset),
+                                        Expected::Key,
+                                        t.kind(),
+                                    ));
                                     return false;
                                 }
 
-                                Some(Err(byte_offset)) => {
-                                    listener.handle_error(ParseError {
-                                        byte_offset,
-                                        reason: "invalid token",
-                                    });
+                                Some(Err(err)) => {
+                                    listener.handle_error(err);
                                     return false;
                                 }
 
                                 None => {
-                                    listener.handle_error(ParseError {
-                                        byte_offset: input_size,
-                                        reason: "unexpected end of input while parsing object",
-                                    });
+                                    listener.handle_error(ParseError::unexpected_end_of_input(
+                                        input_size,
+                                        Expected::Key,
+                                    ));
                                     return false;
                                 }
                             }
                         }
 
                         State::ExpectColon => {
-                            match lexer.peek().copied() {
+                            match lexer.peek().cloned() {
                                 Some(Ok((byte_offset, Token::Comment { size_in_bytes }))) => {
-                                    listener.handle_comment(byte_offset, size_in_bytes);
+                                    if !listener
+                                        .handle_comment(byte_offset, size_in_bytes)
+                                        .keep_parsing()
+                                    {
+                                        return false;
+                                    }
                                     lexer.next(); // consume comment
                                     continue;
                                 }
@@ -260,34 +459,39 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                                     state = State::ExpectValue;
                                 }
 
-                                Some(Ok((byte_offset, _))) => {
-                                    listener.handle_error(ParseError {
-                                        byte_offset,
-                                        reason: "unexpected token",
-                                    });
+                                Some(Ok((byte_offset, t))) => {
+                                    listener.handle_error(ParseError::unexpected_token(
+                                        t.start_offset(byte_offset),
+                                        Expected::Colon,
+                                        t.kind(),
+                                    ));
                                     return false;
                                 }
 
-                                Some(Err(byte_offset)) => {
-                                    listener.handle_error(ParseError {
-                                        byte_offset,
-                                        reason: "invalid token",
-                                    });
+                                Some(Err(err)) => {
+                                    listener.handle_error(err);
                                     return false;
                                 }
 
                                 None => {
-                                    listener.handle_error(ParseError {
-                                        byte_offset: input_size,
-                                        reason: "unexpected end of input while parsing object",
-                                    });
+                                    listener.handle_error(ParseError::unexpected_end_of_input(
+                                        input_size,
+                                        Expected::Colon,
+                                    ));
                                     return false;
                                 }
                             }
                         }
 
                         State::ExpectValue => {
-                            if !parse_single(lexer, input_size, listener) {
+                            if !parse_single(
+                                lexer,
+                                input_size,
+                                config,
+                                depth + 1,
+                                Expected::Value,
+                                listener,
+                            ) {
                                 return false;
                             }
                             object_is_empty = false;
@@ -298,41 +502,54 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
             }
 
             Token::True => {
-                listener.handle_bool(byte_offset, true);
-                return true;
+                return listener.handle_bool(byte_offset, true).keep_parsing();
             }
 
             Token::False => {
-                listener.handle_bool(byte_offset, false);
-                return true;
+                return listener.handle_bool(byte_offset, false).keep_parsing();
             }
 
             Token::Null => {
-                listener.handle_null(byte_offset);
-                return true;
+                return listener.handle_null(byte_offset).keep_parsing();
             }
 
             Token::Int(i) => {
-                listener.handle_int(byte_offset, i);
-                return true;
+                return listener.handle_int(byte_offset, i).keep_parsing();
+            }
+
+            Token::NegInt(i) => {
+                return listener.handle_neg_int(byte_offset, i).keep_parsing();
+            }
+
+            Token::Float(f) => {
+                return listener.handle_float(byte_offset, f).keep_parsing();
+            }
+
+            Token::RawNumber { size_in_bytes } => {
+                return listener
+                    .handle_raw_number(byte_offset, size_in_bytes)
+                    .keep_parsing();
             }
 
-            Token::Str { size_in_bytes } => {
-                listener.handle_str(byte_offset, size_in_bytes);
-                return true;
+            Token::Str {
+                size_in_bytes,
+                has_escapes,
+            } => {
+                return listener
+                    .handle_str(byte_offset, size_in_bytes, has_escapes)
+                    .keep_parsing();
             }
 
             Token::RBracket | Token::RBrace | Token::Colon | Token::Comma => {
-                listener.handle_error(ParseError {
+                listener.handle_error(ParseError::unexpected_token(
                     byte_offset,
-                    reason: "unexpected token",
-                });
+                    expected,
+                    token.kind(),
+                ));
                 return false;
             }
         }
     }
-
-    true
 }
 
 #[cfg(test)]
@@ -381,3 +598,105 @@ fn event_to_tree_random_tests() {
         assert_eq!(event_ast, ast);
     }
 }
+
+#[test]
+fn int_overflow_tests() {
+    for (str, byte_offset, ast) in crate::test_common::int_overflow_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse(crate::tokenize_iter(&str), &mut listener, str.len());
+        let (_, error) = listener.into_events();
+        assert_eq!(
+            error,
+            Some(ParseError::new(
+                byte_offset,
+                crate::ParseErrorKind::IntegerOverflow
+            ))
+        );
+
+        let config = crate::test_common::raw_int_config();
+        let mut listener = crate::PushToEvents::new();
+        parse(
+            crate::Lexer::with_config(&str, config),
+            &mut listener,
+            str.len(),
+        );
+        let (events, error) = listener.into_events();
+        assert_eq!(error, None);
+        let ast_ = crate::event_to_tree(&mut events.into_iter().map(Result::Ok), &str).unwrap();
+        assert_eq!(ast_, ast);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse(crate::tokenize_iter(&str), &mut listener, str.len());
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, Some(error));
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(crate::tokenize_iter(&str), &mut listener, str.len(), config);
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn limit_tests() {
+    for (str, config, error) in crate::test_common::limit_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(crate::tokenize_iter(&str), &mut listener, str.len(), config);
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn listener_action_tests() {
+    for (action, str, events, result, error) in crate::test_common::listener_action_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::test_common::ActionListener::new(action);
+        assert_eq!(
+            parse(crate::tokenize_iter(&str), &mut listener, str.len()),
+            result
+        );
+        assert_eq!(listener.events, events);
+        assert_eq!(listener.error, error);
+    }
+}
+
+#[test]
+fn bytes_tests() {
+    let events = |input: &[u8]| {
+        let mut listener = crate::PushToEvents::new();
+        parse_bytes(input, &mut listener);
+        listener.into_events()
+    };
+
+    // Valid UTF-8 is parsed the same as `&str` input.
+    for (str, _) in crate::test_common::error_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse(crate::tokenize_iter(&str), &mut listener, str.len());
+        assert_eq!(events(str.as_bytes()), listener.into_events());
+    }
+
+    // Input size errors are at a character boundary, unlike with `parse`.
+    for (str, config, error) in crate::test_common::non_ascii_input_size_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse_bytes_with_config(str.as_bytes(), &mut listener, config);
+        assert_eq!(listener.into_events().1, error);
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(events(&bytes).1, Some(error), "{:?}", bytes);
+    }
+}
@@ -1,16 +1,71 @@
 use crate::direct_non_recursive::ParserState;
 use crate::event_parser::Container;
-use crate::{EventListener, ParseError, Token};
+use crate::event_push_parser_3::skip_tokens;
+use crate::limits::LimitListener;
+use crate::{EventListener, ListenerAction, ParseError, ParseErrorKind, ParserConfig, Token};
 
-type Item = Result<(usize, Token), usize>;
+type Item = Result<(usize, Token), ParseError>;
 
 #[allow(unused)]
 pub fn parse<L: EventListener, I: Iterator<Item = Item>>(
     lexer: &mut I,
     listener: &mut L,
     input_size: usize,
+) -> Result<(), L::Error> {
+    parse_with_config(lexer, listener, input_size, ParserConfig::default())
+}
+
+/// Same as [parse], but with the given configuration.
+///
+/// Numbers are scanned by the lexer, so [ParserConfig::int_overflow] should be passed to the
+/// lexer.
+pub fn parse_with_config<L: EventListener, I: Iterator<Item = Item>>(
+    lexer: &mut I,
+    listener: &mut L,
+    input_size: usize,
+    config: ParserConfig,
+) -> Result<(), L::Error> {
+    let listener = &mut LimitListener::new(listener, config);
+    parse_tokens(lexer, listener, input_size, config);
+    listener.take_result()
+}
+
+/// Same as [parse], but tokenizes `input` with [Lexer::from_bytes][crate::Lexer::from_bytes],
+/// which validates UTF-8 in strings and comments.
+pub fn parse_bytes<L: EventListener>(input: &[u8], listener: &mut L) -> Result<(), L::Error> {
+    parse_bytes_with_config(input, listener, ParserConfig::default())
+}
+
+/// Same as [parse_bytes], but with the given configuration.
+pub fn parse_bytes_with_config<L: EventListener>(
+    input: &[u8],
+    listener: &mut L,
+    config: ParserConfig,
+) -> Result<(), L::Error> {
+    let listener = &mut LimitListener::new(listener, config);
+    // Check the size before the lexer, so that the error is at a character boundary.
+    if listener.check_input(input) {
+        parse_tokens(
+            &mut crate::Lexer::from_bytes_with_config(input, config),
+            listener,
+            input.len(),
+            config,
+        );
+    }
+    listener.take_result()
+}
+
+fn parse_tokens<L: EventListener, I: Iterator<Item = Item>>(
+    lexer: &mut I,
+    listener: &mut LimitListener<L>,
+    input_size: usize,
+    config: ParserConfig,
 ) {
-    if !parse_single(lexer, input_size, listener) {
+    if !listener.check_input_size(input_size, |byte_offset| byte_offset) {
+        return;
+    }
+
+    if !parse_single(lexer, input_size, config, listener) {
         return;
     }
 
@@ -19,21 +74,28 @@ pub fn parse<L: EventListener, I: Iterator<Item = Item>>(
         match token {
             Ok((byte_offset, t)) => match t {
                 Token::Comment { size_in_bytes } => {
-                    listener.handle_comment(byte_offset, size_in_bytes);
+                    if !listener
+                        .handle_comment(byte_offset, size_in_bytes)
+                        .keep_parsing()
+                    {
+                        break;
+                    }
                 }
                 _ => {
-                    listener.handle_error(ParseError {
-                        byte_offset,
-                        reason: "trailing token",
-                    });
+                    listener.handle_error(ParseError::new(
+                        t.start_offset(byte_offset),
+                        ParseErrorKind::TrailingCharacters,
+                    ));
                     break;
                 }
             },
-            Err(byte_offset) => {
-                listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "invalid token",
-                });
+            Err(err) => {
+                // Same as the parsers that don't use a lexer: anything other than trivia after
+                // the value is trailing characters, even when it's not a valid token.
+                listener.handle_error(ParseError::new(
+                    err.byte_offset,
+                    ParseErrorKind::TrailingCharacters,
+                ));
                 break;
             }
         }
@@ -43,6 +105,7 @@ pub fn parse<L: EventListener, I: Iterator<Item = Item>>(
 fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
     lexer: &mut I,
     input_size: usize,
+    config: ParserConfig,
     listener: &mut L,
 ) -> bool {
     let mut container_stack: Vec<Container> = vec![];
@@ -52,53 +115,100 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
         let (byte_offset, token) = match lexer.next() {
             Some(Ok(next)) => next,
 
-            Some(Err(byte_offset)) => {
-                listener.handle_error(ParseError {
-                    byte_offset,
-                    reason: "invalid token",
-                });
+            Some(Err(err)) => {
+                listener.handle_error(err);
                 return false;
             }
 
             None => {
-                listener.handle_error(ParseError {
-                    byte_offset: input_size,
-                    reason: "unexpected end of input",
-                });
+                listener.handle_error(ParseError::unexpected_end_of_input(
+                    input_size,
+                    state.expected(container_stack.last() == Some(&Container::Object)),
+                ));
                 return false;
             }
         };
 
         if let Token::Comment { size_in_bytes } = token {
-            listener.handle_comment(byte_offset, size_in_bytes);
+            if !listener
+                .handle_comment(byte_offset, size_in_bytes)
+                .keep_parsing()
+            {
+                return false;
+            }
             continue;
         }
 
+        let unexpected_token = ParseError::unexpected_token(
+            token.start_offset(byte_offset),
+            state.expected(container_stack.last() == Some(&Container::Object)),
+            token.kind(),
+        );
+
         match state {
-            ParserState::TopLevel => match token {
+            ParserState::TopLevel | ParserState::ArrayStart => match token {
+                Token::LBrace | Token::LBracket if container_stack.len() == config.max_depth => {
+                    listener
+                        .handle_error(ParseError::new(byte_offset, ParseErrorKind::NestingTooDeep));
+                    return false;
+                }
+
                 Token::LBrace => {
                     container_stack.push(Container::Object);
                     state = ParserState::ObjectExpectKeyValueTerminate;
-                    listener.handle_start_object(byte_offset);
+                    match listener.handle_start_object(byte_offset) {
+                        ListenerAction::Continue => {}
+                        ListenerAction::SkipChildren => {
+                            let end_offset =
+                                match skip_tokens(lexer, input_size, Container::Object, listener) {
+                                    Some(end_offset) => end_offset,
+                                    None => return false,
+                                };
+                            container_stack.pop();
+                            if !listener.handle_end_object(end_offset).keep_parsing() {
+                                return false;
+                            }
+
+                            if container_stack.is_empty() {
+                                break;
+                            }
+                            state = ParserState::ExpectComma;
+                        }
+                        ListenerAction::Stop | ListenerAction::Abort(_) => return false,
+                    }
                 }
 
                 Token::LBracket => {
                     container_stack.push(Container::Array);
-                    state = ParserState::TopLevel;
-                    listener.handle_start_array(byte_offset);
+                    state = ParserState::ArrayStart;
+                    match listener.handle_start_array(byte_offset) {
+                        ListenerAction::Continue => {}
+                        ListenerAction::SkipChildren => {
+                            let end_offset =
+                                match skip_tokens(lexer, input_size, Container::Array, listener) {
+                                    Some(end_offset) => end_offset,
+                                    None => return false,
+                                };
+                            container_stack.pop();
+                            if !listener.handle_end_array(end_offset).keep_parsing() {
+                                return false;
+                            }
+
+                            if container_stack.is_empty() {
+                                break;
+                            }
+                            state = ParserState::ExpectComma;
+                        }
+                        ListenerAction::Stop | ListenerAction::Abort(_) => return false,
+                    }
                 }
 
-                Token::RBracket => {
-                    if !matches!(container_stack.pop(), Some(Container::Array)) {
-                        listener.handle_error(ParseError {
-                            byte_offset,
-                            reason: "unexpected ']'",
-                        });
+                Token::RBracket if matches!(state, ParserState::ArrayStart) => {
+                    container_stack.pop();
+                    if !listener.handle_end_array(byte_offset).keep_parsing() {
                         return false;
                     }
 
-                    listener.handle_end_array(byte_offset);
-
                     if container_stack.is_empty() {
                         break;
                     }
@@ -106,7 +216,9 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                 }
 
                 Token::True => {
-                    listener.handle_bool(byte_offset, true);
+                    if !listener.handle_bool(byte_offset, true).keep_parsing() {
+                        return false;
+                    }
                     if container_stack.is_empty() {
                         break;
                     }
@@ -114,7 +226,9 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                 }
 
                 Token::False => {
-                    listener.handle_bool(byte_offset, false);
+                    if !listener.handle_bool(byte_offset, false).keep_parsing() {
+                        return false;
+                    }
                     if container_stack.is_empty() {
                         break;
                     }
@@ -122,7 +236,9 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                 }
 
                 Token::Null => {
-                    listener.handle_null(byte_offset);
+                    if !listener.handle_null(byte_offset).keep_parsing() {
+                        return false;
+                    }
                     if container_stack.is_empty() {
                         break;
                     }
@@ -130,15 +246,58 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                 }
 
                 Token::Int(i) => {
-                    listener.handle_int(byte_offset, i);
+                    if !listener.handle_int(byte_offset, i).keep_parsing() {
+                        return false;
+                    }
+                    if container_stack.is_empty() {
+                        break;
+                    }
+                    state = ParserState::ExpectComma;
+                }
+
+                Token::NegInt(i) => {
+                    if !listener.handle_neg_int(byte_offset, i).keep_parsing() {
+                        return false;
+                    }
                     if container_stack.is_empty() {
                         break;
                     }
                     state = ParserState::ExpectComma;
                 }
 
-                Token::Str { size_in_bytes } => {
-                    listener.handle_str(byte_offset, size_in_bytes);
+                Token::Float(f) => {
+                    if !listener.handle_float(byte_offset, f).keep_parsing() {
+                        return false;
+                    }
+                    if container_stack.is_empty() {
+                        break;
+                    }
+                    state = ParserState::ExpectComma;
+                }
+
+                Token::RawNumber { size_in_bytes } => {
+                    if !listener
+                        .handle_raw_number(byte_offset, size_in_bytes)
+                        .keep_parsing()
+                    {
+                        return false;
+                    }
+                    if container_stack.is_empty() {
+                        break;
+                    }
+                    state = ParserState::ExpectComma;
+                }
+
+                Token::Str {
+                    size_in_bytes,
+                    has_escapes,
+                } => {
+                    if !listener
+                        .handle_str(byte_offset, size_in_bytes, has_escapes)
+                        .keep_parsing()
+                    {
+                        return false;
+                    }
                     if container_stack.is_empty() {
                         break;
                     }
@@ -146,10 +305,8 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                 }
 
                 _ => {
-                    listener.handle_error(ParseError {
-                        byte_offset,
-                        reason: "unexpected token",
-                    });
+                    listener.handle_error(unexpected_token);
+                    return false;
                 }
             },
 
@@ -160,34 +317,24 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                     None => unreachable!(),
                 },
 
-                Token::RBrace => {
-                    if let Container::Array = container_stack.pop().unwrap() {
-                        listener.handle_error(ParseError {
-                            byte_offset,
-                            reason: "unexpected '}'",
-                        });
+                Token::RBrace if container_stack.last() == Some(&Container::Object) => {
+                    container_stack.pop();
+                    if !listener.handle_end_object(byte_offset).keep_parsing() {
                         return false;
                     }
 
-                    listener.handle_end_object(byte_offset);
-
                     if container_stack.is_empty() {
                         break;
                     }
                     state = ParserState::ExpectComma;
                 }
 
-                Token::RBracket => {
-                    if let Container::Object = container_stack.pop().unwrap() {
-                        listener.handle_error(ParseError {
-                            byte_offset,
-                            reason: "unexpected ']'",
-                        });
+                Token::RBracket if container_stack.last() == Some(&Container::Array) => {
+                    container_stack.pop();
+                    if !listener.handle_end_array(byte_offset).keep_parsing() {
                         return false;
                     }
 
-                    listener.handle_end_array(byte_offset);
-
                     if container_stack.is_empty() {
                         break;
                     }
@@ -195,38 +342,50 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                 }
 
                 _ => {
-                    listener.handle_error(ParseError {
-                        byte_offset,
-                        reason: "unexpected token",
-                    });
+                    listener.handle_error(unexpected_token);
                     return false;
                 }
             },
 
             ParserState::ObjectExpectKeyValue => match token {
-                Token::Str { size_in_bytes } => {
-                    listener.handle_str(byte_offset, size_in_bytes);
+                Token::Str {
+                    size_in_bytes,
+                    has_escapes,
+                } => {
+                    if !listener
+                        .handle_str(byte_offset, size_in_bytes, has_escapes)
+                        .keep_parsing()
+                    {
+                        return false;
+                    }
                     state = ParserState::ObjectExpectColon;
                 }
 
                 _ => {
-                    listener.handle_error(ParseError {
-                        byte_offset,
-                        reason: "unexpected token",
-                    });
+                    listener.handle_error(unexpected_token);
                     return false;
                 }
             },
 
             ParserState::ObjectExpectKeyValueTerminate => match token {
-                Token::Str { size_in_bytes } => {
-                    listener.handle_str(byte_offset, size_in_bytes);
+                Token::Str {
+                    size_in_bytes,
+                    has_escapes,
+                } => {
+                    if !listener
+                        .handle_str(byte_offset, size_in_bytes, has_escapes)
+                        .keep_parsing()
+                    {
+                        return false;
+                    }
                     state = ParserState::ObjectExpectColon;
                 }
 
                 Token::RBrace => {
                     container_stack.pop();
-                    listener.handle_end_object(byte_offset);
+                    if !listener.handle_end_object(byte_offset).keep_parsing() {
+                        return false;
+                    }
 
                     if container_stack.is_empty() {
                         break;
@@ -235,10 +394,7 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                 }
 
                 _ => {
-                    listener.handle_error(ParseError {
-                        byte_offset,
-                        reason: "unexpected token",
-                    });
+                    listener.handle_error(unexpected_token);
                     return false;
                 }
             },
@@ -249,10 +405,7 @@ fn parse_single<L: EventListener, I: Iterator<Item = Item>>(
                 }
 
                 _ => {
-                    listener.handle_error(ParseError {
-                        byte_offset,
-                        reason: "unexpected token",
-                    });
+                    listener.handle_error(unexpected_token);
                     return false;
                 }
             },
@@ -308,3 +461,115 @@ fn event_to_tree_random_tests() {
         assert_eq!(event_ast, ast);
     }
 }
+
+#[test]
+fn int_overflow_tests() {
+    for (str, byte_offset, ast) in crate::test_common::int_overflow_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse(&mut crate::tokenize_iter(&str), &mut listener, str.len());
+        let (_, error) = listener.into_events();
+        assert_eq!(
+            error,
+            Some(ParseError::new(
+                byte_offset,
+                crate::ParseErrorKind::IntegerOverflow
+            ))
+        );
+
+        let config = crate::test_common::raw_int_config();
+        let mut listener = crate::PushToEvents::new();
+        parse(
+            &mut crate::Lexer::with_config(&str, config),
+            &mut listener,
+            str.len(),
+        );
+        let (events, error) = listener.into_events();
+        assert_eq!(error, None);
+        let ast_ = crate::event_to_tree(&mut events.into_iter().map(Result::Ok), &str).unwrap();
+        assert_eq!(ast_, ast);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse(&mut crate::tokenize_iter(&str), &mut listener, str.len());
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, Some(error));
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(
+            &mut crate::tokenize_iter(&str),
+            &mut listener,
+            str.len(),
+            config,
+        );
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn limit_tests() {
+    for (str, config, error) in crate::test_common::limit_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::PushToEvents::new();
+        parse_with_config(
+            &mut crate::tokenize_iter(&str),
+            &mut listener,
+            str.len(),
+            config,
+        );
+        let (_, error_) = listener.into_events();
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn listener_action_tests() {
+    for (action, str, events, result, error) in crate::test_common::listener_action_tests() {
+        println!("Parsing {:?}", str);
+        let mut listener = crate::test_common::ActionListener::new(action);
+        assert_eq!(
+            parse(&mut crate::tokenize_iter(&str), &mut listener, str.len()),
+            result
+        );
+        assert_eq!(listener.events, events);
+        assert_eq!(listener.error, error);
+    }
+}
+
+#[test]
+fn bytes_tests() {
+    let events = |input: &[u8]| {
+        let mut listener = crate::PushToEvents::new();
+        parse_bytes(input, &mut listener);
+        listener.into_events()
+    };
+
+    // Valid UTF-8 is parsed the same as `&str` input.
+    for (str, _) in crate::test_common::error_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse(&mut crate::tokenize_iter(&str), &mut listener, str.len());
+        assert_eq!(events(str.as_bytes()), listener.into_events());
+    }
+
+    // Input size errors are at a character boundary, unlike with `parse`.
+    for (str, config, error) in crate::test_common::non_ascii_input_size_tests() {
+        let mut listener = crate::PushToEvents::new();
+        parse_bytes_with_config(str.as_bytes(), &mut listener, config);
+        assert_eq!(listener.into_events().1, error);
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(events(&bytes).1, Some(error), "{:?}", bytes);
+    }
+}
@@ -0,0 +1,694 @@
+use crate::escape::ScanStringError;
+use crate::event_push_parser_2::LexerEventListenerImpl;
+use crate::lexer::{invalid_token, scan_string_error};
+use crate::number::{scan_number, Number};
+use crate::utf8::{floor_char_boundary, Utf8Validator};
+use crate::{EventListener, LexerEventListener, ParseError, ParserConfig};
+
+/// An event push parser for input that arrives in chunks, for example from a socket.
+///
+/// Input is passed with [ChunkedPushParser::feed] as it becomes available, and
+/// [ChunkedPushParser::finish] is called at the end of the input. Chunks can end anywhere,
+/// including in the middle of a token or a UTF-8 encoded character.
+///
+/// The listener can be passed by value or as a `&mut` reference. Events are passed to the listener
+/// as soon as they are complete, with byte offsets in the whole input. The parser does not keep the
+/// input, other than the number being scanned, so listeners that need the text of strings should
+/// keep the input themselves.
+///
+/// [ParserConfig::max_string_size] is checked as a string is scanned, so a string longer than the
+/// limit fails with [StringTooLong][crate::ParseErrorKind::StringTooLong] as soon as the limit is
+/// exceeded, before the rest of the string is fed. Other parsers report errors later in the string,
+/// such as an unterminated string or an invalid escape, before checking the size of the string.
+///
+/// Errors are the same as [parse_events_push][crate::parse_events_push], except non-UTF-8
+/// bytes in strings and comments are reported as
+/// [InvalidUtf8][crate::ParseErrorKind::InvalidUtf8].
+///
+/// When the listener aborts parsing with [ListenerAction::Abort][crate::ListenerAction::Abort],
+/// the error is returned by the [ChunkedPushParser::feed] or [ChunkedPushParser::finish] call that
+/// passed the event, and the rest of the input is ignored.
+pub struct ChunkedPushParser<L: EventListener> {
+    /// Parses the tokens. Keeps the container stack between chunks.
+    parser: LexerEventListenerImpl<L>,
+
+    config: ParserConfig,
+
+    /// Byte offset of the next byte to be fed, in the whole input.
+    byte_offset: usize,
+
+    /// State of the token being scanned.
+    state: LexerState,
+}
+
+enum LexerState {
+    /// Not in a token.
+    Idle,
+
+    /// After a '/', expecting another '/'.
+    Slash { start: usize },
+
+    /// In a comment, until the newline.
+    Comment { start: usize, utf8: Utf8Validator },
+
+    /// In `true`, `false`, or `null`, after `matched` bytes of the keyword.
+    Keyword {
+        start: usize,
+        keyword: &'static [u8],
+        matched: usize,
+    },
+
+    /// In a number. Numbers are scanned with [scan_number] when we see a byte that can't be a
+    /// part of a number, so we keep the number text.
+    Number { start: usize, text: String },
+
+    /// In a string, after the opening double quote at `quote_offset`.
+    Str {
+        quote_offset: usize,
+        has_escapes: bool,
+        escape: Escape,
+        utf8: Utf8Validator,
+    },
+}
+
+impl LexerState {
+    /// Start of the UTF-8 encoded character being scanned in a string or comment, when the input
+    /// so far ends in the middle of the character.
+    fn char_start(&self) -> Option<usize> {
+        match self {
+            LexerState::Comment { utf8, .. } | LexerState::Str { utf8, .. } => utf8.char_start(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Escape {
+    /// Not in an escape sequence.
+    None,
+
+    /// After the backslash at `byte_offset`.
+    Backslash { byte_offset: usize },
+
+    /// In a `\uXXXX` escape sequence starting at `byte_offset`, with `remaining` hex digits left.
+    Unicode { byte_offset: usize, remaining: u8 },
+}
+
+impl<L: EventListener> ChunkedPushParser<L> {
+    pub fn new(listener: L) -> ChunkedPushParser<L> {
+        ChunkedPushParser::with_config(listener, ParserConfig::default())
+    }
+
+    pub fn with_config(listener: L, config: ParserConfig) -> ChunkedPushParser<L> {
+        ChunkedPushParser {
+            parser: LexerEventListenerImpl::new(listener, config),
+            config,
+            byte_offset: 0,
+            state: LexerState::Idle,
+        }
+    }
+
+    /// Parses the next chunk of the input. Input after an error is ignored.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(), L::Error> {
+        let chunk_offset = self.byte_offset;
+        let state = &self.state;
+        let within_limit =
+            self.parser
+                .check_input_size(chunk_offset + bytes.len(), |byte_offset| {
+                    // The previous chunks were within the limit, so the limit is in this chunk. When
+                    // the chunk starts in the middle of a character in a string or comment, the
+                    // character started in a previous chunk.
+                    match floor_char_boundary(bytes, byte_offset - chunk_offset) {
+                        0 => state.char_start().unwrap_or(chunk_offset),
+                        byte_idx => chunk_offset + byte_idx,
+                    }
+                });
+        if within_limit {
+            for (byte_idx, byte) in bytes.iter().enumerate() {
+                if self.parser.failed() {
+                    break;
+                }
+                self.lex_byte(*byte, self.byte_offset + byte_idx);
+            }
+        }
+        self.byte_offset += bytes.len();
+        self.parser.take_result()
+    }
+
+    /// Ends the input, and returns the listener. Reports an error to the listener if the input
+    /// ended before the value was complete.
+    pub fn finish(mut self) -> Result<L, L::Error> {
+        self.end_input()?;
+        Ok(self.parser.into_listener())
+    }
+
+    pub fn listener(&self) -> &L {
+        self.parser.listener()
+    }
+
+    pub fn listener_mut(&mut self) -> &mut L {
+        self.parser.listener_mut()
+    }
+
+    /// Same as [ChunkedPushParser::finish], without consuming the parser. Should be called once.
+    pub(crate) fn end_input(&mut self) -> Result<(), L::Error> {
+        if !self.parser.failed() {
+            self.end_token();
+        }
+        self.parser.end_of_input(self.byte_offset);
+        self.parser.take_result()
+    }
+
+    /// Byte offset of the token being scanned, when the input so far ends in the middle of a
+    /// token.
+    pub(crate) fn token_start(&self) -> Option<usize> {
+        match &self.state {
+            LexerState::Idle => None,
+            LexerState::Slash { start }
+            | LexerState::Comment { start, .. }
+            | LexerState::Keyword { start, .. }
+            | LexerState::Number { start, .. } => Some(*start),
+            LexerState::Str { quote_offset, .. } => Some(*quote_offset),
+        }
+    }
+
+    fn lex_byte(&mut self, byte: u8, byte_offset: usize) {
+        self.state = match std::mem::replace(&mut self.state, LexerState::Idle) {
+            LexerState::Idle => self.start_token(byte, byte_offset),
+
+            LexerState::Slash { start } => {
+                if byte == b'/' {
+                    LexerState::Comment {
+                        start,
+                        utf8: Utf8Validator::default(),
+                    }
+                } else {
+                    self.parser.handle_error(invalid_token(start));
+                    LexerState::Idle
+                }
+            }
+
+            LexerState::Comment { start, mut utf8 } => {
+                if let Err(err) = utf8.feed(byte, byte_offset) {
+                    self.parser.handle_error(err);
+                    LexerState::Idle
+                } else if byte == b'\n' {
+                    self.parser.handle_comment(start, byte_offset - start + 1);
+                    LexerState::Idle
+                } else {
+                    LexerState::Comment { start, utf8 }
+                }
+            }
+
+            LexerState::Keyword {
+                start,
+                keyword,
+                matched,
+            } => {
+                if byte != keyword[matched] {
+                    self.parser.handle_error(invalid_token(start));
+                    LexerState::Idle
+                } else if matched + 1 == keyword.len() {
+                    match keyword {
+                        b"true" => self.parser.handle_true(start),
+                        b"false" => self.parser.handle_false(start),
+                        _ => self.parser.handle_null(start),
+                    }
+                    LexerState::Idle
+                } else {
+                    LexerState::Keyword {
+                        start,
+                        keyword,
+                        matched: matched + 1,
+                    }
+                }
+            }
+
+            LexerState::Number { start, mut text } => {
+                if matches!(byte, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') {
+                    text.push(char::from(byte));
+                    LexerState::Number { start, text }
+                } else {
+                    // `end_number` updates the state, as it may start new tokens.
+                    self.end_number(start, text);
+                    if !self.parser.failed() {
+                        self.lex_byte(byte, byte_offset);
+                    }
+                    return;
+                }
+            }
+
+            LexerState::Str {
+                quote_offset,
+                has_escapes,
+                escape,
+                utf8,
+            } => self.lex_string_byte(byte, byte_offset, quote_offset, has_escapes, escape, utf8),
+        };
+    }
+
+    /// Handles the first byte of a token, or whitespace.
+    fn start_token(&mut self, byte: u8, byte_offset: usize) -> LexerState {
+        let keyword = |keyword| LexerState::Keyword {
+            start: byte_offset,
+            keyword,
+            matched: 1,
+        };
+
+        match byte {
+            b'/' => {
+                return LexerState::Slash { start: byte_offset };
+            }
+
+            b'"' => {
+                return LexerState::Str {
+                    quote_offset: byte_offset,
+                    has_escapes: false,
+                    escape: Escape::None,
+                    utf8: Utf8Validator::default(),
+                }
+            }
+
+            b't' => return keyword(b"true"),
+
+            b'f' => return keyword(b"false"),
+
+            b'n' => return keyword(b"null"),
+
+            b'-' | b'0'..=b'9' => {
+                return LexerState::Number {
+                    start: byte_offset,
+                    text: char::from(byte).to_string(),
+                }
+            }
+
+            b',' => self.parser.handle_comma(byte_offset),
+
+            b':' => self.parser.handle_colon(byte_offset),
+
+            b'[' => self.parser.handle_lbracket(byte_offset),
+
+            b']' => self.parser.handle_rbracket(byte_offset),
+
+            b'{' => self.parser.handle_lbrace(byte_offset),
+
+            b'}' => self.parser.handle_rbrace(byte_offset),
+
+            _ if byte.is_ascii_whitespace() => {}
+
+            _ => self.parser.handle_error(invalid_token(byte_offset)),
+        }
+
+        LexerState::Idle
+    }
+
+    fn lex_string_byte(
+        &mut self,
+        byte: u8,
+        byte_offset: usize,
+        quote_offset: usize,
+        mut has_escapes: bool,
+        escape: Escape,
+        mut utf8: Utf8Validator,
+    ) -> LexerState {
+        // Strings are not kept, but check the size as the string is scanned rather than at the end
+        // of the string, to fail early on long strings. The string is passed to the parser as it
+        // is so far, so that the parser reports the same error as it would at the end of the
+        // string: [StringTooLong][crate::ParseErrorKind::StringTooLong], or an error about a
+        // string where it's not expected, or an earlier limit.
+        let size_in_bytes = byte_offset - quote_offset;
+        let is_closing_quote = byte == b'"' && matches!(escape, Escape::None);
+        if size_in_bytes > self.config.max_string_size
+            && !is_closing_quote
+            && !self.parser.skipping()
+        {
+            self.parser
+                .handle_str(quote_offset + 1, size_in_bytes, has_escapes);
+            debug_assert!(self.parser.failed());
+            return LexerState::Idle;
+        }
+
+        let escape = match escape {
+            Escape::None => {
+                if let Err(err) = utf8.feed(byte, byte_offset) {
+                    self.parser.handle_error(err);
+                    return LexerState::Idle;
+                }
+                match byte {
+                    b'"' => {
+                        self.parser.handle_str(
+                            quote_offset + 1,
+                            byte_offset - quote_offset - 1,
+                            has_escapes,
+                        );
+                        return LexerState::Idle;
+                    }
+                    b'\\' => {
+                        has_escapes = true;
+                        Escape::Backslash { byte_offset }
+                    }
+                    _ => Escape::None,
+                }
+            }
+
+            Escape::Backslash {
+                byte_offset: backslash_offset,
+            } => match byte {
+                b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't' => Escape::None,
+                b'u' => Escape::Unicode {
+                    byte_offset: backslash_offset,
+                    remaining: 4,
+                },
+                _ => return self.invalid_escape(backslash_offset, quote_offset),
+            },
+
+            Escape::Unicode {
+                byte_offset: backslash_offset,
+                remaining,
+            } => {
+                if !byte.is_ascii_hexdigit() {
+                    return self.invalid_escape(backslash_offset, quote_offset);
+                }
+                if remaining == 1 {
+                    Escape::None
+                } else {
+                    Escape::Unicode {
+                        byte_offset: backslash_offset,
+                        remaining: remaining - 1,
+                    }
+                }
+            }
+        };
+
+        LexerState::Str {
+            quote_offset,
+            has_escapes,
+            escape,
+            utf8,
+        }
+    }
+
+    fn invalid_escape(&mut self, backslash_offset: usize, quote_offset: usize) -> LexerState {
+        self.parser.handle_error(scan_string_error(
+            ScanStringError::InvalidEscape {
+                byte_offset: backslash_offset,
+            },
+            quote_offset,
+        ));
+        LexerState::Idle
+    }
+
+    /// Scans the number `text` starting at `start`. Characters after the number are lexed as the
+    /// next tokens.
+    fn end_number(&mut self, start: usize, text: String) {
+        match scan_number(text.as_bytes(), 0, self.config.int_overflow) {
+            Ok((number, end)) => {
+                match number {
+                    Number::Int(i) => self.parser.handle_int(start, i),
+                    Number::NegInt(i) => self.parser.handle_neg_int(start, i),
+                    Number::Float(f) => self.parser.handle_float(start, f),
+                    Number::Raw { size_in_bytes } => {
+                        self.parser.handle_raw_number(start, size_in_bytes)
+                    }
+                }
+                for (byte_idx, byte) in text.bytes().enumerate().skip(end) {
+                    if self.parser.failed() {
+                        break;
+                    }
+                    self.lex_byte(byte, start + byte_idx);
+                }
+            }
+            Err(err) => {
+                self.parser
+                    .handle_error(ParseError::new(start + err.byte_offset, err.kind));
+            }
+        }
+    }
+
+    /// Finishes the token being scanned at the end of the input.
+    fn end_token(&mut self) {
+        match std::mem::replace(&mut self.state, LexerState::Idle) {
+            LexerState::Idle => {}
+
+            LexerState::Slash { start } | LexerState::Keyword { start, .. } => {
+                self.parser.handle_error(invalid_token(start));
+            }
+
+            LexerState::Comment { start, utf8 } => match utf8.end() {
+                Ok(()) => self.parser.handle_comment(start, self.byte_offset - start),
+                Err(err) => self.parser.handle_error(err),
+            },
+
+            LexerState::Number { start, text } => {
+                self.end_number(start, text);
+                // Characters after the number may start another token.
+                if !self.parser.failed() {
+                    self.end_token();
+                }
+            }
+
+            LexerState::Str { quote_offset, .. } => {
+                self.parser.handle_error(scan_string_error(
+                    ScanStringError::Unterminated,
+                    quote_offset,
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::{ListenerAction, ParseEvent, ParseEventKind, PushToEvents};
+
+#[cfg(test)]
+fn parse_chunks(chunks: &[&[u8]], config: ParserConfig) -> (Vec<ParseEvent>, Option<ParseError>) {
+    let mut parser = ChunkedPushParser::with_config(PushToEvents::new(), config);
+    for chunk in chunks {
+        let Ok(()) = parser.feed(chunk);
+    }
+    let Ok(listener) = parser.finish();
+    listener.into_events()
+}
+
+/// Checks that parsing `input` in chunks of various sizes generates the same events and errors as
+/// parsing it at once.
+#[cfg(test)]
+fn check_chunks(input: &str, config: ParserConfig) {
+    let mut listener = PushToEvents::new();
+    crate::parse_events_push_using_lexer_push_with_config(input, config, &mut listener);
+    let expected = listener.into_events();
+
+    let bytes = input.as_bytes();
+    assert_eq!(parse_chunks(&[bytes], config), expected, "{:?}", input);
+
+    for chunk_size in [1, 2, 3, 7] {
+        let chunks: Vec<&[u8]> = bytes.chunks(chunk_size).collect();
+        assert_eq!(
+            parse_chunks(&chunks, config),
+            expected,
+            "{:?} {}",
+            input,
+            chunk_size
+        );
+    }
+
+    // All splits into two chunks, including empty chunks at the ends.
+    for split in 0..=bytes.len() {
+        let (chunk1, chunk2) = bytes.split_at(split);
+        assert_eq!(
+            parse_chunks(&[chunk1, chunk2], config),
+            expected,
+            "{:?} {}",
+            input,
+            split
+        );
+    }
+}
+
+#[test]
+fn event_tests() {
+    for (str, events) in crate::test_common::event_tests() {
+        println!("Parsing {:?}", str);
+        let (events_, error) = parse_chunks(&[str.as_bytes()], ParserConfig::default());
+        assert_eq!(error, None);
+        let kinds: Vec<ParseEventKind> = events_.into_iter().map(|ev| ev.kind).collect();
+        assert_eq!(kinds, events);
+        check_chunks(&str, ParserConfig::default());
+    }
+}
+
+#[test]
+fn ast_tests() {
+    for (str, _) in crate::test_common::ast_tests() {
+        check_chunks(&str, ParserConfig::default());
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        let (_, error_) = parse_chunks(&[str.as_bytes()], ParserConfig::default());
+        assert_eq!(error_, Some(error));
+        check_chunks(&str, ParserConfig::default());
+    }
+}
+
+#[test]
+fn int_overflow_tests() {
+    for (str, _, _) in crate::test_common::int_overflow_tests() {
+        for int_overflow in [
+            crate::IntOverflow::Error,
+            crate::IntOverflow::Float,
+            crate::IntOverflow::Raw,
+        ] {
+            check_chunks(
+                &str,
+                ParserConfig {
+                    int_overflow,
+                    ..ParserConfig::default()
+                },
+            );
+        }
+    }
+}
+
+#[test]
+fn number_boundary_tests() {
+    // Numbers followed by characters that can be in a number, but not after the number.
+    for str in [
+        "[1-2]", "[01]", "[1.5.3]", "[1e5e5]", "[-]", "[1.]", "[1e+]", "1 2", "-1",
+    ] {
+        check_chunks(str, ParserConfig::default());
+    }
+}
+
+#[test]
+fn random_tests() {
+    for input_size in [10, 100, 1_000, 10_000] {
+        let input = crate::gen_input(input_size);
+        let mut listener = PushToEvents::new();
+        crate::parse_events_push(&input, &mut listener);
+        let expected = listener.into_events();
+        for chunk_size in [1, 5, 64, 4096] {
+            let chunks: Vec<&[u8]> = input.as_bytes().chunks(chunk_size).collect();
+            assert_eq!(parse_chunks(&chunks, ParserConfig::default()), expected);
+        }
+    }
+}
+
+#[test]
+fn utf8_tests() {
+    // Multi-byte characters split over chunks.
+    check_chunks("[\"é😀\", 1] // ключ 😀", ParserConfig::default());
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        for chunk_size in [1, 2, 3, bytes.len()] {
+            let chunks: Vec<&[u8]> = bytes.chunks(chunk_size).collect();
+            assert_eq!(
+                parse_chunks(&chunks, ParserConfig::default()).1,
+                Some(error.clone()),
+                "{:?}",
+                bytes
+            );
+        }
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        let (_, error_) = parse_chunks(&[str.as_bytes()], config);
+        assert_eq!(error_, error);
+        let chunks: Vec<&[u8]> = str.as_bytes().chunks(1000).collect();
+        let (_, error_) = parse_chunks(&chunks, config);
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn limit_tests() {
+    let tests = crate::test_common::limit_tests()
+        .into_iter()
+        .chain(crate::test_common::non_ascii_input_size_tests());
+    for (str, config, error) in tests {
+        println!("Parsing {:?}", str);
+        let (_, error_) = parse_chunks(&[str.as_bytes()], config);
+        assert_eq!(error_, error);
+        let chunks: Vec<&[u8]> = str.as_bytes().chunks(1).collect();
+        let (_, error_) = parse_chunks(&chunks, config);
+        assert_eq!(error_, error);
+    }
+}
+
+#[test]
+fn long_string_limit_test() {
+    let config = ParserConfig {
+        max_string_size: 10,
+        ..ParserConfig::default()
+    };
+
+    // An unterminated string longer than the limit fails in the first chunk, instead of with
+    // UnterminatedString at the end of the input.
+    let input = format!("[\"{}", "a".repeat(1_000_000));
+    let mut parser = ChunkedPushParser::with_config(PushToEvents::new(), config);
+    let mut chunks = input.as_bytes().chunks(4096);
+    let Ok(()) = parser.feed(chunks.next().unwrap());
+    let (events, error) = parser.listener_mut().take_events();
+    assert_eq!(events, vec![ParseEvent::new(0, ParseEventKind::StartArray)]);
+    assert_eq!(
+        error,
+        Some(ParseError::new(1, crate::ParseErrorKind::StringTooLong))
+    );
+
+    // Same error as at the end of a terminated string, also when the string is not expected.
+    for (str, error) in [
+        (
+            r#"["aaaaaaaaaaa"]"#,
+            Some(ParseError::new(1, crate::ParseErrorKind::StringTooLong)),
+        ),
+        (
+            r#"[1 "aaaaaaaaaaa"]"#,
+            crate::parse_events_iter(r#"[1 "a"]"#).find_map(Result::err),
+        ),
+        (r#"["aaaaaaaaaa"]"#, None),
+    ] {
+        for chunk_size in [1, 3, str.len()] {
+            let chunks: Vec<&[u8]> = str.as_bytes().chunks(chunk_size).collect();
+            assert_eq!(parse_chunks(&chunks, config).1, error, "{:?}", str);
+        }
+    }
+
+    // Strings in skipped arrays and objects are not checked.
+    let mut listener = crate::test_common::ActionListener::new(|depth, _| {
+        if depth == 1 {
+            ListenerAction::SkipChildren
+        } else {
+            ListenerAction::Continue
+        }
+    });
+    let mut parser = ChunkedPushParser::with_config(&mut listener, config);
+    let input = format!(r#"[[1, "{}"], "a"]"#, "a".repeat(1_000));
+    assert_eq!(parser.feed(input.as_bytes()), Ok(()));
+    assert!(parser.finish().is_ok());
+    assert_eq!(listener.error, None);
+}
+
+#[test]
+fn listener_action_tests() {
+    for (action, str, events, result, error) in crate::test_common::listener_action_tests() {
+        for chunk_size in [1, 3, str.len()] {
+            println!("Parsing {:?} in chunks of {}", str, chunk_size);
+            let mut listener = crate::test_common::ActionListener::new(action);
+            let mut parser = ChunkedPushParser::new(&mut listener);
+            let mut result_ = Ok(());
+            for chunk in str.as_bytes().chunks(chunk_size) {
+                result_ = result_.and(parser.feed(chunk));
+            }
+            result_ = result_.and(parser.finish().map(|_| ()));
+            assert_eq!(result_, result);
+            assert_eq!(listener.events, events);
+            assert_eq!(listener.error, error);
+        }
+    }
+}
@@ -1,146 +1,410 @@
-use crate::{Json, ParseError, ParseEvent, ParseEventKind};
+use crate::limits::LimitChecker;
+use crate::object_members::{MemberSlot, ObjectMembers};
+use crate::{
+    unescape, Expected, Json, ParseError, ParseEvent, ParseEventKind, ParserConfig, Span,
+    SpannedJson,
+};
 
 /// Parses a stream of [ParseEvent]s to [Json].
 pub fn event_to_tree<I: Iterator<Item = Result<ParseEvent, ParseError>>>(
     parser: &mut I,
     input: &str,
 ) -> Result<Json, ParseError> {
-    let mut container_stack: Vec<Container> = vec![];
+    build_tree(parser, TreeInput::Str(input), ParserConfig::default())
+}
+
+/// Same as [event_to_tree], but checks the resource limits in the given configuration, other than
+/// [ParserConfig::max_depth] which is checked by the parsers, and handles duplicate object keys as
+/// configured in [ParserConfig::duplicate_keys]. Other configuration is used by the parsers, and
+/// ignored.
+pub fn event_to_tree_with_config<I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: &mut I,
+    input: &str,
+    config: ParserConfig,
+) -> Result<Json, ParseError> {
+    build_tree(parser, TreeInput::Str(input), config)
+}
+
+/// Parses a stream of [ParseEvent]s to [SpannedJson].
+pub fn event_to_spanned_tree<I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: &mut I,
+    input: &str,
+) -> Result<SpannedJson, ParseError> {
+    build_tree(parser, TreeInput::Str(input), ParserConfig::default())
+}
+
+/// Same as [event_to_spanned_tree], but checks the resource limits and handles duplicate object
+/// keys as configured, see [event_to_tree_with_config].
+pub fn event_to_spanned_tree_with_config<I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: &mut I,
+    input: &str,
+    config: ParserConfig,
+) -> Result<SpannedJson, ParseError> {
+    build_tree(parser, TreeInput::Str(input), config)
+}
+
+/// Same as [event_to_tree], for events parsed from byte input, for example with
+/// [parse_events_iter_bytes][crate::parse_events_iter_bytes]. The parser validates UTF-8 of the
+/// strings.
+pub fn event_to_tree_bytes<I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: &mut I,
+    input: &[u8],
+) -> Result<Json, ParseError> {
+    build_tree(parser, TreeInput::Bytes(input), ParserConfig::default())
+}
+
+/// Same as [event_to_tree_bytes], but with the given configuration, see
+/// [event_to_tree_with_config].
+pub fn event_to_tree_bytes_with_config<I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: &mut I,
+    input: &[u8],
+    config: ParserConfig,
+) -> Result<Json, ParseError> {
+    build_tree(parser, TreeInput::Bytes(input), config)
+}
+
+/// Same as [event_to_spanned_tree], for events parsed from byte input, see [event_to_tree_bytes].
+pub fn event_to_spanned_tree_bytes<I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: &mut I,
+    input: &[u8],
+) -> Result<SpannedJson, ParseError> {
+    build_tree(parser, TreeInput::Bytes(input), ParserConfig::default())
+}
+
+/// Same as [event_to_spanned_tree_bytes], but with the given configuration, see
+/// [event_to_tree_with_config].
+pub fn event_to_spanned_tree_bytes_with_config<
+    I: Iterator<Item = Result<ParseEvent, ParseError>>,
+>(
+    parser: &mut I,
+    input: &[u8],
+    config: ParserConfig,
+) -> Result<SpannedJson, ParseError> {
+    build_tree(parser, TreeInput::Bytes(input), config)
+}
+
+fn build_tree<T: Tree, I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: &mut I,
+    input: TreeInput,
+    config: ParserConfig,
+) -> Result<T, ParseError> {
+    let mut container_stack: Vec<Container<T>> = vec![];
+
+    let mut limits = if config.has_limits() {
+        let checker = LimitChecker::new(config);
+        checker.check_input(input.as_bytes())?;
+        Some(checker)
+    } else {
+        None
+    };
 
     for event in parser {
-        let ParseEvent { kind, byte_offset } = match event {
-            Ok(event) => event,
-            Err(err) => return Err(err),
-        };
+        let ParseEvent { kind, byte_offset } = event?;
 
-        match kind {
-            ParseEventKind::StartObject => {
-                container_stack.push(Container::new_map());
-            }
+        if let Some(limits) = &mut limits {
+            limits.check(byte_offset, &kind)?;
+        }
 
-            ParseEventKind::EndObject => {
-                let object = container_stack.pop().unwrap().into_map().finish();
-                match container_stack.last_mut() {
-                    Some(container) => container.add_object(object),
-                    None => return Ok(object),
-                }
+        let object = match kind {
+            ParseEventKind::StartObject => {
+                container_stack.push(Container::new_map(byte_offset, config));
+                continue;
             }
 
             ParseEventKind::StartArray => {
-                container_stack.push(Container::new_array());
+                container_stack.push(Container::new_array(byte_offset));
+                continue;
             }
 
-            ParseEventKind::EndArray => {
-                let array = container_stack.pop().unwrap().into_array();
-                let object = Json::Array(array);
-                match container_stack.last_mut() {
-                    Some(container) => container.add_object(object),
-                    None => return Ok(object),
-                }
+            ParseEventKind::EndObject | ParseEventKind::EndArray => {
+                container_stack.pop().unwrap().finish(byte_offset + 1)
             }
 
-            ParseEventKind::Int(int) => {
-                let object = Json::Int(int);
-                match container_stack.last_mut() {
-                    Some(container) => container.add_object(object),
-                    None => return Ok(object),
-                }
-            }
+            ParseEventKind::Comment { .. } => continue,
 
-            ParseEventKind::Str { size_in_bytes } => {
-                let string = input[byte_offset..byte_offset + size_in_bytes].to_string();
-                let object = Json::String(string);
-                match container_stack.last_mut() {
-                    Some(container) => container.add_object(object),
-                    None => return Ok(object),
-                }
-            }
+            kind => T::leaf(input, byte_offset, kind)?,
+        };
 
-            ParseEventKind::Bool(bool) => {
-                let object = Json::Bool(bool);
-                match container_stack.last_mut() {
-                    Some(container) => container.add_object(object),
-                    None => return Ok(object),
-                }
-            }
+        match container_stack.last_mut() {
+            Some(container) => container.add_object(object, byte_offset)?,
+            None => return Ok(object),
+        }
+    }
 
-            ParseEventKind::Null => {
-                let object = Json::Null;
-                match container_stack.last_mut() {
-                    Some(container) => container.add_object(object),
-                    None => return Ok(object),
-                }
-            }
+    let expected = match container_stack.last() {
+        Some(container) => container.expected(),
+        None => Expected::Value,
+    };
+
+    Err(ParseError::unexpected_end_of_input(
+        input.as_bytes().len(),
+        expected,
+    ))
+}
 
-            ParseEventKind::Comment { .. } => {}
+/// Input of the AST builders, as text or bytes.
+///
+/// The AST builders only read the text of the strings and numbers in the events. Parsers of byte
+/// input validate UTF-8 of strings, and numbers are ASCII, so the text of these tokens is valid
+/// UTF-8 in byte inputs too.
+#[derive(Debug, Clone, Copy)]
+pub enum TreeInput<'a> {
+    Str(&'a str),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> TreeInput<'a> {
+    pub(crate) fn as_bytes(self) -> &'a [u8] {
+        match self {
+            TreeInput::Str(str) => str.as_bytes(),
+            TreeInput::Bytes(bytes) => bytes,
         }
     }
 
-    Err(ParseError {
-        byte_offset: input.len(),
-        reason: "unexpected end of input",
-    })
+    /// Text of the string or number at `byte_offset`.
+    pub(crate) fn text(self, byte_offset: usize, size_in_bytes: usize) -> &'a str {
+        let range = byte_offset..byte_offset + size_in_bytes;
+        match self {
+            TreeInput::Str(str) => &str[range],
+            TreeInput::Bytes(bytes) => std::str::from_utf8(&bytes[range]).unwrap(),
+        }
+    }
+}
+
+/// The AST types built from events: [Json] and [SpannedJson].
+pub trait Tree: Sized {
+    /// Type of object keys.
+    type Key: AsRef<str>;
+
+    /// Builds a value from an event of a scalar value, which starts at `byte_offset`.
+    fn leaf(input: TreeInput, byte_offset: usize, kind: ParseEventKind)
+        -> Result<Self, ParseError>;
+
+    fn array(span: Span, elems: Vec<Self>) -> Self;
+
+    fn object(span: Span, members: Vec<(Self::Key, Self)>) -> Self;
+
+    /// Converts a string value to a key. Panics if the value is not a string.
+    fn into_key(self) -> Self::Key;
+}
+
+impl Tree for Json {
+    type Key = String;
+
+    fn leaf(
+        input: TreeInput,
+        byte_offset: usize,
+        kind: ParseEventKind,
+    ) -> Result<Self, ParseError> {
+        Ok(match kind {
+            ParseEventKind::Int(int) => Json::Int(int),
+            ParseEventKind::NegInt(int) => Json::NegInt(int),
+            ParseEventKind::Float(float) => Json::Float(float),
+            ParseEventKind::RawNumber { size_in_bytes } => {
+                Json::RawNumber(input.text(byte_offset, size_in_bytes).to_owned())
+            }
+            ParseEventKind::Str {
+                size_in_bytes,
+                has_escapes,
+            } => Json::String(decode_text(
+                input.text(byte_offset, size_in_bytes),
+                byte_offset,
+                has_escapes,
+            )?),
+            ParseEventKind::Bool(bool) => Json::Bool(bool),
+            ParseEventKind::Null => Json::Null,
+            ParseEventKind::StartObject
+            | ParseEventKind::EndObject
+            | ParseEventKind::StartArray
+            | ParseEventKind::EndArray
+            | ParseEventKind::Comment { .. } => panic!(),
+        })
+    }
+
+    fn array(_span: Span, elems: Vec<Self>) -> Self {
+        Json::Array(elems)
+    }
+
+    fn object(_span: Span, members: Vec<(Self::Key, Self)>) -> Self {
+        Json::Object(members)
+    }
+
+    fn into_key(self) -> Self::Key {
+        self.into_string()
+    }
 }
 
-pub(crate) enum Container {
-    Array(Vec<Json>),
-    Map(MapInProgress),
+/// Returns contents of the string at the given location, decoding escape sequences.
+pub(crate) fn decode_string(
+    input: &str,
+    byte_offset: usize,
+    size_in_bytes: usize,
+    has_escapes: bool,
+) -> Result<String, ParseError> {
+    decode_text(
+        &input[byte_offset..byte_offset + size_in_bytes],
+        byte_offset,
+        has_escapes,
+    )
 }
 
-pub(crate) struct MapInProgress {
-    built: Vec<(String, Json)>,
-    next: Option<String>,
+/// Returns the contents of the string `str` at `byte_offset`, decoding escape sequences.
+pub(crate) fn decode_text(
+    str: &str,
+    byte_offset: usize,
+    has_escapes: bool,
+) -> Result<String, ParseError> {
+    if has_escapes {
+        unescape(str, byte_offset)
+    } else {
+        Ok(str.to_string())
+    }
 }
 
-impl Container {
-    pub(crate) fn new_map() -> Container {
+pub(crate) enum Container<T: Tree> {
+    Array(ArrayInProgress<T>),
+    Map(MapInProgress<T>),
+}
+
+pub(crate) struct ArrayInProgress<T> {
+    /// Byte offset of the '['.
+    start: usize,
+    built: Vec<T>,
+}
+
+pub(crate) struct MapInProgress<T: Tree> {
+    /// Byte offset of the '{'.
+    start: usize,
+    built: ObjectMembers<T::Key, T>,
+
+    /// The key of the member being parsed, and where to add the member.
+    next: Option<(T::Key, MemberSlot)>,
+}
+
+impl<T: Tree> Container<T> {
+    pub(crate) fn new_map(start: usize, config: ParserConfig) -> Container<T> {
         Container::Map(MapInProgress {
-            built: vec![],
+            start,
+            built: ObjectMembers::new(config.duplicate_keys),
             next: None,
         })
     }
 
-    pub(crate) fn new_array() -> Container {
-        Container::Array(vec![])
+    pub(crate) fn new_array(start: usize) -> Container<T> {
+        Container::Array(ArrayInProgress {
+            start,
+            built: vec![],
+        })
     }
 
-    pub(crate) fn into_map(self) -> MapInProgress {
+    /// Adds an element, object key, or object value. `byte_offset` is the byte offset of the
+    /// object's event, used to report duplicate keys.
+    pub(crate) fn add_object(&mut self, object: T, byte_offset: usize) -> Result<(), ParseError> {
         match self {
-            Container::Array(_) => panic!(),
-            Container::Map(map) => map,
+            Container::Array(array) => {
+                array.built.push(object);
+                Ok(())
+            }
+            Container::Map(map) => map.add(object, byte_offset),
         }
     }
 
-    pub(crate) fn into_array(self) -> Vec<Json> {
+    /// Builds the array or object. `end` is the byte offset after the closing bracket.
+    pub(crate) fn finish(self, end: usize) -> T {
         match self {
-            Container::Array(array) => array,
-            Container::Map(_) => panic!(),
+            Container::Array(ArrayInProgress { start, built }) => {
+                T::array(Span::new(start, end), built)
+            }
+            Container::Map(MapInProgress { start, built, next }) => {
+                assert!(next.is_none());
+                T::object(Span::new(start, end), built.into_vec())
+            }
         }
     }
 
-    pub(crate) fn add_object(&mut self, object: Json) {
+    /// What is expected next in the container, for errors when the events end before the
+    /// container is finished.
+    pub(crate) fn expected(&self) -> Expected {
         match self {
-            Container::Array(array) => array.push(object),
-            Container::Map(map) => map.add(object),
+            Container::Array(array) if array.built.is_empty() => Expected::ValueOrEndArray,
+            Container::Array(_) => Expected::CommaOrEndArray,
+            Container::Map(map) if map.next.is_some() => Expected::Value,
+            Container::Map(map) if map.built.is_empty() => Expected::KeyOrEndObject,
+            Container::Map(_) => Expected::CommaOrEndObject,
         }
     }
 }
 
-impl MapInProgress {
-    pub(crate) fn add(&mut self, object: Json) {
+impl<T: Tree> MapInProgress<T> {
+    fn add(&mut self, object: T, byte_offset: usize) -> Result<(), ParseError> {
         match self.next.take() {
-            Some(key) => {
-                self.built.push((key, object));
+            Some((key, slot)) => {
+                self.built.push(key, object, slot);
             }
             None => {
-                self.next = Some(object.into_string());
+                let key = object.into_key();
+                // Strings events are at the first byte after the opening double quote.
+                let slot = self.built.check_key(key.as_ref(), byte_offset - 1)?;
+                self.next = Some((key, slot));
             }
         }
+        Ok(())
+    }
+}
+
+#[test]
+fn limit_tests() {
+    let tests = crate::test_common::limit_tests()
+        .into_iter()
+        .chain(crate::test_common::non_ascii_input_size_tests());
+    for (str, config, error) in tests {
+        println!("Parsing {:?}", str);
+        let result = event_to_tree_with_config(&mut crate::parse_events_iter(&str), &str, config);
+        assert_eq!(result.err(), error);
+        let result =
+            event_to_spanned_tree_with_config(&mut crate::parse_events_iter(&str), &str, config);
+        assert_eq!(result.err(), error);
+    }
+}
+
+#[test]
+fn duplicate_key_tests() {
+    for (str, config, result) in crate::test_common::duplicate_key_tests() {
+        println!("Parsing {:?}", str);
+        let ast = event_to_tree_with_config(&mut crate::parse_events_iter(&str), &str, config);
+        assert_eq!(ast, result);
+        let ast =
+            event_to_spanned_tree_with_config(&mut crate::parse_events_iter(&str), &str, config);
+        assert_eq!(ast.map(SpannedJson::into_json), result);
+    }
+}
+
+#[test]
+fn bytes_tests() {
+    // Valid UTF-8 is built the same as `&str` input.
+    for (str, _) in crate::test_common::ast_tests() {
+        let events = || crate::parse_events_iter_bytes(str.as_bytes());
+        assert_eq!(
+            event_to_tree_bytes(&mut events(), str.as_bytes()),
+            event_to_tree(&mut crate::parse_events_iter(&str), &str)
+        );
+        assert_eq!(
+            event_to_spanned_tree_bytes(&mut events(), str.as_bytes()),
+            event_to_spanned_tree(&mut crate::parse_events_iter(&str), &str)
+        );
+    }
+
+    for (str, config, error) in crate::test_common::non_ascii_input_size_tests() {
+        let result = event_to_tree_bytes_with_config(
+            &mut crate::parse_events_iter_bytes_with_config(str.as_bytes(), config),
+            str.as_bytes(),
+            config,
+        );
+        assert_eq!(result.err(), error);
     }
 
-    pub(crate) fn finish(self) -> Json {
-        let MapInProgress { built, next } = self;
-        assert!(next.is_none());
-        Json::Object(built)
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        let result = event_to_tree_bytes(&mut crate::parse_events_iter_bytes(&bytes), &bytes);
+        assert_eq!(result, Err(error), "{:?}", bytes);
     }
 }
@@ -0,0 +1,471 @@
+use crate::{
+    number_text, write_json, EventListener, Json, ListenerAction, ParseError, ParseEvent,
+    ParseEventKind,
+};
+
+use std::convert::Infallible;
+
+use std::fmt;
+use std::io;
+
+/// Writes [ParseEvent]s of an input as JSON text, without building an AST.
+///
+/// Events can be passed with [EventWriter::write_event], or the writer can be used as an
+/// [EventListener]. Events should be generated from `input`: the writer copies strings and
+/// numbers from the input, as they appear in the input. Values that are not in the input, for
+/// example to replace a part of the input, can be written with [EventWriter::write_json].
+///
+/// Output is compact, and the writer only allocates for the nesting of containers. Commas and
+/// colons are written by the writer, so event streams with events removed (e.g. an object member)
+/// are still written as valid JSON.
+///
+/// The writer does not buffer the output. Use a [io::BufWriter] when writing to a file or socket.
+pub struct EventWriter<'a, W: io::Write> {
+    input: &'a str,
+    out: W,
+    keep_comments: bool,
+    container_stack: Vec<Container>,
+
+    /// The first error in [EventListener] methods.
+    error: Option<EventWriteError>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Container {
+    Array {
+        is_empty: bool,
+    },
+    Object {
+        is_empty: bool,
+
+        /// Whether the next string is a key.
+        expect_key: bool,
+    },
+}
+
+/// Errors reported by [EventWriter].
+#[derive(Debug)]
+pub enum EventWriteError {
+    /// The input could not be parsed.
+    Parse(ParseError),
+
+    /// The output could not be written.
+    Io(io::Error),
+}
+
+impl<'a, W: io::Write> EventWriter<'a, W> {
+    /// Creates a writer for the events of `input`. When `keep_comments` is `true`, comments in the
+    /// events are written, each followed by a newline.
+    pub fn new(input: &'a str, out: W, keep_comments: bool) -> EventWriter<'a, W> {
+        EventWriter {
+            input,
+            out,
+            keep_comments,
+            container_stack: vec![],
+            error: None,
+        }
+    }
+
+    /// Writes the next event.
+    pub fn write_event(&mut self, event: &ParseEvent) -> io::Result<()> {
+        let byte_offset = event.byte_offset;
+        match event.kind {
+            ParseEventKind::StartObject => {
+                self.start_value()?;
+                self.container_stack.push(Container::Object {
+                    is_empty: true,
+                    expect_key: true,
+                });
+                self.out.write_all(b"{")
+            }
+
+            ParseEventKind::EndObject => {
+                let container = self.container_stack.pop();
+                debug_assert!(matches!(container, Some(Container::Object { .. })));
+                self.out.write_all(b"}")
+            }
+
+            ParseEventKind::StartArray => {
+                self.start_value()?;
+                self.container_stack
+                    .push(Container::Array { is_empty: true });
+                self.out.write_all(b"[")
+            }
+
+            ParseEventKind::EndArray => {
+                let container = self.container_stack.pop();
+                debug_assert!(matches!(container, Some(Container::Array { .. })));
+                self.out.write_all(b"]")
+            }
+
+            ParseEventKind::Int(_) | ParseEventKind::NegInt(_) | ParseEventKind::Float(_) => {
+                self.start_value()?;
+                let text = number_text(self.input, byte_offset).unwrap();
+                self.out.write_all(text.as_bytes())
+            }
+
+            ParseEventKind::RawNumber { size_in_bytes } => {
+                self.start_value()?;
+                let text = &self.input[byte_offset..byte_offset + size_in_bytes];
+                self.out.write_all(text.as_bytes())
+            }
+
+            ParseEventKind::Str { size_in_bytes, .. } => {
+                let is_key = self.start_value()?;
+                // Strings are reported after the opening double quote.
+                let text = &self.input[byte_offset - 1..byte_offset + size_in_bytes + 1];
+                self.out.write_all(text.as_bytes())?;
+                if is_key {
+                    self.out.write_all(b":")?;
+                }
+                Ok(())
+            }
+
+            ParseEventKind::Bool(b) => {
+                self.start_value()?;
+                self.out.write_all(if b { b"true" } else { b"false" })
+            }
+
+            ParseEventKind::Null => {
+                self.start_value()?;
+                self.out.write_all(b"null")
+            }
+
+            ParseEventKind::Comment { size_in_bytes } => {
+                if !self.keep_comments {
+                    return Ok(());
+                }
+                let text = &self.input[byte_offset..byte_offset + size_in_bytes];
+                self.out.write_all(text.as_bytes())?;
+                // Comment at the end of the input does not have a newline, but we need one to end
+                // the comment if more is written after it.
+                if !text.ends_with('\n') {
+                    self.out.write_all(b"\n")?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes `json` as the next value, in place of the events of a value.
+    ///
+    /// `json` cannot be an object key: keys should be written with [EventWriter::write_event].
+    pub fn write_json(&mut self, json: &Json) -> io::Result<()> {
+        let is_key = self.start_value()?;
+        debug_assert!(!is_key);
+        self.out.write_all(write_json(json).as_bytes())
+    }
+
+    /// Returns the output, or the first error reported to the writer as an [EventListener].
+    pub fn finish(self) -> Result<W, EventWriteError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.out),
+        }
+    }
+
+    /// Writes the ',' before the next value or key when needed, and updates the container state.
+    /// Returns whether the next value is an object key.
+    fn start_value(&mut self) -> io::Result<bool> {
+        let (needs_comma, is_key) = match self.container_stack.last_mut() {
+            None => (false, false),
+
+            Some(Container::Array { is_empty }) => {
+                let needs_comma = !*is_empty;
+                *is_empty = false;
+                (needs_comma, false)
+            }
+
+            Some(Container::Object {
+                is_empty,
+                expect_key,
+            }) => {
+                let is_key = *expect_key;
+                *expect_key = !is_key;
+                let needs_comma = is_key && !*is_empty;
+                *is_empty = false;
+                (needs_comma, is_key)
+            }
+        };
+
+        if needs_comma {
+            self.out.write_all(b",")?;
+        }
+
+        Ok(is_key)
+    }
+
+    fn handle_event(
+        &mut self,
+        byte_offset: usize,
+        kind: ParseEventKind,
+    ) -> ListenerAction<Infallible> {
+        if self.error.is_some() {
+            return ListenerAction::Stop;
+        }
+        match self.write_event(&ParseEvent::new(byte_offset, kind)) {
+            Ok(()) => ListenerAction::Continue,
+            Err(error) => {
+                self.error = Some(EventWriteError::Io(error));
+                ListenerAction::Stop
+            }
+        }
+    }
+}
+
+impl<'a, W: io::Write> EventListener for EventWriter<'a, W> {
+    type Error = Infallible;
+
+    fn handle_start_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::StartObject)
+    }
+
+    fn handle_end_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::EndObject)
+    }
+
+    fn handle_start_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::StartArray)
+    }
+
+    fn handle_end_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::EndArray)
+    }
+
+    fn handle_int(&mut self, byte_offset: usize, i: u64) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::Int(i))
+    }
+
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::NegInt(i))
+    }
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::Float(f))
+    }
+
+    fn handle_raw_number(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::RawNumber { size_in_bytes })
+    }
+
+    fn handle_str(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+        has_escapes: bool,
+    ) -> ListenerAction<Infallible> {
+        self.handle_event(
+            byte_offset,
+            ParseEventKind::Str {
+                size_in_bytes,
+                has_escapes,
+            },
+        )
+    }
+
+    fn handle_bool(&mut self, byte_offset: usize, b: bool) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::Bool(b))
+    }
+
+    fn handle_null(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::Null)
+    }
+
+    fn handle_comment(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
+        self.handle_event(byte_offset, ParseEventKind::Comment { size_in_bytes })
+    }
+
+    fn handle_error(&mut self, error: ParseError) {
+        self.error.get_or_insert(EventWriteError::Parse(error));
+    }
+}
+
+impl From<ParseError> for EventWriteError {
+    fn from(error: ParseError) -> Self {
+        EventWriteError::Parse(error)
+    }
+}
+
+impl From<io::Error> for EventWriteError {
+    fn from(error: io::Error) -> Self {
+        EventWriteError::Io(error)
+    }
+}
+
+impl fmt::Display for EventWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventWriteError::Parse(error) => write!(f, "{}", error),
+            EventWriteError::Io(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for EventWriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EventWriteError::Parse(error) => Some(error),
+            EventWriteError::Io(error) => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+fn write_iter(input: &str, keep_comments: bool) -> Result<String, EventWriteError> {
+    let mut writer = EventWriter::new(input, vec![], keep_comments);
+    for event in crate::parse_events_iter(input) {
+        writer.write_event(&event?)?;
+    }
+    Ok(String::from_utf8(writer.finish()?).unwrap())
+}
+
+#[cfg(test)]
+fn write_push(input: &str, keep_comments: bool) -> Result<String, EventWriteError> {
+    let mut writer = EventWriter::new(input, vec![], keep_comments);
+    crate::parse_events_push(input, &mut writer);
+    Ok(String::from_utf8(writer.finish()?).unwrap())
+}
+
+#[test]
+fn write_test() {
+    let input = r#" { "a" : [ 1 , -2.50e1, "x\n" ] , // comment
+        "b":{}, "c" : [ ], "d": 123456789012345678901234567890 } // end"#;
+
+    let config = crate::ParserConfig {
+        int_overflow: crate::IntOverflow::Raw,
+        ..crate::ParserConfig::default()
+    };
+    let mut writer = EventWriter::new(input, vec![], false);
+    for event in crate::parse_events_iter_with_config(input, config) {
+        writer.write_event(&event.unwrap()).unwrap();
+    }
+    assert_eq!(
+        String::from_utf8(writer.finish().unwrap()).unwrap(),
+        r#"{"a":[1,-2.50e1,"x\n"],"b":{},"c":[],"d":123456789012345678901234567890}"#
+    );
+
+    let mut writer = EventWriter::new(input, vec![], true);
+    crate::parse_events_push_with_config(input, config, &mut writer);
+    assert_eq!(
+        String::from_utf8(writer.finish().unwrap()).unwrap(),
+        "{\"a\":[1,-2.50e1,\"x\\n\"]// comment\n,\"b\":{},\"c\":[],\
+         \"d\":123456789012345678901234567890}// end\n"
+    );
+}
+
+#[test]
+fn round_trip_tests() {
+    for (str, ast) in crate::test_common::ast_tests() {
+        println!("Parsing {:?}", str);
+        for keep_comments in [false, true] {
+            let output = write_iter(&str, keep_comments).unwrap();
+            assert_eq!(crate::parse_ast_recursive(&output).unwrap(), ast);
+            assert_eq!(write_push(&str, keep_comments).unwrap(), output);
+        }
+    }
+}
+
+#[test]
+fn round_trip_random_tests() {
+    for input_size in [10, 100, 1_000, 10_000] {
+        // `gen_input` generates compact JSON.
+        let input = crate::gen_input(input_size);
+        assert_eq!(write_iter(&input, false).unwrap(), input);
+        assert_eq!(write_push(&input, false).unwrap(), input);
+    }
+}
+
+#[test]
+fn filter_test() {
+    // Redact passwords and drop "debug" members, without building an AST.
+    let input = r#"[{"user": "a", "password": "x", "debug": [1, {"b": 2}], "id": 1},
+                    {"debug": null, "password": "y"}]"#;
+
+    let mut writer = EventWriter::new(input, vec![], false);
+    let mut events = crate::parse_events_iter(input);
+    let mut depth = 0;
+    while let Some(event) = events.next() {
+        let event = event.unwrap();
+        let key = match event.kind {
+            ParseEventKind::Str { size_in_bytes, .. } if depth == 2 => {
+                Some(&input[event.byte_offset..event.byte_offset + size_in_bytes])
+            }
+            _ => None,
+        };
+        match event.kind {
+            ParseEventKind::StartArray | ParseEventKind::StartObject => depth += 1,
+            ParseEventKind::EndArray | ParseEventKind::EndObject => depth -= 1,
+            _ => {}
+        }
+        match key {
+            Some("password") => {
+                writer.write_event(&event).unwrap();
+                events.next().unwrap().unwrap();
+                writer.write_json(&Json::String("***".to_string())).unwrap();
+            }
+            Some("debug") => {
+                // Skip the value, with nested containers.
+                let mut value_depth = 0;
+                for event in events.by_ref() {
+                    match event.unwrap().kind {
+                        ParseEventKind::StartArray | ParseEventKind::StartObject => {
+                            value_depth += 1
+                        }
+                        ParseEventKind::EndArray | ParseEventKind::EndObject => value_depth -= 1,
+                        _ => {}
+                    }
+                    if value_depth == 0 {
+                        break;
+                    }
+                }
+            }
+            _ => writer.write_event(&event).unwrap(),
+        }
+    }
+
+    assert_eq!(
+        String::from_utf8(writer.finish().unwrap()).unwrap(),
+        r#"[{"user":"a","password":"***","id":1},{"password":"***"}]"#
+    );
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        assert!(
+            matches!(write_iter(&str, true), Err(EventWriteError::Parse(error_)) if error_ == error)
+        );
+        assert!(
+            matches!(write_push(&str, true), Err(EventWriteError::Parse(error_)) if error_ == error)
+        );
+    }
+}
+
+#[test]
+fn io_error_test() {
+    struct FailingWriter;
+
+    impl io::Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let input = "[1, 2]";
+    let mut writer = EventWriter::new(input, FailingWriter, false);
+    crate::parse_events_push(input, &mut writer);
+    assert!(matches!(writer.finish(), Err(EventWriteError::Io(_))));
+}
@@ -0,0 +1,764 @@
+use crate::event_to_tree::decode_string;
+use crate::path_matcher::{Builders, PathMatcher, PathQuery, Step};
+use crate::{
+    parse_events_iter, EventListener, Json, ListenerAction, ParseError, ParseEvent, ParseEventKind,
+};
+
+use std::convert::Infallible;
+use std::fmt;
+
+/// A path to a field, like `user.id` or `events[3].ts`.
+///
+/// A path is a sequence of object keys separated by `.`, each followed by any number of array
+/// indices or quoted keys in brackets. The path can also start with a bracket, like `[0].id`. The
+/// empty path refers to the whole document.
+///
+/// Keys with `.`, `[`, or other special characters are written quoted in brackets, as JSON
+/// strings: `labels["k8s.pod"]` is the `k8s.pod` member of `labels`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPath {
+    segments: Vec<PathSegment>,
+}
+
+/// A step in a [FieldPath].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// An object member.
+    Key(String),
+
+    /// An array element.
+    Index(usize),
+}
+
+/// An invalid [FieldPath], reported by [FieldPath::parse]. `byte_offset` is the location of the
+/// error in the path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPathError {
+    pub byte_offset: usize,
+}
+
+impl FieldPath {
+    pub fn parse(path: &str) -> Result<FieldPath, FieldPathError> {
+        let mut segments = vec![];
+        if path.is_empty() {
+            return Ok(FieldPath { segments });
+        }
+
+        let mut byte_offset = 0;
+        loop {
+            let rest = &path[byte_offset..];
+
+            // A key, which can only be omitted at the start before an index.
+            let key_len = rest.find(['.', '[']).unwrap_or(rest.len());
+            if key_len != 0 {
+                segments.push(PathSegment::Key(rest[..key_len].to_owned()));
+            } else if byte_offset != 0 || !rest.starts_with('[') {
+                return Err(FieldPathError { byte_offset });
+            }
+            byte_offset += key_len;
+
+            // Indices and quoted keys.
+            while path[byte_offset..].starts_with('[') {
+                let rest = &path[byte_offset + 1..];
+                if rest.starts_with('"') {
+                    let (key, key_len) = parse_quoted_key(rest, byte_offset + 1)?;
+                    if !rest[key_len..].starts_with(']') {
+                        return Err(FieldPathError { byte_offset });
+                    }
+                    segments.push(PathSegment::Key(key));
+                    byte_offset += key_len + 2;
+                    continue;
+                }
+                let digits_len = rest.find(']').ok_or(FieldPathError { byte_offset })?;
+                let digits = &rest[..digits_len];
+                if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+                    return Err(FieldPathError { byte_offset });
+                }
+                let index = digits.parse().map_err(|_| FieldPathError { byte_offset })?;
+                segments.push(PathSegment::Index(index));
+                byte_offset += digits_len + 2;
+            }
+
+            match path[byte_offset..].chars().next() {
+                None => return Ok(FieldPath { segments }),
+                Some('.') => byte_offset += 1,
+                Some(_) => return Err(FieldPathError { byte_offset }),
+            }
+        }
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+}
+
+/// Parses a key in double quotes at the start of `str`, with JSON escape sequences. `byte_offset`
+/// is the location of `str` in the path.
+///
+/// Returns the decoded key and the size of the key in `str`, with the quotes.
+fn parse_quoted_key(str: &str, byte_offset: usize) -> Result<(String, usize), FieldPathError> {
+    let mut chars = str.char_indices().skip(1);
+    while let Some((char_idx, char)) = chars.next() {
+        match char {
+            '"' => {
+                let key = crate::unescape(&str[1..char_idx], byte_offset + 1).map_err(|err| {
+                    FieldPathError {
+                        byte_offset: err.byte_offset,
+                    }
+                })?;
+                return Ok((key, char_idx + 1));
+            }
+            '\\' => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+
+    // Unterminated key, report the opening bracket.
+    Err(FieldPathError {
+        byte_offset: byte_offset - 1,
+    })
+}
+
+/// The expected type of a field extracted by [FieldExtractor].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    /// A non-negative integer.
+    U64,
+
+    /// An integer that fits into `i64`.
+    I64,
+
+    /// Any number other than raw numbers, converted to `f64`.
+    F64,
+
+    Bool,
+
+    /// A string, with escape sequences decoded.
+    String,
+
+    /// Any value, including arrays and objects.
+    Json,
+}
+
+/// A value extracted by [FieldExtractor].
+#[derive(Debug, PartialEq)]
+pub enum FieldValue {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    String(String),
+    Json(Json),
+}
+
+/// Extracts the values at the given paths in `input`, without building an AST for the rest of the
+/// input. See [FieldExtractor].
+///
+/// Arrays and objects that can't contain the fields are skipped with `EventParser::skip_value`, so
+/// they are only checked for balanced brackets and strings.
+pub fn extract_fields(
+    input: &str,
+    fields: Vec<(FieldPath, FieldType)>,
+) -> Result<Vec<Option<FieldValue>>, ParseError> {
+    let mut parser = parse_events_iter(input);
+    let mut extractor = FieldExtractor::new(input, fields);
+    loop {
+        if extractor.skips_next_value() {
+            if let Err(error) = parser.skip_value() {
+                extractor.handle_error(error);
+                break;
+            }
+            extractor.value_skipped();
+        }
+        match parser.next() {
+            None => break,
+            Some(Ok(ParseEvent { kind, byte_offset })) => {
+                if !extractor.event(byte_offset, &kind) {
+                    break;
+                }
+            }
+            Some(Err(error)) => {
+                extractor.handle_error(error);
+                break;
+            }
+        }
+    }
+    extractor.into_values()
+}
+
+/// Same as [extract_fields], but with the events of `input` generated by the given parser. Events
+/// after the last field are not consumed.
+pub fn extract_fields_from_events<I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: &mut I,
+    input: &str,
+    fields: Vec<(FieldPath, FieldType)>,
+) -> Result<Vec<Option<FieldValue>>, ParseError> {
+    let mut extractor = FieldExtractor::new(input, fields);
+    for event in parser {
+        match event {
+            Ok(ParseEvent { kind, byte_offset }) => {
+                if !extractor.event(byte_offset, &kind) {
+                    break;
+                }
+            }
+            Err(error) => {
+                extractor.handle_error(error);
+                break;
+            }
+        }
+    }
+    extractor.into_values()
+}
+
+/// An [EventListener] that extracts the values at a set of [FieldPath]s in one pass.
+///
+/// Each field is resolved by the first value at its path. When the value doesn't have the field's
+/// [FieldType] the field's value is `None`, the same as when the input doesn't have the field.
+///
+/// Values that can't contain any of the unresolved fields are skipped without decoding. The
+/// listener returns [ListenerAction::SkipChildren] for arrays and objects that can't contain the
+/// unresolved fields, so with [parse_events_push][crate::event_push_parser::parse] they are only
+/// checked for balanced brackets and strings. The listener returns [ListenerAction::Stop] when
+/// all fields are resolved, or when the rest of the input can't contain the unresolved fields, so
+/// errors after that are not reported.
+pub struct FieldExtractor<'a> {
+    /// Tracks the arrays and objects that may contain unresolved fields, with the indices of the
+    /// fields with paths that go through them.
+    matcher: PathMatcher<'a, Vec<usize>>,
+
+    fields: Fields<'a>,
+
+    error: Option<ParseError>,
+
+    /// Whether the listener stopped parsing. Later events and errors are ignored.
+    done: bool,
+}
+
+/// The fields of a [FieldExtractor], and their values. The state of a value is the indices of the
+/// unresolved fields with paths that match the location of the value.
+struct Fields<'a> {
+    input: &'a str,
+
+    fields: Vec<(FieldPath, FieldType)>,
+
+    values: Vec<Option<FieldValue>>,
+
+    /// Whether the fields are resolved. [FieldType::Json] fields are resolved when their first
+    /// event is seen, and their values are set when the values are built.
+    resolved: Vec<bool>,
+
+    num_unresolved: usize,
+
+    /// Builders of [FieldType::Json] values, with the indices of their fields.
+    builders: Builders<'a, usize>,
+}
+
+impl<'a> FieldExtractor<'a> {
+    pub fn new(input: &'a str, fields: Vec<(FieldPath, FieldType)>) -> FieldExtractor<'a> {
+        let num_fields = fields.len();
+        FieldExtractor {
+            matcher: PathMatcher::new(input),
+            fields: Fields {
+                input,
+                fields,
+                values: (0..num_fields).map(|_| None).collect(),
+                resolved: vec![false; num_fields],
+                num_unresolved: num_fields,
+                builders: Builders::new(input),
+            },
+            error: None,
+            done: false,
+        }
+    }
+
+    /// Returns the values of the fields, in the order of the fields passed to
+    /// [FieldExtractor::new].
+    pub fn into_values(self) -> Result<Vec<Option<FieldValue>>, ParseError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.fields.values),
+        }
+    }
+
+    /// Handles an event. Returns whether to keep parsing.
+    fn event(&mut self, byte_offset: usize, kind: &ParseEventKind) -> bool {
+        if self.done {
+            return false;
+        }
+
+        let fields = &mut self.fields;
+        let num_builders = fields.builders.len();
+        let result = fields
+            .feed_builders(0, byte_offset, kind)
+            .and_then(|()| self.matcher.event(fields, byte_offset, kind))
+            .and_then(|()| fields.feed_builders(num_builders, byte_offset, kind));
+        if let Err(error) = result {
+            self.error = Some(error);
+            self.done = true;
+            return false;
+        }
+
+        if self.fields.builders.is_empty()
+            && (self.fields.num_unresolved == 0 || self.matcher.finished())
+        {
+            self.done = true;
+            return false;
+        }
+
+        true
+    }
+
+    /// Handles the start of an array or object from a push parser. Skips the contents when they
+    /// can't contain unresolved fields, and are not a part of a [FieldType::Json] value.
+    fn start_event(
+        &mut self,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> ListenerAction<Infallible> {
+        if !self.event(byte_offset, kind) {
+            ListenerAction::Stop
+        } else if self.matcher.skips_children() && self.fields.builders.is_empty() {
+            ListenerAction::SkipChildren
+        } else {
+            ListenerAction::Continue
+        }
+    }
+
+    /// Returns whether the next value can't contain unresolved fields, so it can be skipped
+    /// without events. Call [FieldExtractor::value_skipped] after skipping it.
+    fn skips_next_value(&self) -> bool {
+        if self.done || !self.fields.builders.is_empty() {
+            return false;
+        }
+        matches!(
+            self.matcher.next_value_state(&self.fields),
+            Some(matches) if matches.is_empty()
+        )
+    }
+
+    /// Updates the innermost container after skipping a value. See
+    /// [FieldExtractor::skips_next_value].
+    fn value_skipped(&mut self) {
+        self.matcher.value_skipped();
+    }
+}
+
+impl<'a> Fields<'a> {
+    /// Passes the event to the builders starting at index `first`, and sets the values that are
+    /// complete.
+    fn feed_builders(
+        &mut self,
+        first: usize,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> Result<(), ParseError> {
+        let values = &mut self.values;
+        self.builders.feed(first, byte_offset, kind, |field, json| {
+            values[field] = Some(FieldValue::Json(json))
+        })
+    }
+
+    /// Resolves a field with the first event of its value.
+    fn resolve(
+        &mut self,
+        field: usize,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> Result<(), ParseError> {
+        self.resolved[field] = true;
+        self.num_unresolved -= 1;
+
+        self.values[field] = match (self.fields[field].1, kind) {
+            (FieldType::U64, ParseEventKind::Int(i)) => Some(FieldValue::U64(*i)),
+            (FieldType::I64, ParseEventKind::Int(i)) => i64::try_from(*i).ok().map(FieldValue::I64),
+            (FieldType::I64, ParseEventKind::NegInt(i)) => Some(FieldValue::I64(*i)),
+            (FieldType::F64, ParseEventKind::Int(i)) => Some(FieldValue::F64(*i as f64)),
+            (FieldType::F64, ParseEventKind::NegInt(i)) => Some(FieldValue::F64(*i as f64)),
+            (FieldType::F64, ParseEventKind::Float(f)) => Some(FieldValue::F64(*f)),
+            (FieldType::Bool, ParseEventKind::Bool(b)) => Some(FieldValue::Bool(*b)),
+            (
+                FieldType::String,
+                ParseEventKind::Str {
+                    size_in_bytes,
+                    has_escapes,
+                },
+            ) => Some(FieldValue::String(decode_string(
+                self.input,
+                byte_offset,
+                *size_in_bytes,
+                *has_escapes,
+            )?)),
+            (FieldType::Json, _) => {
+                // The builder gets the event after matching, and sets the value when the value is
+                // complete.
+                self.builders.push(field);
+                None
+            }
+            _ => None,
+        };
+
+        Ok(())
+    }
+}
+
+impl<'a> PathQuery for Fields<'a> {
+    type State = Vec<usize>;
+
+    fn top_level(&self) -> Vec<usize> {
+        (0..self.fields.len())
+            .filter(|&field| !self.resolved[field])
+            .collect()
+    }
+
+    fn step(&self, candidates: &Vec<usize>, depth: usize, step: &Step) -> Vec<usize> {
+        candidates
+            .iter()
+            .copied()
+            .filter(|&field| {
+                !self.resolved[field]
+                    && match (&self.fields[field].0.segments[depth - 1], step) {
+                        (PathSegment::Key(key), Step::Key(step_key)) => key == step_key,
+                        (PathSegment::Index(index), Step::Index(step_index)) => index == step_index,
+                        _ => false,
+                    }
+            })
+            .collect()
+    }
+
+    /// Resolves the fields at the value, and keeps the fields with paths that go through the
+    /// value, when it's an array or object.
+    fn value(
+        &mut self,
+        matches: &mut Vec<usize>,
+        depth: usize,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> Result<bool, ParseError> {
+        let is_array = match kind {
+            ParseEventKind::StartArray => Some(true),
+            ParseEventKind::StartObject => Some(false),
+            _ => None,
+        };
+
+        let mut candidates = vec![];
+        for &field in matches.iter() {
+            let segments = &self.fields[field].0.segments;
+            if segments.len() == depth {
+                self.resolve(field, byte_offset, kind)?;
+                continue;
+            }
+            match (is_array, &segments[depth]) {
+                (Some(true), PathSegment::Index(_)) | (Some(false), PathSegment::Key(_)) => {
+                    candidates.push(field)
+                }
+                _ => {}
+            }
+        }
+
+        *matches = candidates;
+        Ok(!matches.is_empty())
+    }
+}
+
+impl<'a> EventListener for FieldExtractor<'a> {
+    type Error = Infallible;
+
+    fn handle_start_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.start_event(byte_offset, &ParseEventKind::StartObject)
+    }
+
+    fn handle_end_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.event(byte_offset, &ParseEventKind::EndObject))
+    }
+
+    fn handle_start_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.start_event(byte_offset, &ParseEventKind::StartArray)
+    }
+
+    fn handle_end_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.event(byte_offset, &ParseEventKind::EndArray))
+    }
+
+    fn handle_int(&mut self, byte_offset: usize, i: u64) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.event(byte_offset, &ParseEventKind::Int(i)))
+    }
+
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.event(byte_offset, &ParseEventKind::NegInt(i)))
+    }
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.event(byte_offset, &ParseEventKind::Float(f)))
+    }
+
+    fn handle_raw_number(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(
+            self.event(byte_offset, &ParseEventKind::RawNumber { size_in_bytes }),
+        )
+    }
+
+    fn handle_str(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+        has_escapes: bool,
+    ) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.event(
+            byte_offset,
+            &ParseEventKind::Str {
+                size_in_bytes,
+                has_escapes,
+            },
+        ))
+    }
+
+    fn handle_bool(&mut self, byte_offset: usize, b: bool) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.event(byte_offset, &ParseEventKind::Bool(b)))
+    }
+
+    fn handle_null(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(self.event(byte_offset, &ParseEventKind::Null))
+    }
+
+    fn handle_comment(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
+        ListenerAction::continue_if(
+            self.event(byte_offset, &ParseEventKind::Comment { size_in_bytes }),
+        )
+    }
+
+    fn handle_error(&mut self, error: ParseError) {
+        if !self.done {
+            self.error = Some(error);
+            self.done = true;
+        }
+    }
+}
+
+impl fmt::Display for FieldPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid field path at byte {}", self.byte_offset)
+    }
+}
+
+impl std::error::Error for FieldPathError {}
+
+#[test]
+fn path_parse_test() {
+    use PathSegment::*;
+
+    let key = |key: &str| Key(key.to_owned());
+    let tests = [
+        ("", vec![]),
+        ("user.id", vec![key("user"), key("id")]),
+        ("events[3].ts", vec![key("events"), Index(3), key("ts")]),
+        ("[0][12].a", vec![Index(0), Index(12), key("a")]),
+        ("a b.c-d", vec![key("a b"), key("c-d")]),
+        (r#"labels["k8s.pod"]"#, vec![key("labels"), key("k8s.pod")]),
+        (r#"["a[0]"][1].b"#, vec![key("a[0]"), Index(1), key("b")]),
+        (
+            r#"a["\"]\\"][""].b"#,
+            vec![key("a"), key("\"]\\"), key(""), key("b")],
+        ),
+        (r#"a["\u00e9"]"#, vec![key("a"), key("é")]),
+    ];
+    for (path, segments) in tests {
+        assert_eq!(FieldPath::parse(path).unwrap().segments(), &segments);
+    }
+
+    let error_tests = [
+        ("a..b", 2),
+        (".a", 0),
+        ("a.", 2),
+        ("a[x]", 1),
+        ("a[]", 1),
+        ("a[1", 1),
+        ("a[1]b", 4),
+        ("a.[1]", 2),
+        (r#"a["b"#, 1),
+        (r#"a["b\"]"#, 1),
+        (r#"a["b"x]"#, 1),
+        (r#"a["b"].["c"]"#, 7),
+        (r#"a["b\x"]"#, 4),
+    ];
+    for (path, byte_offset) in error_tests {
+        assert_eq!(FieldPath::parse(path), Err(FieldPathError { byte_offset }));
+    }
+}
+
+#[cfg(test)]
+const TEST_INPUT: &str = r#"{
+    "user": {"id": 42, "name": "alice"},
+    "events": [{"ts": 1}, {"ts": 2}, {"ts": 3}, {"ts": -4, "tags": ["a", "b"]}],
+    "ok": true,
+    "score": 1.5,
+    "labels": {"k8s.pod": "web-1", "k8s": {"pod": "db-1"}}
+}"#;
+
+/// Fields of [TEST_INPUT], and their values.
+#[cfg(test)]
+fn extract_tests() -> Vec<(FieldPath, FieldType, Option<FieldValue>)> {
+    let json = |text: &str| Some(FieldValue::Json(crate::parse_ast_recursive(text).unwrap()));
+
+    [
+        ("user.id", FieldType::U64, Some(FieldValue::U64(42))),
+        (
+            "user.name",
+            FieldType::String,
+            Some(FieldValue::String("alice".to_owned())),
+        ),
+        ("events[3].ts", FieldType::I64, Some(FieldValue::I64(-4))),
+        ("events[3].tags", FieldType::Json, json(r#"["a", "b"]"#)),
+        // Nested in another field
+        (
+            "events[3]",
+            FieldType::Json,
+            json(r#"{"ts": -4, "tags": ["a", "b"]}"#),
+        ),
+        ("events[0].ts", FieldType::F64, Some(FieldValue::F64(1.0))),
+        ("ok", FieldType::Bool, Some(FieldValue::Bool(true))),
+        ("score", FieldType::F64, Some(FieldValue::F64(1.5))),
+        (
+            r#"labels["k8s.pod"]"#,
+            FieldType::String,
+            Some(FieldValue::String("web-1".to_owned())),
+        ),
+        (
+            "labels.k8s.pod",
+            FieldType::String,
+            Some(FieldValue::String("db-1".to_owned())),
+        ),
+        ("user.id", FieldType::Json, json("42")),
+        // Missing
+        ("user.email", FieldType::String, None),
+        ("events[9].ts", FieldType::U64, None),
+        ("user[0]", FieldType::U64, None),
+        ("ok.x", FieldType::U64, None),
+        // Different type
+        ("user.id", FieldType::String, None),
+        ("events[3].ts", FieldType::U64, None),
+    ]
+    .into_iter()
+    .map(|(path, ty, value)| (FieldPath::parse(path).unwrap(), ty, value))
+    .collect()
+}
+
+#[test]
+fn extract_fields_test() {
+    let (fields, values): (Vec<_>, Vec<_>) = extract_tests()
+        .into_iter()
+        .map(|(path, ty, value)| ((path, ty), value))
+        .unzip();
+    assert_eq!(extract_fields(TEST_INPUT, fields), Ok(values));
+
+    // One field at a time
+    for (path, ty, value) in extract_tests() {
+        println!("Path {:?}", path);
+        assert_eq!(
+            extract_fields(TEST_INPUT, vec![(path, ty)]),
+            Ok(vec![value])
+        );
+    }
+}
+
+#[test]
+fn listener_test() {
+    let parsers: [fn(&str, &mut FieldExtractor); 4] = [
+        |input, listener| crate::parse_events_push(input, listener).unwrap(),
+        |input, listener| crate::parse_events_push_using_lexer_push(input, listener).unwrap(),
+        |input, listener| {
+            crate::parse_events_push_using_lexer_iter(
+                crate::tokenize_iter(input),
+                listener,
+                input.len(),
+            )
+            .unwrap()
+        },
+        |input, listener| {
+            crate::parse_events_push_using_lexer_iter_non_recursive(
+                &mut crate::tokenize_iter(input),
+                listener,
+                input.len(),
+            )
+            .unwrap()
+        },
+    ];
+
+    for parser in parsers {
+        let (fields, values): (Vec<_>, Vec<_>) = extract_tests()
+            .into_iter()
+            .map(|(path, ty, value)| ((path, ty), value))
+            .unzip();
+        let mut listener = FieldExtractor::new(TEST_INPUT, fields);
+        parser(TEST_INPUT, &mut listener);
+        assert_eq!(listener.into_values(), Ok(values));
+    }
+}
+
+#[test]
+fn stop_after_fields_test() {
+    let field = |path: &str| (FieldPath::parse(path).unwrap(), FieldType::U64);
+    let tests = [
+        // Errors after the fields are not reported
+        (
+            r#"{"a": 1, "b": tru"#,
+            vec![field("a")],
+            Ok(vec![Some(FieldValue::U64(1))]),
+        ),
+        (r#"{"a": 1} x"#, vec![field("b")], Ok(vec![None])),
+        (r#"[1, 2] x"#, vec![field("a")], Ok(vec![None])),
+        // Errors before are
+        (
+            r#"{"a": 1, "b": tru, "c": 2}"#,
+            vec![field("a"), field("c")],
+            Err(ParseError::new(14, crate::ParseErrorKind::InvalidToken)),
+        ),
+        // Skipped arrays and objects are only checked for balanced brackets and strings
+        (
+            r#"{"b": [tru], "a": 1}"#,
+            vec![field("a")],
+            Ok(vec![Some(FieldValue::U64(1))]),
+        ),
+        (
+            r#"{"b": {"c": "}}, "a": 1}"#,
+            vec![field("a")],
+            Err(ParseError::new(
+                19,
+                crate::ParseErrorKind::UnterminatedString,
+            )),
+        ),
+        (
+            r#"[[1}, 2]"#,
+            vec![field("[1]")],
+            Err(ParseError::unexpected_char(
+                3,
+                crate::Expected::CommaOrEndArray,
+                '}',
+            )),
+        ),
+        // Other skipped values are checked
+        (
+            r#"{"b": tru, "a": 1}"#,
+            vec![field("a")],
+            Err(ParseError::new(6, crate::ParseErrorKind::InvalidToken)),
+        ),
+    ];
+
+    for (input, fields, result) in tests {
+        println!("Parsing {:?}", input);
+        assert_eq!(extract_fields(input, fields.clone()), result);
+
+        let mut listener = FieldExtractor::new(input, fields);
+        crate::parse_events_push(input, &mut listener);
+        assert_eq!(listener.into_values(), result);
+    }
+}
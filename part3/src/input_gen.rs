@@ -5,7 +5,13 @@ const MAX_STR_SIZE: u64 = 10; // bytes
 
 /// Generates JSON input, for testing and benchmarking.
 pub fn gen_input(size: usize) -> String {
-    let mut rng = Rand64::new(RAND_SEED);
+    gen_input_with_seed(size, RAND_SEED)
+}
+
+/// Like [gen_input], but with the given random seed, to generate different inputs of the same
+/// size.
+pub fn gen_input_with_seed(size: usize, seed: u128) -> String {
+    let mut rng = Rand64::new(seed);
 
     // Allocate larger than `size`: atoms (numbers, strings, keywords) can be larger than the
     // remaining space, objects allocate key + value regardless of the remaining size, containers
@@ -83,7 +89,10 @@ pub fn gen_input(size: usize) -> String {
             }
         }
 
-        if output.len() > size {
+        // Stop if the ',' would fill up the space, as the loop would end with a trailing comma. In
+        // other cases this stops at the same place as checking `output.len() > size`, so the inputs
+        // used in the benchmarks are the same as with the original check.
+        if output.len() + 1 >= size {
             break;
         }
 
@@ -121,3 +130,139 @@ fn gen_str(rng: &mut Rand64, output: &mut String) {
     }
     output.push('"');
 }
+
+/// Generates a random [Json][crate::Json] with the values that [gen_input] doesn't generate:
+/// strings with escaped, control, and non-ASCII characters, negative integers, floats, and
+/// numbers that don't fit into `u64` or `i64`. For testing the writer.
+///
+/// The generated value is an array or object, with arrays and objects nested at most `max_depth`
+/// levels.
+#[cfg(test)]
+pub(crate) fn gen_json_with_seed(max_depth: usize, seed: u128) -> crate::Json {
+    let mut rng = Rand64::new(seed);
+    let kind = rng.rand_range(8..10);
+    gen_json_of_kind(&mut rng, kind, max_depth)
+}
+
+#[cfg(test)]
+fn gen_json(rng: &mut Rand64, max_depth: usize) -> crate::Json {
+    let num_kinds = if max_depth == 0 { 8 } else { 10 };
+    let kind = rng.rand_range(0..num_kinds);
+    gen_json_of_kind(rng, kind, max_depth)
+}
+
+#[cfg(test)]
+fn gen_json_of_kind(rng: &mut Rand64, kind: u64, max_depth: usize) -> crate::Json {
+    use crate::Json;
+
+    match kind {
+        0 => Json::Int(rng.rand_u64()),
+
+        1 => Json::NegInt(if rng.rand_range(0..10) == 0 {
+            i64::MIN
+        } else {
+            -(rng.rand_range(1..1 << 63) as i64)
+        }),
+
+        2 => Json::Float(gen_float(rng)),
+
+        3 => {
+            // At least 20 digits, larger than `u64::MAX` and smaller than `i64::MIN`.
+            let mut number = String::new();
+            if rand_bool(rng) {
+                number.push('-');
+            }
+            number.push(char::from(b'1' + rng.rand_range(0..9) as u8));
+            for _ in 0..rng.rand_range(20..40) {
+                number.push(char::from(b'0' + rng.rand_range(0..10) as u8));
+            }
+            Json::RawNumber(number)
+        }
+
+        4 | 5 => Json::String(gen_escaped_str(rng)),
+
+        6 => Json::Bool(rand_bool(rng)),
+
+        7 => Json::Null,
+
+        8 => {
+            let num_elems = rng.rand_range(0..8);
+            Json::Array(
+                (0..num_elems)
+                    .map(|_| gen_json(rng, max_depth - 1))
+                    .collect(),
+            )
+        }
+
+        9 => {
+            let num_members = rng.rand_range(0..8);
+            Json::Object(
+                (0..num_members)
+                    .map(|_| (gen_escaped_str(rng), gen_json(rng, max_depth - 1)))
+                    .collect(),
+            )
+        }
+
+        _ => unreachable!(),
+    }
+}
+
+/// Generates a finite float: either a random bit pattern (which can be subnormal, or have a large
+/// exponent), or a small fraction.
+#[cfg(test)]
+fn gen_float(rng: &mut Rand64) -> f64 {
+    if rand_bool(rng) {
+        return (rng.rand_i64() % 100_000) as f64 / 64.0;
+    }
+    loop {
+        let float = f64::from_bits(rng.rand_u64());
+        if float.is_finite() {
+            return float;
+        }
+    }
+}
+
+#[cfg(test)]
+fn gen_escaped_str(rng: &mut Rand64) -> String {
+    const SPECIAL_CHARS: [char; 10] = [
+        '"', '\\', '/', '\u{7f}', 'é', '€', '\u{7ff}', '\u{800}', '\u{2028}', '\u{ffff}',
+    ];
+
+    let str_size = rng.rand_range(0..MAX_STR_SIZE + 1);
+    let mut str = String::new();
+    for _ in 0..str_size {
+        let char = match rng.rand_range(0..5) {
+            0 => char::from(rng.rand_range(u64::from(b'a')..u64::from(b'z') + 1) as u8),
+            1 => SPECIAL_CHARS[rng.rand_range(0..SPECIAL_CHARS.len() as u64) as usize],
+            // Control characters, written as `\n`, `\t` etc. or `\uXXXX`.
+            2 => char::from(rng.rand_range(0..0x20) as u8),
+            // Characters outside of the BMP, escaped as surrogate pairs.
+            3 => char::from_u32(rng.rand_range(0x10000..0x110000) as u32).unwrap(),
+            // Any other character, skipping the surrogate range.
+            4 => loop {
+                if let Some(char) = char::from_u32(rng.rand_range(0x80..0x110000) as u32) {
+                    break char;
+                }
+            },
+            _ => unreachable!(),
+        };
+        str.push(char);
+    }
+    str
+}
+
+#[test]
+fn gen_input_with_seed_valid_tests() {
+    for seed in 0..100 {
+        for input_size in [10, 100, 1_000] {
+            let input = gen_input_with_seed(input_size, seed);
+            assert!(
+                crate::parse_ast_recursive(&input).is_ok(),
+                "seed = {}, input_size = {}, input = {}",
+                seed,
+                input_size,
+                input
+            );
+        }
+    }
+}
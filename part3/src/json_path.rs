@@ -0,0 +1,655 @@
+use crate::path_matcher::{Builders, PathMatcher, PathQuery, Step};
+use crate::{parse_events_iter, Json, ParseError, ParseEvent, ParseEventKind};
+
+use std::collections::VecDeque;
+use std::fmt;
+
+/// A JSONPath query, like `$.items[*].price` or `$..id`.
+///
+/// Supported syntax is a subset of [RFC 9535](https://www.rfc-editor.org/rfc/rfc9535):
+///
+/// - `$`: the root value.
+/// - `.name`, `['name']`, `["name"]`: object members with the key.
+/// - `[3]`: array elements at the index. Negative indices are not supported.
+/// - `.*`, `[*]`: all array elements and object members.
+/// - `[0, 'a', *]`: any of the selectors in the brackets.
+/// - `..name`, `..*`, `..[3]`: same as above, but selects from the value and all its
+///   descendants.
+///
+/// Filters and slices are not supported.
+///
+/// Queries select each value at most once, and values are returned in the order they appear in
+/// the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+/// An invalid [JsonPath], reported by [JsonPath::parse]. `byte_offset` is the location of the
+/// error in the query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPathError {
+    pub byte_offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    /// Whether the segment selects from the descendants of the values, in addition to the values.
+    descendant: bool,
+
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Selector {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+impl JsonPath {
+    pub fn parse(query: &str) -> Result<JsonPath, JsonPathError> {
+        let mut parser = QueryParser {
+            query,
+            byte_offset: 0,
+        };
+        parser.parse()
+    }
+}
+
+struct QueryParser<'a> {
+    query: &'a str,
+    byte_offset: usize,
+}
+
+impl<'a> QueryParser<'a> {
+    fn parse(&mut self) -> Result<JsonPath, JsonPathError> {
+        if !self.eat('$') {
+            return Err(self.error());
+        }
+
+        let mut segments = vec![];
+        while let Some(char) = self.peek() {
+            let segment = match char {
+                '.' => {
+                    self.byte_offset += 1;
+                    let descendant = self.eat('.');
+                    let selectors = match self.peek() {
+                        Some('[') if descendant => self.parse_brackets()?,
+                        Some('*') => {
+                            self.byte_offset += 1;
+                            vec![Selector::Wildcard]
+                        }
+                        _ => vec![Selector::Key(self.parse_name()?)],
+                    };
+                    Segment {
+                        descendant,
+                        selectors,
+                    }
+                }
+                '[' => Segment {
+                    descendant: false,
+                    selectors: self.parse_brackets()?,
+                },
+                _ => return Err(self.error()),
+            };
+            segments.push(segment);
+        }
+
+        Ok(JsonPath { segments })
+    }
+
+    fn error(&self) -> JsonPathError {
+        JsonPathError {
+            byte_offset: self.byte_offset,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.query[self.byte_offset..].chars().next()
+    }
+
+    fn eat(&mut self, char: char) -> bool {
+        if self.peek() == Some(char) {
+            self.byte_offset += char.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_blank(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.byte_offset += 1;
+        }
+    }
+
+    /// Parses a member name after `.`.
+    fn parse_name(&mut self) -> Result<String, JsonPathError> {
+        let start = self.byte_offset;
+        while let Some(char) = self.peek() {
+            let is_name_char = char == '_'
+                || char.is_ascii_alphabetic()
+                || !char.is_ascii()
+                || (self.byte_offset != start && char.is_ascii_digit());
+            if !is_name_char {
+                break;
+            }
+            self.byte_offset += char.len_utf8();
+        }
+        if self.byte_offset == start {
+            return Err(self.error());
+        }
+        Ok(self.query[start..self.byte_offset].to_owned())
+    }
+
+    /// Parses selectors in brackets, starting at the `[`.
+    fn parse_brackets(&mut self) -> Result<Vec<Selector>, JsonPathError> {
+        self.byte_offset += 1;
+        let mut selectors = vec![];
+        loop {
+            self.skip_blank();
+            let selector = match self.peek() {
+                Some('*') => {
+                    self.byte_offset += 1;
+                    Selector::Wildcard
+                }
+                Some(quote @ ('\'' | '"')) => Selector::Key(self.parse_string(quote)?),
+                Some('0'..='9') => Selector::Index(self.parse_index()?),
+                _ => return Err(self.error()),
+            };
+            selectors.push(selector);
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(selectors);
+            }
+            if !self.eat(',') {
+                return Err(self.error());
+            }
+        }
+    }
+
+    fn parse_index(&mut self) -> Result<usize, JsonPathError> {
+        let start = self.byte_offset;
+        while let Some('0'..='9') = self.peek() {
+            self.byte_offset += 1;
+        }
+        let digits = &self.query[start..self.byte_offset];
+        if digits.len() > 1 && digits.starts_with('0') {
+            return Err(JsonPathError { byte_offset: start });
+        }
+        digits
+            .parse()
+            .map_err(|_| JsonPathError { byte_offset: start })
+    }
+
+    /// Parses a string literal, starting at the opening quote.
+    fn parse_string(&mut self, quote: char) -> Result<String, JsonPathError> {
+        self.byte_offset += 1;
+        let mut string = String::new();
+        loop {
+            let char = self.peek().ok_or_else(|| self.error())?;
+            if char == quote {
+                self.byte_offset += 1;
+                return Ok(string);
+            }
+            if char != '\\' {
+                string.push(char);
+                self.byte_offset += char.len_utf8();
+                continue;
+            }
+
+            let escape_offset = self.byte_offset;
+            self.byte_offset += 1;
+            let unescaped = match self.peek() {
+                Some('b') => '\u{8}',
+                Some('f') => '\u{c}',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some(char @ ('/' | '\\' | '\'' | '"')) => char,
+                Some('u') => {
+                    let hex = self
+                        .query
+                        .get(self.byte_offset + 1..self.byte_offset + 5)
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .and_then(char::from_u32);
+                    match hex {
+                        Some(char) => {
+                            self.byte_offset += 4;
+                            char
+                        }
+                        None => {
+                            return Err(JsonPathError {
+                                byte_offset: escape_offset,
+                            })
+                        }
+                    }
+                }
+                _ => {
+                    return Err(JsonPathError {
+                        byte_offset: escape_offset,
+                    })
+                }
+            };
+            string.push(unescaped);
+            self.byte_offset += 1;
+        }
+    }
+}
+
+impl Selector {
+    fn matches(&self, step: &Step) -> bool {
+        match (self, step) {
+            (Selector::Wildcard, _) => true,
+            (Selector::Key(key), Step::Key(step_key)) => key == step_key,
+            (Selector::Index(index), Step::Index(step_index)) => index == step_index,
+            _ => false,
+        }
+    }
+}
+
+/// Matching a query is running a nondeterministic automaton on the path to a value. The states
+/// are the numbers of segments matched, and a value is selected when the final state is in its
+/// state set.
+///
+/// Returns the state set of the element or member at `step` of a value with the state set
+/// `states`. State sets are sorted.
+fn next_states(segments: &[Segment], states: &[usize], step: &Step) -> Vec<usize> {
+    let mut next = vec![];
+    for &state in states {
+        let segment = match segments.get(state) {
+            Some(segment) => segment,
+            None => continue,
+        };
+        if segment.descendant {
+            next.push(state);
+        }
+        if segment
+            .selectors
+            .iter()
+            .any(|selector| selector.matches(step))
+        {
+            next.push(state + 1);
+        }
+    }
+    next.dedup();
+    next
+}
+
+/// Whether a value with the state set `states` may have selected descendants.
+fn has_live_states(segments: &[Segment], states: &[usize]) -> bool {
+    states.iter().any(|&state| state < segments.len())
+}
+
+/// Whether any of the segments at the states select keys, so keys need to be decoded.
+fn needs_keys(segments: &[Segment], states: &[usize]) -> bool {
+    states.iter().any(|&state| match segments.get(state) {
+        Some(segment) => segment
+            .selectors
+            .iter()
+            .any(|selector| matches!(selector, Selector::Key(_))),
+        None => false,
+    })
+}
+
+impl Json {
+    /// Returns the values selected by `path`, in document order.
+    pub fn query(&self, path: &JsonPath) -> Vec<&Json> {
+        let mut selected = vec![];
+        query_value(self, &path.segments, &[0], &mut selected);
+        selected
+    }
+}
+
+fn query_value<'j>(
+    value: &'j Json,
+    segments: &[Segment],
+    states: &[usize],
+    selected: &mut Vec<&'j Json>,
+) {
+    if states.last() == Some(&segments.len()) {
+        selected.push(value);
+    }
+
+    if !has_live_states(segments, states) {
+        return;
+    }
+
+    match value {
+        Json::Array(elems) => {
+            for (index, elem) in elems.iter().enumerate() {
+                let next = next_states(segments, states, &Step::Index(index));
+                if !next.is_empty() {
+                    query_value(elem, segments, &next, selected);
+                }
+            }
+        }
+        Json::Object(members) => {
+            for (key, value) in members {
+                let next = next_states(segments, states, &Step::Key(key));
+                if !next.is_empty() {
+                    query_value(value, segments, &next, selected);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns an iterator of the values in `input` selected by `path`. See [query_json_path_events].
+pub fn query_json_path<'a>(
+    input: &'a str,
+    path: &'a JsonPath,
+) -> JsonPathMatches<'a, impl Iterator<Item = Result<ParseEvent, ParseError>> + 'a> {
+    query_json_path_events(parse_events_iter(input), input, path)
+}
+
+/// Returns an iterator of the values selected by `path`, in document order, in the events of
+/// `input` generated by `parser`.
+///
+/// Only the arrays and objects on the paths to the values that can be selected are tracked, and
+/// only the selected values are built, so memory use is bounded by the nesting depth and the sizes
+/// of the selected values. Parsing stops when the rest of the input can't contain selected values,
+/// so errors after that are not reported.
+pub fn query_json_path_events<'a, I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: I,
+    input: &'a str,
+    path: &'a JsonPath,
+) -> JsonPathMatches<'a, I> {
+    JsonPathMatches {
+        parser,
+        matcher: PathMatcher::new(input),
+        selection: Selection {
+            segments: &path.segments,
+            builders: Builders::new(input),
+            selected: VecDeque::new(),
+            num_returned: 0,
+        },
+        done: false,
+    }
+}
+
+/// Iterator returned by [query_json_path] and [query_json_path_events].
+pub struct JsonPathMatches<'a, I> {
+    parser: I,
+
+    /// Tracks the arrays and objects that may contain selected values, with their state sets.
+    matcher: PathMatcher<'a, Vec<usize>>,
+
+    selection: Selection<'a>,
+
+    /// Whether the iterator stopped parsing, after an error or when the rest of the input can't
+    /// contain selected values.
+    done: bool,
+}
+
+/// Runs the automaton of the query (see [next_states]) on the paths to the values, and builds the
+/// selected values.
+struct Selection<'a> {
+    segments: &'a [Segment],
+
+    /// Builders of the selected values, with the indices of the values in document order.
+    builders: Builders<'a, usize>,
+
+    /// Selected values that are not returned yet, in document order. Values are `None` while they
+    /// are built, and returned after they and the values before them are complete.
+    selected: VecDeque<Option<Json>>,
+
+    /// Number of selected values returned.
+    num_returned: usize,
+}
+
+impl<'a, I: Iterator<Item = Result<ParseEvent, ParseError>>> Iterator for JsonPathMatches<'a, I> {
+    type Item = Result<Json, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(Some(_)) = self.selection.selected.front() {
+                self.selection.num_returned += 1;
+                return self.selection.selected.pop_front().flatten().map(Ok);
+            }
+
+            if self.done {
+                return None;
+            }
+
+            let ParseEvent { kind, byte_offset } = match self.parser.next() {
+                Some(Ok(event)) => event,
+                Some(Err(error)) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+                None => {
+                    self.done = true;
+                    continue;
+                }
+            };
+
+            let selection = &mut self.selection;
+            let num_builders = selection.builders.len();
+            let result = selection
+                .feed_builders(0, byte_offset, &kind)
+                .and_then(|()| self.matcher.event(selection, byte_offset, &kind))
+                .and_then(|()| selection.feed_builders(num_builders, byte_offset, &kind));
+            if let Err(error) = result {
+                self.done = true;
+                return Some(Err(error));
+            }
+
+            if self.matcher.finished() && self.selection.builders.is_empty() {
+                self.done = true;
+            }
+        }
+    }
+}
+
+impl<'a> Selection<'a> {
+    /// Passes the event to the builders starting at index `first`, see [Builders::feed].
+    fn feed_builders(
+        &mut self,
+        first: usize,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> Result<(), ParseError> {
+        let selected = &mut self.selected;
+        let num_returned = self.num_returned;
+        self.builders.feed(first, byte_offset, kind, |index, json| {
+            selected[index - num_returned] = Some(json)
+        })
+    }
+}
+
+impl<'a> PathQuery for Selection<'a> {
+    type State = Vec<usize>;
+
+    fn top_level(&self) -> Vec<usize> {
+        vec![0]
+    }
+
+    fn step(&self, states: &Vec<usize>, _depth: usize, step: &Step) -> Vec<usize> {
+        next_states(self.segments, states, step)
+    }
+
+    fn needs_keys(&self, states: &Vec<usize>) -> bool {
+        needs_keys(self.segments, states)
+    }
+
+    fn value(
+        &mut self,
+        states: &mut Vec<usize>,
+        _depth: usize,
+        _byte_offset: usize,
+        _kind: &ParseEventKind,
+    ) -> Result<bool, ParseError> {
+        if states.last() == Some(&self.segments.len()) {
+            // The builder gets the event after matching.
+            self.builders.push(self.num_returned + self.selected.len());
+            self.selected.push_back(None);
+        }
+        Ok(has_live_states(self.segments, states))
+    }
+}
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid JSONPath query at byte {}", self.byte_offset)
+    }
+}
+
+impl std::error::Error for JsonPathError {}
+
+#[cfg(test)]
+const TEST_INPUT: &str = r#"{
+    "store": {
+        "items": [{"id": 1, "price": 10, "tags": ["a"]}, {"id": 2, "price": 20.5}, {"id": 3}],
+        "id": "s"
+    },
+    "id": 0,
+    "a b": {"id": 4}
+}"#;
+
+/// Queries on [TEST_INPUT], and the selected values as a JSON array.
+#[cfg(test)]
+fn query_tests() -> Vec<(&'static str, String)> {
+    vec![
+        ("$", format!("[{}]", TEST_INPUT)),
+        ("$.store.items[*].price", "[10, 20.5]".to_owned()),
+        ("$..id", r#"[1, 2, 3, "s", 0, 4]"#.to_owned()),
+        ("$.store.items[1]", r#"[{"id": 2, "price": 20.5}]"#.to_owned()),
+        ("$.store.items[5]", "[]".to_owned()),
+        ("$['a b'].id", "[4]".to_owned()),
+        (r#"$["a b"]"#, r#"[{"id": 4}]"#.to_owned()),
+        (r#"$[ 'a b' ]['id']"#, "[4]".to_owned()),
+        ("$.store.items[0,2].id", "[1, 3]".to_owned()),
+        // Values are selected once, in document order
+        ("$.store.items[2, 0, 2].id", "[1, 3]".to_owned()),
+        ("$.store.items[0]..*", r#"[1, 10, ["a"], "a"]"#.to_owned()),
+        ("$.*", r#"[{"items": [{"id": 1, "price": 10, "tags": ["a"]}, {"id": 2, "price": 20.5}, {"id": 3}], "id": "s"}, 0, {"id": 4}]"#.to_owned()),
+        ("$..[0]", r#"[{"id": 1, "price": 10, "tags": ["a"]}, "a"]"#.to_owned()),
+        ("$..items..price", "[10, 20.5]".to_owned()),
+        ("$..*..tags[*]", r#"["a"]"#.to_owned()),
+        ("$.id.x", "[]".to_owned()),
+        ("$.store[0]", "[]".to_owned()),
+    ]
+}
+
+#[test]
+fn parse_error_test() {
+    let tests = [
+        ("", 0),
+        ("a", 0),
+        ("$x", 1),
+        ("$.", 2),
+        ("$.1a", 2),
+        ("$.[0]", 2),
+        ("$[01]", 2),
+        ("$[-1]", 2),
+        ("$['a", 4),
+        ("$[1", 3),
+        ("$[1,]", 4),
+        (r"$['\x']", 3),
+        (r"$['\u12']", 3),
+    ];
+    for (query, byte_offset) in tests {
+        println!("Parsing {:?}", query);
+        assert_eq!(JsonPath::parse(query), Err(JsonPathError { byte_offset }));
+    }
+}
+
+#[test]
+fn query_test() {
+    let json = crate::parse_ast_recursive(TEST_INPUT).unwrap();
+    for (query, expected) in query_tests() {
+        println!("Query {:?}", query);
+        let path = JsonPath::parse(query).unwrap();
+        let expected = match crate::parse_ast_recursive(&expected).unwrap() {
+            Json::Array(values) => values,
+            _ => panic!(),
+        };
+        assert_eq!(json.query(&path), expected.iter().collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn query_events_test() {
+    for (query, expected) in query_tests() {
+        println!("Query {:?}", query);
+        let path = JsonPath::parse(query).unwrap();
+        let expected = crate::parse_ast_recursive(&expected).unwrap();
+
+        let values = query_json_path(TEST_INPUT, &path).collect::<Result<Vec<_>, _>>();
+        assert_eq!(values.map(Json::Array), Ok(expected));
+    }
+
+    for (query, expected) in query_tests() {
+        let path = JsonPath::parse(query).unwrap();
+        let expected = crate::parse_ast_recursive(&expected).unwrap();
+
+        let parser = crate::parse_events_iter_indexed(TEST_INPUT);
+        let values =
+            query_json_path_events(parser, TEST_INPUT, &path).collect::<Result<Vec<_>, _>>();
+        assert_eq!(values.map(Json::Array), Ok(expected));
+    }
+}
+
+#[test]
+fn query_events_stop_test() {
+    let tests = [
+        // The object can have another "a" member
+        (
+            r#"{"a": [1, 2], "b": tru"#,
+            "$.a[*]",
+            "[1, 2]",
+            Some(ParseError::new(19, crate::ParseErrorKind::InvalidToken)),
+        ),
+        // Nothing is selected after the top-level value
+        (r#"{"a": 1} x"#, "$.a", "[1]", None),
+        (r#"[1, 2] x"#, "$.a", "[]", None),
+        (
+            r#"{"a": [1, tru]}"#,
+            "$.a[0]",
+            "[1]",
+            Some(ParseError::new(10, crate::ParseErrorKind::InvalidToken)),
+        ),
+        // Values are returned in document order
+        (
+            r#"{"a": [{"a": 1}, tru]}"#,
+            "$..a",
+            "[]",
+            Some(ParseError::new(17, crate::ParseErrorKind::InvalidToken)),
+        ),
+    ];
+
+    for (input, query, expected, error) in tests {
+        println!("Parsing {:?}", input);
+        let path = JsonPath::parse(query).unwrap();
+        let mut values = vec![];
+        let mut result_error = None;
+        for value in query_json_path(input, &path) {
+            match value {
+                Ok(value) => values.push(value),
+                Err(error) => result_error = Some(error),
+            }
+        }
+        assert_eq!(
+            Json::Array(values),
+            crate::parse_ast_recursive(expected).unwrap()
+        );
+        assert_eq!(result_error, error);
+    }
+}
+
+#[test]
+fn query_random_tests() {
+    let input = crate::gen_input(10_000);
+    let json = crate::parse_ast_recursive(&input).unwrap();
+    for query in ["$..*", "$.*", "$..[0]", "$[*][*]", "$..*..*[1]"] {
+        println!("Query {:?}", query);
+        let path = JsonPath::parse(query).unwrap();
+        let values = query_json_path(&input, &path)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(values.iter().collect::<Vec<_>>(), json.query(&path));
+    }
+}
@@ -0,0 +1,644 @@
+use crate::path_matcher::{PathMatcher, PathQuery, Step};
+use crate::{
+    event_to_tree, parse_events_iter, AstBuilderListener, EventListener, Json, ListenerAction,
+    ParseError, ParseEvent, ParseEventKind,
+};
+
+use std::convert::Infallible;
+use std::fmt;
+
+/// A JSON Pointer, as specified in [RFC 6901](https://www.rfc-editor.org/rfc/rfc6901).
+///
+/// A pointer is a sequence of reference tokens, each selecting an object member by key or an
+/// array element by index. When an object has duplicate keys, the first member with the key is
+/// selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPointer {
+    /// Reference tokens, with `~0` and `~1` decoded.
+    tokens: Vec<String>,
+}
+
+/// Errors reported by [JsonPointer::parse].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonPointerError {
+    /// A non-empty pointer that does not start with `/`.
+    MissingLeadingSlash,
+
+    /// A `~` that is not followed by `0` or `1`. `byte_offset` is the offset of the `~` in the
+    /// pointer.
+    InvalidEscape { byte_offset: usize },
+}
+
+impl JsonPointer {
+    /// Parses a pointer in the JSON string representation, e.g. `/foo/0/a~1b`. The empty string is
+    /// the pointer to the whole document.
+    pub fn parse(pointer: &str) -> Result<JsonPointer, JsonPointerError> {
+        if pointer.is_empty() {
+            return Ok(JsonPointer { tokens: vec![] });
+        }
+
+        let rest = match pointer.strip_prefix('/') {
+            Some(rest) => rest,
+            None => return Err(JsonPointerError::MissingLeadingSlash),
+        };
+
+        let mut tokens = vec![];
+        let mut token_offset = 1;
+        for token in rest.split('/') {
+            tokens.push(unescape_token(token, token_offset)?);
+            token_offset += token.len() + 1;
+        }
+
+        Ok(JsonPointer { tokens })
+    }
+
+    /// The reference tokens of the pointer, with `~0` and `~1` decoded.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+}
+
+/// Decodes `~0` and `~1` in a reference token. `token_offset` is the byte offset of the token in
+/// the pointer, for errors.
+fn unescape_token(token: &str, token_offset: usize) -> Result<String, JsonPointerError> {
+    let mut unescaped = String::with_capacity(token.len());
+    let mut chars = token.char_indices();
+    while let Some((byte_offset, char)) = chars.next() {
+        if char != '~' {
+            unescaped.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some((_, '0')) => unescaped.push('~'),
+            Some((_, '1')) => unescaped.push('/'),
+            _ => {
+                return Err(JsonPointerError::InvalidEscape {
+                    byte_offset: token_offset + byte_offset,
+                })
+            }
+        }
+    }
+    Ok(unescaped)
+}
+
+/// Parses a reference token as an array index. Returns `None` for tokens that can't select an
+/// array element: `-`, indices with leading zeros, and non-numbers.
+fn array_index(token: &str) -> Option<usize> {
+    if token.is_empty()
+        || !token.bytes().all(|byte| byte.is_ascii_digit())
+        || (token.len() > 1 && token.starts_with('0'))
+    {
+        return None;
+    }
+    token.parse().ok()
+}
+
+impl Json {
+    /// Returns the value that `pointer` refers to, if it exists.
+    pub fn pointer(&self, pointer: &JsonPointer) -> Option<&Json> {
+        let mut value = self;
+        for token in &pointer.tokens {
+            value = match value {
+                Json::Array(elems) => elems.get(array_index(token)?)?,
+                Json::Object(members) => &members.iter().find(|(key, _)| key == token)?.1,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// Finds the value that `pointer` refers to in `input`, without building an AST for the rest of
+/// the input.
+///
+/// Parsing stops after the value, so errors in the rest of the input are not reported.
+pub fn find_pointer(input: &str, pointer: &JsonPointer) -> Result<Option<Json>, ParseError> {
+    find_pointer_in_events(&mut parse_events_iter(input), input, pointer)
+}
+
+/// Same as [find_pointer], but with the events of `input` generated by the given parser. Events
+/// after the value are not consumed.
+pub fn find_pointer_in_events<I: Iterator<Item = Result<ParseEvent, ParseError>>>(
+    parser: &mut I,
+    input: &str,
+    pointer: &JsonPointer,
+) -> Result<Option<Json>, ParseError> {
+    let mut matcher = PointerMatcher::new(input, pointer);
+
+    while let Some(event) = parser.next() {
+        let event = event?;
+        match matcher.event(event.byte_offset, &event.kind)? {
+            PointerMatch::Continue => {}
+            PointerMatch::NotFound => return Ok(None),
+            PointerMatch::Found => {
+                let mut events = std::iter::once(Ok(event)).chain(parser);
+                return event_to_tree(&mut events, input).map(Some);
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// An [EventListener] that finds the value that a [JsonPointer] refers to, see [find_pointer].
+///
+/// The listener returns [ListenerAction::Stop] after the value is built, or when the input can't
+/// contain the value. Contents of arrays and objects that can't contain the value are skipped with
+/// [ListenerAction::SkipChildren].
+pub struct JsonPointerListener<'a> {
+    input: &'a str,
+
+    matcher: PointerMatcher<'a>,
+
+    /// Builds the value, after the matcher finds its first event.
+    builder: Option<AstBuilderListener<'a>>,
+
+    error: Option<ParseError>,
+
+    /// Whether the value is built, or the input can't contain the value, or there was an error.
+    /// Later events and errors are ignored.
+    done: bool,
+}
+
+impl<'a> JsonPointerListener<'a> {
+    pub fn new(input: &'a str, pointer: &'a JsonPointer) -> JsonPointerListener<'a> {
+        JsonPointerListener {
+            input,
+            matcher: PointerMatcher::new(input, pointer),
+            builder: None,
+            error: None,
+            done: false,
+        }
+    }
+
+    /// Returns the value, or `None` if the input doesn't contain it.
+    pub fn into_value(self) -> Result<Option<Json>, ParseError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        match self.builder {
+            None => Ok(None),
+            Some(builder) => match builder.into_ast() {
+                (_, Some(error)) => Err(error),
+                (ast, None) => Ok(ast),
+            },
+        }
+    }
+
+    /// Matches the event, or passes it to the AST builder with `handle` when it's a part of the
+    /// value.
+    fn event(
+        &mut self,
+        byte_offset: usize,
+        kind: ParseEventKind,
+        handle: impl FnOnce(&mut AstBuilderListener<'a>) -> ListenerAction<Infallible>,
+    ) -> ListenerAction<Infallible> {
+        if self.done {
+            return ListenerAction::Stop;
+        }
+
+        if self.builder.is_none() {
+            match self.matcher.event(byte_offset, &kind) {
+                Ok(PointerMatch::Continue) => {
+                    let is_start = matches!(
+                        kind,
+                        ParseEventKind::StartArray | ParseEventKind::StartObject
+                    );
+                    return if is_start && self.matcher.skips_children() {
+                        ListenerAction::SkipChildren
+                    } else {
+                        ListenerAction::Continue
+                    };
+                }
+                Ok(PointerMatch::Found) => {
+                    self.builder = Some(AstBuilderListener::new(self.input));
+                }
+                Ok(PointerMatch::NotFound) => {
+                    self.done = true;
+                    return ListenerAction::Stop;
+                }
+                Err(error) => {
+                    self.error = Some(error);
+                    self.done = true;
+                    return ListenerAction::Stop;
+                }
+            }
+        }
+
+        let builder = self.builder.as_mut().unwrap();
+        if !handle(builder).keep_parsing() || builder.has_ast() {
+            self.done = true;
+            return ListenerAction::Stop;
+        }
+        ListenerAction::Continue
+    }
+}
+
+impl<'a> EventListener for JsonPointerListener<'a> {
+    type Error = Infallible;
+
+    fn handle_start_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.event(byte_offset, ParseEventKind::StartObject, |l| {
+            l.handle_start_object(byte_offset)
+        })
+    }
+
+    fn handle_end_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.event(byte_offset, ParseEventKind::EndObject, |l| {
+            l.handle_end_object(byte_offset)
+        })
+    }
+
+    fn handle_start_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.event(byte_offset, ParseEventKind::StartArray, |l| {
+            l.handle_start_array(byte_offset)
+        })
+    }
+
+    fn handle_end_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.event(byte_offset, ParseEventKind::EndArray, |l| {
+            l.handle_end_array(byte_offset)
+        })
+    }
+
+    fn handle_int(&mut self, byte_offset: usize, i: u64) -> ListenerAction<Infallible> {
+        self.event(byte_offset, ParseEventKind::Int(i), |l| {
+            l.handle_int(byte_offset, i)
+        })
+    }
+
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64) -> ListenerAction<Infallible> {
+        self.event(byte_offset, ParseEventKind::NegInt(i), |l| {
+            l.handle_neg_int(byte_offset, i)
+        })
+    }
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64) -> ListenerAction<Infallible> {
+        self.event(byte_offset, ParseEventKind::Float(f), |l| {
+            l.handle_float(byte_offset, f)
+        })
+    }
+
+    fn handle_raw_number(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
+        self.event(
+            byte_offset,
+            ParseEventKind::RawNumber { size_in_bytes },
+            |l| l.handle_raw_number(byte_offset, size_in_bytes),
+        )
+    }
+
+    fn handle_str(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+        has_escapes: bool,
+    ) -> ListenerAction<Infallible> {
+        self.event(
+            byte_offset,
+            ParseEventKind::Str {
+                size_in_bytes,
+                has_escapes,
+            },
+            |l| l.handle_str(byte_offset, size_in_bytes, has_escapes),
+        )
+    }
+
+    fn handle_bool(&mut self, byte_offset: usize, b: bool) -> ListenerAction<Infallible> {
+        self.event(byte_offset, ParseEventKind::Bool(b), |l| {
+            l.handle_bool(byte_offset, b)
+        })
+    }
+
+    fn handle_null(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
+        self.event(byte_offset, ParseEventKind::Null, |l| {
+            l.handle_null(byte_offset)
+        })
+    }
+
+    fn handle_comment(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
+        self.event(
+            byte_offset,
+            ParseEventKind::Comment { size_in_bytes },
+            |l| l.handle_comment(byte_offset, size_in_bytes),
+        )
+    }
+
+    fn handle_error(&mut self, error: ParseError) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        match &mut self.builder {
+            Some(builder) => builder.handle_error(error),
+            None => self.error = Some(error),
+        }
+    }
+}
+
+/// Result of matching an event with [PointerMatcher::event].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PointerMatch {
+    /// The event is not a part of the value, and the value may come later.
+    Continue,
+
+    /// The event is the first event of the value.
+    Found,
+
+    /// The input doesn't contain the value.
+    NotFound,
+}
+
+/// Finds the first event of the value that a [JsonPointer] refers to, in a stream of events.
+///
+/// Only the arrays and objects on the path to the value are tracked. Other values are skipped
+/// without decoding.
+pub(crate) struct PointerMatcher<'a> {
+    matcher: PathMatcher<'a, bool>,
+
+    path: PointerPath<'a>,
+}
+
+/// The path of a [JsonPointer]. The state of a value is whether it's on the path.
+struct PointerPath<'a> {
+    tokens: &'a [String],
+
+    /// Number of arrays and objects on the path to the value that the matcher entered.
+    path_depth: usize,
+
+    /// [PointerMatch::Found] after the first event of the value, [PointerMatch::NotFound] after a
+    /// scalar value on the path to the value.
+    result: Option<PointerMatch>,
+}
+
+impl<'a> PointerMatcher<'a> {
+    pub(crate) fn new(input: &'a str, pointer: &'a JsonPointer) -> PointerMatcher<'a> {
+        PointerMatcher {
+            matcher: PathMatcher::new(input),
+            path: PointerPath {
+                tokens: &pointer.tokens,
+                path_depth: 0,
+                result: None,
+            },
+        }
+    }
+
+    /// After an event that starts an array or object, returns whether the array or object can't
+    /// contain the value, so its contents can be skipped.
+    pub(crate) fn skips_children(&self) -> bool {
+        self.matcher.skips_children()
+    }
+
+    /// Matches the next event. Should not be called again after returning
+    /// [PointerMatch::Found] or [PointerMatch::NotFound].
+    pub(crate) fn event(
+        &mut self,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> Result<PointerMatch, ParseError> {
+        self.matcher.event(&mut self.path, byte_offset, kind)?;
+        if let Some(result) = self.path.result {
+            return Ok(result);
+        }
+        // An array or object on the path ended without the value. Later members with the same
+        // key are not matched, the pointer refers to the first one.
+        if self.matcher.depth() < self.path.path_depth {
+            return Ok(PointerMatch::NotFound);
+        }
+        Ok(PointerMatch::Continue)
+    }
+}
+
+impl<'a> PathQuery for PointerPath<'a> {
+    type State = bool;
+
+    fn top_level(&self) -> bool {
+        true
+    }
+
+    fn step(&self, on_path: &bool, depth: usize, step: &Step) -> bool {
+        let token = &self.tokens[depth - 1];
+        *on_path
+            && match step {
+                Step::Index(index) => array_index(token) == Some(*index),
+                Step::Key(key) => key == token,
+            }
+    }
+
+    fn value(
+        &mut self,
+        on_path: &mut bool,
+        depth: usize,
+        _byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> Result<bool, ParseError> {
+        if !*on_path {
+            return Ok(false);
+        }
+        if depth == self.tokens.len() {
+            self.result = Some(PointerMatch::Found);
+            return Ok(false);
+        }
+        match kind {
+            ParseEventKind::StartArray | ParseEventKind::StartObject => {
+                self.path_depth = depth + 1;
+                Ok(true)
+            }
+            _ => {
+                self.result = Some(PointerMatch::NotFound);
+                Ok(false)
+            }
+        }
+    }
+}
+
+impl fmt::Display for JsonPointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonPointerError::MissingLeadingSlash => {
+                f.write_str("JSON Pointer does not start with '/'")
+            }
+            JsonPointerError::InvalidEscape { byte_offset } => {
+                write!(f, "invalid escape in JSON Pointer at byte {}", byte_offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for JsonPointerError {}
+
+/// The example document in RFC 6901, with pointers and the values they refer to.
+#[cfg(test)]
+fn rfc_tests() -> (String, Vec<(&'static str, Option<Json>)>) {
+    let input = r#"{
+        "foo": ["bar", "baz"],
+        "": 0,
+        "a/b": 1,
+        "c%d": 2,
+        "e^f": 3,
+        "g|h": 4,
+        "i\\j": 5,
+        "k\"l": 6,
+        " ": 7,
+        "m~n": 8
+    }"#
+    .to_string();
+
+    let value = |text: &str| Some(crate::parse_ast_recursive(text).unwrap());
+
+    let tests = vec![
+        ("", value(&input)),
+        ("/foo", value(r#"["bar", "baz"]"#)),
+        ("/foo/0", value(r#""bar""#)),
+        ("/", value("0")),
+        ("/a~1b", value("1")),
+        ("/c%d", value("2")),
+        ("/e^f", value("3")),
+        ("/g|h", value("4")),
+        ("/i\\j", value("5")),
+        ("/k\"l", value("6")),
+        ("/ ", value("7")),
+        ("/m~0n", value("8")),
+        ("/foo/2", None),
+        ("/foo/-", None),
+        ("/foo/00", None),
+        ("/foo/0/x", None),
+        ("/bar", None),
+        ("/a~1b/x", None),
+        ("/a/b", None),
+    ];
+
+    (input, tests)
+}
+
+#[test]
+fn parse_test() {
+    assert_eq!(JsonPointer::parse("").unwrap().tokens(), &[] as &[String]);
+    assert_eq!(JsonPointer::parse("/").unwrap().tokens(), &[""]);
+    assert_eq!(
+        JsonPointer::parse("/a~1b/~0~01/0").unwrap().tokens(),
+        &["a/b", "~~1", "0"]
+    );
+    assert_eq!(
+        JsonPointer::parse("a"),
+        Err(JsonPointerError::MissingLeadingSlash)
+    );
+    assert_eq!(
+        JsonPointer::parse("/a/b~2"),
+        Err(JsonPointerError::InvalidEscape { byte_offset: 4 })
+    );
+    assert_eq!(
+        JsonPointer::parse("/a~"),
+        Err(JsonPointerError::InvalidEscape { byte_offset: 2 })
+    );
+}
+
+#[test]
+fn json_pointer_test() {
+    let (input, tests) = rfc_tests();
+    let json = crate::parse_ast_recursive(&input).unwrap();
+    for (pointer, value) in tests {
+        println!("Pointer {:?}", pointer);
+        let pointer = JsonPointer::parse(pointer).unwrap();
+        assert_eq!(json.pointer(&pointer), value.as_ref());
+    }
+}
+
+#[test]
+fn find_pointer_test() {
+    let (input, tests) = rfc_tests();
+    for (pointer, value) in tests {
+        println!("Pointer {:?}", pointer);
+        let pointer = JsonPointer::parse(pointer).unwrap();
+        assert_eq!(find_pointer(&input, &pointer), Ok(value));
+    }
+}
+
+#[test]
+fn listener_test() {
+    let parsers: [fn(&str, &mut JsonPointerListener); 4] = [
+        |input, listener| crate::parse_events_push(input, listener).unwrap(),
+        |input, listener| crate::parse_events_push_using_lexer_push(input, listener).unwrap(),
+        |input, listener| {
+            crate::parse_events_push_using_lexer_iter(
+                crate::tokenize_iter(input),
+                listener,
+                input.len(),
+            )
+            .unwrap()
+        },
+        |input, listener| {
+            crate::parse_events_push_using_lexer_iter_non_recursive(
+                &mut crate::tokenize_iter(input),
+                listener,
+                input.len(),
+            )
+            .unwrap()
+        },
+    ];
+
+    let (input, tests) = rfc_tests();
+    for parser in parsers {
+        for (pointer, value) in &tests {
+            println!("Pointer {:?}", pointer);
+            let pointer = JsonPointer::parse(pointer).unwrap();
+            let mut listener = JsonPointerListener::new(&input, &pointer);
+            parser(&input, &mut listener);
+            assert_eq!(&listener.into_value(), &Ok(value.as_ref().map(clone_json)));
+        }
+    }
+}
+
+#[test]
+fn stop_after_value_test() {
+    let value = |text: &str| Ok(Some(crate::parse_ast_recursive(text).unwrap()));
+    let tests = [
+        // Errors after the value are not reported
+        (
+            r#"{"a": [1, {"b": 2}], "c": tru"#,
+            "/a/1",
+            value(r#"{"b": 2}"#),
+        ),
+        (r#"[1, 2] x"#, "/5", Ok(None)),
+        (r#"[1, 2] x"#, "/1", value("2")),
+        // Errors before the value are
+        (
+            r#"{"c": tru, "a": 1}"#,
+            "/a",
+            Err(ParseError::new(6, crate::ParseErrorKind::InvalidToken)),
+        ),
+        // Errors in the value are
+        (
+            r#"{"a": [1, tru], "c": 1}"#,
+            "/a",
+            Err(ParseError::new(10, crate::ParseErrorKind::InvalidToken)),
+        ),
+    ];
+
+    for (input, pointer, result) in tests {
+        println!("Parsing {:?}, pointer {:?}", input, pointer);
+        let pointer = JsonPointer::parse(pointer).unwrap();
+        assert_eq!(find_pointer(input, &pointer), result);
+
+        let mut listener = JsonPointerListener::new(input, &pointer);
+        crate::parse_events_push(input, &mut listener);
+        assert_eq!(listener.into_value(), result);
+    }
+}
+
+/// Copies a value by writing and parsing it, as [Json] doesn't implement [Clone].
+#[cfg(test)]
+fn clone_json(json: &Json) -> Json {
+    crate::parse_ast_recursive_with_config(
+        &crate::write_json(json),
+        crate::test_common::raw_int_config(),
+    )
+    .unwrap()
+}
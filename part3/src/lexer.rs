@@ -1,162 +1,257 @@
-use crate::Token;
-
-use std::iter::Peekable;
-use std::str::CharIndices;
+use crate::escape::{scan_string_bytes, ScanStringError};
+use crate::number::{scan_number, Number};
+use crate::utf8::{invalid_utf8, Utf8Validator};
+use crate::{Expected, IntOverflow, ParseError, ParseErrorKind, ParserConfig, Token};
 
 pub fn tokenize_iter<'a>(input: &'a str) -> Lexer<'a> {
     Lexer::new(input)
 }
 
+/// Same as [tokenize_iter], but the input does not have to be valid UTF-8. UTF-8 is validated in
+/// strings and comments, other tokens are ASCII.
+pub fn tokenize_iter_bytes<'a>(input: &'a [u8]) -> Lexer<'a> {
+    Lexer::from_bytes(input)
+}
+
 pub struct Lexer<'a> {
-    input: Peekable<CharIndices<'a>>,
+    input: &'a [u8],
+    byte_offset: usize,
+    int_overflow: IntOverflow,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        Lexer::with_config(input, ParserConfig::default())
+    }
+
+    pub fn with_config(input: &'a str, config: ParserConfig) -> Self {
+        Lexer::from_bytes_with_config(input.as_bytes(), config)
+    }
+
+    pub fn from_bytes(input: &'a [u8]) -> Self {
+        Lexer::from_bytes_with_config(input, ParserConfig::default())
+    }
+
+    pub fn from_bytes_with_config(input: &'a [u8], config: ParserConfig) -> Self {
         Lexer {
-            input: input.char_indices().peekable(),
+            input,
+            byte_offset: 0,
+            int_overflow: config.int_overflow,
         }
     }
 }
 
 impl<'a> Iterator for Lexer<'a> {
-    type Item = Result<(usize, Token), usize>;
+    type Item = Result<(usize, Token), ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let (byte_offset, c) = loop {
-            let next @ (_, c) = match self.input.next() {
-                None => return None,
-                Some(next) => next,
-            };
+        let (byte_offset, byte) = loop {
+            let byte_offset = self.byte_offset;
+            let byte = *self.input.get(byte_offset)?;
+            self.byte_offset += 1;
 
-            if c.is_ascii_whitespace() {
+            if byte.is_ascii_whitespace() {
                 continue;
             }
 
-            break next;
+            break (byte_offset, byte);
         };
 
-        match c {
-            '/' => {
-                match self.input.next() {
-                    Some((mut end_offset, '/')) => {
-                        // Skip until newline.
-                        for (byte_offset_, c_) in self.input.by_ref() {
-                            end_offset = byte_offset_;
-                            if c_ == '\n' {
-                                return Some(Ok((
-                                    byte_offset,
-                                    Token::Comment {
-                                        size_in_bytes: end_offset - byte_offset + 1,
-                                    },
-                                )));
-                            }
-                        }
-
-                        // End of input.
-                        Some(Ok((
-                            byte_offset,
-                            Token::Comment {
-                                size_in_bytes: end_offset - byte_offset + 1,
-                            },
-                        )))
-                    }
-                    _ => Some(Err(byte_offset + 1)),
+        match byte {
+            b'/' => match scan_comment(self.input, byte_offset) {
+                Ok(end_offset) => {
+                    self.byte_offset = end_offset;
+                    Some(Ok((
+                        byte_offset,
+                        Token::Comment {
+                            size_in_bytes: end_offset - byte_offset,
+                        },
+                    )))
                 }
-            }
-
-            '"' => {
-                let mut last_byte_offset = byte_offset;
-                for (byte_offset_, c_) in self.input.by_ref() {
-                    if c_ == '"' {
-                        return Some(Ok((
-                            byte_offset + 1,
-                            Token::Str {
-                                size_in_bytes: byte_offset_ - byte_offset - 1,
-                            },
-                        )));
-                    }
-
-                    last_byte_offset = byte_offset_;
+                Err(err) => Some(Err(err)),
+            },
+
+            b'"' => match scan_string_bytes(self.input, byte_offset + 1) {
+                Ok((end_offset, has_escapes)) => {
+                    self.byte_offset = end_offset + 1;
+                    Some(Ok((
+                        byte_offset + 1,
+                        Token::Str {
+                            size_in_bytes: end_offset - byte_offset - 1,
+                            has_escapes,
+                        },
+                    )))
                 }
 
-                // Unterminated string.
-                Some(Err(last_byte_offset))
-            }
-
-            't' => {
-                if self.next_char() == Some('r')
-                    && self.next_char() == Some('u')
-                    && self.next_char() == Some('e')
-                {
-                    Some(Ok((byte_offset, Token::True)))
-                } else {
-                    Some(Err(byte_offset))
-                }
-            }
+                Err(err) => Some(Err(scan_string_error(err, byte_offset))),
+            },
 
-            'f' => {
-                if self.next_char() == Some('a')
-                    && self.next_char() == Some('l')
-                    && self.next_char() == Some('s')
-                    && self.next_char() == Some('e')
-                {
-                    Some(Ok((byte_offset, Token::False)))
-                } else {
-                    Some(Err(byte_offset))
+            b't' | b'f' | b'n' => match scan_keyword(self.input, byte_offset) {
+                Ok((token, end_offset)) => {
+                    self.byte_offset = end_offset;
+                    Some(Ok((byte_offset, token)))
                 }
-            }
+                Err(err) => Some(Err(err)),
+            },
 
-            'n' => {
-                if self.next_char() == Some('u')
-                    && self.next_char() == Some('l')
-                    && self.next_char() == Some('l')
-                {
-                    Some(Ok((byte_offset, Token::Null)))
-                } else {
-                    Some(Err(byte_offset))
+            b'-' | b'0'..=b'9' => match scan_number(self.input, byte_offset, self.int_overflow) {
+                Ok((number, end_offset)) => {
+                    self.byte_offset = end_offset;
+                    Some(Ok((byte_offset, number_token(number))))
                 }
-            }
+                Err(err) => Some(Err(err)),
+            },
 
-            c if c.is_ascii_digit() => {
-                let mut i: u64 = u64::from((c as u8) - b'0');
+            b',' => Some(Ok((byte_offset, Token::Comma))),
 
-                while let Some((_, next)) = self.input.peek().copied() {
-                    if !next.is_ascii_digit() {
-                        break;
-                    }
+            b':' => Some(Ok((byte_offset, Token::Colon))),
 
-                    // Consume the digit.
-                    self.input.next();
+            b'[' => Some(Ok((byte_offset, Token::LBracket))),
 
-                    // Ignore overflows for the purposes of this post.
-                    i *= 10;
-                    i += u64::from((next as u8) - b'0');
-                }
+            b']' => Some(Ok((byte_offset, Token::RBracket))),
 
-                Some(Ok((byte_offset, Token::Int(i))))
-            }
+            b'{' => Some(Ok((byte_offset, Token::LBrace))),
 
-            ',' => Some(Ok((byte_offset, Token::Comma))),
+            b'}' => Some(Ok((byte_offset, Token::RBrace))),
 
-            ':' => Some(Ok((byte_offset, Token::Colon))),
+            _ => Some(Err(invalid_token(byte_offset))),
+        }
+    }
+}
 
-            '[' => Some(Ok((byte_offset, Token::LBracket))),
+/// Scans `true`, `false`, or `null` starting at `byte_offset`. Returns the token and the byte
+/// offset after the keyword.
+pub(crate) fn scan_keyword(input: &[u8], byte_offset: usize) -> Result<(Token, usize), ParseError> {
+    let (keyword, token): (&[u8], Token) = match input[byte_offset] {
+        b't' => (b"true", Token::True),
+        b'f' => (b"false", Token::False),
+        _ => (b"null", Token::Null),
+    };
+    if input[byte_offset..].starts_with(keyword) {
+        Ok((token, byte_offset + keyword.len()))
+    } else {
+        Err(invalid_token(byte_offset))
+    }
+}
 
-            ']' => Some(Ok((byte_offset, Token::RBracket))),
+/// Scans a comment starting at `byte_offset`, validating UTF-8 of the comment. Expects a '/' at
+/// `byte_offset`.
+///
+/// Returns the byte offset after the newline at the end of the comment, or the end of the input.
+pub(crate) fn scan_comment(input: &[u8], byte_offset: usize) -> Result<usize, ParseError> {
+    if input.get(byte_offset + 1) != Some(&b'/') {
+        return Err(invalid_token(byte_offset));
+    }
 
-            '{' => Some(Ok((byte_offset, Token::LBrace))),
+    let mut utf8 = Utf8Validator::default();
+    for (byte_idx, byte) in input[byte_offset + 2..].iter().copied().enumerate() {
+        let byte_offset = byte_offset + 2 + byte_idx;
+        utf8.feed(byte, byte_offset)?;
+        if byte == b'\n' {
+            return Ok(byte_offset + 1);
+        }
+    }
+    utf8.end()?;
 
-            '}' => Some(Ok((byte_offset, Token::RBrace))),
+    Ok(input.len())
+}
 
-            _ => Some(Err(byte_offset)),
+/// Skips an array or object starting at `byte_offset`, without checking its contents other than
+/// balancing of brackets, and strings and comments that may contain brackets. Expects a '[' or
+/// '{' at `byte_offset`.
+///
+/// UTF-8 of the strings and comments is validated, as when parsing them, so that byte inputs with
+/// invalid UTF-8 are rejected whether or not they are skipped.
+///
+/// Returns the byte offset of the matching ']' or '}'.
+pub(crate) fn skip_container(input: &[u8], byte_offset: usize) -> Result<usize, ParseError> {
+    let (close, expected) = match input[byte_offset] {
+        b'[' => (b']', Expected::CommaOrEndArray),
+        _ => (b'}', Expected::CommaOrEndObject),
+    };
+
+    // Mismatched brackets inside the container are not errors, only the closing bracket of the
+    // container itself is checked.
+    let mut depth: usize = 0;
+    let mut byte_offset = byte_offset;
+    while let Some(byte) = input.get(byte_offset).copied() {
+        match byte {
+            b'[' | b'{' => {
+                depth += 1;
+                byte_offset += 1;
+            }
+            b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    if byte != close {
+                        return Err(ParseError::unexpected_char(
+                            byte_offset,
+                            expected,
+                            char::from(byte),
+                        ));
+                    }
+                    return Ok(byte_offset);
+                }
+                byte_offset += 1;
+            }
+            b'"' => {
+                let quote_offset = byte_offset;
+                byte_offset += 1;
+                let mut utf8 = Utf8Validator::default();
+                let mut escaped = false;
+                loop {
+                    let byte = match input.get(byte_offset) {
+                        Some(byte) => *byte,
+                        None => {
+                            return Err(ParseError::new(
+                                quote_offset,
+                                ParseErrorKind::UnterminatedString,
+                            ))
+                        }
+                    };
+                    utf8.feed(byte, byte_offset)?;
+                    byte_offset += 1;
+                    if escaped {
+                        escaped = false;
+                    } else if byte == b'\\' {
+                        escaped = true;
+                    } else if byte == b'"' {
+                        break;
+                    }
+                }
+            }
+            b'/' => byte_offset = scan_comment(input, byte_offset)?,
+            _ => byte_offset += 1,
         }
     }
+
+    Err(ParseError::unexpected_end_of_input(input.len(), expected))
 }
 
-impl<'a> Lexer<'a> {
-    fn next_char(&mut self) -> Option<char> {
-        self.input.next().map(|(_, c)| c)
+pub(crate) fn number_token(number: Number) -> Token {
+    match number {
+        Number::Int(i) => Token::Int(i),
+        Number::NegInt(i) => Token::NegInt(i),
+        Number::Float(f) => Token::Float(f),
+        Number::Raw { size_in_bytes } => Token::RawNumber { size_in_bytes },
+    }
+}
+
+pub(crate) fn invalid_token(byte_offset: usize) -> ParseError {
+    ParseError::new(byte_offset, ParseErrorKind::InvalidToken)
+}
+
+/// Converts a [ScanStringError] to a [ParseError]. `quote_offset` is the location of the opening
+/// double quote.
+pub(crate) fn scan_string_error(err: ScanStringError, quote_offset: usize) -> ParseError {
+    match err {
+        ScanStringError::Unterminated => {
+            ParseError::new(quote_offset, ParseErrorKind::UnterminatedString)
+        }
+        ScanStringError::InvalidEscape { byte_offset } => {
+            ParseError::new(byte_offset, ParseErrorKind::InvalidEscape)
+        }
+        ScanStringError::InvalidUtf8 { byte_offset } => invalid_utf8(byte_offset),
     }
 }
 
@@ -207,17 +302,186 @@ fn test_comments() {
         tokenize("// asdf\n"),
         vec![(0, Token::Comment { size_in_bytes: 8 })]
     );
+
+    // Non-ASCII character at the end of input.
+    assert_eq!(
+        tokenize("// é"),
+        vec![(0, Token::Comment { size_in_bytes: 5 })]
+    );
 }
 
 #[test]
 fn test_strings() {
     assert_eq!(
         tokenize(r#""""#),
-        vec![(1, Token::Str { size_in_bytes: 0 })]
+        vec![(
+            1,
+            Token::Str {
+                size_in_bytes: 0,
+                has_escapes: false
+            }
+        )]
     );
 
     assert_eq!(
         tokenize(r#""a""#),
-        vec![(1, Token::Str { size_in_bytes: 1 })]
+        vec![(
+            1,
+            Token::Str {
+                size_in_bytes: 1,
+                has_escapes: false
+            }
+        )]
+    );
+}
+
+#[test]
+fn test_numbers() {
+    assert_eq!(
+        tokenize("0 123 -45 1.5 -2e3 1E-2"),
+        vec![
+            (0, Token::Int(0)),
+            (2, Token::Int(123)),
+            (6, Token::NegInt(-45)),
+            (10, Token::Float(1.5)),
+            (14, Token::Float(-2e3)),
+            (19, Token::Float(1e-2)),
+        ]
+    );
+
+    assert_eq!(
+        tokenize("[1,-2]"),
+        vec![
+            (0, Token::LBracket),
+            (1, Token::Int(1)),
+            (2, Token::Comma),
+            (3, Token::NegInt(-2)),
+            (5, Token::RBracket),
+        ]
+    );
+
+    let error_offset = |input: &str| Lexer::new(input).next().unwrap().unwrap_err().byte_offset;
+    assert_eq!(error_offset("-"), 1);
+    assert_eq!(error_offset("1."), 2);
+    assert_eq!(error_offset("1e+x"), 3);
+}
+
+#[test]
+fn test_int_overflow() {
+    let input = "[18446744073709551616, -9223372036854775809]";
+
+    assert_eq!(
+        Lexer::new(input).nth(1),
+        Some(Err(ParseError::new(1, ParseErrorKind::IntegerOverflow)))
+    );
+
+    let tokens = |int_overflow| -> Vec<(usize, Token)> {
+        Lexer::with_config(
+            input,
+            ParserConfig {
+                int_overflow,
+                ..ParserConfig::default()
+            },
+        )
+        .map(|t| t.unwrap())
+        .collect()
+    };
+
+    assert_eq!(
+        tokens(IntOverflow::Float),
+        vec![
+            (0, Token::LBracket),
+            (1, Token::Float(18446744073709551616.0)),
+            (21, Token::Comma),
+            (23, Token::Float(-9223372036854775809.0)),
+            (43, Token::RBracket),
+        ]
+    );
+
+    assert_eq!(
+        tokens(IntOverflow::Raw),
+        vec![
+            (0, Token::LBracket),
+            (1, Token::RawNumber { size_in_bytes: 20 }),
+            (21, Token::Comma),
+            (23, Token::RawNumber { size_in_bytes: 20 }),
+            (43, Token::RBracket),
+        ]
     );
 }
+
+#[test]
+fn test_string_escapes() {
+    assert_eq!(
+        tokenize(r#""a\"b" "\\" "\u00e9""#),
+        vec![
+            (
+                1,
+                Token::Str {
+                    size_in_bytes: 4,
+                    has_escapes: true
+                }
+            ),
+            (
+                8,
+                Token::Str {
+                    size_in_bytes: 2,
+                    has_escapes: true
+                }
+            ),
+            (
+                13,
+                Token::Str {
+                    size_in_bytes: 6,
+                    has_escapes: true
+                }
+            ),
+        ]
+    );
+
+    let error_offset = |input: &str| Lexer::new(input).next().unwrap().unwrap_err().byte_offset;
+    assert_eq!(error_offset(r#""a\x""#), 2);
+    assert_eq!(error_offset(r#""\u12""#), 1);
+    assert_eq!(error_offset(r#""a\""#), 0);
+}
+
+#[test]
+fn bytes_tests() {
+    let all_tokens = |lexer: Lexer| -> Vec<Result<(usize, Token), ParseError>> {
+        let mut tokens = vec![];
+        for token in lexer {
+            let is_error = token.is_err();
+            tokens.push(token);
+            if is_error {
+                break;
+            }
+        }
+        tokens
+    };
+
+    // Valid UTF-8 is lexed the same as `&str` input.
+    let strs = crate::test_common::ast_tests()
+        .into_iter()
+        .map(|(str, _)| str)
+        .chain(
+            crate::test_common::error_tests()
+                .into_iter()
+                .map(|(str, _)| str),
+        )
+        .chain(["[\"é😀\", 1] // ключ 😀".to_string()]);
+    for str in strs {
+        assert_eq!(
+            all_tokens(tokenize_iter_bytes(str.as_bytes())),
+            all_tokens(tokenize_iter(&str))
+        );
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(
+            tokenize_iter_bytes(&bytes).find_map(Result::err),
+            Some(error),
+            "{:?}",
+            bytes
+        );
+    }
+}
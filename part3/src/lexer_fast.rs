@@ -0,0 +1,413 @@
+use crate::escape::ScanStringError;
+use crate::lexer::{invalid_token, number_token, scan_keyword, scan_string_error};
+use crate::number::scan_number;
+use crate::utf8::{invalid_utf8, Utf8Validator};
+use crate::{IntOverflow, ParseError, ParserConfig, Token};
+
+/// Same as [tokenize_iter][crate::tokenize_iter], but uses [FastLexer].
+pub fn tokenize_fast<'a>(input: &'a str) -> FastLexer<'a> {
+    FastLexer::new(input)
+}
+
+/// Same as [tokenize_fast], but the input does not have to be valid UTF-8. UTF-8 is validated in
+/// strings and comments, other tokens are ASCII.
+pub fn tokenize_fast_bytes<'a>(input: &'a [u8]) -> FastLexer<'a> {
+    FastLexer::from_bytes(input)
+}
+
+/// An iterator lexer that generates the same tokens as [Lexer][crate::Lexer], but skips
+/// whitespace with a lookup table, and scans strings and comments 8 bytes at a time.
+pub struct FastLexer<'a> {
+    input: &'a [u8],
+    byte_offset: usize,
+    int_overflow: IntOverflow,
+}
+
+impl<'a> FastLexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        FastLexer::with_config(input, ParserConfig::default())
+    }
+
+    pub fn with_config(input: &'a str, config: ParserConfig) -> Self {
+        FastLexer::from_bytes_with_config(input.as_bytes(), config)
+    }
+
+    pub fn from_bytes(input: &'a [u8]) -> Self {
+        FastLexer::from_bytes_with_config(input, ParserConfig::default())
+    }
+
+    pub fn from_bytes_with_config(input: &'a [u8], config: ParserConfig) -> Self {
+        FastLexer {
+            input,
+            byte_offset: 0,
+            int_overflow: config.int_overflow,
+        }
+    }
+}
+
+/// Bytes skipped between tokens. Same as [u8::is_ascii_whitespace].
+static WHITESPACE: [bool; 256] = {
+    let mut table = [false; 256];
+    table[b' ' as usize] = true;
+    table[b'\t' as usize] = true;
+    table[b'\n' as usize] = true;
+    table[b'\x0C' as usize] = true;
+    table[b'\r' as usize] = true;
+    table
+};
+
+impl<'a> Iterator for FastLexer<'a> {
+    type Item = Result<(usize, Token), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while WHITESPACE[usize::from(*self.input.get(self.byte_offset)?)] {
+            self.byte_offset += 1;
+        }
+
+        let byte_offset = self.byte_offset;
+        self.byte_offset += 1;
+
+        let token = match self.input[byte_offset] {
+            b'[' => Token::LBracket,
+
+            b']' => Token::RBracket,
+
+            b'{' => Token::LBrace,
+
+            b'}' => Token::RBrace,
+
+            b',' => Token::Comma,
+
+            b':' => Token::Colon,
+
+            b'"' => match scan_string(self.input, byte_offset + 1) {
+                Ok((end_offset, has_escapes)) => {
+                    self.byte_offset = end_offset + 1;
+                    return Some(Ok((
+                        byte_offset + 1,
+                        Token::Str {
+                            size_in_bytes: end_offset - byte_offset - 1,
+                            has_escapes,
+                        },
+                    )));
+                }
+                Err(err) => return Some(Err(scan_string_error(err, byte_offset))),
+            },
+
+            b'-' | b'0'..=b'9' => match scan_number(self.input, byte_offset, self.int_overflow) {
+                Ok((number, end_offset)) => {
+                    self.byte_offset = end_offset;
+                    number_token(number)
+                }
+                Err(err) => return Some(Err(err)),
+            },
+
+            b't' | b'f' | b'n' => match scan_keyword(self.input, byte_offset) {
+                Ok((token, end_offset)) => {
+                    self.byte_offset = end_offset;
+                    token
+                }
+                Err(err) => return Some(Err(err)),
+            },
+
+            b'/' => match scan_comment(self.input, byte_offset) {
+                Ok(end_offset) => {
+                    self.byte_offset = end_offset;
+                    Token::Comment {
+                        size_in_bytes: end_offset - byte_offset,
+                    }
+                }
+                Err(err) => return Some(Err(err)),
+            },
+
+            _ => return Some(Err(invalid_token(byte_offset))),
+        };
+
+        Some(Ok((byte_offset, token)))
+    }
+}
+
+/// Same as [scan_string_bytes][crate::escape::scan_string_bytes], but skips the bytes that don't
+/// need to be checked 8 bytes at a time.
+fn scan_string(input: &[u8], mut byte_offset: usize) -> Result<(usize, bool), ScanStringError> {
+    let mut has_escapes = false;
+
+    loop {
+        byte_offset = find_byte(input, byte_offset, [b'"', b'\\']);
+
+        let byte = match input.get(byte_offset) {
+            Some(byte) => *byte,
+            None => return Err(ScanStringError::Unterminated),
+        };
+
+        match byte {
+            b'"' => return Ok((byte_offset, has_escapes)),
+
+            b'\\' => {
+                has_escapes = true;
+                let backslash_offset = byte_offset;
+                let invalid_escape = ScanStringError::InvalidEscape {
+                    byte_offset: backslash_offset,
+                };
+                byte_offset += 1;
+                match input.get(byte_offset) {
+                    Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {
+                        byte_offset += 1;
+                    }
+
+                    Some(b'u') => {
+                        for _ in 0..4 {
+                            byte_offset += 1;
+                            match input.get(byte_offset) {
+                                Some(byte) if byte.is_ascii_hexdigit() => {}
+                                Some(_) => return Err(invalid_escape),
+                                None => return Err(ScanStringError::Unterminated),
+                            }
+                        }
+                        byte_offset += 1;
+                    }
+
+                    Some(_) => return Err(invalid_escape),
+
+                    None => return Err(ScanStringError::Unterminated),
+                }
+            }
+
+            _ => {
+                // Non-ASCII, input ending in the middle of a character is an unterminated string.
+                byte_offset = match skip_char(input, byte_offset) {
+                    Ok(Some(next_offset)) => next_offset,
+                    Ok(None) => return Err(ScanStringError::Unterminated),
+                    Err(err) => {
+                        return Err(ScanStringError::InvalidUtf8 {
+                            byte_offset: err.byte_offset,
+                        })
+                    }
+                };
+            }
+        }
+    }
+}
+
+/// Same as [scan_comment][crate::lexer::scan_comment], but skips ASCII bytes 8 bytes at a time.
+fn scan_comment(input: &[u8], byte_offset: usize) -> Result<usize, ParseError> {
+    if input.get(byte_offset + 1) != Some(&b'/') {
+        return Err(invalid_token(byte_offset));
+    }
+
+    let mut byte_offset = byte_offset + 2;
+    loop {
+        byte_offset = find_byte(input, byte_offset, [b'\n', b'\n']);
+
+        match input.get(byte_offset) {
+            None => return Ok(input.len()),
+
+            Some(b'\n') => return Ok(byte_offset + 1),
+
+            Some(_) => match skip_char(input, byte_offset)? {
+                Some(next_offset) => byte_offset = next_offset,
+                None => {
+                    // Input ended in the middle of a character.
+                    return Err(invalid_utf8(byte_offset));
+                }
+            },
+        }
+    }
+}
+
+/// Validates the non-ASCII character at `byte_offset`. Returns the byte offset after the
+/// character, or `None` if the input ends before the character.
+fn skip_char(input: &[u8], mut byte_offset: usize) -> Result<Option<usize>, ParseError> {
+    let mut utf8 = Utf8Validator::default();
+    loop {
+        let byte = match input.get(byte_offset) {
+            Some(byte) => *byte,
+            None => return Ok(None),
+        };
+        utf8.feed(byte, byte_offset)?;
+        byte_offset += 1;
+        if utf8.end().is_ok() {
+            return Ok(Some(byte_offset));
+        }
+    }
+}
+
+const LO: u64 = 0x0101_0101_0101_0101;
+const HI: u64 = 0x8080_8080_8080_8080;
+
+/// Returns the byte offset of the first byte starting at `byte_offset` that is one of `bytes` or
+/// non-ASCII, or the input length if there isn't one.
+///
+/// Checks 8 bytes at a time, with the "determine if a word has a zero byte" trick from "Bit
+/// Twiddling Hacks".
+fn find_byte(input: &[u8], mut byte_offset: usize, bytes: [u8; 2]) -> usize {
+    let pattern0 = LO * u64::from(bytes[0]);
+    let pattern1 = LO * u64::from(bytes[1]);
+
+    while let Some(word) = input.get(byte_offset..byte_offset + 8) {
+        let word = u64::from_le_bytes(word.try_into().unwrap());
+        let matches = zero_bytes(word ^ pattern0) | zero_bytes(word ^ pattern1) | (word & HI);
+        if matches != 0 {
+            // Only the bytes after the first zero byte can be falsely marked by `zero_bytes`, so
+            // the lowest marked byte is the first match.
+            return byte_offset + (matches.trailing_zeros() / 8) as usize;
+        }
+        byte_offset += 8;
+    }
+
+    while let Some(byte) = input.get(byte_offset) {
+        if bytes.contains(byte) || !byte.is_ascii() {
+            break;
+        }
+        byte_offset += 1;
+    }
+
+    byte_offset
+}
+
+/// Marks the high bit of zero bytes in `word`.
+fn zero_bytes(word: u64) -> u64 {
+    word.wrapping_sub(LO) & !word & HI
+}
+
+#[cfg(test)]
+fn all_tokens<I: Iterator<Item = Result<(usize, Token), ParseError>>>(
+    lexer: I,
+) -> Vec<Result<(usize, Token), ParseError>> {
+    let mut tokens = vec![];
+    for token in lexer {
+        let is_error = token.is_err();
+        tokens.push(token);
+        if is_error {
+            break;
+        }
+    }
+    tokens
+}
+
+#[test]
+fn same_tokens_tests() {
+    let strs = crate::test_common::ast_tests()
+        .into_iter()
+        .map(|(str, _)| str)
+        .chain(
+            crate::test_common::error_tests()
+                .into_iter()
+                .map(|(str, _)| str),
+        )
+        .chain([
+            "[\"é😀\", 1] // ключ 😀".to_string(),
+            // Strings and comments longer than 8 bytes, with the interesting bytes at different
+            // locations in the words.
+            format!(
+                "[\"{}\", \"{}\\\"\\u00e9\"]",
+                "a".repeat(30),
+                "b".repeat(13)
+            ),
+            format!(
+                "// {}é{}\n\"{}\"",
+                "c".repeat(20),
+                "d".repeat(7),
+                "é".repeat(9)
+            ),
+        ]);
+
+    for str in strs {
+        assert_eq!(
+            all_tokens(tokenize_fast(&str)),
+            all_tokens(crate::tokenize_iter(&str)),
+            "{:?}",
+            str
+        );
+    }
+
+    for input_size in [10, 100, 1_000, 10_000] {
+        let input = crate::gen_input(input_size);
+        assert_eq!(
+            all_tokens(tokenize_fast(&input)),
+            all_tokens(crate::tokenize_iter(&input))
+        );
+    }
+}
+
+#[test]
+fn event_to_tree_tests() {
+    for (str, ast) in crate::test_common::ast_tests() {
+        let mut parser = crate::parse_events_iter_using_lexer_iter(tokenize_fast(&str), str.len());
+        assert_eq!(crate::event_to_tree(&mut parser, &str).unwrap(), ast);
+    }
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        let error_ = crate::parse_events_iter_using_lexer_iter(tokenize_fast(&str), str.len())
+            .find_map(Result::err);
+        assert_eq!(error_, Some(error), "{:?}", str);
+    }
+}
+
+#[test]
+fn int_overflow_tests() {
+    let config = crate::test_common::raw_int_config();
+    for (str, _, _) in crate::test_common::int_overflow_tests() {
+        assert_eq!(
+            all_tokens(FastLexer::new(&str)),
+            all_tokens(crate::Lexer::new(&str))
+        );
+        assert_eq!(
+            all_tokens(FastLexer::with_config(&str, config)),
+            all_tokens(crate::Lexer::with_config(&str, config))
+        );
+    }
+}
+
+#[test]
+fn utf8_tests() {
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(
+            tokenize_fast_bytes(&bytes).find_map(Result::err),
+            Some(error),
+            "{:?}",
+            bytes
+        );
+
+        // Also at different offsets in the 8-byte words.
+        for padding in 1..8 {
+            let mut padded = vec![b' '; padding];
+            padded.extend_from_slice(&bytes);
+            assert_eq!(
+                all_tokens(tokenize_fast_bytes(&padded)),
+                all_tokens(crate::tokenize_iter_bytes(&padded)),
+                "{:?}",
+                padded
+            );
+        }
+    }
+
+    // Input ending in the middle of a character in a string.
+    assert_eq!(
+        all_tokens(tokenize_fast_bytes(b"\"abcdefgh\xe2\x82")),
+        all_tokens(crate::tokenize_iter_bytes(b"\"abcdefgh\xe2\x82")),
+    );
+}
+
+#[test]
+fn find_byte_test() {
+    let input = b"0123456789abcdef\"0123456789\\";
+    assert_eq!(find_byte(input, 0, [b'"', b'\\']), 16);
+    assert_eq!(find_byte(input, 17, [b'"', b'\\']), 27);
+    assert_eq!(find_byte(input, 28, [b'"', b'\\']), 28);
+    assert_eq!(find_byte(b"abcdefghijk\xc3\xa9", 0, [b'"', b'"']), 11);
+    assert_eq!(find_byte(b"abcdefghijk", 0, [b'"', b'"']), 11);
+
+    // Byte 0x01 before the match: `zero_bytes` can't mark bytes before a zero byte.
+    assert_eq!(
+        find_byte(b"\x01\x01\x01\"\x01\x01\x01\x01", 0, [b'"', b'"']),
+        3
+    );
+    assert_eq!(
+        find_byte(b"\"\x01\x01\x01\x01\x01\x01\x01", 0, [b'"', b'"']),
+        0
+    );
+}
@@ -1,135 +1,92 @@
-use crate::Token;
+use crate::escape::scan_string_bytes;
+use crate::lexer::{invalid_token, number_token, scan_comment, scan_keyword, scan_string_error};
+use crate::number::scan_number;
+use crate::{ParseError, ParserConfig, Token};
 
-pub fn tokenize_list(input: &str) -> Result<Vec<(usize, Token)>, usize> {
-    let mut tokens: Vec<(usize, Token)> = vec![];
+pub fn tokenize_list(input: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    tokenize_list_with_config(input, ParserConfig::default())
+}
 
-    let mut input = input.char_indices().peekable();
+pub fn tokenize_list_with_config(
+    input: &str,
+    config: ParserConfig,
+) -> Result<Vec<(usize, Token)>, ParseError> {
+    tokenize_list_bytes_with_config(input.as_bytes(), config)
+}
 
-    'outer: while let Some((byte_offset, c)) = input.next() {
-        if c.is_ascii_whitespace() {
-            continue;
-        }
+/// Same as [tokenize_list], but the input does not have to be valid UTF-8. UTF-8 is validated in
+/// strings and comments, other tokens are ASCII.
+pub fn tokenize_list_bytes(input: &[u8]) -> Result<Vec<(usize, Token)>, ParseError> {
+    tokenize_list_bytes_with_config(input, ParserConfig::default())
+}
 
-        match c {
-            '/' => {
-                match input.next() {
-                    Some((mut end_offset, '/')) => {
-                        // Skip until newline.
-                        for (byte_offset_, c_) in input.by_ref() {
-                            end_offset = byte_offset_;
-                            if c_ == '\n' {
-                                tokens.push((
-                                    byte_offset,
-                                    Token::Comment {
-                                        size_in_bytes: end_offset - byte_offset + 1,
-                                    },
-                                ));
-                                continue 'outer;
-                            }
-                        }
-
-                        // End of input.
-                        tokens.push((
-                            byte_offset,
-                            Token::Comment {
-                                size_in_bytes: end_offset - byte_offset + 1,
-                            },
-                        ));
-
-                        break;
-                    }
-                    _ => {
-                        return Err(byte_offset + 1);
-                    }
-                }
-            }
+pub fn tokenize_list_bytes_with_config(
+    input: &[u8],
+    config: ParserConfig,
+) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens: Vec<(usize, Token)> = vec![];
 
-            '"' => {
-                let mut last_byte_offset = byte_offset;
-                for (byte_offset_, c_) in input.by_ref() {
-                    if c_ == '"' {
-                        tokens.push((
-                            byte_offset,
-                            Token::Str {
-                                size_in_bytes: byte_offset_ - byte_offset - 1,
-                            },
-                        ));
-                        continue 'outer;
-                    }
-
-                    last_byte_offset = byte_offset_;
-                }
+    let mut next_byte_offset = 0;
 
-                // Unterminated string.
-                return Err(last_byte_offset);
-            }
+    while let Some(&byte) = input.get(next_byte_offset) {
+        let byte_offset = next_byte_offset;
+        next_byte_offset += 1;
 
-            't' => {
-                if matches!(input.next(), Some((_, 'r')))
-                    && matches!(input.next(), Some((_, 'u')))
-                    && matches!(input.next(), Some((_, 'e')))
-                {
-                    tokens.push((byte_offset, Token::True));
-                } else {
-                    return Err(byte_offset);
-                }
-            }
+        if byte.is_ascii_whitespace() {
+            continue;
+        }
 
-            'f' => {
-                if matches!(input.next(), Some((_, 'a')))
-                    && matches!(input.next(), Some((_, 'l')))
-                    && matches!(input.next(), Some((_, 's')))
-                    && matches!(input.next(), Some((_, 'e')))
-                {
-                    tokens.push((byte_offset, Token::False));
-                } else {
-                    return Err(byte_offset);
-                }
+        match byte {
+            b'/' => {
+                next_byte_offset = scan_comment(input, byte_offset)?;
+                tokens.push((
+                    byte_offset,
+                    Token::Comment {
+                        size_in_bytes: next_byte_offset - byte_offset,
+                    },
+                ));
             }
 
-            'n' => {
-                if matches!(input.next(), Some((_, 'u')))
-                    && matches!(input.next(), Some((_, 'l')))
-                    && matches!(input.next(), Some((_, 'l')))
-                {
-                    tokens.push((byte_offset, Token::Null));
-                } else {
-                    return Err(byte_offset);
+            b'"' => match scan_string_bytes(input, byte_offset + 1) {
+                Ok((end_offset, has_escapes)) => {
+                    next_byte_offset = end_offset + 1;
+                    tokens.push((
+                        byte_offset + 1,
+                        Token::Str {
+                            size_in_bytes: end_offset - byte_offset - 1,
+                            has_escapes,
+                        },
+                    ));
                 }
-            }
-
-            c if c.is_ascii_digit() => {
-                let mut i: u64 = u64::from((c as u8) - b'0');
-
-                while let Some((_, next)) = input.peek().copied() {
-                    if !next.is_ascii_digit() {
-                        break;
-                    }
 
-                    // Consume the digit.
-                    input.next();
+                Err(err) => return Err(scan_string_error(err, byte_offset)),
+            },
 
-                    // Ignore overflows for the purposes of this post.
-                    i *= 10;
-                    i += u64::from((next as u8) - b'0');
-                }
+            b't' | b'f' | b'n' => {
+                let (token, end_offset) = scan_keyword(input, byte_offset)?;
+                next_byte_offset = end_offset;
+                tokens.push((byte_offset, token));
+            }
 
-                tokens.push((byte_offset, Token::Int(i)));
+            b'-' | b'0'..=b'9' => {
+                let (number, end_offset) = scan_number(input, byte_offset, config.int_overflow)?;
+                next_byte_offset = end_offset;
+                tokens.push((byte_offset, number_token(number)));
             }
 
-            ',' => tokens.push((byte_offset, Token::Comma)),
+            b',' => tokens.push((byte_offset, Token::Comma)),
 
-            ':' => tokens.push((byte_offset, Token::Colon)),
+            b':' => tokens.push((byte_offset, Token::Colon)),
 
-            '[' => tokens.push((byte_offset, Token::LBracket)),
+            b'[' => tokens.push((byte_offset, Token::LBracket)),
 
-            ']' => tokens.push((byte_offset, Token::RBracket)),
+            b']' => tokens.push((byte_offset, Token::RBracket)),
 
-            '{' => tokens.push((byte_offset, Token::LBrace)),
+            b'{' => tokens.push((byte_offset, Token::LBrace)),
 
-            '}' => tokens.push((byte_offset, Token::RBrace)),
+            b'}' => tokens.push((byte_offset, Token::RBrace)),
 
-            _ => return Err(byte_offset),
+            _ => return Err(invalid_token(byte_offset)),
         }
     }
 
@@ -1,9 +1,19 @@
+use crate::escape::scan_string_bytes;
+use crate::lexer::{invalid_token, scan_comment, scan_keyword, scan_string_error};
+use crate::number::{scan_number, Number};
 use crate::token::Token;
+use crate::{ParseError, ParserConfig};
 
 pub trait LexerEventListener {
     fn handle_int(&mut self, byte_offset: usize, i: u64);
 
-    fn handle_str(&mut self, byte_offset: usize, size_in_bytes: usize);
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64);
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64);
+
+    fn handle_raw_number(&mut self, byte_offset: usize, size_in_bytes: usize);
+
+    fn handle_str(&mut self, byte_offset: usize, size_in_bytes: usize, has_escapes: bool);
 
     fn handle_true(&mut self, byte_offset: usize);
 
@@ -25,128 +35,114 @@ pub trait LexerEventListener {
 
     fn handle_comment(&mut self, byte_offset: usize, size_in_bytes: usize);
 
-    fn handle_error(&mut self, byte_offset: usize);
+    fn handle_error(&mut self, error: ParseError);
 }
 
 // TODO: Skip trivia everywhere.
 pub fn tokenize_push<L: LexerEventListener>(input: &str, listener: &mut L) {
-    let mut input = input.char_indices().peekable();
-
-    'outer: while let Some((byte_offset, c)) = input.next() {
-        if c.is_ascii_whitespace() {
-            continue;
-        }
+    tokenize_push_with_config(input, ParserConfig::default(), listener)
+}
 
-        match c {
-            '/' => {
-                match input.next() {
-                    Some((mut end_offset, '/')) => {
-                        // Skip until newline.
-                        for (byte_offset_, c_) in input.by_ref() {
-                            end_offset = byte_offset_;
-                            if c_ == '\n' {
-                                listener.handle_comment(byte_offset, end_offset - byte_offset + 1);
-                                continue 'outer;
-                            }
-                        }
+pub fn tokenize_push_with_config<L: LexerEventListener>(
+    input: &str,
+    config: ParserConfig,
+    listener: &mut L,
+) {
+    tokenize_push_bytes_with_config(input.as_bytes(), config, listener)
+}
 
-                        // End of input.
-                        listener.handle_comment(byte_offset, end_offset - byte_offset + 1);
-                        break;
-                    }
-                    _ => {
-                        listener.handle_error(byte_offset + 1);
-                        break;
-                    }
-                }
-            }
+/// Same as [tokenize_push], but the input does not have to be valid UTF-8. UTF-8 is validated in
+/// strings and comments, other tokens are ASCII.
+pub fn tokenize_push_bytes<L: LexerEventListener>(input: &[u8], listener: &mut L) {
+    tokenize_push_bytes_with_config(input, ParserConfig::default(), listener)
+}
 
-            '"' => {
-                let mut last_byte_offset = byte_offset;
-                for (byte_offset_, c_) in input.by_ref() {
-                    if c_ == '"' {
-                        listener.handle_str(byte_offset + 1, byte_offset_ - byte_offset - 1);
-                        continue 'outer;
-                    }
+pub fn tokenize_push_bytes_with_config<L: LexerEventListener>(
+    input: &[u8],
+    config: ParserConfig,
+    listener: &mut L,
+) {
+    let mut next_byte_offset = 0;
 
-                    last_byte_offset = byte_offset_;
-                }
+    while let Some(&byte) = input.get(next_byte_offset) {
+        let byte_offset = next_byte_offset;
+        next_byte_offset += 1;
 
-                // Unterminated string.
-                listener.handle_error(last_byte_offset);
-                break;
-            }
+        if byte.is_ascii_whitespace() {
+            continue;
+        }
 
-            't' => {
-                if matches!(input.next(), Some((_, 'r')))
-                    && matches!(input.next(), Some((_, 'u')))
-                    && matches!(input.next(), Some((_, 'e')))
-                {
-                    listener.handle_true(byte_offset);
-                } else {
-                    listener.handle_error(byte_offset);
+        match byte {
+            b'/' => match scan_comment(input, byte_offset) {
+                Ok(end_offset) => {
+                    next_byte_offset = end_offset;
+                    listener.handle_comment(byte_offset, end_offset - byte_offset);
+                }
+                Err(err) => {
+                    listener.handle_error(err);
                     break;
                 }
-            }
+            },
 
-            'f' => {
-                if matches!(input.next(), Some((_, 'a')))
-                    && matches!(input.next(), Some((_, 'l')))
-                    && matches!(input.next(), Some((_, 's')))
-                    && matches!(input.next(), Some((_, 'e')))
-                {
-                    listener.handle_false(byte_offset);
-                } else {
-                    listener.handle_error(byte_offset);
-                    break;
+            b'"' => match scan_string_bytes(input, byte_offset + 1) {
+                Ok((end_offset, has_escapes)) => {
+                    next_byte_offset = end_offset + 1;
+                    listener.handle_str(byte_offset + 1, end_offset - byte_offset - 1, has_escapes);
                 }
-            }
 
-            'n' => {
-                if matches!(input.next(), Some((_, 'u')))
-                    && matches!(input.next(), Some((_, 'l')))
-                    && matches!(input.next(), Some((_, 'l')))
-                {
-                    listener.handle_null(byte_offset);
-                } else {
-                    listener.handle_error(byte_offset);
+                Err(err) => {
+                    listener.handle_error(scan_string_error(err, byte_offset));
                     break;
                 }
-            }
-
-            c if c.is_ascii_digit() => {
-                let mut i: u64 = u64::from((c as u8) - b'0');
-
-                while let Some((_, next)) = input.peek().copied() {
-                    if !next.is_ascii_digit() {
-                        break;
+            },
+
+            b't' | b'f' | b'n' => match scan_keyword(input, byte_offset) {
+                Ok((token, end_offset)) => {
+                    next_byte_offset = end_offset;
+                    match token {
+                        Token::True => listener.handle_true(byte_offset),
+                        Token::False => listener.handle_false(byte_offset),
+                        _ => listener.handle_null(byte_offset),
                     }
-
-                    // Consume the digit.
-                    input.next();
-
-                    // Ignore overflows for the purposes of this post.
-                    i *= 10;
-                    i += u64::from((next as u8) - b'0');
                 }
+                Err(err) => {
+                    listener.handle_error(err);
+                    break;
+                }
+            },
+
+            b'-' | b'0'..=b'9' => match scan_number(input, byte_offset, config.int_overflow) {
+                Ok((number, end_offset)) => {
+                    next_byte_offset = end_offset;
+                    match number {
+                        Number::Int(i) => listener.handle_int(byte_offset, i),
+                        Number::NegInt(i) => listener.handle_neg_int(byte_offset, i),
+                        Number::Float(f) => listener.handle_float(byte_offset, f),
+                        Number::Raw { size_in_bytes } => {
+                            listener.handle_raw_number(byte_offset, size_in_bytes)
+                        }
+                    }
+                }
+                Err(err) => {
+                    listener.handle_error(err);
+                    break;
+                }
+            },
 
-                listener.handle_int(byte_offset, i);
-            }
-
-            ',' => listener.handle_comma(byte_offset),
+            b',' => listener.handle_comma(byte_offset),
 
-            ':' => listener.handle_colon(byte_offset),
+            b':' => listener.handle_colon(byte_offset),
 
-            '[' => listener.handle_lbracket(byte_offset),
+            b'[' => listener.handle_lbracket(byte_offset),
 
-            ']' => listener.handle_rbracket(byte_offset),
+            b']' => listener.handle_rbracket(byte_offset),
 
-            '{' => listener.handle_lbrace(byte_offset),
+            b'{' => listener.handle_lbrace(byte_offset),
 
-            '}' => listener.handle_rbrace(byte_offset),
+            b'}' => listener.handle_rbrace(byte_offset),
 
             _ => {
-                listener.handle_error(byte_offset);
+                listener.handle_error(invalid_token(byte_offset));
                 break;
             }
         }
@@ -155,7 +151,7 @@ pub fn tokenize_push<L: LexerEventListener>(input: &str, listener: &mut L) {
 
 pub struct PushToTokens {
     tokens: Vec<(usize, Token)>,
-    error: Option<usize>,
+    error: Option<ParseError>,
 }
 
 impl LexerEventListener for PushToTokens {
@@ -163,9 +159,27 @@ impl LexerEventListener for PushToTokens {
         self.tokens.push((byte_offset, Token::Int(i)));
     }
 
-    fn handle_str(&mut self, byte_offset: usize, size_in_bytes: usize) {
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64) {
+        self.tokens.push((byte_offset, Token::NegInt(i)));
+    }
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64) {
+        self.tokens.push((byte_offset, Token::Float(f)));
+    }
+
+    fn handle_raw_number(&mut self, byte_offset: usize, size_in_bytes: usize) {
         self.tokens
-            .push((byte_offset, Token::Str { size_in_bytes }));
+            .push((byte_offset, Token::RawNumber { size_in_bytes }));
+    }
+
+    fn handle_str(&mut self, byte_offset: usize, size_in_bytes: usize, has_escapes: bool) {
+        self.tokens.push((
+            byte_offset,
+            Token::Str {
+                size_in_bytes,
+                has_escapes,
+            },
+        ));
     }
 
     fn handle_true(&mut self, byte_offset: usize) {
@@ -209,8 +223,8 @@ impl LexerEventListener for PushToTokens {
             .push((byte_offset, Token::Comment { size_in_bytes }));
     }
 
-    fn handle_error(&mut self, byte_offset: usize) {
-        self.error = Some(byte_offset);
+    fn handle_error(&mut self, error: ParseError) {
+        self.error = Some(error);
     }
 }
 
@@ -222,7 +236,7 @@ impl PushToTokens {
         }
     }
 
-    pub fn into_tokens(self) -> (Vec<(usize, Token)>, Option<usize>) {
+    pub fn into_tokens(self) -> (Vec<(usize, Token)>, Option<ParseError>) {
         (self.tokens, self.error)
     }
 }
@@ -284,12 +298,24 @@ fn test_comments() {
 fn test_strings() {
     assert_eq!(
         tokenize_(r#""""#),
-        vec![(1, Token::Str { size_in_bytes: 0 })]
+        vec![(
+            1,
+            Token::Str {
+                size_in_bytes: 0,
+                has_escapes: false
+            }
+        )]
     );
 
     assert_eq!(
         tokenize_(r#""a""#),
-        vec![(1, Token::Str { size_in_bytes: 1 })]
+        vec![(
+            1,
+            Token::Str {
+                size_in_bytes: 1,
+                has_escapes: false
+            }
+        )]
     );
 }
 
@@ -299,14 +325,92 @@ fn test_object() {
         tokenize_(r#"{"a":1, "b":2}"#),
         vec![
             (0, Token::LBrace),
-            (2, Token::Str { size_in_bytes: 1 }),
+            (
+                2,
+                Token::Str {
+                    size_in_bytes: 1,
+                    has_escapes: false
+                }
+            ),
             (4, Token::Colon),
             (5, Token::Int(1)),
             (6, Token::Comma),
-            (9, Token::Str { size_in_bytes: 1 }),
+            (
+                9,
+                Token::Str {
+                    size_in_bytes: 1,
+                    has_escapes: false
+                }
+            ),
             (11, Token::Colon),
             (12, Token::Int(2)),
             (13, Token::RBrace),
         ]
     );
 }
+
+#[test]
+fn test_numbers() {
+    assert_eq!(
+        tokenize_("[0, -45, 1.5e2]"),
+        vec![
+            (0, Token::LBracket),
+            (1, Token::Int(0)),
+            (2, Token::Comma),
+            (4, Token::NegInt(-45)),
+            (7, Token::Comma),
+            (9, Token::Float(150.0)),
+            (14, Token::RBracket),
+        ]
+    );
+}
+
+#[test]
+fn test_int_overflow() {
+    let mut listener = PushToTokens::new();
+    tokenize_push("[1, 99999999999999999999]", &mut listener);
+    let (tokens, error) = listener.into_tokens();
+    assert_eq!(
+        tokens,
+        vec![(0, Token::LBracket), (1, Token::Int(1)), (2, Token::Comma)]
+    );
+    assert_eq!(
+        error,
+        Some(ParseError::new(4, crate::ParseErrorKind::IntegerOverflow))
+    );
+
+    let mut listener = PushToTokens::new();
+    let config = ParserConfig {
+        int_overflow: crate::IntOverflow::Raw,
+        ..crate::ParserConfig::default()
+    };
+    tokenize_push_with_config("99999999999999999999", config, &mut listener);
+    assert_eq!(
+        listener.into_tokens(),
+        (vec![(0, Token::RawNumber { size_in_bytes: 20 })], None)
+    );
+}
+
+#[test]
+fn bytes_tests() {
+    let tokens = |input: &[u8]| {
+        let mut listener = PushToTokens::new();
+        tokenize_push_bytes(input, &mut listener);
+        listener.into_tokens()
+    };
+
+    // Valid UTF-8 is lexed the same as `&str` input.
+    for (str, _) in crate::test_common::error_tests() {
+        let mut listener = PushToTokens::new();
+        tokenize_push(&str, &mut listener);
+        assert_eq!(tokens(str.as_bytes()), listener.into_tokens());
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(tokens(&bytes).1, Some(error), "{:?}", bytes);
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(crate::tokenize_list_bytes(&bytes), Err(error));
+    }
+}
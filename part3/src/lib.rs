@@ -27,6 +27,19 @@ mod direct_non_recursive;
 /// Implements a parser that extracts timestamps from events, without building an AST.
 mod timestamp_parser;
 
+/// Implements extracting fields at given paths from events, without building an AST.
+mod field_extractor;
+
+/// Implements JSON Pointer lookups on the AST and on parse events.
+mod json_pointer;
+
+/// Implements JSONPath queries on the AST and on parse events.
+mod json_path;
+
+/// Implements matching paths to values in events, shared by the field extractor, JSON Pointer, and
+/// JSONPath.
+mod path_matcher;
+
 /// Implements generating an AST from an event parser.
 mod event_to_tree;
 
@@ -48,6 +61,15 @@ mod event_push_parser_3;
 /// Implements an event push parser using the iterator lexer, without recursion.
 mod event_push_parser_3_non_recursive;
 
+/// Implements building an index of the tokens in the input with SIMD instructions.
+mod structural_index;
+
+/// Implements an event push parser for input that arrives in chunks.
+mod event_push_parser_chunked;
+
+/// Implements an iterator event parser that reads the input from an `io::Read`.
+mod event_parser_read;
+
 /// Implements an iterator lexer.
 mod lexer;
 
@@ -57,43 +79,137 @@ mod lexer_push;
 /// Implements a lexer that generates a vector of tokens.
 mod lexer_list;
 
+/// Implements an iterator lexer that scans multiple bytes at a time.
+mod lexer_fast;
+
 /// Lexer tokens.
 mod token;
 
+/// Implements scanning and decoding string escape sequences.
+mod escape;
+
+/// Implements scanning numbers.
+mod number;
+
+/// Implements validating UTF-8 in strings and comments.
+mod utf8;
+
+/// Defines parser configuration.
+mod config;
+
+/// Defines parse errors.
+mod error;
+
+/// Implements checking the resource limits in the parser configuration.
+mod limits;
+
+/// Implements the policies for duplicate object keys.
+mod object_members;
+
+/// Implements mapping byte offsets to line and column numbers.
+mod source_map;
+
+/// Implements rendering parse errors for humans.
+mod diagnostic;
+
+/// Implements a lossless concrete syntax tree.
+mod cst;
+
+/// Defines the AST with source locations.
+mod spanned_ast;
+
+/// Implements writing the AST as JSON text.
+mod writer;
+
+/// Implements writing parse events as JSON text.
+mod event_writer;
+
 #[cfg(test)]
 mod test_common;
 
 pub use ast_builder_listener::AstBuilderListener;
+pub use config::{DuplicateKeys, IntOverflow, ParserConfig, DEFAULT_MAX_DEPTH};
+pub use cst::{
+    parse_cst, parse_cst_with_config, Cst, CstElement, CstMember, CstToken, CstValue, Trivia,
+};
+pub use diagnostic::{render_error, RenderOptions};
 pub use direct_non_recursive::parse as parse_ast_non_recursive;
+pub use direct_non_recursive::parse_bytes as parse_ast_non_recursive_bytes;
+pub use direct_non_recursive::parse_bytes_with_config as parse_ast_non_recursive_bytes_with_config;
+pub use direct_non_recursive::parse_with_config as parse_ast_non_recursive_with_config;
+pub use error::{Expected, ParseError, ParseErrorKind, TokenKind};
+pub use escape::unescape;
 pub use event::{ParseEvent, ParseEventKind};
-pub use event_listener::EventListener;
-pub use event_to_tree::event_to_tree;
+pub use event_listener::{EventListener, ListenerAction};
+pub use event_to_tree::{
+    event_to_spanned_tree, event_to_spanned_tree_bytes, event_to_spanned_tree_bytes_with_config,
+    event_to_spanned_tree_with_config, event_to_tree, event_to_tree_bytes,
+    event_to_tree_bytes_with_config, event_to_tree_with_config,
+};
+pub use event_writer::{EventWriteError, EventWriter};
+pub use field_extractor::{
+    extract_fields, extract_fields_from_events, FieldExtractor, FieldPath, FieldPathError,
+    FieldType, FieldValue, PathSegment,
+};
+pub use json_path::{
+    query_json_path, query_json_path_events, JsonPath, JsonPathError, JsonPathMatches,
+};
+pub use json_pointer::{
+    find_pointer, find_pointer_in_events, JsonPointer, JsonPointerError, JsonPointerListener,
+};
+pub use number::number_text;
 pub use push_to_events::PushToEvents;
 pub use recursive_descent::parse as parse_ast_recursive;
+pub use recursive_descent::parse_bytes as parse_ast_recursive_bytes;
+pub use recursive_descent::parse_bytes_with_config as parse_ast_recursive_bytes_with_config;
+pub use recursive_descent::parse_with_config as parse_ast_recursive_with_config;
 pub use simple_ast::Json;
-pub use timestamp_parser::{parse_timestamp, TimestampParserListener};
+pub use source_map::{Position, SourceMap};
+pub use spanned_ast::{Span, SpannedJson, SpannedKey, SpannedValue};
+pub use structural_index::StructuralIndex;
+pub use timestamp_parser::parse_timestamp;
 pub use token::Token;
-
-pub use lexer::{tokenize_iter, Lexer};
-pub use lexer_list::tokenize_list;
-pub use lexer_push::{tokenize_push, LexerEventListener, PushToTokens};
-
-pub use event_parser::parse_events_iter;
-pub use event_parser_2::parse_events_iter_using_lexer_iter;
+pub use writer::{write_json, write_json_with_config, WriterConfig};
+
+pub use lexer::{tokenize_iter, tokenize_iter_bytes, Lexer};
+pub use lexer_fast::{tokenize_fast, tokenize_fast_bytes, FastLexer};
+pub use lexer_list::{
+    tokenize_list, tokenize_list_bytes, tokenize_list_bytes_with_config, tokenize_list_with_config,
+};
+pub use lexer_push::{
+    tokenize_push, tokenize_push_bytes, tokenize_push_bytes_with_config, tokenize_push_with_config,
+    LexerEventListener, PushToTokens,
+};
+
+pub use event_parser::{
+    parse_events_iter, parse_events_iter_bytes, parse_events_iter_bytes_with_config,
+    parse_events_iter_indexed, parse_events_iter_indexed_with_config, parse_events_iter_recovering,
+    parse_events_iter_recovering_with_config, parse_events_iter_with_config,
+};
+pub use event_parser_2::{
+    parse_events_iter_using_lexer_iter, parse_events_iter_using_lexer_iter_with_config,
+};
+pub use event_parser_read::{
+    parse_events_read, parse_events_read_with_config, ReadError, ReadEvent, ReadEventKind,
+    ReadEventParser, DEFAULT_READ_BUFFER_SIZE,
+};
 pub use event_push_parser::parse as parse_events_push;
+pub use event_push_parser::parse_bytes as parse_events_push_bytes;
+pub use event_push_parser::parse_bytes_with_config as parse_events_push_bytes_with_config;
+pub use event_push_parser::parse_with_config as parse_events_push_with_config;
 pub use event_push_parser_2::parse as parse_events_push_using_lexer_push;
+pub use event_push_parser_2::parse_bytes as parse_events_push_using_lexer_push_bytes;
+pub use event_push_parser_2::parse_bytes_with_config as parse_events_push_using_lexer_push_bytes_with_config;
+pub use event_push_parser_2::parse_with_config as parse_events_push_using_lexer_push_with_config;
 pub use event_push_parser_3::parse as parse_events_push_using_lexer_iter;
+pub use event_push_parser_3::parse_bytes as parse_events_push_using_lexer_iter_bytes;
+pub use event_push_parser_3::parse_bytes_with_config as parse_events_push_using_lexer_iter_bytes_with_config;
+pub use event_push_parser_3::parse_with_config as parse_events_push_using_lexer_iter_with_config;
 pub use event_push_parser_3_non_recursive::parse as parse_events_push_using_lexer_iter_non_recursive;
+pub use event_push_parser_3_non_recursive::parse_bytes as parse_events_push_using_lexer_iter_non_recursive_bytes;
+pub use event_push_parser_3_non_recursive::parse_bytes_with_config as parse_events_push_using_lexer_iter_non_recursive_bytes_with_config;
+pub use event_push_parser_3_non_recursive::parse_with_config as parse_events_push_using_lexer_iter_non_recursive_with_config;
+pub use event_push_parser_chunked::ChunkedPushParser;
 
 #[doc(hidden)]
-pub use input_gen::gen_input;
-
-/// A parse error, common for both event and AST parsers.
-#[derive(Debug, PartialEq, Eq)]
-pub struct ParseError {
-    /// Byte offset of the parse error in the input.
-    pub byte_offset: usize,
-
-    /// The error message.
-    pub reason: &'static str,
-}
+pub use input_gen::{gen_input, gen_input_with_seed};
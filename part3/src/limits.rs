@@ -0,0 +1,469 @@
+use crate::utf8::floor_char_boundary;
+use crate::{
+    EventListener, ListenerAction, ParseError, ParseErrorKind, ParseEvent, ParseEventKind,
+    ParserConfig,
+};
+
+/// Checks the limits in a [ParserConfig] on a stream of events.
+#[derive(Debug, Clone)]
+pub(crate) struct LimitChecker {
+    config: ParserConfig,
+
+    /// Number of events checked so far.
+    num_events: usize,
+
+    /// The open arrays and objects, and the number of values in them. In objects both keys and
+    /// values are counted.
+    container_stack: Vec<(ContainerKind, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContainerKind {
+    Array,
+    Object,
+}
+
+impl LimitChecker {
+    pub(crate) fn new(config: ParserConfig) -> LimitChecker {
+        LimitChecker {
+            config,
+            num_events: 0,
+            container_stack: vec![],
+        }
+    }
+
+    /// Checks the size of the input. Can be called with the size of the whole input, or the size
+    /// of the input seen so far.
+    ///
+    /// The error is reported at [ParserConfig::max_input_size], moved back to the start of the
+    /// UTF-8 encoded character at that offset with `char_start`, so that the error can be mapped
+    /// to a position in a `&str` input. Parsers that don't have the input pass the offset as it is.
+    pub(crate) fn check_input_size(
+        &self,
+        input_size: usize,
+        char_start: impl FnOnce(usize) -> usize,
+    ) -> Result<(), ParseError> {
+        if input_size > self.config.max_input_size {
+            return Err(ParseError::new(
+                char_start(self.config.max_input_size),
+                ParseErrorKind::InputTooLarge,
+            ));
+        }
+        Ok(())
+    }
+
+    /// [LimitChecker::check_input_size] for the whole input.
+    pub(crate) fn check_input(&self, input: &[u8]) -> Result<(), ParseError> {
+        self.check_input_size(input.len(), |byte_offset| {
+            floor_char_boundary(input, byte_offset)
+        })
+    }
+
+    /// Checks the next event. Events should be checked in the order they are generated.
+    pub(crate) fn check(
+        &mut self,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> Result<(), ParseError> {
+        // String events are at the first byte after the opening double quote, errors are reported
+        // at the double quote.
+        let token_offset = match kind {
+            ParseEventKind::Str { .. } => byte_offset - 1,
+            _ => byte_offset,
+        };
+
+        self.num_events += 1;
+        if self.num_events > self.config.max_events {
+            return Err(ParseError::new(token_offset, ParseErrorKind::TooManyEvents));
+        }
+
+        match kind {
+            ParseEventKind::EndObject | ParseEventKind::EndArray => {
+                self.container_stack.pop();
+                return Ok(());
+            }
+
+            ParseEventKind::Comment { .. } => return Ok(()),
+
+            _ => {}
+        }
+
+        self.count_value(token_offset)?;
+
+        let container = match kind {
+            ParseEventKind::Str { size_in_bytes, .. } => {
+                if *size_in_bytes > self.config.max_string_size {
+                    return Err(ParseError::new(token_offset, ParseErrorKind::StringTooLong));
+                }
+                return Ok(());
+            }
+            ParseEventKind::StartArray => ContainerKind::Array,
+            ParseEventKind::StartObject => ContainerKind::Object,
+            _ => return Ok(()),
+        };
+
+        if self.container_stack.len() == self.config.max_depth {
+            return Err(ParseError::new(
+                token_offset,
+                ParseErrorKind::NestingTooDeep,
+            ));
+        }
+        self.container_stack.push((container, 0));
+
+        Ok(())
+    }
+
+    /// Checks a value at `byte_offset` that was skipped without generating events for it, see
+    /// [EventParser::skip_value][crate::event_parser::EventParser::skip_value]. The value is
+    /// counted in its container, the input in the value is not checked.
+    pub(crate) fn check_skipped_value(&mut self, byte_offset: usize) -> Result<(), ParseError> {
+        self.count_value(byte_offset)
+    }
+
+    /// Counts a value in the current container, checking the container size.
+    fn count_value(&mut self, token_offset: usize) -> Result<(), ParseError> {
+        if let Some((container, num_values)) = self.container_stack.last_mut() {
+            let num_elements = match container {
+                ContainerKind::Array => *num_values + 1,
+                // Keys are the values at even indices.
+                ContainerKind::Object if *num_values % 2 == 0 => *num_values / 2 + 1,
+                ContainerKind::Object => 0,
+            };
+            if num_elements > self.config.max_container_size {
+                return Err(ParseError::new(
+                    token_offset,
+                    ParseErrorKind::ContainerTooLarge,
+                ));
+            }
+            *num_values += 1;
+        }
+        Ok(())
+    }
+}
+
+/// An [EventListener] that checks the limits in a [ParserConfig] before passing the events to
+/// another listener. Used by the push parsers.
+///
+/// After an error, or after the listener stops or aborts parsing, the listener returns
+/// [ListenerAction::Stop] and ignores the rest of the events and errors. The error of a
+/// [ListenerAction::Abort] is kept for the parser to return, see [LimitListener::take_result].
+pub(crate) struct LimitListener<L: EventListener> {
+    listener: L,
+
+    /// `None` when the configuration doesn't have limits other than the nesting depth, which is
+    /// checked by the parsers.
+    checker: Option<LimitChecker>,
+
+    /// Whether an error was reported to the listener, or the listener stopped parsing.
+    failed: bool,
+
+    /// The error of the listener's [ListenerAction::Abort].
+    abort_error: Option<L::Error>,
+}
+
+impl<L: EventListener> LimitListener<L> {
+    pub(crate) fn new(listener: L, config: ParserConfig) -> LimitListener<L> {
+        LimitListener {
+            listener,
+            checker: if config.has_limits() {
+                Some(LimitChecker::new(config))
+            } else {
+                None
+            },
+            failed: false,
+            abort_error: None,
+        }
+    }
+
+    pub(crate) fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Returns the error of the listener's [ListenerAction::Abort], if it aborted parsing.
+    pub(crate) fn take_result(&mut self) -> Result<(), L::Error> {
+        match self.abort_error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn listener(&self) -> &L {
+        &self.listener
+    }
+
+    pub(crate) fn listener_mut(&mut self) -> &mut L {
+        &mut self.listener
+    }
+
+    pub(crate) fn into_listener(self) -> L {
+        self.listener
+    }
+
+    /// Checks the size of the input, see [LimitChecker::check_input_size]. Returns whether to keep
+    /// parsing.
+    pub(crate) fn check_input_size(
+        &mut self,
+        input_size: usize,
+        char_start: impl FnOnce(usize) -> usize,
+    ) -> bool {
+        if self.failed {
+            return false;
+        }
+        let checker = match &self.checker {
+            Some(checker) => checker,
+            None => return true,
+        };
+        match checker.check_input_size(input_size, char_start) {
+            Ok(()) => true,
+            Err(err) => {
+                self.handle_error(err);
+                false
+            }
+        }
+    }
+
+    /// [LimitListener::check_input_size] for the whole input.
+    pub(crate) fn check_input(&mut self, input: &[u8]) -> bool {
+        self.check_input_size(input.len(), |byte_offset| {
+            floor_char_boundary(input, byte_offset)
+        })
+    }
+
+    /// Checks the event, and calls `handle` to pass it to the listener if it's within the limits.
+    fn event(
+        &mut self,
+        byte_offset: usize,
+        kind: ParseEventKind,
+        handle: impl FnOnce(&mut L) -> ListenerAction<L::Error>,
+    ) -> ListenerAction<L::Error> {
+        if self.failed {
+            return ListenerAction::Stop;
+        }
+        if let Some(checker) = &mut self.checker {
+            if let Err(err) = checker.check(byte_offset, &kind) {
+                self.handle_error(err);
+                return ListenerAction::Stop;
+            }
+        }
+        match handle(&mut self.listener) {
+            ListenerAction::Continue => ListenerAction::Continue,
+            ListenerAction::SkipChildren => ListenerAction::SkipChildren,
+            ListenerAction::Stop => {
+                self.failed = true;
+                ListenerAction::Stop
+            }
+            ListenerAction::Abort(error) => {
+                self.failed = true;
+                self.abort_error = Some(error);
+                ListenerAction::Stop
+            }
+        }
+    }
+}
+
+impl<L: EventListener> EventListener for LimitListener<L> {
+    type Error = L::Error;
+
+    fn handle_start_object(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        self.event(byte_offset, ParseEventKind::StartObject, |l| {
+            l.handle_start_object(byte_offset)
+        })
+    }
+
+    fn handle_end_object(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        self.event(byte_offset, ParseEventKind::EndObject, |l| {
+            l.handle_end_object(byte_offset)
+        })
+    }
+
+    fn handle_start_array(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        self.event(byte_offset, ParseEventKind::StartArray, |l| {
+            l.handle_start_array(byte_offset)
+        })
+    }
+
+    fn handle_end_array(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        self.event(byte_offset, ParseEventKind::EndArray, |l| {
+            l.handle_end_array(byte_offset)
+        })
+    }
+
+    fn handle_int(&mut self, byte_offset: usize, i: u64) -> ListenerAction<L::Error> {
+        self.event(byte_offset, ParseEventKind::Int(i), |l| {
+            l.handle_int(byte_offset, i)
+        })
+    }
+
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64) -> ListenerAction<L::Error> {
+        self.event(byte_offset, ParseEventKind::NegInt(i), |l| {
+            l.handle_neg_int(byte_offset, i)
+        })
+    }
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64) -> ListenerAction<L::Error> {
+        self.event(byte_offset, ParseEventKind::Float(f), |l| {
+            l.handle_float(byte_offset, f)
+        })
+    }
+
+    fn handle_raw_number(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<L::Error> {
+        self.event(
+            byte_offset,
+            ParseEventKind::RawNumber { size_in_bytes },
+            |l| l.handle_raw_number(byte_offset, size_in_bytes),
+        )
+    }
+
+    fn handle_str(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+        has_escapes: bool,
+    ) -> ListenerAction<L::Error> {
+        self.event(
+            byte_offset,
+            ParseEventKind::Str {
+                size_in_bytes,
+                has_escapes,
+            },
+            |l| l.handle_str(byte_offset, size_in_bytes, has_escapes),
+        )
+    }
+
+    fn handle_bool(&mut self, byte_offset: usize, b: bool) -> ListenerAction<L::Error> {
+        self.event(byte_offset, ParseEventKind::Bool(b), |l| {
+            l.handle_bool(byte_offset, b)
+        })
+    }
+
+    fn handle_null(&mut self, byte_offset: usize) -> ListenerAction<L::Error> {
+        self.event(byte_offset, ParseEventKind::Null, |l| {
+            l.handle_null(byte_offset)
+        })
+    }
+
+    fn handle_comment(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<L::Error> {
+        self.event(
+            byte_offset,
+            ParseEventKind::Comment { size_in_bytes },
+            |l| l.handle_comment(byte_offset, size_in_bytes),
+        )
+    }
+
+    fn handle_error(&mut self, error: ParseError) {
+        if !self.failed {
+            self.failed = true;
+            self.listener.handle_error(error);
+        }
+    }
+}
+
+/// Checks the limits in a [ParserConfig] on the events of an iterator parser. Used by the iterator
+/// parsers.
+///
+/// After an error the parser is not called again, so the iterator ends after the error.
+#[derive(Debug, Clone)]
+pub(crate) struct IterLimits {
+    checker: LimitChecker,
+
+    /// The [ParseErrorKind::InputTooLarge] error, reported before the first event.
+    input_size_error: Option<ParseError>,
+
+    failed: bool,
+}
+
+impl IterLimits {
+    /// Returns `None` when the configuration doesn't have limits other than the nesting depth,
+    /// which is checked by the parsers.
+    ///
+    /// For parsers that don't have the input. Use [IterLimits::with_input] when the input is
+    /// available.
+    pub(crate) fn new(config: ParserConfig, input_size: usize) -> Option<IterLimits> {
+        if !config.has_limits() {
+            return None;
+        }
+        let checker = LimitChecker::new(config);
+        Some(IterLimits {
+            input_size_error: checker
+                .check_input_size(input_size, |byte_offset| byte_offset)
+                .err(),
+            checker,
+            failed: false,
+        })
+    }
+
+    /// Same as [IterLimits::new], but reports [ParseErrorKind::InputTooLarge] at a character
+    /// boundary in `input`, see [LimitChecker::check_input].
+    pub(crate) fn with_input(config: ParserConfig, input: &[u8]) -> Option<IterLimits> {
+        if !config.has_limits() {
+            return None;
+        }
+        let checker = LimitChecker::new(config);
+        Some(IterLimits {
+            input_size_error: checker.check_input(input).err(),
+            checker,
+            failed: false,
+        })
+    }
+
+    /// Gets the next event from the parser with `next`, and checks it.
+    pub(crate) fn next(
+        &mut self,
+        next: impl FnOnce() -> Option<Result<ParseEvent, ParseError>>,
+    ) -> Option<Result<ParseEvent, ParseError>> {
+        if self.failed {
+            return None;
+        }
+
+        if let Err(err) = self.check_started() {
+            return Some(Err(err));
+        }
+
+        let event = next();
+        if let Some(Ok(ParseEvent { byte_offset, kind })) = &event {
+            if let Err(err) = self.checker.check(*byte_offset, kind) {
+                self.failed = true;
+                return Some(Err(err));
+            }
+        }
+        event
+    }
+
+    /// Skips the next value with `skip`, which returns the byte offset of the skipped value, or
+    /// `None` if there wasn't a value to skip. See [LimitChecker::check_skipped_value].
+    pub(crate) fn skip(
+        &mut self,
+        skip: impl FnOnce() -> Result<Option<usize>, ParseError>,
+    ) -> Result<(), ParseError> {
+        if self.failed {
+            return Ok(());
+        }
+
+        self.check_started()?;
+
+        if let Some(byte_offset) = skip()? {
+            if let Err(err) = self.checker.check_skipped_value(byte_offset) {
+                self.failed = true;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reports the input size error before the first event.
+    fn check_started(&mut self) -> Result<(), ParseError> {
+        if let Some(err) = self.input_size_error.take() {
+            self.failed = true;
+            return Err(err);
+        }
+        Ok(())
+    }
+}
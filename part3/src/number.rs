@@ -0,0 +1,229 @@
+use crate::{IntOverflow, ParseError, ParseErrorKind};
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// A number scanned by [scan_number].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Number {
+    /// A non-negative integer.
+    Int(u64),
+
+    /// A negative integer.
+    NegInt(i64),
+
+    /// A number with a fraction or exponent, or `-0`.
+    Float(f64),
+
+    /// An integer that does not fit into `u64` or `i64`, when [IntOverflow::Raw] is used.
+    Raw { size_in_bytes: usize },
+}
+
+/// Scans a number starting at `byte_offset`, following the grammar in RFC 8259:
+///
+/// ```text
+/// number = [ minus ] int [ frac ] [ exp ]
+/// ```
+///
+/// Returns the number and byte offset of the end of the number.
+pub(crate) fn scan_number(
+    bytes: &[u8],
+    byte_offset: usize,
+    int_overflow: IntOverflow,
+) -> Result<(Number, usize), ParseError> {
+    let mut end = byte_offset;
+
+    let negative = bytes.get(end) == Some(&b'-');
+    if negative {
+        end += 1;
+    }
+
+    // Integer part: a single '0', or digits not starting with '0'. `None` means overflow.
+    let mut i: Option<u64> = Some(0);
+    match bytes.get(end) {
+        Some(b'0') => end += 1,
+        Some(b'1'..=b'9') => {
+            while let Some(digit @ b'0'..=b'9') = bytes.get(end).copied() {
+                end += 1;
+                i = i
+                    .and_then(|i| i.checked_mul(10))
+                    .and_then(|i| i.checked_add(u64::from(digit - b'0')));
+            }
+        }
+        _ => return Err(invalid_number(end)),
+    }
+
+    let mut is_float = false;
+
+    // Fraction.
+    if bytes.get(end) == Some(&b'.') {
+        end += 1;
+        end = skip_digits(bytes, end)?;
+        is_float = true;
+    }
+
+    // Exponent.
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        end += 1;
+        if matches!(bytes.get(end), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        end = skip_digits(bytes, end)?;
+        is_float = true;
+    }
+
+    // The grammar accepted by this function is a subset of what `f64::from_str` accepts, and it's
+    // ASCII.
+    let float = || {
+        let text = std::str::from_utf8(&bytes[byte_offset..end]).unwrap();
+        Number::Float(text.parse::<f64>().unwrap())
+    };
+
+    let number = if is_float {
+        float()
+    } else {
+        match (negative, i) {
+            (false, Some(i)) => Number::Int(i),
+
+            // `i64` can't represent `-0`.
+            (true, Some(0)) => Number::Float(-0.0),
+
+            (true, Some(i)) if i <= i64::MIN.unsigned_abs() => {
+                Number::NegInt(0i64.wrapping_sub_unsigned(i))
+            }
+
+            _ => match int_overflow {
+                IntOverflow::Error => {
+                    return Err(ParseError::new(
+                        byte_offset,
+                        ParseErrorKind::IntegerOverflow,
+                    ))
+                }
+                IntOverflow::Float => float(),
+                IntOverflow::Raw => Number::Raw {
+                    size_in_bytes: end - byte_offset,
+                },
+            },
+        }
+    };
+
+    Ok((number, end))
+}
+
+/// Skips one or more digits starting at `byte_offset`, returns the byte offset after the digits.
+fn skip_digits(bytes: &[u8], mut byte_offset: usize) -> Result<usize, ParseError> {
+    if !matches!(bytes.get(byte_offset), Some(b'0'..=b'9')) {
+        return Err(invalid_number(byte_offset));
+    }
+    while let Some(b'0'..=b'9') = bytes.get(byte_offset) {
+        byte_offset += 1;
+    }
+    Ok(byte_offset)
+}
+
+fn invalid_number(byte_offset: usize) -> ParseError {
+    ParseError::new(byte_offset, ParseErrorKind::InvalidNumber)
+}
+
+/// Advances a character iterator until `byte_offset`. Used after [scan_number] by the parsers and
+/// lexers that iterate characters.
+pub(crate) fn skip_until(iter: &mut Peekable<CharIndices>, byte_offset: usize) {
+    while let Some((next_byte_offset, _)) = iter.peek() {
+        if *next_byte_offset >= byte_offset {
+            break;
+        }
+        iter.next();
+    }
+}
+
+/// Returns the source text of the number at `byte_offset`. `byte_offset` should be the location
+/// of a number token or event.
+///
+/// This can be used to get the exact representation of a number in the input, for example to
+/// parse it as a type other than `u64`, `i64`, and `f64`.
+pub fn number_text(input: &str, byte_offset: usize) -> Option<&str> {
+    scan_number(input.as_bytes(), byte_offset, IntOverflow::Raw)
+        .ok()
+        .map(|(_, end)| &input[byte_offset..end])
+}
+
+#[test]
+fn scan_number_test() {
+    fn scan(input: &str) -> Result<(Number, usize), usize> {
+        scan_number(input.as_bytes(), 0, IntOverflow::Error).map_err(|err| err.byte_offset)
+    }
+
+    assert_eq!(scan("0"), Ok((Number::Int(0), 1)));
+    assert_eq!(scan("123,"), Ok((Number::Int(123), 3)));
+    assert_eq!(scan("-123]"), Ok((Number::NegInt(-123), 4)));
+    assert_eq!(scan("-0"), Ok((Number::Float(-0.0), 2)));
+    assert_eq!(scan("2.25"), Ok((Number::Float(2.25), 4)));
+    assert_eq!(scan("1e9"), Ok((Number::Float(1e9), 3)));
+    assert_eq!(scan("-2.5E-3 "), Ok((Number::Float(-2.5e-3), 7)));
+    assert_eq!(scan("1e+2"), Ok((Number::Float(100.0), 4)));
+    assert_eq!(
+        scan("18446744073709551615"),
+        Ok((Number::Int(u64::MAX), 20))
+    );
+    assert_eq!(
+        scan("-9223372036854775808"),
+        Ok((Number::NegInt(i64::MIN), 20))
+    );
+
+    // Leading zeros: scan "0", the rest is a separate token.
+    assert_eq!(scan("01"), Ok((Number::Int(0), 1)));
+
+    assert_eq!(scan("-"), Err(1));
+    assert_eq!(scan("-a"), Err(1));
+    assert_eq!(scan("1."), Err(2));
+    assert_eq!(scan("1.e3"), Err(2));
+    assert_eq!(scan("1e"), Err(2));
+    assert_eq!(scan("1e+"), Err(3));
+    assert_eq!(scan(".5"), Err(0));
+}
+
+#[test]
+fn scan_number_overflow_test() {
+    let overflow = |input: &str| {
+        (
+            scan_number(input.as_bytes(), 1, IntOverflow::Error),
+            scan_number(input.as_bytes(), 1, IntOverflow::Float),
+            scan_number(input.as_bytes(), 1, IntOverflow::Raw),
+        )
+    };
+
+    for input in [
+        "[18446744073709551616]",
+        "[99999999999999999999]",
+        "[-9223372036854775809]",
+    ] {
+        let size_in_bytes = input.len() - 2;
+        let (error, float, raw) = overflow(input);
+        assert_eq!(
+            error,
+            Err(ParseError::new(1, ParseErrorKind::IntegerOverflow))
+        );
+        assert_eq!(
+            float,
+            Ok((
+                Number::Float(input[1..input.len() - 1].parse().unwrap()),
+                input.len() - 1
+            ))
+        );
+        assert_eq!(raw, Ok((Number::Raw { size_in_bytes }, input.len() - 1)));
+    }
+
+    // Overflowing integer part is fine when the number is a float.
+    assert_eq!(
+        scan_number(b"99999999999999999999.5", 0, IntOverflow::Error),
+        Ok((Number::Float(99999999999999999999.5), 22))
+    );
+}
+
+#[test]
+fn number_text_test() {
+    assert_eq!(number_text("[1.50, 2]", 1), Some("1.50"));
+    assert_eq!(number_text("[1.50, 2]", 7), Some("2"));
+    assert_eq!(number_text("-1e0}", 0), Some("-1e0"));
+    assert_eq!(number_text("true", 0), None);
+}
@@ -0,0 +1,86 @@
+use crate::{DuplicateKeys, ParseError, ParseErrorKind};
+
+use std::collections::HashMap;
+
+/// Members of an object being built, with a [DuplicateKeys] policy applied.
+///
+/// Keys are checked with [ObjectMembers::check_key] when parsed, before the value, so that a
+/// duplicate key error is reported before any errors in the value. The result is then passed to
+/// [ObjectMembers::push] with the value.
+#[derive(Debug)]
+pub(crate) struct ObjectMembers<K, V> {
+    policy: DuplicateKeys,
+
+    members: Vec<(K, V)>,
+
+    /// Indices of the members in `members`, by key. Empty with [DuplicateKeys::KeepAll].
+    indices: HashMap<String, usize>,
+}
+
+/// Where to add a member, returned by [ObjectMembers::check_key].
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum MemberSlot {
+    /// The key is new in the object, or duplicate keys are kept.
+    New,
+
+    /// The key is a duplicate of the key of the member at the index.
+    Duplicate(usize),
+}
+
+impl<K: AsRef<str>, V> ObjectMembers<K, V> {
+    pub(crate) fn new(policy: DuplicateKeys) -> ObjectMembers<K, V> {
+        ObjectMembers::with_capacity(policy, 0)
+    }
+
+    pub(crate) fn with_capacity(policy: DuplicateKeys, capacity: usize) -> ObjectMembers<K, V> {
+        ObjectMembers {
+            policy,
+            members: Vec::with_capacity(capacity),
+            indices: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Checks the key of the next member. `quote_offset` is the byte offset of the key's opening
+    /// double quote, for errors.
+    pub(crate) fn check_key(
+        &self,
+        key: &str,
+        quote_offset: usize,
+    ) -> Result<MemberSlot, ParseError> {
+        if self.policy == DuplicateKeys::KeepAll {
+            return Ok(MemberSlot::New);
+        }
+        match self.indices.get(key) {
+            None => Ok(MemberSlot::New),
+            Some(_) if self.policy == DuplicateKeys::Error => {
+                Err(ParseError::new(quote_offset, ParseErrorKind::DuplicateKey))
+            }
+            Some(idx) => Ok(MemberSlot::Duplicate(*idx)),
+        }
+    }
+
+    /// Adds a member, with the slot returned by [ObjectMembers::check_key] for the key.
+    pub(crate) fn push(&mut self, key: K, value: V, slot: MemberSlot) {
+        match slot {
+            MemberSlot::New => {
+                if self.policy != DuplicateKeys::KeepAll {
+                    self.indices
+                        .insert(key.as_ref().to_owned(), self.members.len());
+                }
+                self.members.push((key, value));
+            }
+            MemberSlot::Duplicate(idx) => match self.policy {
+                DuplicateKeys::KeepLast => self.members[idx] = (key, value),
+                DuplicateKeys::KeepAll | DuplicateKeys::KeepFirst | DuplicateKeys::Error => {}
+            },
+        }
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<(K, V)> {
+        self.members
+    }
+}
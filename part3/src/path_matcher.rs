@@ -0,0 +1,310 @@
+use crate::event_listener::send_event;
+use crate::event_to_tree::decode_string;
+use crate::{AstBuilderListener, Json, ParseError, ParseEventKind};
+
+use std::borrow::Cow;
+
+/// A step from an array or object to one of its elements or members.
+pub(crate) enum Step<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// The paths that a [PathMatcher] matches, and what to do with the values at the paths.
+///
+/// Each array or object that the matcher tracks has a state, which tells which paths may go
+/// through it. [PathQuery::step] matches one segment of the paths, to get the states of the
+/// elements and members.
+pub(crate) trait PathQuery {
+    type State;
+
+    /// State of the top-level value.
+    fn top_level(&self) -> Self::State;
+
+    /// Returns the state of the element or member at `step` of an array or object with the state
+    /// `state`. `depth` is the number of arrays and objects the element or member is in.
+    fn step(&self, state: &Self::State, depth: usize, step: &Step) -> Self::State;
+
+    /// Whether [PathQuery::step] matches the keys of an object with the state `state`, so keys
+    /// need to be decoded. When not, keys are passed to [PathQuery::step] as empty strings.
+    fn needs_keys(&self, _state: &Self::State) -> bool {
+        true
+    }
+
+    /// Handles the first event of a value at a tracked location, with the value's state. `depth`
+    /// is the number of arrays and objects the value is in.
+    ///
+    /// When the event starts an array or object, returns whether its contents may have values at
+    /// the paths, so the array or object is tracked with `state`. Otherwise it's skipped.
+    fn value(
+        &mut self,
+        state: &mut Self::State,
+        depth: usize,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> Result<bool, ParseError>;
+}
+
+/// Matches a stream of events with the paths of a [PathQuery].
+///
+/// Only the arrays and objects that may contain values at the paths are tracked. Other values are
+/// skipped without decoding.
+pub(crate) struct PathMatcher<'a, S> {
+    input: &'a str,
+
+    /// Whether the first event of the top-level value was seen.
+    started: bool,
+
+    /// The arrays and objects that the next event is in, when they may contain values at the paths.
+    container_stack: Vec<Frame<S>>,
+
+    /// Number of arrays and objects the next event is in, in a value that is skipped.
+    skip_depth: usize,
+}
+
+/// An array or object that may contain values at the paths.
+struct Frame<S> {
+    state: S,
+
+    position: Position<S>,
+}
+
+enum Position<S> {
+    Array {
+        next_index: usize,
+    },
+
+    /// The next event is a key or the end of the object.
+    ObjectKey,
+
+    /// The next event is a value, with the value's state.
+    ObjectValue {
+        state: S,
+    },
+}
+
+impl<'a, S> PathMatcher<'a, S> {
+    pub(crate) fn new(input: &'a str) -> PathMatcher<'a, S> {
+        PathMatcher {
+            input,
+            started: false,
+            container_stack: vec![],
+            skip_depth: 0,
+        }
+    }
+
+    /// Number of tracked arrays and objects that the next event is in.
+    pub(crate) fn depth(&self) -> usize {
+        self.container_stack.len()
+    }
+
+    /// Whether the top-level value started, and the arrays and objects that may contain values at
+    /// the paths ended, so the rest of the input can't contain values at the paths.
+    pub(crate) fn finished(&self) -> bool {
+        self.started && self.container_stack.is_empty()
+    }
+
+    /// After an event that starts an array or object, returns whether the array or object is not
+    /// tracked, so its contents can be skipped.
+    pub(crate) fn skips_children(&self) -> bool {
+        self.skip_depth == 1
+    }
+
+    /// Updates the tracked arrays and objects with the event, and passes the first event of each
+    /// value at a tracked location to [PathQuery::value].
+    pub(crate) fn event<Q: PathQuery<State = S>>(
+        &mut self,
+        query: &mut Q,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+    ) -> Result<(), ParseError> {
+        if let ParseEventKind::Comment { .. } = kind {
+            return Ok(());
+        }
+
+        if self.skip_depth != 0 {
+            match kind {
+                ParseEventKind::StartArray | ParseEventKind::StartObject => self.skip_depth += 1,
+                ParseEventKind::EndArray | ParseEventKind::EndObject => self.skip_depth -= 1,
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        let depth = self.container_stack.len();
+
+        let mut state = match self.container_stack.last_mut() {
+            None => {
+                self.started = true;
+                query.top_level()
+            }
+
+            Some(frame) => match &mut frame.position {
+                Position::Array { next_index } => {
+                    if let ParseEventKind::EndArray = kind {
+                        self.container_stack.pop();
+                        return Ok(());
+                    }
+                    let index = *next_index;
+                    *next_index += 1;
+                    query.step(&frame.state, depth, &Step::Index(index))
+                }
+
+                Position::ObjectKey => match kind {
+                    ParseEventKind::Str {
+                        size_in_bytes,
+                        has_escapes,
+                    } => {
+                        let key = if query.needs_keys(&frame.state) {
+                            decode_key(self.input, byte_offset, *size_in_bytes, *has_escapes)?
+                        } else {
+                            Cow::Borrowed("")
+                        };
+                        let state = query.step(&frame.state, depth, &Step::Key(&key));
+                        frame.position = Position::ObjectValue { state };
+                        return Ok(());
+                    }
+                    // Parsers only generate keys and the end of the object in this position.
+                    _ => {
+                        self.container_stack.pop();
+                        return Ok(());
+                    }
+                },
+
+                Position::ObjectValue { .. } => {
+                    match std::mem::replace(&mut frame.position, Position::ObjectKey) {
+                        Position::ObjectValue { state } => state,
+                        _ => unreachable!(),
+                    }
+                }
+            },
+        };
+
+        let tracked = query.value(&mut state, depth, byte_offset, kind)?;
+
+        let position = match kind {
+            ParseEventKind::StartArray => Position::Array { next_index: 0 },
+            ParseEventKind::StartObject => Position::ObjectKey,
+            _ => return Ok(()),
+        };
+        if tracked {
+            self.container_stack.push(Frame { state, position });
+        } else {
+            self.skip_depth = 1;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the state of the next value, when it's an element or member of a tracked array or
+    /// object. Returns `None` before keys and the ends of arrays and objects.
+    pub(crate) fn next_value_state<Q: PathQuery<State = S>>(&self, query: &Q) -> Option<Cow<'_, S>>
+    where
+        S: Clone,
+    {
+        if self.skip_depth != 0 {
+            return None;
+        }
+        let frame = self.container_stack.last()?;
+        match &frame.position {
+            Position::Array { next_index } => Some(Cow::Owned(query.step(
+                &frame.state,
+                self.container_stack.len(),
+                &Step::Index(*next_index),
+            ))),
+            Position::ObjectKey => None,
+            Position::ObjectValue { state } => Some(Cow::Borrowed(state)),
+        }
+    }
+
+    /// Updates the innermost tracked array or object after skipping a value without events. See
+    /// [PathMatcher::next_value_state].
+    pub(crate) fn value_skipped(&mut self) {
+        if let Some(frame) = self.container_stack.last_mut() {
+            match frame.position {
+                Position::Array { ref mut next_index } => *next_index += 1,
+                Position::ObjectValue { .. } => frame.position = Position::ObjectKey,
+                Position::ObjectKey => {}
+            }
+        }
+    }
+}
+
+fn decode_key(
+    input: &str,
+    byte_offset: usize,
+    size_in_bytes: usize,
+    has_escapes: bool,
+) -> Result<Cow<'_, str>, ParseError> {
+    if has_escapes {
+        Ok(Cow::Owned(decode_string(
+            input,
+            byte_offset,
+            size_in_bytes,
+            has_escapes,
+        )?))
+    } else {
+        Ok(Cow::Borrowed(
+            &input[byte_offset..byte_offset + size_in_bytes],
+        ))
+    }
+}
+
+/// [AstBuilderListener]s building the values at the paths, with keys that identify the values.
+pub(crate) struct Builders<'a, K> {
+    input: &'a str,
+
+    builders: Vec<(K, AstBuilderListener<'a>)>,
+}
+
+impl<'a, K> Builders<'a, K> {
+    pub(crate) fn new(input: &'a str) -> Builders<'a, K> {
+        Builders {
+            input,
+            builders: vec![],
+        }
+    }
+
+    /// Starts building a value. The builder should get the first event of the value next.
+    pub(crate) fn push(&mut self, key: K) {
+        self.builders
+            .push((key, AstBuilderListener::new(self.input)));
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.builders.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.builders.is_empty()
+    }
+
+    /// Passes the event to the builders starting at index `first`, and calls `built` with the
+    /// values that are complete.
+    ///
+    /// Values start at events passed to [PathMatcher::event], so the builders are fed before the
+    /// event is matched, and the builders started by the event are fed after.
+    pub(crate) fn feed(
+        &mut self,
+        first: usize,
+        byte_offset: usize,
+        kind: &ParseEventKind,
+        mut built: impl FnMut(K, Json),
+    ) -> Result<(), ParseError> {
+        let mut i = first;
+        while i < self.builders.len() {
+            let builder = &mut self.builders[i].1;
+            if send_event(builder, byte_offset, kind).keep_parsing() && !builder.has_ast() {
+                i += 1;
+                continue;
+            }
+            let (key, builder) = self.builders.remove(i);
+            match builder.into_ast() {
+                (_, Some(error)) => return Err(error),
+                (Some(json), None) => built(key, json),
+                (None, None) => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{EventListener, ParseError, ParseEvent, ParseEventKind};
+use crate::{EventListener, ListenerAction, ParseError, ParseEvent, ParseEventKind};
+
+use std::convert::Infallible;
 
 /// An [EventListener] that collects parse events.
 pub struct PushToEvents {
@@ -7,62 +9,100 @@ pub struct PushToEvents {
 }
 
 impl EventListener for PushToEvents {
-    fn handle_start_object(&mut self, byte_offset: usize) -> bool {
+    type Error = Infallible;
+
+    fn handle_start_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
         self.events
             .push(ParseEvent::new(byte_offset, ParseEventKind::StartObject));
-        true
+        ListenerAction::Continue
     }
 
-    fn handle_end_object(&mut self, byte_offset: usize) -> bool {
+    fn handle_end_object(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
         self.events
             .push(ParseEvent::new(byte_offset, ParseEventKind::EndObject));
-        true
+        ListenerAction::Continue
     }
 
-    fn handle_start_array(&mut self, byte_offset: usize) -> bool {
+    fn handle_start_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
         self.events
             .push(ParseEvent::new(byte_offset, ParseEventKind::StartArray));
-        true
+        ListenerAction::Continue
     }
 
-    fn handle_end_array(&mut self, byte_offset: usize) -> bool {
+    fn handle_end_array(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
         self.events
             .push(ParseEvent::new(byte_offset, ParseEventKind::EndArray));
-        true
+        ListenerAction::Continue
     }
 
-    fn handle_int(&mut self, byte_offset: usize, i: u64) -> bool {
+    fn handle_int(&mut self, byte_offset: usize, i: u64) -> ListenerAction<Infallible> {
         self.events
             .push(ParseEvent::new(byte_offset, ParseEventKind::Int(i)));
-        true
+        ListenerAction::Continue
+    }
+
+    fn handle_neg_int(&mut self, byte_offset: usize, i: i64) -> ListenerAction<Infallible> {
+        self.events
+            .push(ParseEvent::new(byte_offset, ParseEventKind::NegInt(i)));
+        ListenerAction::Continue
+    }
+
+    fn handle_float(&mut self, byte_offset: usize, f: f64) -> ListenerAction<Infallible> {
+        self.events
+            .push(ParseEvent::new(byte_offset, ParseEventKind::Float(f)));
+        ListenerAction::Continue
     }
 
-    fn handle_str(&mut self, byte_offset: usize, size_in_bytes: usize) -> bool {
+    fn handle_raw_number(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
         self.events.push(ParseEvent::new(
             byte_offset,
-            ParseEventKind::Str { size_in_bytes },
+            ParseEventKind::RawNumber { size_in_bytes },
         ));
-        true
+        ListenerAction::Continue
     }
 
-    fn handle_bool(&mut self, byte_offset: usize, b: bool) -> bool {
+    fn handle_str(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+        has_escapes: bool,
+    ) -> ListenerAction<Infallible> {
+        self.events.push(ParseEvent::new(
+            byte_offset,
+            ParseEventKind::Str {
+                size_in_bytes,
+                has_escapes,
+            },
+        ));
+        ListenerAction::Continue
+    }
+
+    fn handle_bool(&mut self, byte_offset: usize, b: bool) -> ListenerAction<Infallible> {
         self.events
             .push(ParseEvent::new(byte_offset, ParseEventKind::Bool(b)));
-        true
+        ListenerAction::Continue
     }
 
-    fn handle_null(&mut self, byte_offset: usize) -> bool {
+    fn handle_null(&mut self, byte_offset: usize) -> ListenerAction<Infallible> {
         self.events
             .push(ParseEvent::new(byte_offset, ParseEventKind::Null));
-        true
+        ListenerAction::Continue
     }
 
-    fn handle_comment(&mut self, byte_offset: usize, size_in_bytes: usize) -> bool {
+    fn handle_comment(
+        &mut self,
+        byte_offset: usize,
+        size_in_bytes: usize,
+    ) -> ListenerAction<Infallible> {
         self.events.push(ParseEvent::new(
             byte_offset,
             ParseEventKind::Comment { size_in_bytes },
         ));
-        true
+        ListenerAction::Continue
     }
 
     fn handle_error(&mut self, error: crate::ParseError) {
@@ -88,6 +128,11 @@ impl PushToEvents {
     pub fn into_events(self) -> (Vec<ParseEvent>, Option<ParseError>) {
         (self.events, self.error)
     }
+
+    /// Takes the events collected so far, and the error if there was one.
+    pub(crate) fn take_events(&mut self) -> (Vec<ParseEvent>, Option<ParseError>) {
+        (std::mem::take(&mut self.events), self.error.take())
+    }
 }
 
 #[test]
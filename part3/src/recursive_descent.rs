@@ -1,193 +1,266 @@
-use crate::{Json, ParseError};
+use crate::escape::{scan_string, unescape};
+use crate::lexer::{invalid_token, scan_string_error};
+use crate::number::{scan_number, skip_until, Number};
+use crate::object_members::{MemberSlot, ObjectMembers};
+use crate::{
+    AstBuilderListener, Expected, IntOverflow, Json, ParseError, ParseErrorKind, ParserConfig,
+    TokenKind,
+};
 
 use std::iter::Peekable;
 use std::str::CharIndices;
 
 /// Parses input directly to [Json].
 pub fn parse(input: &str) -> Result<Json, ParseError> {
+    parse_with_config(input, ParserConfig::default())
+}
+
+/// Same as [parse], but with the given configuration.
+pub fn parse_with_config(input: &str, config: ParserConfig) -> Result<Json, ParseError> {
     let mut iter = input.char_indices().peekable();
-    let (_, json) = parse_single(&mut iter, input)?;
+    let json = parse_single(&mut iter, input, &config, 0, Expected::Value)?;
     skip_trivia(&mut iter)?;
     if let Some((byte_offset, _)) = iter.next() {
         // We should return the parsed object with this error, but it's OK for the purposes of this
         // post.
-        return Err(ParseError {
+        return Err(ParseError::new(
             byte_offset,
-            reason: "trailing characters after paring",
-        });
+            ParseErrorKind::TrailingCharacters,
+        ));
     }
     Ok(json)
 }
 
+/// Same as [parse], but the input does not have to be valid UTF-8. UTF-8 is validated in strings
+/// and comments.
+pub fn parse_bytes(input: &[u8]) -> Result<Json, ParseError> {
+    parse_bytes_with_config(input, ParserConfig::default())
+}
+
+/// Same as [parse_bytes], but with the given configuration.
+///
+/// This parser scans `char`s, so byte input is parsed with the recursive push parser on the
+/// UTF-8-validating [Lexer][crate::Lexer], building the [Json] with
+/// [AstBuilderListener][crate::AstBuilderListener]. Unlike [parse_with_config], this also checks
+/// the resource limits in the configuration.
+pub fn parse_bytes_with_config(input: &[u8], config: ParserConfig) -> Result<Json, ParseError> {
+    let mut listener = AstBuilderListener::from_bytes_with_config(input, config);
+    let Ok(()) = crate::event_push_parser_3::parse_bytes_with_config(input, &mut listener, config);
+    match listener.into_ast() {
+        (_, Some(err)) => Err(err),
+        (Some(json), None) => Ok(json),
+        // The parser reports an error when it doesn't parse a complete value.
+        (None, None) => unreachable!(),
+    }
+}
+
+/// Parses a value. `depth` is the number of arrays and objects the value is in. `expected` is used
+/// in errors when the next token is not a value.
+///
+/// The functions called recursively (this, [parse_array], and [parse_object]) leave scanning the
+/// tokens to non-recursive functions, to keep their stack frames small. Debug builds don't reuse
+/// stack slots of temporaries, and a frame with many `?` and error returns can take kilobytes,
+/// which limits the nesting depth that can be parsed without overflowing the stack.
 fn parse_single(
     iter: &mut Peekable<CharIndices>,
     input: &str,
-) -> Result<(usize, Json), ParseError> {
+    config: &ParserConfig,
+    depth: usize,
+    expected: Expected,
+) -> Result<Json, ParseError> {
+    let (byte_offset, char) = parse_value_start(iter, input, config, depth, expected)?;
+    match char {
+        '[' => parse_array(iter, input, config, depth),
+        '{' => parse_object(iter, input, config, depth),
+        _ => parse_scalar(iter, input, config, byte_offset, char, expected),
+    }
+}
+
+/// Consumes the first character of a value, checking the nesting depth when it's an array or
+/// object.
+fn parse_value_start(
+    iter: &mut Peekable<CharIndices>,
+    input: &str,
+    config: &ParserConfig,
+    depth: usize,
+    expected: Expected,
+) -> Result<(usize, char), ParseError> {
     skip_trivia(iter)?;
 
     let (byte_offset, char) = match iter.next() {
         Some(next) => next,
-        None => {
-            return Err(ParseError {
-                byte_offset: input.len(),
-                reason: "unexpected end of input",
-            })
-        }
+        None => return Err(ParseError::unexpected_end_of_input(input.len(), expected)),
     };
 
-    if char == '[' {
-        let mut array: Vec<Json> = Vec::with_capacity(10);
-        loop {
-            skip_trivia(iter)?;
-            match iter.peek().copied() {
-                Some((_, ']')) => {
-                    // Consume ']'
-                    iter.next();
-                    return Ok((byte_offset, Json::Array(array)));
-                }
+    if (char == '[' || char == '{') && depth == config.max_depth {
+        return Err(ParseError::new(byte_offset, ParseErrorKind::NestingTooDeep));
+    }
 
-                Some((comma_byte_offset, ',')) => {
-                    if array.is_empty() {
-                        return Err(ParseError {
-                            byte_offset: comma_byte_offset,
-                            reason: "unexpected character while parsing array",
-                        });
-                    }
-
-                    // Consume ','
-                    iter.next();
-                    array.push(parse_single(iter, input)?.1);
-                }
+    Ok((byte_offset, char))
+}
 
-                Some((byte_offset, _)) => {
-                    if !array.is_empty() {
-                        // Need to see a ',' before the next element.
-                        return Err(ParseError {
-                            byte_offset,
-                            reason: "unexpected character while parsing array",
-                        });
-                    }
+/// Parses the rest of an array, after the `[`.
+fn parse_array(
+    iter: &mut Peekable<CharIndices>,
+    input: &str,
+    config: &ParserConfig,
+    depth: usize,
+) -> Result<Json, ParseError> {
+    let mut array: Vec<Json> = Vec::with_capacity(10);
+    while let Some(expected) = parse_array_separator(iter, input, array.is_empty())? {
+        array.push(parse_single(iter, input, config, depth + 1, expected)?);
+    }
+    Ok(Json::Array(array))
+}
 
-                    array.push(parse_single(iter, input)?.1);
-                }
+/// Parses the tokens before an array element: the `,` after the previous element, or the `]` at
+/// the end of the array. Returns `None` after the `]`, otherwise what to use in errors when the
+/// element is missing.
+fn parse_array_separator(
+    iter: &mut Peekable<CharIndices>,
+    input: &str,
+    is_empty: bool,
+) -> Result<Option<Expected>, ParseError> {
+    skip_trivia(iter)?;
+    let expected = if is_empty {
+        Expected::ValueOrEndArray
+    } else {
+        Expected::CommaOrEndArray
+    };
+    match iter.peek().copied() {
+        Some((_, ']')) => {
+            // Consume ']'
+            iter.next();
+            Ok(None)
+        }
 
-                None => {
-                    return Err(ParseError {
-                        byte_offset: input.len(),
-                        reason: "end of input while parsing array",
-                    })
-                }
+        Some((comma_byte_offset, ',')) => {
+            if is_empty {
+                return Err(ParseError::unexpected_token(
+                    comma_byte_offset,
+                    expected,
+                    TokenKind::Comma,
+                ));
             }
-        }
-    }
 
-    if char == '{' {
-        let mut object: Vec<(String, Json)> = Vec::with_capacity(10);
+            // Consume ','
+            iter.next();
+            Ok(Some(Expected::Value))
+        }
 
-        enum State {
-            Done,
-            ExpectKey,
-            ExpectColon { key: String },
-            ExpectValue { key: String },
+        Some((byte_offset, char)) => {
+            if !is_empty {
+                // Need to see a ',' before the next element.
+                return Err(ParseError::unexpected_char(byte_offset, expected, char));
+            }
+            Ok(Some(expected))
         }
 
-        let mut state = State::Done;
+        None => Err(ParseError::unexpected_end_of_input(input.len(), expected)),
+    }
+}
+
+/// Parses the rest of an object, after the `{`.
+fn parse_object(
+    iter: &mut Peekable<CharIndices>,
+    input: &str,
+    config: &ParserConfig,
+    depth: usize,
+) -> Result<Json, ParseError> {
+    let mut object: ObjectMembers<String, Json> =
+        ObjectMembers::with_capacity(config.duplicate_keys, 10);
+    while let Some((key, slot)) = parse_object_key(iter, input, &mut object)? {
+        let value = parse_single(iter, input, config, depth + 1, Expected::Value)?;
+        object.push(key, value, slot);
+    }
+    Ok(Json::Object(object.into_vec()))
+}
 
-        loop {
+/// Parses the tokens before an object member's value: the `,` after the previous member, the key,
+/// and the `:`. Returns `None` after the `}` at the end of the object.
+fn parse_object_key(
+    iter: &mut Peekable<CharIndices>,
+    input: &str,
+    object: &mut ObjectMembers<String, Json>,
+) -> Result<Option<(String, MemberSlot)>, ParseError> {
+    skip_trivia(iter)?;
+    let expected = if object.is_empty() {
+        Expected::KeyOrEndObject
+    } else {
+        Expected::CommaOrEndObject
+    };
+    let key_byte_offset = match iter.next() {
+        Some((_, ',')) if !object.is_empty() => {
             skip_trivia(iter)?;
-            match std::mem::replace(&mut state, State::Done) {
-                State::Done => {
-                    match iter.peek().copied() {
-                        Some((byte_offset, ',')) => {
-                            if object.is_empty() {
-                                return Err(ParseError {
-                                    byte_offset,
-                                    reason: "unexpected comma while parsing object",
-                                });
-                            }
-                            iter.next(); // consume ','
-                            state = State::ExpectKey;
-                        }
-
-                        Some((_, '}')) => {
-                            iter.next(); // consume '}'
-                            return Ok((byte_offset, Json::Object(object)));
-                        }
-
-                        Some((_, '"')) => {
-                            let key = parse_single(iter, input)?.1.into_string();
-                            state = State::ExpectColon { key };
-                        }
-
-                        Some((byte_offset, _)) => {
-                            return Err(ParseError {
-                                byte_offset,
-                                reason: "unexpected char while parsing object",
-                            })
-                        }
-
-                        None => {
-                            return Err(ParseError {
-                                byte_offset: input.len(),
-                                reason: "unexpected end of input while parsing object",
-                            })
-                        }
-                    }
+            match iter.next() {
+                Some((byte_offset, '"')) => byte_offset,
+
+                Some((byte_offset, char)) => {
+                    return Err(ParseError::unexpected_char(
+                        byte_offset,
+                        Expected::Key,
+                        char,
+                    ))
                 }
 
-                State::ExpectKey => match parse_single(iter, input)? {
-                    (_, Json::String(key)) => {
-                        state = State::ExpectColon { key };
-                    }
-
-                    (byte_offset, _) => {
-                        return Err(ParseError {
-                            byte_offset,
-                            reason: "unexpected value while parsing object key",
-                        })
-                    }
-                },
-
-                State::ExpectColon { key } => match iter.next() {
-                    Some((_, ':')) => {
-                        state = State::ExpectValue { key };
-                    }
-
-                    Some((byte_offset, _)) => {
-                        return Err(ParseError {
-                            byte_offset,
-                            reason: "unexpected char while parsing object",
-                        })
-                    }
-
-                    None => {
-                        return Err(ParseError {
-                            byte_offset: input.len(),
-                            reason: "unexpected end of input while parsing object",
-                        })
-                    }
-                },
-
-                State::ExpectValue { key } => {
-                    let value = parse_single(iter, input)?.1;
-                    object.push((key, value));
-                    state = State::Done;
+                None => {
+                    return Err(ParseError::unexpected_end_of_input(
+                        input.len(),
+                        Expected::Key,
+                    ))
                 }
             }
         }
+
+        Some((_, '}')) => return Ok(None),
+
+        Some((byte_offset, '"')) if object.is_empty() => byte_offset,
+
+        Some((byte_offset, char)) => {
+            return Err(ParseError::unexpected_char(byte_offset, expected, char))
+        }
+
+        None => return Err(ParseError::unexpected_end_of_input(input.len(), expected)),
+    };
+
+    let key = parse_string(input, key_byte_offset, iter)?;
+    let slot = object.check_key(&key, key_byte_offset)?;
+
+    skip_trivia(iter)?;
+    match iter.next() {
+        Some((_, ':')) => Ok(Some((key, slot))),
+
+        Some((byte_offset, char)) => Err(ParseError::unexpected_char(
+            byte_offset,
+            Expected::Colon,
+            char,
+        )),
+
+        None => Err(ParseError::unexpected_end_of_input(
+            input.len(),
+            Expected::Colon,
+        )),
     }
+}
 
+/// Parses a value other than an array or object. `char` is the first character of the value, at
+/// `byte_offset`.
+fn parse_scalar(
+    iter: &mut Peekable<CharIndices>,
+    input: &str,
+    config: &ParserConfig,
+    byte_offset: usize,
+    char: char,
+    expected: Expected,
+) -> Result<Json, ParseError> {
     if char == 't' {
         if next_char(iter) == Some('r')
             && next_char(iter) == Some('u')
             && next_char(iter) == Some('e')
         {
-            return Ok((byte_offset, Json::Bool(true)));
+            return Ok(Json::Bool(true));
         }
-        return Err(ParseError {
-            byte_offset,
-            reason: "unexpected keyword",
-        });
+        return Err(invalid_token(byte_offset));
     }
 
     if char == 'f' {
@@ -196,12 +269,9 @@ fn parse_single(
             && next_char(iter) == Some('s')
             && next_char(iter) == Some('e')
         {
-            return Ok((byte_offset, Json::Bool(false)));
+            return Ok(Json::Bool(false));
         }
-        return Err(ParseError {
-            byte_offset,
-            reason: "unexpected keyword",
-        });
+        return Err(invalid_token(byte_offset));
     }
 
     if char == 'n' {
@@ -209,51 +279,62 @@ fn parse_single(
             && next_char(iter) == Some('l')
             && next_char(iter) == Some('l')
         {
-            return Ok((byte_offset, Json::Null));
+            return Ok(Json::Null);
         }
-        return Err(ParseError {
-            byte_offset,
-            reason: "unexpected keyword",
-        });
+        return Err(invalid_token(byte_offset));
     }
 
-    if char.is_ascii_digit() {
-        let mut i: u64 = u64::from((char as u8) - b'0');
+    if char == '-' || char.is_ascii_digit() {
+        return parse_number(input, byte_offset, config.int_overflow, iter);
+    }
 
-        while let Some((_, next)) = iter.peek().copied() {
-            if !next.is_ascii_digit() {
-                break;
-            }
+    if char == '"' {
+        let string = parse_string(input, byte_offset, iter)?;
+        return Ok(Json::String(string));
+    }
 
-            // Consume the digit.
-            iter.next();
+    Err(ParseError::unexpected_char(byte_offset, expected, char))
+}
 
-            // Ignore overflows for the purposes of this post.
-            i *= 10;
-            i += u64::from((next as u8) - b'0');
+// NB. First character of the number should be consumed in `iter`, but not in
+// `byte_offset`.
+pub(crate) fn parse_number(
+    input: &str,
+    byte_offset: usize,
+    int_overflow: IntOverflow,
+    iter: &mut Peekable<CharIndices>,
+) -> Result<Json, ParseError> {
+    let (number, end_offset) = scan_number(input.as_bytes(), byte_offset, int_overflow)?;
+    skip_until(iter, end_offset);
+    Ok(match number {
+        Number::Int(i) => Json::Int(i),
+        Number::NegInt(i) => Json::NegInt(i),
+        Number::Float(f) => Json::Float(f),
+        Number::Raw { size_in_bytes } => {
+            Json::RawNumber(input[byte_offset..byte_offset + size_in_bytes].to_owned())
         }
+    })
+}
 
-        return Ok((byte_offset, Json::Int(i)));
-    }
-
-    if char == '"' {
-        for (next_byte_offset, next) in iter.by_ref() {
-            if next == '"' {
-                let string = input[byte_offset + 1..next_byte_offset].to_string();
-                return Ok((byte_offset, Json::String(string)));
+// NB. Initial double quote should be consumed in `iter`, but not in
+// `byte_offset`.
+pub(crate) fn parse_string(
+    input: &str,
+    byte_offset: usize,
+    iter: &mut Peekable<CharIndices>,
+) -> Result<String, ParseError> {
+    match scan_string(iter) {
+        Ok((end_offset, has_escapes)) => {
+            let str = &input[byte_offset + 1..end_offset];
+            if has_escapes {
+                unescape(str, byte_offset + 1)
+            } else {
+                Ok(str.to_string())
             }
         }
 
-        return Err(ParseError {
-            byte_offset: input.len(),
-            reason: "unexpected end of input while parsing string",
-        });
+        Err(err) => Err(scan_string_error(err, byte_offset)),
     }
-
-    Err(ParseError {
-        byte_offset,
-        reason: "unexpected character",
-    })
 }
 
 pub(crate) fn next_char(iter: &mut Peekable<CharIndices>) -> Option<char> {
@@ -272,22 +353,10 @@ pub(crate) fn skip_trivia(iter: &mut Peekable<CharIndices>) -> Result<(), ParseE
             match iter.next() {
                 Some((_, '/')) => {
                     skip_until_eol(iter);
-                    break;
+                    continue;
                 }
 
-                Some(_) => {
-                    return Err(ParseError {
-                        byte_offset,
-                        reason: "unexpected character",
-                    })
-                }
-
-                None => {
-                    return Err(ParseError {
-                        byte_offset,
-                        reason: "unexpected end of input",
-                    })
-                }
+                _ => return Err(invalid_token(byte_offset)),
             }
         }
 
@@ -312,3 +381,78 @@ fn ast_tests() {
         assert_eq!(parse(&str).unwrap(), ast);
     }
 }
+
+#[test]
+fn surrogate_error_tests() {
+    for (str, byte_offset) in crate::test_common::surrogate_error_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse(&str).unwrap_err().byte_offset, byte_offset);
+    }
+}
+
+#[test]
+fn int_overflow_tests() {
+    for (str, byte_offset, ast) in crate::test_common::int_overflow_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(
+            parse(&str),
+            Err(ParseError::new(
+                byte_offset,
+                crate::ParseErrorKind::IntegerOverflow
+            ))
+        );
+
+        let config = crate::test_common::raw_int_config();
+        assert_eq!(parse_with_config(&str, config).unwrap(), ast);
+    }
+
+    let config = ParserConfig {
+        int_overflow: IntOverflow::Float,
+        ..ParserConfig::default()
+    };
+    assert_eq!(
+        parse_with_config("[18446744073709551616]", config),
+        Ok(Json::Array(vec![Json::Float(18446744073709551616.0)]))
+    );
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse(&str), Err(error));
+    }
+}
+
+#[test]
+fn max_depth_tests() {
+    for (str, config, error) in crate::test_common::max_depth_tests() {
+        assert_eq!(parse_with_config(&str, config).err(), error);
+    }
+}
+
+#[test]
+fn duplicate_key_tests() {
+    for (str, config, result) in crate::test_common::duplicate_key_tests() {
+        println!("Parsing {:?}", str);
+        assert_eq!(parse_with_config(&str, config), result);
+    }
+}
+
+#[test]
+fn bytes_tests() {
+    // Valid UTF-8 is parsed the same as `&str` input.
+    for (str, _) in crate::test_common::ast_tests() {
+        assert_eq!(parse_bytes(str.as_bytes()), parse(&str), "{:?}", str);
+    }
+    for (str, _) in crate::test_common::error_tests() {
+        assert_eq!(parse_bytes(str.as_bytes()), parse(&str), "{:?}", str);
+    }
+    for (str, config, result) in crate::test_common::duplicate_key_tests() {
+        assert_eq!(parse_bytes_with_config(str.as_bytes(), config), result);
+    }
+
+    for (bytes, error) in crate::test_common::utf8_error_tests() {
+        assert_eq!(parse_bytes(&bytes), Err(error), "{:?}", bytes);
+    }
+}
@@ -1,7 +1,11 @@
 /// A simple AST without comments and source locations.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq)]
 pub enum Json {
     Int(u64),
+    NegInt(i64),
+    Float(f64),
+    /// An integer that does not fit into `u64` or `i64`, as it appears in the input.
+    RawNumber(String),
     String(String),
     Bool(bool),
     Array(Vec<Json>),
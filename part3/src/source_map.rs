@@ -0,0 +1,175 @@
+use crate::utf8::floor_char_boundary;
+use crate::{ParseError, ParseEvent};
+
+/// Maps byte offsets in an input to line and column numbers.
+///
+/// Lines are separated by `'\n'`. A `'\r'` before a `'\n'` is a part of the line when counting
+/// columns, but not in [SourceMap::line_text].
+#[derive(Debug, Clone)]
+pub struct SourceMap<'a> {
+    input: &'a str,
+
+    /// Byte offsets of the first characters of the lines. First line starts at 0.
+    line_starts: Vec<usize>,
+}
+
+/// A location in the input, as line and column numbers. All numbers start from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+
+    /// Column, in UTF-8 bytes.
+    pub column: usize,
+
+    /// Column, in UTF-16 code units. This is what editors and the Language Server Protocol use.
+    pub utf16_column: usize,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(input: &'a str) -> SourceMap<'a> {
+        let mut line_starts = vec![0];
+        line_starts.extend(
+            input
+                .bytes()
+                .enumerate()
+                .filter(|(_, byte)| *byte == b'\n')
+                .map(|(byte_offset, _)| byte_offset + 1),
+        );
+        SourceMap { input, line_starts }
+    }
+
+    /// Number of lines in the input. An empty input has one line.
+    pub fn num_lines(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Text of the line `line`, without the `'\n'` or `"\r\n"` at the end. Lines start from 1.
+    ///
+    /// Panics if `line` is 0 or larger than [SourceMap::num_lines].
+    pub fn line_text(&self, line: usize) -> &'a str {
+        let start = self.line_starts[line - 1];
+        let end = match self.line_starts.get(line) {
+            Some(next_line_start) => next_line_start - 1,
+            None => self.input.len(),
+        };
+        let text = &self.input[start..end];
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+
+    /// Position of `byte_offset`.
+    ///
+    /// `byte_offset` can be the length of the input, which is where the parsers report unexpected
+    /// end of input.
+    ///
+    /// Offsets larger than the input size are clamped to the input size, and offsets in the middle
+    /// of a character are moved back to the start of the character.
+    pub fn position(&self, byte_offset: usize) -> Position {
+        let byte_offset = self.char_boundary(byte_offset);
+
+        // Number of lines starting at or before the offset, which is the 1-based line number.
+        let line = self
+            .line_starts
+            .partition_point(|line_start| *line_start <= byte_offset);
+
+        let line_start = self.line_starts[line - 1];
+        let line_prefix = &self.input[line_start..byte_offset];
+
+        // ASCII prefixes are common, and they have the same length in UTF-8 and UTF-16.
+        let utf16_column = if line_prefix.is_ascii() {
+            line_prefix.len()
+        } else {
+            line_prefix.encode_utf16().count()
+        };
+
+        Position {
+            line,
+            column: byte_offset - line_start + 1,
+            utf16_column: utf16_column + 1,
+        }
+    }
+
+    /// `byte_offset` clamped to the input size, and moved back to the start of the character when
+    /// it's in the middle of a character.
+    pub(crate) fn char_boundary(&self, byte_offset: usize) -> usize {
+        floor_char_boundary(self.input.as_bytes(), byte_offset.min(self.input.len()))
+    }
+}
+
+impl ParseError {
+    /// Position of the error in the input of `source_map`.
+    pub fn position(&self, source_map: &SourceMap) -> Position {
+        source_map.position(self.byte_offset)
+    }
+}
+
+impl ParseEvent {
+    /// Position of the event in the input of `source_map`.
+    pub fn position(&self, source_map: &SourceMap) -> Position {
+        source_map.position(self.byte_offset)
+    }
+}
+
+#[cfg(test)]
+fn position(line: usize, column: usize, utf16_column: usize) -> Position {
+    Position {
+        line,
+        column,
+        utf16_column,
+    }
+}
+
+#[test]
+fn position_test() {
+    let input = "[1,\n  \"é😀\", 2\r\n]\n";
+    let source_map = SourceMap::new(input);
+    assert_eq!(source_map.num_lines(), 4);
+
+    assert_eq!(source_map.position(0), position(1, 1, 1));
+    assert_eq!(source_map.position(3), position(1, 4, 4)); // '\n'
+    assert_eq!(source_map.position(4), position(2, 1, 1));
+    assert_eq!(source_map.position(6), position(2, 3, 3)); // '"'
+
+    // After "é😀": 2 + 4 UTF-8 bytes, 1 + 2 UTF-16 code units.
+    let comma = input.find(',').unwrap();
+    let comma = input[comma + 1..].find(',').unwrap() + comma + 1;
+    assert_eq!(source_map.position(comma), position(2, 11, 8));
+
+    let rbracket = input.find(']').unwrap();
+    assert_eq!(source_map.position(rbracket), position(3, 1, 1));
+
+    // End of input.
+    assert_eq!(source_map.position(input.len()), position(4, 1, 1));
+
+    // Offsets in the middle of a character, and after the end of the input.
+    assert_eq!(source_map.position(8), position(2, 4, 4)); // 'é'
+    assert_eq!(source_map.position(11), position(2, 6, 5)); // '😀'
+    assert_eq!(source_map.position(input.len() + 10), position(4, 1, 1));
+
+    assert_eq!(source_map.line_text(1), "[1,");
+    assert_eq!(source_map.line_text(2), "  \"é😀\", 2");
+    assert_eq!(source_map.line_text(3), "]");
+    assert_eq!(source_map.line_text(4), "");
+
+    let source_map = SourceMap::new("");
+    assert_eq!(source_map.num_lines(), 1);
+    assert_eq!(source_map.line_text(1), "");
+    assert_eq!(source_map.position(0), position(1, 1, 1));
+}
+
+#[test]
+fn error_position_test() {
+    let input = "{\n  \"ключ\": 1,\n  \"b\" 2\n}";
+    let source_map = SourceMap::new(input);
+
+    let error = crate::parse_ast_recursive(input).unwrap_err();
+    assert_eq!(error.position(&source_map), position(3, 7, 7));
+
+    let events: Vec<ParseEvent> = crate::parse_events_iter(input)
+        .take_while(Result::is_ok)
+        .map(Result::unwrap)
+        .collect();
+
+    // The `1` after the non-ASCII key.
+    assert_eq!(events[2].kind, crate::ParseEventKind::Int(1));
+    assert_eq!(events[2].position(&source_map), position(2, 15, 11));
+}
@@ -0,0 +1,289 @@
+use crate::event_to_tree::{decode_text, Tree, TreeInput};
+use crate::number::scan_number;
+use crate::{IntOverflow, Json, ParseError, ParseEventKind};
+
+use std::ops::Range;
+
+/// A byte range in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+
+    /// Byte offset after the last character.
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    pub fn range(self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// Like [Json], but every value and object key records its location in the input.
+///
+/// Spans of strings include the double quotes, spans of arrays and objects include the brackets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedJson {
+    pub span: Span,
+    pub value: SpannedValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedValue {
+    Int(u64),
+    NegInt(i64),
+    Float(f64),
+    /// An integer that does not fit into `u64` or `i64`, as it appears in the input.
+    RawNumber(String),
+    String(String),
+    Bool(bool),
+    Array(Vec<SpannedJson>),
+    Object(Vec<(SpannedKey, SpannedJson)>),
+    Null,
+}
+
+/// An object key, with the span of the string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedKey {
+    pub span: Span,
+    pub key: String,
+}
+
+impl AsRef<str> for SpannedKey {
+    fn as_ref(&self) -> &str {
+        &self.key
+    }
+}
+
+impl SpannedJson {
+    /// Drops the spans. Strings are moved, not copied.
+    pub fn into_json(self) -> Json {
+        match self.value {
+            SpannedValue::Int(i) => Json::Int(i),
+            SpannedValue::NegInt(i) => Json::NegInt(i),
+            SpannedValue::Float(f) => Json::Float(f),
+            SpannedValue::RawNumber(str) => Json::RawNumber(str),
+            SpannedValue::String(str) => Json::String(str),
+            SpannedValue::Bool(b) => Json::Bool(b),
+            SpannedValue::Array(elems) => {
+                Json::Array(elems.into_iter().map(SpannedJson::into_json).collect())
+            }
+            SpannedValue::Object(members) => Json::Object(
+                members
+                    .into_iter()
+                    .map(|(key, value)| (key.key, value.into_json()))
+                    .collect(),
+            ),
+            SpannedValue::Null => Json::Null,
+        }
+    }
+}
+
+impl From<SpannedJson> for Json {
+    fn from(json: SpannedJson) -> Json {
+        json.into_json()
+    }
+}
+
+impl Tree for SpannedJson {
+    type Key = SpannedKey;
+
+    fn leaf(
+        input: TreeInput,
+        byte_offset: usize,
+        kind: ParseEventKind,
+    ) -> Result<Self, ParseError> {
+        let (value, end) = match kind {
+            ParseEventKind::Int(i) => (SpannedValue::Int(i), number_end(input, byte_offset)),
+            ParseEventKind::NegInt(i) => (SpannedValue::NegInt(i), number_end(input, byte_offset)),
+            ParseEventKind::Float(f) => (SpannedValue::Float(f), number_end(input, byte_offset)),
+            ParseEventKind::RawNumber { size_in_bytes } => {
+                let end = byte_offset + size_in_bytes;
+                (
+                    SpannedValue::RawNumber(input.text(byte_offset, size_in_bytes).to_owned()),
+                    end,
+                )
+            }
+            ParseEventKind::Str {
+                size_in_bytes,
+                has_escapes,
+            } => {
+                let string = decode_text(
+                    input.text(byte_offset, size_in_bytes),
+                    byte_offset,
+                    has_escapes,
+                )?;
+                // Strings are reported after the opening double quote.
+                return Ok(SpannedJson {
+                    span: Span::new(byte_offset - 1, byte_offset + size_in_bytes + 1),
+                    value: SpannedValue::String(string),
+                });
+            }
+            ParseEventKind::Bool(true) => (SpannedValue::Bool(true), byte_offset + 4),
+            ParseEventKind::Bool(false) => (SpannedValue::Bool(false), byte_offset + 5),
+            ParseEventKind::Null => (SpannedValue::Null, byte_offset + 4),
+            ParseEventKind::StartObject
+            | ParseEventKind::EndObject
+            | ParseEventKind::StartArray
+            | ParseEventKind::EndArray
+            | ParseEventKind::Comment { .. } => panic!(),
+        };
+        Ok(SpannedJson {
+            span: Span::new(byte_offset, end),
+            value,
+        })
+    }
+
+    fn array(span: Span, elems: Vec<Self>) -> Self {
+        SpannedJson {
+            span,
+            value: SpannedValue::Array(elems),
+        }
+    }
+
+    fn object(span: Span, members: Vec<(Self::Key, Self)>) -> Self {
+        SpannedJson {
+            span,
+            value: SpannedValue::Object(members),
+        }
+    }
+
+    fn into_key(self) -> Self::Key {
+        match self.value {
+            SpannedValue::String(key) => SpannedKey {
+                span: self.span,
+                key,
+            },
+            _ => panic!(),
+        }
+    }
+}
+
+fn number_end(input: TreeInput, byte_offset: usize) -> usize {
+    // The parser already scanned the number, so this can't fail.
+    scan_number(input.as_bytes(), byte_offset, IntOverflow::Raw)
+        .unwrap()
+        .1
+}
+
+#[cfg(test)]
+fn check_spans(input: &str, json: &SpannedJson) {
+    // Parsing the text in the span gives the same value.
+    let text = &input[json.span.range()];
+    assert_eq!(
+        crate::parse_ast_recursive(text).unwrap(),
+        json.clone().into_json(),
+        "{:?}",
+        text
+    );
+
+    match &json.value {
+        SpannedValue::Array(elems) => {
+            for elem in elems {
+                check_spans(input, elem);
+            }
+        }
+        SpannedValue::Object(members) => {
+            for (key, value) in members {
+                assert_eq!(
+                    crate::parse_ast_recursive(&input[key.span.range()]).unwrap(),
+                    Json::String(key.key.clone())
+                );
+                check_spans(input, value);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+fn parse_push(input: &str) -> Result<SpannedJson, ParseError> {
+    let mut listener = crate::AstBuilderListener::new_spanned(input);
+    crate::parse_events_push(input, &mut listener);
+    match listener.into_ast() {
+        (_, Some(err)) => Err(err),
+        (Some(json), None) => Ok(json),
+        (None, None) => panic!(),
+    }
+}
+
+#[test]
+fn ast_tests() {
+    for (str, ast) in crate::test_common::ast_tests() {
+        println!("Parsing {:?}", str);
+
+        let json = crate::event_to_spanned_tree(&mut crate::parse_events_iter(&str), &str).unwrap();
+        check_spans(&str, &json);
+        assert_eq!(json.into_json(), ast);
+
+        let json = parse_push(&str).unwrap();
+        check_spans(&str, &json);
+        assert_eq!(Json::from(json), ast);
+    }
+}
+
+#[test]
+fn random_tests() {
+    for input_size in [10, 100, 1_000, 10_000] {
+        let input = crate::gen_input(input_size);
+        let json =
+            crate::event_to_spanned_tree(&mut crate::parse_events_iter(&input), &input).unwrap();
+        check_spans(&input, &json);
+        assert_eq!(json, parse_push(&input).unwrap());
+        assert_eq!(
+            json.into_json(),
+            crate::parse_ast_recursive(&input).unwrap()
+        );
+    }
+}
+
+#[test]
+fn span_test() {
+    let input = r#" {"a": [1, -2.5e1, true], "ключ" : "x\ny" } "#;
+    let json = crate::event_to_spanned_tree(&mut crate::parse_events_iter(input), input).unwrap();
+    assert_eq!(json, parse_push(input).unwrap());
+
+    let span = |str: &str| {
+        let start = input.find(str).unwrap();
+        Span::new(start, start + str.len())
+    };
+
+    assert_eq!(json.span, Span::new(1, input.len() - 1));
+    let SpannedValue::Object(members) = &json.value else {
+        panic!()
+    };
+
+    assert_eq!(members[0].0.span, span(r#""a""#));
+    assert_eq!(members[0].1.span, span("[1, -2.5e1, true]"));
+    let SpannedValue::Array(elems) = &members[0].1.value else {
+        panic!()
+    };
+    assert_eq!(elems[0].span, span("1"));
+    assert_eq!(elems[1].span, span("-2.5e1"));
+    assert_eq!(elems[2].span, span("true"));
+
+    assert_eq!(members[1].0.span, span(r#""ключ""#));
+    assert_eq!(members[1].0.key, "ключ");
+    assert_eq!(members[1].1.span, span(r#""x\ny""#));
+}
+
+#[test]
+fn error_tests() {
+    for (str, error) in crate::test_common::error_tests() {
+        println!("Parsing {:?}", str);
+        // `event_to_spanned_tree` stops after the top-level value, without checking the rest of
+        // the input.
+        if error.kind != crate::ParseErrorKind::TrailingCharacters {
+            assert_eq!(
+                crate::event_to_spanned_tree(&mut crate::parse_events_iter(&str), &str),
+                Err(error.clone())
+            );
+        }
+        assert_eq!(parse_push(&str), Err(error));
+    }
+}
@@ -0,0 +1,477 @@
+/// Byte offsets of the tokens in an input, built by scanning the input in 64-byte blocks with SIMD
+/// instructions, as in the first stage of [simdjson](https://arxiv.org/abs/1902.08318).
+///
+/// The index has the offsets of:
+///
+/// - `[`, `]`, `{`, `}`, `:`, and `,` outside of strings and comments,
+/// - opening and closing double quotes of strings,
+/// - `/` characters that start comments,
+/// - the first characters of numbers, keywords, and any other characters that don't form a token.
+///
+/// Characters in strings and comments are not indexed, so the offset after an opening double
+/// quote is the string's closing double quote. After any other token the next token is either
+/// right after the token, or at the next offset in the index.
+///
+/// Building the index does not validate the input: an invalid input generates an index, and the
+/// second stage ([parse_events_iter_indexed][crate::parse_events_iter_indexed]) reports the
+/// error.
+///
+/// SIMD instructions are used on x86_64 (SSE2) and aarch64 (NEON). On other architectures the
+/// blocks are classified one byte at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuralIndex {
+    offsets: Vec<usize>,
+}
+
+impl StructuralIndex {
+    pub fn new(input: &[u8]) -> StructuralIndex {
+        let mut offsets = Vec::with_capacity(input.len() / 4);
+        let mut builder = IndexBuilder::new(input);
+        while builder.index_blocks(usize::MAX, &mut offsets) {}
+        StructuralIndex { offsets }
+    }
+
+    /// Offsets of the tokens, sorted.
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+}
+
+/// Builds a [StructuralIndex] a few blocks at a time.
+///
+/// Used by the indexed event parser to index the input as it parses, instead of building the whole
+/// index first.
+#[derive(Debug, Clone)]
+pub(crate) struct IndexBuilder<'a> {
+    input: &'a [u8],
+
+    /// Offset of the next block to index.
+    block_offset: usize,
+
+    state: ScanState,
+}
+
+impl<'a> IndexBuilder<'a> {
+    pub(crate) fn new(input: &'a [u8]) -> IndexBuilder<'a> {
+        IndexBuilder {
+            input,
+            block_offset: 0,
+            state: ScanState::default(),
+        }
+    }
+
+    /// Indexes the next `num_blocks` blocks, or until the end of the input, adding the offsets to
+    /// `out`. Returns `false` when the whole input was already indexed.
+    pub(crate) fn index_blocks(&mut self, num_blocks: usize, out: &mut Vec<usize>) -> bool {
+        if self.block_offset >= self.input.len() {
+            return false;
+        }
+
+        let rest = &self.input[self.block_offset..];
+        let mut blocks = rest.chunks_exact(64).take(num_blocks);
+        for block in blocks.by_ref() {
+            index_block(
+                block.try_into().unwrap(),
+                self.block_offset,
+                &mut self.state,
+                out,
+            );
+            self.block_offset += 64;
+        }
+
+        // Pad the last block with whitespace.
+        if self.block_offset < self.input.len() && self.input.len() - self.block_offset < 64 {
+            let rest = &self.input[self.block_offset..];
+            let mut block = [b' '; 64];
+            block[..rest.len()].copy_from_slice(rest);
+            index_block(&block, self.block_offset, &mut self.state, out);
+            self.block_offset = self.input.len();
+        }
+
+        true
+    }
+}
+
+/// State carried from a block to the next one.
+#[derive(Debug, Default, Clone, Copy)]
+struct ScanState {
+    /// Whether the previous block ended in a string.
+    in_string: bool,
+
+    /// Whether the previous block ended with a backslash that escapes the first byte of the block.
+    escaped: bool,
+
+    /// Whether the previous block ended in a comment.
+    in_comment: bool,
+
+    /// Whether the last byte of the previous block is a part of a number, keyword, or another
+    /// token that isn't a string or a delimiter.
+    in_scalar: bool,
+}
+
+/// Bit masks of the bytes in a block. Bit `i` is for byte `i` of the block.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct BlockMasks {
+    quote: u64,
+    backslash: u64,
+    slash: u64,
+
+    /// `[`, `]`, `{`, `}`, `:`, and `,`.
+    delimiter: u64,
+
+    whitespace: u64,
+}
+
+fn index_block(block: &[u8; 64], block_offset: usize, state: &mut ScanState, out: &mut Vec<usize>) {
+    let masks = classify(block);
+
+    let mut escaped_next = state.escaped;
+    let escaped = escaped_bytes(masks.backslash, &mut escaped_next);
+    let quote = masks.quote & !escaped;
+    let in_string = prefix_xor(quote) ^ if state.in_string { u64::MAX } else { 0 };
+
+    if state.in_comment || masks.slash & !in_string != 0 {
+        // Comments change where the strings are, scan the block one byte at a time. The masks
+        // computed above are discarded, including the escape state.
+        index_block_scalar(block, block_offset, state, out);
+        return;
+    }
+
+    let scalar = !(masks.delimiter | masks.whitespace | quote | in_string);
+    let scalar_start = scalar & !((scalar << 1) | u64::from(state.in_scalar));
+
+    add_offsets(
+        (masks.delimiter & !in_string) | quote | scalar_start,
+        block_offset,
+        out,
+    );
+
+    state.escaped = escaped_next;
+    state.in_string = in_string >> 63 == 1;
+    state.in_scalar = scalar >> 63 == 1;
+}
+
+/// Indexes a block one byte at a time. Used for blocks with comments, and in tests.
+fn index_block_scalar(
+    block: &[u8; 64],
+    block_offset: usize,
+    state: &mut ScanState,
+    out: &mut Vec<usize>,
+) {
+    for (byte_idx, byte) in block.iter().copied().enumerate() {
+        if state.in_comment {
+            state.in_comment = byte != b'\n';
+            continue;
+        }
+
+        if state.in_string {
+            if state.escaped {
+                state.escaped = false;
+            } else if byte == b'\\' {
+                state.escaped = true;
+            } else if byte == b'"' {
+                state.in_string = false;
+                out.push(block_offset + byte_idx);
+            }
+            continue;
+        }
+
+        let in_scalar = std::mem::replace(&mut state.in_scalar, false);
+
+        match byte {
+            b'"' => state.in_string = true,
+
+            // When the '/' is not followed by another '/' the second stage reports an error at
+            // the '/', so the rest of the input doesn't matter.
+            b'/' => state.in_comment = true,
+
+            b'[' | b']' | b'{' | b'}' | b':' | b',' => {}
+
+            b' ' | b'\t' | b'\n' | b'\x0C' | b'\r' => continue,
+
+            _ => {
+                state.in_scalar = true;
+                if in_scalar {
+                    continue;
+                }
+            }
+        }
+
+        out.push(block_offset + byte_idx);
+    }
+}
+
+/// Returns the bytes escaped by a backslash. `escaped` is whether the first byte of the block is
+/// escaped, and is updated for the next block.
+fn escaped_bytes(backslash: u64, escaped: &mut bool) -> u64 {
+    let mut escaped_bytes = u64::from(*escaped);
+    let mut backslash = backslash & !escaped_bytes;
+    *escaped = false;
+
+    // Backslashes are rare, handle them one at a time.
+    while backslash != 0 {
+        let bit = backslash & backslash.wrapping_neg();
+        if bit == 1 << 63 {
+            *escaped = true;
+        }
+        let next_bit = bit << 1;
+        escaped_bytes |= next_bit;
+        backslash &= !(bit | next_bit);
+    }
+
+    escaped_bytes
+}
+
+/// Bit `i` of the result is the XOR of the bits `0..=i` of `bits`.
+///
+/// When `bits` has the double quotes, this sets the bits of the bytes from an opening double quote
+/// to the byte before the closing one.
+fn prefix_xor(mut bits: u64) -> u64 {
+    bits ^= bits << 1;
+    bits ^= bits << 2;
+    bits ^= bits << 4;
+    bits ^= bits << 8;
+    bits ^= bits << 16;
+    bits ^= bits << 32;
+    bits
+}
+
+fn add_offsets(mut bits: u64, block_offset: usize, out: &mut Vec<usize>) {
+    while bits != 0 {
+        out.push(block_offset + bits.trailing_zeros() as usize);
+        bits &= bits - 1;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn classify(block: &[u8; 64]) -> BlockMasks {
+    use std::arch::x86_64::*;
+
+    let mut masks = BlockMasks::default();
+
+    // SAFETY: SSE2 is available on all x86_64 CPUs, and the loads are within `block`.
+    unsafe {
+        for lane_idx in 0..4 {
+            let lane = _mm_loadu_si128(block.as_ptr().add(lane_idx * 16) as *const __m128i);
+            let eq = |byte: u8| _mm_cmpeq_epi8(lane, _mm_set1_epi8(byte as i8));
+            let to_bits =
+                |vector: __m128i| (_mm_movemask_epi8(vector) as u16 as u64) << (lane_idx * 16);
+
+            masks.quote |= to_bits(eq(b'"'));
+            masks.backslash |= to_bits(eq(b'\\'));
+            masks.slash |= to_bits(eq(b'/'));
+            masks.delimiter |= to_bits(_mm_or_si128(
+                _mm_or_si128(
+                    _mm_or_si128(eq(b'['), eq(b']')),
+                    _mm_or_si128(eq(b'{'), eq(b'}')),
+                ),
+                _mm_or_si128(eq(b':'), eq(b',')),
+            ));
+            masks.whitespace |= to_bits(_mm_or_si128(
+                _mm_or_si128(_mm_or_si128(eq(b' '), eq(b'\t')), eq(b'\n')),
+                _mm_or_si128(eq(b'\x0C'), eq(b'\r')),
+            ));
+        }
+    }
+
+    masks
+}
+
+#[cfg(target_arch = "aarch64")]
+fn classify(block: &[u8; 64]) -> BlockMasks {
+    use std::arch::aarch64::*;
+
+    // NEON doesn't have "movemask", select a different bit in each byte and add the bytes.
+    const BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+    // SAFETY: NEON is available on all aarch64 CPUs, and the loads are within `block` and `BITS`.
+    unsafe {
+        let lanes: [uint8x16_t; 4] =
+            std::array::from_fn(|lane_idx| vld1q_u8(block.as_ptr().add(lane_idx * 16)));
+        let bits = vld1q_u8(BITS.as_ptr());
+
+        let to_bits = |vectors: [uint8x16_t; 4]| -> u64 {
+            let sum0 = vpaddq_u8(vandq_u8(vectors[0], bits), vandq_u8(vectors[1], bits));
+            let sum1 = vpaddq_u8(vandq_u8(vectors[2], bits), vandq_u8(vectors[3], bits));
+            let sum = vpaddq_u8(sum0, sum1);
+            let sum = vpaddq_u8(sum, sum);
+            vgetq_lane_u64::<0>(vreinterpretq_u64_u8(sum))
+        };
+
+        let eq = |bytes: &[u8]| -> u64 {
+            to_bits(lanes.map(|lane| {
+                let mut result = vdupq_n_u8(0);
+                for byte in bytes {
+                    result = vorrq_u8(result, vceqq_u8(lane, vdupq_n_u8(*byte)));
+                }
+                result
+            }))
+        };
+
+        BlockMasks {
+            quote: eq(b"\""),
+            backslash: eq(b"\\"),
+            slash: eq(b"/"),
+            delimiter: eq(b"[]{}:,"),
+            whitespace: eq(b" \t\n\x0C\r"),
+        }
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn classify(block: &[u8; 64]) -> BlockMasks {
+    classify_scalar(block)
+}
+
+#[cfg_attr(
+    any(target_arch = "x86_64", target_arch = "aarch64"),
+    cfg_attr(not(test), allow(dead_code))
+)]
+fn classify_scalar(block: &[u8; 64]) -> BlockMasks {
+    let mut masks = BlockMasks::default();
+    for (byte_idx, byte) in block.iter().enumerate() {
+        let bit = 1 << byte_idx;
+        match byte {
+            b'"' => masks.quote |= bit,
+            b'\\' => masks.backslash |= bit,
+            b'/' => masks.slash |= bit,
+            b'[' | b']' | b'{' | b'}' | b':' | b',' => masks.delimiter |= bit,
+            b' ' | b'\t' | b'\n' | b'\x0C' | b'\r' => masks.whitespace |= bit,
+            _ => {}
+        }
+    }
+    masks
+}
+
+/// Index of the input built one byte at a time.
+#[cfg(test)]
+fn scalar_index(input: &[u8]) -> Vec<usize> {
+    let mut offsets = vec![];
+    let mut state = ScanState::default();
+    for (block_idx, block) in input.chunks(64).enumerate() {
+        let mut padded = [b' '; 64];
+        padded[..block.len()].copy_from_slice(block);
+        index_block_scalar(&padded, block_idx * 64, &mut state, &mut offsets);
+    }
+    offsets
+}
+
+#[cfg(test)]
+fn test_inputs() -> Vec<String> {
+    let mut inputs: Vec<String> = crate::test_common::ast_tests()
+        .into_iter()
+        .map(|(str, _)| str)
+        .chain(
+            crate::test_common::error_tests()
+                .into_iter()
+                .map(|(str, _)| str),
+        )
+        .collect();
+
+    // Strings, escapes, and comments crossing the block boundaries.
+    for padding in 0..70 {
+        let spaces = " ".repeat(padding);
+        inputs.push(format!(r#"{spaces}["a\\", "b\"c", "\\\"", 123, true]"#));
+        inputs.push(format!("{spaces}[1, // a \"comment\"\n \"x // y\", 2]"));
+        inputs.push(format!("{spaces}{{\"{}\": [null]}}", "k".repeat(padding)));
+        inputs.push(format!(r#"["{}\\\\", 1]"#, "a".repeat(padding)));
+        inputs.push(format!(r#"["{}\\\"", 1]"#, "a".repeat(padding)));
+        inputs.push(format!("[\"{}\\\"\", // c\n 1]", "a".repeat(padding)));
+    }
+
+    for input_size in [10, 100, 1_000, 10_000] {
+        inputs.push(crate::gen_input(input_size));
+    }
+
+    inputs
+}
+
+#[test]
+fn classify_test() {
+    for input in test_inputs() {
+        for block in input.as_bytes().chunks(64) {
+            let mut padded = [b' '; 64];
+            padded[..block.len()].copy_from_slice(block);
+            assert_eq!(classify(&padded), classify_scalar(&padded));
+        }
+    }
+
+    let all_bytes: Vec<u8> = (0..=255).collect();
+    for block in all_bytes.chunks(64) {
+        let block: &[u8; 64] = block.try_into().unwrap();
+        assert_eq!(classify(block), classify_scalar(block));
+    }
+}
+
+#[test]
+fn index_test() {
+    let index = |input: &str| StructuralIndex::new(input.as_bytes()).offsets;
+
+    assert_eq!(
+        index(r#"{"a": [1, true]}"#),
+        vec![0, 1, 3, 4, 6, 7, 8, 10, 14, 15]
+    );
+    assert_eq!(index(r#""a\"b" 12x"#), vec![0, 5, 7]);
+    assert_eq!(index("[1] // [2]\n 3"), vec![0, 1, 2, 4, 12]);
+
+    for input in test_inputs() {
+        assert_eq!(index(&input), scalar_index(input.as_bytes()), "{:?}", input);
+    }
+}
+
+#[test]
+fn escaped_bytes_test() {
+    let mut escaped = false;
+    assert_eq!(escaped_bytes(0b0110, &mut escaped), 0b0100);
+    assert!(!escaped);
+    assert_eq!(escaped_bytes(0b1110, &mut escaped), 0b10100);
+    assert_eq!(escaped_bytes(1 << 63, &mut escaped), 0);
+    assert!(escaped);
+    assert_eq!(escaped_bytes(0b11, &mut escaped), 0b101);
+    assert!(!escaped);
+}
+
+#[test]
+fn indexed_event_parser_test() {
+    fn collect(
+        parser: crate::event_parser::EventParser,
+    ) -> Vec<Result<crate::ParseEvent, crate::ParseError>> {
+        let mut events = vec![];
+        for event in parser {
+            let is_err = event.is_err();
+            events.push(event);
+            if is_err {
+                break;
+            }
+        }
+        events
+    }
+
+    let mut inputs = test_inputs();
+    inputs.push(format!(
+        "{}[1,{}2]{}",
+        " ".repeat(100),
+        "\n".repeat(100),
+        " ".repeat(100)
+    ));
+    inputs.push("[1, / 2]".to_owned());
+
+    // The indexed parser indexes the input a few blocks at a time: whitespace and strings longer
+    // than the window, and strings crossing the end of the window.
+    inputs.push(format!("[1,{}2]", " ".repeat(5_000)));
+    inputs.push(format!("[\"{}\", 1]", "a".repeat(5_000)));
+    inputs.push(format!("[\"{}\\\"\", 1]", "a".repeat(5_000)));
+    for padding in 1_010..1_030 {
+        inputs.push(format!("{}[\"abc\", \"d\\ne\"]", " ".repeat(padding)));
+    }
+    inputs.push("[\"a\" \"b\"]".to_owned());
+
+    for input in inputs {
+        assert_eq!(
+            collect(crate::parse_events_iter_indexed(&input)),
+            collect(crate::parse_events_iter(&input)),
+            "{:?}",
+            input
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{Json, ParseEventKind};
+use crate::{
+    DuplicateKeys, EventListener, Expected, IntOverflow, Json, ListenerAction, ParseError,
+    ParseErrorKind, ParseEventKind, ParserConfig, TokenKind,
+};
 
 #[rustfmt::skip]
 pub(crate) fn ast_tests() -> Vec<(String, Json)> {
@@ -54,6 +57,33 @@ pub(crate) fn ast_tests() -> Vec<(String, Json)> {
             ])
         ),
 
+        // Numbers
+        (s("0"), Json::Int(0)),
+        (s("-1"), Json::NegInt(-1)),
+        (s("-0"), Json::Float(-0.0)),
+        (s("2.5"), Json::Float(2.5)),
+        (s("1e9"), Json::Float(1e9)),
+        (s("-1.25E-2"), Json::Float(-1.25e-2)),
+        (s("18446744073709551615"), Json::Int(u64::MAX)),
+        (s("-9223372036854775808"), Json::NegInt(i64::MIN)),
+        (
+            s("[-1, 0.5, 1e+2, {\"a\": -3}]"),
+            Json::Array(vec![
+                Json::NegInt(-1),
+                Json::Float(0.5),
+                Json::Float(100.0),
+                Json::Object(vec![(s("a"), Json::NegInt(-3))]),
+            ])
+        ),
+
+        // Strings with escape sequences
+        (s(r#""a\"b""#), Json::String(s("a\"b"))),
+        (s(r#""C:\\Users\\x""#), Json::String(s(r"C:\Users\x"))),
+        (s(r#""\/\b\f\n\r\t""#), Json::String(s("/\u{8}\u{c}\n\r\t"))),
+        (s(r#""caf\u00e9""#), Json::String(s("café"))),
+        (s(r#""\uD83D\uDE00 \ud834\udd1e""#), Json::String(s("😀 𝄞"))),
+        (s(r#"["\"", "\\"]"#), Json::Array(vec![Json::String(s("\"")), Json::String(s("\\"))])),
+
         // Object
         (s(r#"{ "a" : 1 }"#), Json::Object(vec![(s("a"), Json::Int(1))])),
         (
@@ -68,9 +98,474 @@ pub(crate) fn ast_tests() -> Vec<(String, Json)> {
                 (s("g"), Json::Array(vec![])),
             ])
         ),
+        (
+            s(r#"{ "a\"b": "\\" }"#),
+            Json::Object(vec![(s("a\"b"), Json::String(s("\\")))])
+        ),
+    ]
+}
+
+/// Inputs with invalid `\uXXXX` escapes, and byte offsets of the invalid escapes.
+#[rustfmt::skip]
+pub(crate) fn surrogate_error_tests() -> Vec<(String, usize)> {
+    vec![
+        // Lone high surrogate
+        (s(r#""\uD83D""#), 1),
+        (s(r#"["ab", "x\uD83Dy"]"#), 9),
+
+        // High surrogate followed by a non-surrogate
+        (s(r#"{"a": "\uD83D\u0041"}"#), 7),
+
+        // Lone low surrogate
+        (s(r#"{"\uDE00": 1}"#), 2),
+
+        // Reversed pair
+        (s(r#"[1, "\uDE00\uD83D"]"#), 5),
+    ]
+}
+
+/// Invalid inputs and the errors all parsers should report for them.
+///
+/// Unpaired surrogates are not here as event parsers don't unescape strings, see
+/// [surrogate_error_tests].
+#[rustfmt::skip]
+pub(crate) fn error_tests() -> Vec<(String, ParseError)> {
+    use Expected::*;
+    use ParseErrorKind::*;
+
+    let eof = |byte_offset, expected| ParseError::unexpected_end_of_input(byte_offset, expected);
+    let unexpected = |byte_offset, expected, found| {
+        ParseError::unexpected_token(byte_offset, expected, found)
+    };
+    let error = ParseError::new;
+
+    vec![
+        // End of input
+        (s(""), eof(0, Value)),
+        (s("  // comment"), eof(12, Value)),
+        (s("["), eof(1, ValueOrEndArray)),
+        (s("[1"), eof(2, CommaOrEndArray)),
+        (s("[1,"), eof(3, Value)),
+        (s("{"), eof(1, KeyOrEndObject)),
+        (s(r#"{"a""#), eof(4, Colon)),
+        (s(r#"{"a":"#), eof(5, Value)),
+        (s(r#"{"a":1"#), eof(6, CommaOrEndObject)),
+        (s(r#"{"a":1,"#), eof(7, Key)),
+
+        // Unexpected tokens in arrays
+        (s("[1 2]"), unexpected(3, CommaOrEndArray, TokenKind::Number)),
+        (s("[1,]"), unexpected(3, Value, TokenKind::RBracket)),
+        (s("[,1]"), unexpected(1, ValueOrEndArray, TokenKind::Comma)),
+        (s("[}"), unexpected(1, ValueOrEndArray, TokenKind::RBrace)),
+        (s("[1}"), unexpected(2, CommaOrEndArray, TokenKind::RBrace)),
+        (s("[1 true]"), unexpected(3, CommaOrEndArray, TokenKind::True)),
+
+        // Unexpected tokens in objects
+        (s("{1:2}"), unexpected(1, KeyOrEndObject, TokenKind::Number)),
+        (s("{,}"), unexpected(1, KeyOrEndObject, TokenKind::Comma)),
+        (s(r#"{"a" 1}"#), unexpected(5, Colon, TokenKind::Number)),
+        (s(r#"{"a":}"#), unexpected(5, Value, TokenKind::RBrace)),
+        (s(r#"{"a":1,}"#), unexpected(7, Key, TokenKind::RBrace)),
+        (s(r#"{"a":1 "b":2}"#), unexpected(7, CommaOrEndObject, TokenKind::String)),
+        (s(r#"{"a":1]"#), unexpected(6, CommaOrEndObject, TokenKind::RBracket)),
+        (s(r#"{"a":1, null}"#), unexpected(8, Key, TokenKind::Null)),
+
+        // Unexpected tokens at the top level
+        (s(":"), unexpected(0, Value, TokenKind::Colon)),
+        (s("]"), unexpected(0, Value, TokenKind::RBracket)),
+
+        // Invalid tokens
+        (s("tru"), error(0, InvalidToken)),
+        (s("[nul]"), error(1, InvalidToken)),
+        (s("@"), error(0, InvalidToken)),
+        (s("[1, /]"), error(4, InvalidToken)),
+
+        // Strings and numbers
+        (s(r#""abc"#), error(0, UnterminatedString)),
+        (s(r#"{"a": "b}"#), error(6, UnterminatedString)),
+        (s(r#"["a\x"]"#), error(3, InvalidEscape)),
+        (s(r#"["\ud83d\u12G4"]"#), error(8, InvalidEscape)),
+        (s("-"), error(1, InvalidNumber)),
+        (s("[1.]"), error(3, InvalidNumber)),
+        (s("18446744073709551616"), error(0, IntegerOverflow)),
+
+        // Trailing characters
+        (s("1 2"), error(2, TrailingCharacters)),
+        (s("[] ]"), error(3, TrailingCharacters)),
+        (s("{} // comment\n x"), error(15, TrailingCharacters)),
+    ]
+}
+
+/// Inputs that are not valid UTF-8, and the errors parsers of byte input should report for them.
+#[rustfmt::skip]
+pub(crate) fn utf8_error_tests() -> Vec<(Vec<u8>, ParseError)> {
+    use ParseErrorKind::*;
+
+    let error = ParseError::new;
+
+    vec![
+        // Continuation byte without a start byte
+        (b"[\"a\x80\"]".to_vec(), error(3, InvalidUtf8)),
+        // Truncated character before the closing quote
+        (b"[\"a\xc3\"]".to_vec(), error(3, InvalidUtf8)),
+        // Overlong encoding of '/'
+        (b"\"\xc0\xaf\"".to_vec(), error(1, InvalidUtf8)),
+        // Surrogate
+        (b"\"\xed\xa0\x80\"".to_vec(), error(1, InvalidUtf8)),
+        // Above U+10FFFF
+        (b"\"\xf4\x90\x80\x80\"".to_vec(), error(1, InvalidUtf8)),
+        // In a comment, and truncated at the end of the input
+        (b"[1 // \xff\n]".to_vec(), error(6, InvalidUtf8)),
+        (b"[1 // \xe2\x82".to_vec(), error(6, InvalidUtf8)),
+        // Outside of strings and comments non-ASCII bytes are invalid tokens
+        (b"[\xc3\xa9]".to_vec(), error(1, InvalidToken)),
+        (b"[1, \xff]".to_vec(), error(4, InvalidToken)),
+    ]
+}
+
+/// Inputs with integers that don't fit into `u64` or `i64`, byte offsets of the integers, and the
+/// ASTs when parsed with [IntOverflow::Raw][crate::IntOverflow::Raw].
+#[rustfmt::skip]
+pub(crate) fn int_overflow_tests() -> Vec<(String, usize, Json)> {
+    vec![
+        (s("18446744073709551616"), 0, Json::RawNumber(s("18446744073709551616"))),
+        (
+            s("[1, -9223372036854775809]"),
+            4,
+            Json::Array(vec![Json::Int(1), Json::RawNumber(s("-9223372036854775809"))]),
+        ),
+        (
+            s(r#"{"id": 99999999999999999999}"#),
+            7,
+            Json::Object(vec![(s("id"), Json::RawNumber(s("99999999999999999999")))]),
+        ),
+    ]
+}
+
+pub(crate) fn raw_int_config() -> ParserConfig {
+    ParserConfig {
+        int_overflow: IntOverflow::Raw,
+        ..ParserConfig::default()
+    }
+}
+
+/// Inputs with nested arrays and objects, the configurations to parse them with, and the errors
+/// when they're nested deeper than [ParserConfig::max_depth].
+pub(crate) fn max_depth_tests() -> Vec<(String, ParserConfig, Option<ParseError>)> {
+    let max_depth = |max_depth| ParserConfig {
+        max_depth,
+        ..ParserConfig::default()
+    };
+
+    let nesting_too_deep =
+        |byte_offset| Some(ParseError::new(byte_offset, ParseErrorKind::NestingTooDeep));
+
+    let (input, _) = nested(3);
+    let (deep_input, offsets) = nested(4);
+    let (max_depth_input, _) = nested(crate::DEFAULT_MAX_DEPTH);
+    let (too_deep_input, too_deep_offsets) = nested(1_000_000);
+
+    vec![
+        (s("1"), max_depth(0), None),
+        (s("[]"), max_depth(0), nesting_too_deep(0)),
+        (s(" {}"), max_depth(0), nesting_too_deep(1)),
+        (input.clone(), max_depth(3), None),
+        (input, max_depth(2), nesting_too_deep(offsets[2])),
+        (deep_input, max_depth(3), nesting_too_deep(offsets[3])),
+        (s("[[], {}, [1, {\"a\": []}]]"), max_depth(4), None),
+        (
+            s("[[], {}, [1, {\"a\": [[]]}]]"),
+            max_depth(4),
+            nesting_too_deep(20),
+        ),
+        // The recursive parsers should be able to parse the default maximum depth in a test
+        // thread, which has a 2 MiB stack, also in debug builds.
+        (max_depth_input, ParserConfig::default(), None),
+        (
+            too_deep_input,
+            ParserConfig::default(),
+            nesting_too_deep(too_deep_offsets[crate::DEFAULT_MAX_DEPTH]),
+        ),
+    ]
+}
+
+/// Inputs, configurations with resource limits, and the errors when the inputs exceed the limits.
+#[rustfmt::skip]
+pub(crate) fn limit_tests() -> Vec<(String, ParserConfig, Option<ParseError>)> {
+    use ParseErrorKind::*;
+
+    let error = |byte_offset, kind| Some(ParseError::new(byte_offset, kind));
+
+    let input_size = |max_input_size| ParserConfig { max_input_size, ..ParserConfig::default() };
+    let string_size = |max_string_size| ParserConfig { max_string_size, ..ParserConfig::default() };
+    let container_size =
+        |max_container_size| ParserConfig { max_container_size, ..ParserConfig::default() };
+    let events = |max_events| ParserConfig { max_events, ..ParserConfig::default() };
+
+    vec![
+        (s("[1, 2]"), input_size(6), None),
+        (s("[1, 2]"), input_size(5), error(5, InputTooLarge)),
+        (s(""), input_size(0), error(0, UnexpectedEndOfInput { expected: Expected::Value })),
+
+        (s(r#"["abc", {"abcd": 1}]"#), string_size(4), None),
+        // Object keys are strings
+        (s(r#"["abc", {"abcd": 1}]"#), string_size(3), error(9, StringTooLong)),
+        // Size is before decoding escape sequences
+        (s(r#""a\nb""#), string_size(3), error(0, StringTooLong)),
+
+        (s("[1, 2, 3]"), container_size(3), None),
+        (s("[1, 2, 3]"), container_size(2), error(7, ContainerTooLarge)),
+        (s("[[1, 2], [3, 4]]"), container_size(2), None),
+        (s(r#"{"a": 1, "b": [1], "c": 3}"#), container_size(3), None),
+        // Reported at the key
+        (s(r#"{"a": 1, "b": [1], "c": 3}"#), container_size(2), error(19, ContainerTooLarge)),
+        (s("[]"), container_size(0), None),
+        (s("[{}]"), container_size(0), error(1, ContainerTooLarge)),
+
+        (s("[1, 2]"), events(4), None),
+        (s("[1, 2]"), events(3), error(5, TooManyEvents)),
+        // Comments are events
+        (s("// c\n[1]"), events(3), error(7, TooManyEvents)),
+        // Reported at the double quote
+        (s(r#"["a"]"#), events(1), error(1, TooManyEvents)),
+    ]
+}
+
+/// Non-ASCII inputs larger than [ParserConfig::max_input_size], where the limit is in the middle of
+/// a character. The error is reported at the start of the character. Parsers that take a lexer
+/// instead of the input report the error at the limit.
+#[rustfmt::skip]
+pub(crate) fn non_ascii_input_size_tests() -> Vec<(String, ParserConfig, Option<ParseError>)> {
+    use ParseErrorKind::*;
+
+    let error = |byte_offset, kind| Some(ParseError::new(byte_offset, kind));
+    let input_size = |max_input_size| ParserConfig { max_input_size, ..ParserConfig::default() };
+
+    vec![
+        // 'é' is 2 bytes, at offsets 2 and 3
+        (s(r#"["é"]"#), input_size(6), None),
+        (s(r#"["é"]"#), input_size(3), error(2, InputTooLarge)),
+        (s(r#"["é"]"#), input_size(2), error(2, InputTooLarge)),
+        // '😀' is 4 bytes, at offsets 2 to 5
+        (s(r#"["😀"]"#), input_size(5), error(2, InputTooLarge)),
+        (s(r#"["😀"]"#), input_size(4), error(2, InputTooLarge)),
+        // In a comment
+        (s("// é\n1"), input_size(4), error(3, InputTooLarge)),
+    ]
+}
+
+/// Inputs with duplicate object keys, configurations with duplicate key policies, and the results.
+#[rustfmt::skip]
+pub(crate) fn duplicate_key_tests() -> Vec<(String, ParserConfig, Result<Json, ParseError>)> {
+    use DuplicateKeys::*;
+
+    let config = |duplicate_keys| ParserConfig { duplicate_keys, ..ParserConfig::default() };
+    let object = |members: Vec<(&str, Json)>| {
+        Json::Object(members.into_iter().map(|(key, value)| (s(key), value)).collect())
+    };
+    let duplicate_key = |byte_offset| Err(ParseError::new(byte_offset, ParseErrorKind::DuplicateKey));
+
+    let input = s(r#"{"a": 1, "b": 2, "a": 3}"#);
+
+    // Keys in different objects are not duplicates
+    let nested = s(r#"{"a": {"a": 1}, "b": [{"a": 2}, {"a": 3}]}"#);
+    let nested_ast = || object(vec![
+        ("a", object(vec![("a", Json::Int(1))])),
+        ("b", Json::Array(vec![object(vec![("a", Json::Int(2))]), object(vec![("a", Json::Int(3))])])),
+    ]);
+
+    // Keys are compared after decoding escape sequences
+    let escaped = s(r#"[{"a": 1, "\u0061": 2}]"#);
+
+    // Duplicate key errors are reported before errors in the value
+    let invalid_value = s(r#"{"a": 1, "a": tru}"#);
+
+    vec![
+        (input.clone(), config(KeepAll), Ok(object(vec![("a", Json::Int(1)), ("b", Json::Int(2)), ("a", Json::Int(3))]))),
+        (input.clone(), config(KeepFirst), Ok(object(vec![("a", Json::Int(1)), ("b", Json::Int(2))]))),
+        (input.clone(), config(KeepLast), Ok(object(vec![("a", Json::Int(3)), ("b", Json::Int(2))]))),
+        (input, config(Error), duplicate_key(17)),
+
+        (nested.clone(), config(KeepFirst), Ok(nested_ast())),
+        (nested.clone(), config(KeepLast), Ok(nested_ast())),
+        (nested, config(Error), Ok(nested_ast())),
+
+        (escaped.clone(), config(KeepFirst), Ok(Json::Array(vec![object(vec![("a", Json::Int(1))])]))),
+        (escaped.clone(), config(KeepLast), Ok(Json::Array(vec![object(vec![("a", Json::Int(2))])]))),
+        (escaped, config(Error), duplicate_key(10)),
+
+        (invalid_value, config(Error), duplicate_key(9)),
+    ]
+}
+
+/// An [EventListener] that collects events, and returns the actions of a function of the event and
+/// the number of arrays and objects the event is in.
+pub(crate) struct ActionListener {
+    pub(crate) events: Vec<ParseEventKind>,
+    pub(crate) error: Option<ParseError>,
+    depth: usize,
+    action: fn(usize, &ParseEventKind) -> ListenerAction<String>,
+}
+
+impl ActionListener {
+    pub(crate) fn new(
+        action: fn(usize, &ParseEventKind) -> ListenerAction<String>,
+    ) -> ActionListener {
+        ActionListener {
+            events: vec![],
+            error: None,
+            depth: 0,
+            action,
+        }
+    }
+
+    fn event(&mut self, kind: ParseEventKind) -> ListenerAction<String> {
+        if let ParseEventKind::EndArray | ParseEventKind::EndObject = kind {
+            self.depth -= 1;
+        }
+        let action = (self.action)(self.depth, &kind);
+        if let ParseEventKind::StartArray | ParseEventKind::StartObject = kind {
+            self.depth += 1;
+        }
+        self.events.push(kind);
+        action
+    }
+}
+
+impl EventListener for ActionListener {
+    type Error = String;
+
+    fn handle_start_object(&mut self, _byte_offset: usize) -> ListenerAction<String> {
+        self.event(ParseEventKind::StartObject)
+    }
+
+    fn handle_end_object(&mut self, _byte_offset: usize) -> ListenerAction<String> {
+        self.event(ParseEventKind::EndObject)
+    }
+
+    fn handle_start_array(&mut self, _byte_offset: usize) -> ListenerAction<String> {
+        self.event(ParseEventKind::StartArray)
+    }
+
+    fn handle_end_array(&mut self, _byte_offset: usize) -> ListenerAction<String> {
+        self.event(ParseEventKind::EndArray)
+    }
+
+    fn handle_int(&mut self, _byte_offset: usize, i: u64) -> ListenerAction<String> {
+        self.event(ParseEventKind::Int(i))
+    }
+
+    fn handle_str(
+        &mut self,
+        _byte_offset: usize,
+        size_in_bytes: usize,
+        has_escapes: bool,
+    ) -> ListenerAction<String> {
+        self.event(ParseEventKind::Str {
+            size_in_bytes,
+            has_escapes,
+        })
+    }
+
+    fn handle_error(&mut self, error: ParseError) {
+        self.error = Some(error);
+    }
+}
+
+/// The actions of an [ActionListener], an input, and the events, the result, and the parse error of
+/// parsing the input with the listener.
+pub(crate) type ActionTest = (
+    fn(usize, &ParseEventKind) -> ListenerAction<String>,
+    String,
+    Vec<ParseEventKind>,
+    Result<(), String>,
+    Option<ParseError>,
+);
+
+#[rustfmt::skip]
+pub(crate) fn listener_action_tests() -> Vec<ActionTest> {
+    use ParseEventKind::*;
+
+    // Skips the arrays and objects in the top-level value.
+    fn skip_nested(depth: usize, kind: &ParseEventKind) -> ListenerAction<String> {
+        match kind {
+            StartArray | StartObject if depth != 0 => ListenerAction::SkipChildren,
+            _ => ListenerAction::Continue,
+        }
+    }
+
+    fn skip_all(_depth: usize, _kind: &ParseEventKind) -> ListenerAction<String> {
+        ListenerAction::SkipChildren
+    }
+
+    fn stop_at_2(_depth: usize, kind: &ParseEventKind) -> ListenerAction<String> {
+        match kind {
+            Int(2) => ListenerAction::Stop,
+            _ => ListenerAction::Continue,
+        }
+    }
+
+    fn abort_at_2(_depth: usize, kind: &ParseEventKind) -> ListenerAction<String> {
+        match kind {
+            Int(2) => ListenerAction::Abort(s("2")),
+            _ => ListenerAction::Continue,
+        }
+    }
+
+    fn abort_at_object(_depth: usize, kind: &ParseEventKind) -> ListenerAction<String> {
+        match kind {
+            StartObject => ListenerAction::Abort(s("object")),
+            _ => ListenerAction::Continue,
+        }
+    }
+
+    let unexpected_end = |byte_offset, expected| Some(ParseError::unexpected_end_of_input(byte_offset, expected));
+
+    vec![
+        // Skipped arrays and objects only generate the start and end events
+        (skip_nested, s("[1, [2, \"]\", {\"a\": [3]}], {\"b\": // }\n3}, 4]"),
+            vec![StartArray, Int(1), StartArray, EndArray, StartObject, EndObject, Int(4), EndArray], Ok(()), None),
+        (skip_all, s(r#"{"a": [1]} "#), vec![StartObject, EndObject], Ok(()), None),
+        // `SkipChildren` is the same as `Continue` for other events
+        (skip_all, s("1"), vec![Int(1)], Ok(()), None),
+
+        // Errors in the brackets and strings of skipped arrays and objects
+        (skip_nested, s(r#"[[1, "2]"#), vec![StartArray, StartArray], Ok(()),
+            Some(ParseError::new(5, ParseErrorKind::UnterminatedString))),
+        (skip_nested, s("[[1}]"), vec![StartArray, StartArray], Ok(()),
+            Some(ParseError::unexpected_token(3, Expected::CommaOrEndArray, TokenKind::RBrace))),
+        (skip_nested, s("[{\"a\": [1]]"), vec![StartArray, StartObject], Ok(()),
+            Some(ParseError::unexpected_token(10, Expected::CommaOrEndObject, TokenKind::RBracket))),
+        (skip_nested, s("[[1, [2]"), vec![StartArray, StartArray], Ok(()),
+            unexpected_end(8, Expected::CommaOrEndArray)),
+        // Errors after skipped arrays and objects
+        (skip_nested, s("[[1] 2]"), vec![StartArray, StartArray, EndArray], Ok(()),
+            Some(ParseError::unexpected_token(5, Expected::CommaOrEndArray, TokenKind::Number))),
+
+        // Stopping and aborting ignores the rest of the input, including errors
+        (stop_at_2, s("[1, 2, 3] x"), vec![StartArray, Int(1), Int(2)], Ok(()), None),
+        (abort_at_2, s("[1, 2, 3] x"), vec![StartArray, Int(1), Int(2)], Err(s("2")), None),
+        (abort_at_2, s("[1, 2, tru"), vec![StartArray, Int(1), Int(2)], Err(s("2")), None),
+        (abort_at_object, s("[{}]"), vec![StartArray, StartObject], Err(s("object")), None),
+        (abort_at_2, s("[1, 3]"), vec![StartArray, Int(1), Int(3), EndArray], Ok(()), None),
     ]
 }
 
+/// Generates `depth` nested arrays and objects, and the byte offsets of the `[` and `{` characters.
+fn nested(depth: usize) -> (String, Vec<usize>) {
+    let mut input = String::new();
+    let mut offsets = Vec::with_capacity(depth);
+    for level in 0..depth {
+        offsets.push(input.len());
+        input.push_str(if level % 2 == 0 { "[" } else { "{\"a\":" });
+    }
+    input.push('1');
+    for level in (0..depth).rev() {
+        input.push(if level % 2 == 0 { ']' } else { '}' });
+    }
+    (input, offsets)
+}
+
 #[rustfmt::skip]
 pub(crate) fn event_tests() -> Vec<(String, Vec<ParseEventKind>)> {
     use ParseEventKind::*;
@@ -78,7 +573,7 @@ pub(crate) fn event_tests() -> Vec<(String, Vec<ParseEventKind>)> {
     vec![
         // Simple cases
         (s("123"), vec![Int(123)]),
-        (s(r#""hi""#), vec![Str { size_in_bytes: 2 }]),
+        (s(r#""hi""#), vec![string(2)]),
         (s("true"), vec![Bool(true)]),
         (s("false"), vec![Bool(false)]),
         (s("[]"), vec![StartArray, EndArray]),
@@ -87,7 +582,7 @@ pub(crate) fn event_tests() -> Vec<(String, Vec<ParseEventKind>)> {
 
         // Simple cases + whitespace
         (s(" 123 "), vec![Int(123)]),
-        (s(r#" "hi" "#), vec![Str { size_in_bytes: 2 }]),
+        (s(r#" "hi" "#), vec![string(2)]),
         (s(" true "), vec![Bool(true)]),
         (s(" false "), vec![Bool(false)]),
         (s(" [ ] "), vec![StartArray, EndArray]),
@@ -96,7 +591,7 @@ pub(crate) fn event_tests() -> Vec<(String, Vec<ParseEventKind>)> {
 
         // Simple cases, with comments before JSON
         (add_comment_before("123"), vec![COMMENT, Int(123)]),
-        (add_comment_before(r#""hi""#), vec![COMMENT, Str { size_in_bytes: 2 }]),
+        (add_comment_before(r#""hi""#), vec![COMMENT, string(2)]),
         (add_comment_before("true"), vec![COMMENT, Bool(true)]),
         (add_comment_before("false"), vec![COMMENT, Bool(false)]),
         (add_comment_before("[]"), vec![COMMENT, StartArray, EndArray]),
@@ -105,7 +600,7 @@ pub(crate) fn event_tests() -> Vec<(String, Vec<ParseEventKind>)> {
 
         // Simple cases, with comments after JSON
         (add_comment_after("123"), vec![Int(123), COMMENT]),
-        (add_comment_after(r#""hi""#), vec![Str { size_in_bytes: 2 }, COMMENT]),
+        (add_comment_after(r#""hi""#), vec![string(2), COMMENT]),
         (add_comment_after("true"), vec![Bool(true), COMMENT]),
         (add_comment_after("false"), vec![Bool(false), COMMENT]),
         (add_comment_after("[]"), vec![StartArray, EndArray, COMMENT]),
@@ -117,22 +612,33 @@ pub(crate) fn event_tests() -> Vec<(String, Vec<ParseEventKind>)> {
         (
             s(r#"[ true, false, "hi", null, 456, {}, [] ]"#),
             vec![
-                StartArray, Bool(true), Bool(false), Str { size_in_bytes: 2 },
+                StartArray, Bool(true), Bool(false), string(2),
                 Null, Int(456), StartObject, EndObject, StartArray, EndArray, EndArray,
             ]
         ),
 
+        // Numbers
+        (s("-1"), vec![NegInt(-1)]),
+        (s("2.5e1"), vec![Float(25.0)]),
+        (s("[-1,1.5,2]"), vec![StartArray, NegInt(-1), Float(1.5), Int(2), EndArray]),
+        (s(r#"{"a":-0.5}"#), vec![StartObject, string(1), Float(-0.5), EndObject]),
+
+        // Strings with escape sequences
+        (s(r#""a\"b""#), vec![escaped_string(4)]),
+        (s(r#"["\\", "\u00e9"]"#), vec![StartArray, escaped_string(2), escaped_string(6), EndArray]),
+        (s(r#"{"\"":"\""}"#), vec![StartObject, escaped_string(2), escaped_string(2), EndObject]),
+
         // Object
-        (s(r#"{ "a" : 1 }"#), vec![StartObject, Str { size_in_bytes: 1 }, Int(1), EndObject]),
-        (s(r#"{"a":1, "b":2}"#), vec![StartObject, Str { size_in_bytes: 1 }, Int(1),
-                                     Str { size_in_bytes: 1 }, Int(2), EndObject]),
+        (s(r#"{ "a" : 1 }"#), vec![StartObject, string(1), Int(1), EndObject]),
+        (s(r#"{"a":1, "b":2}"#), vec![StartObject, string(1), Int(1),
+                                     string(1), Int(2), EndObject]),
         (
             s(r#"{ "a": true, "b": false, "c": "hi", "d": null, "e": 456, "f": {}, "g": [] }"#),
             vec![
-                StartObject, Str { size_in_bytes: 1 }, Bool(true), Str { size_in_bytes: 1 }, Bool(false),
-                Str { size_in_bytes: 1 }, Str { size_in_bytes: 2 }, Str { size_in_bytes: 1 }, Null,
-                Str { size_in_bytes: 1 }, Int(456), Str { size_in_bytes: 1 }, StartObject, EndObject,
-                Str { size_in_bytes: 1 }, StartArray, EndArray, EndObject,
+                StartObject, string(1), Bool(true), string(1), Bool(false),
+                string(1), string(2), string(1), Null,
+                string(1), Int(456), string(1), StartObject, EndObject,
+                string(1), StartArray, EndArray, EndObject,
             ]
         ),
 
@@ -148,8 +654,8 @@ pub(crate) fn event_tests() -> Vec<(String, Vec<ParseEventKind>)> {
             ]"#), vec![StartArray, Int(1), COMMENT, Int(2), EndArray]),
         (s(r#"{"a":1 // hi
             ,"b":2
-            }"#), vec![StartObject, Str { size_in_bytes: 1 }, Int(1), COMMENT,
-                       Str { size_in_bytes: 1 }, Int(2), EndObject]),
+            }"#), vec![StartObject, string(1), Int(1), COMMENT,
+                       string(1), Int(2), EndObject]),
 
         // Comments inside container: after a comma
         (s(r#"[1, // hi
@@ -157,16 +663,16 @@ pub(crate) fn event_tests() -> Vec<(String, Vec<ParseEventKind>)> {
             ]"#), vec![StartArray, Int(1), COMMENT, Int(2), EndArray]),
         (s(r#"{"a":1, // hi
             "b":2
-            }"#), vec![StartObject, Str { size_in_bytes: 1 }, Int(1), COMMENT,
-                       Str { size_in_bytes: 1 }, Int(2), EndObject]),
+            }"#), vec![StartObject, string(1), Int(1), COMMENT,
+                       string(1), Int(2), EndObject]),
 
         // Comments inside container: before a colon
         (s(r#"{"a" // hi
-            :1}"#), vec![StartObject, Str { size_in_bytes: 1 }, COMMENT, Int(1), EndObject]),
+            :1}"#), vec![StartObject, string(1), COMMENT, Int(1), EndObject]),
 
         // Comments inside container: after a colon
         (s(r#"{"a": // hi
-            1}"#), vec![StartObject, Str { size_in_bytes: 1 }, COMMENT, Int(1), EndObject]),
+            1}"#), vec![StartObject, string(1), COMMENT, Int(1), EndObject]),
     ]
 }
 
@@ -178,6 +684,20 @@ fn add_comment_after(input: &str) -> String {
     format!("{}\n// hi\n", input)
 }
 
+fn string(size_in_bytes: usize) -> ParseEventKind {
+    ParseEventKind::Str {
+        size_in_bytes,
+        has_escapes: false,
+    }
+}
+
+fn escaped_string(size_in_bytes: usize) -> ParseEventKind {
+    ParseEventKind::Str {
+        size_in_bytes,
+        has_escapes: true,
+    }
+}
+
 const COMMENT: ParseEventKind = ParseEventKind::Comment { size_in_bytes: 6 };
 
 fn s(s: &str) -> String {
@@ -1,7 +1,15 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Token {
     Int(u64),
-    Str { size_in_bytes: usize },
+    NegInt(i64),
+    Float(f64),
+    RawNumber {
+        size_in_bytes: usize,
+    },
+    Str {
+        size_in_bytes: usize,
+        has_escapes: bool,
+    },
     True,
     False,
     Null,
@@ -11,5 +19,7 @@ pub enum Token {
     RBrace,
     Colon,
     Comma,
-    Comment { size_in_bytes: usize },
+    Comment {
+        size_in_bytes: usize,
+    },
 }
@@ -0,0 +1,105 @@
+use crate::{ParseError, ParseErrorKind};
+
+/// Validates UTF-8 in strings and comments, one byte at a time.
+#[derive(Debug, Default)]
+pub(crate) struct Utf8Validator {
+    /// Number of continuation bytes left in the current character.
+    remaining: u8,
+
+    /// Range of the next continuation byte.
+    lower: u8,
+    upper: u8,
+
+    /// Byte offset of the first byte of the current character.
+    char_start: usize,
+}
+
+impl Utf8Validator {
+    /// Valid byte sequences are listed in table 3-7 of the Unicode standard.
+    pub(crate) fn feed(&mut self, byte: u8, byte_offset: usize) -> Result<(), ParseError> {
+        if self.remaining != 0 {
+            if byte < self.lower || byte > self.upper {
+                return Err(invalid_utf8(self.char_start));
+            }
+            self.remaining -= 1;
+            self.lower = 0x80;
+            self.upper = 0xBF;
+            return Ok(());
+        }
+
+        let (remaining, lower, upper) = match byte {
+            0x00..=0x7F => return Ok(()),
+            0xC2..=0xDF => (1, 0x80, 0xBF),
+            0xE0 => (2, 0xA0, 0xBF),
+            0xE1..=0xEC | 0xEE..=0xEF => (2, 0x80, 0xBF),
+            0xED => (2, 0x80, 0x9F),
+            0xF0 => (3, 0x90, 0xBF),
+            0xF1..=0xF3 => (3, 0x80, 0xBF),
+            0xF4 => (3, 0x80, 0x8F),
+            _ => return Err(invalid_utf8(byte_offset)),
+        };
+
+        *self = Utf8Validator {
+            remaining,
+            lower,
+            upper,
+            char_start: byte_offset,
+        };
+        Ok(())
+    }
+
+    /// Byte offset of the first byte of the character being validated, when the last byte fed was
+    /// not the last byte of a character.
+    pub(crate) fn char_start(&self) -> Option<usize> {
+        if self.remaining == 0 {
+            None
+        } else {
+            Some(self.char_start)
+        }
+    }
+
+    /// Checks that the input did not end in the middle of a character.
+    pub(crate) fn end(&self) -> Result<(), ParseError> {
+        if self.remaining == 0 {
+            Ok(())
+        } else {
+            Err(invalid_utf8(self.char_start))
+        }
+    }
+}
+
+/// Returns `byte_offset`, or if it's in the middle of a UTF-8 encoded character in `input`, the
+/// offset of the first byte of the character. Offsets at or after the end of `input` are returned
+/// as they are.
+pub(crate) fn floor_char_boundary(input: &[u8], byte_offset: usize) -> usize {
+    let mut byte_offset = byte_offset;
+    // Characters are at most 4 bytes, so at most 3 continuation bytes are skipped.
+    for _ in 0..3 {
+        match input.get(byte_offset) {
+            Some(byte) if byte_offset > 0 && is_continuation_byte(*byte) => byte_offset -= 1,
+            _ => break,
+        }
+    }
+    byte_offset
+}
+
+fn is_continuation_byte(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+pub(crate) fn invalid_utf8(byte_offset: usize) -> ParseError {
+    ParseError::new(byte_offset, ParseErrorKind::InvalidUtf8)
+}
+
+#[test]
+fn floor_char_boundary_test() {
+    let input = "aé😀".as_bytes();
+    let floor: Vec<usize> = (0..=input.len() + 1)
+        .map(|byte_offset| floor_char_boundary(input, byte_offset))
+        .collect();
+    assert_eq!(floor, vec![0, 1, 1, 3, 3, 3, 3, 7, 8]);
+
+    // Continuation bytes without a first byte.
+    assert_eq!(floor_char_boundary(&[0x80, 0x80], 1), 0);
+    assert_eq!(floor_char_boundary(&[b'a', 0x80, 0x80, 0x80, 0x80], 4), 1);
+}
@@ -0,0 +1,394 @@
+use crate::Json;
+
+use std::fmt::Write;
+
+/// Configuration for [write_json_with_config].
+///
+/// [write_json] uses [WriterConfig::default], which generates compact output without any
+/// whitespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriterConfig {
+    /// Number of spaces to indent nested values with. When `None`, arrays and objects are written
+    /// on one line, without any whitespace.
+    pub indent: Option<usize>,
+
+    /// Add a newline at the end of the output.
+    pub trailing_newline: bool,
+
+    /// Write object members sorted by their keys. Members with the same key are written in the
+    /// original order.
+    pub sort_keys: bool,
+
+    /// Write characters outside of ASCII as `\uXXXX` escape sequences.
+    pub escape_non_ascii: bool,
+}
+
+impl WriterConfig {
+    /// Configuration for human readable output: two spaces of indentation and a trailing newline.
+    pub fn pretty() -> Self {
+        WriterConfig {
+            indent: Some(2),
+            trailing_newline: true,
+            ..WriterConfig::default()
+        }
+    }
+}
+
+/// Writes `json` as compact JSON.
+pub fn write_json(json: &Json) -> String {
+    write_json_with_config(json, WriterConfig::default())
+}
+
+/// Writes `json` as JSON, formatted as specified in `config`.
+///
+/// JSON does not have infinities and NaN, so non-finite floats are written as `null`.
+pub fn write_json_with_config(json: &Json, config: WriterConfig) -> String {
+    let mut out = String::new();
+    write_value(&mut out, json, config, 0);
+    if config.trailing_newline {
+        out.push('\n');
+    }
+    out
+}
+
+fn write_value(out: &mut String, json: &Json, config: WriterConfig, depth: usize) {
+    match json {
+        Json::Int(i) => {
+            let _ = write!(out, "{}", i);
+        }
+
+        Json::NegInt(i) => {
+            let _ = write!(out, "{}", i);
+        }
+
+        Json::Float(f) => write_float(out, *f),
+
+        Json::RawNumber(str) => out.push_str(str),
+
+        Json::String(str) => write_str(out, str, config.escape_non_ascii),
+
+        Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+
+        Json::Null => out.push_str("null"),
+
+        Json::Array(elems) => {
+            if elems.is_empty() {
+                out.push_str("[]");
+                return;
+            }
+            out.push('[');
+            for (elem_idx, elem) in elems.iter().enumerate() {
+                if elem_idx != 0 {
+                    out.push(',');
+                }
+                write_newline(out, config, depth + 1);
+                write_value(out, elem, config, depth + 1);
+            }
+            write_newline(out, config, depth);
+            out.push(']');
+        }
+
+        Json::Object(members) => {
+            if members.is_empty() {
+                out.push_str("{}");
+                return;
+            }
+
+            let mut members: Vec<&(String, Json)> = members.iter().collect();
+            if config.sort_keys {
+                members.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+            }
+
+            out.push('{');
+            for (member_idx, (key, value)) in members.into_iter().enumerate() {
+                if member_idx != 0 {
+                    out.push(',');
+                }
+                write_newline(out, config, depth + 1);
+                write_str(out, key, config.escape_non_ascii);
+                out.push(':');
+                if config.indent.is_some() {
+                    out.push(' ');
+                }
+                write_value(out, value, config, depth + 1);
+            }
+            write_newline(out, config, depth);
+            out.push('}');
+        }
+    }
+}
+
+/// When pretty printing, starts a new line indented for `depth`.
+fn write_newline(out: &mut String, config: WriterConfig, depth: usize) {
+    if let Some(indent) = config.indent {
+        out.push('\n');
+        out.extend(std::iter::repeat_n(' ', indent * depth));
+    }
+}
+
+pub(crate) fn write_float(out: &mut String, f: f64) {
+    if f.is_finite() {
+        // `Debug` output always has a '.' or an exponent, so the number is parsed back as a
+        // float, and it has enough digits to parse back to the same value.
+        let _ = write!(out, "{:?}", f);
+    } else {
+        out.push_str("null");
+    }
+}
+
+/// Writes `str` as a JSON string, with the double quotes.
+pub(crate) fn write_str(out: &mut String, str: &str, escape_non_ascii: bool) {
+    out.push('"');
+    for char in str.chars() {
+        match char {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0'..='\u{1f}' => {
+                let _ = write!(out, "\\u{:04x}", char as u32);
+            }
+            _ if escape_non_ascii && !char.is_ascii() => {
+                let mut utf16 = [0u16; 2];
+                for code_unit in char.encode_utf16(&mut utf16) {
+                    let _ = write!(out, "\\u{:04x}", code_unit);
+                }
+            }
+            _ => out.push(char),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+fn s(str: &str) -> String {
+    str.to_string()
+}
+
+#[cfg(test)]
+fn all_configs() -> Vec<WriterConfig> {
+    let mut configs = vec![];
+    for indent in [None, Some(0), Some(2), Some(4)] {
+        for trailing_newline in [false, true] {
+            for sort_keys in [false, true] {
+                for escape_non_ascii in [false, true] {
+                    configs.push(WriterConfig {
+                        indent,
+                        trailing_newline,
+                        sort_keys,
+                        escape_non_ascii,
+                    });
+                }
+            }
+        }
+    }
+    configs
+}
+
+/// `json` with object members sorted, as [WriterConfig::sort_keys] writes them.
+#[cfg(test)]
+fn sort_keys(json: Json) -> Json {
+    match json {
+        Json::Array(elems) => Json::Array(elems.into_iter().map(sort_keys).collect()),
+        Json::Object(members) => {
+            let mut members: Vec<(String, Json)> = members
+                .into_iter()
+                .map(|(key, value)| (key, sort_keys(value)))
+                .collect();
+            members.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+            Json::Object(members)
+        }
+        other => other,
+    }
+}
+
+#[test]
+fn compact_test() {
+    let json = Json::Object(vec![
+        (
+            s("b"),
+            Json::Array(vec![Json::Int(1), Json::NegInt(-2), Json::Float(2.5)]),
+        ),
+        (s("a"), Json::Object(vec![])),
+        (s("c"), Json::Array(vec![])),
+        (s("d"), Json::Null),
+        (s("e"), Json::Bool(false)),
+    ]);
+    assert_eq!(
+        write_json(&json),
+        r#"{"b":[1,-2,2.5],"a":{},"c":[],"d":null,"e":false}"#
+    );
+
+    let config = WriterConfig {
+        sort_keys: true,
+        ..WriterConfig::default()
+    };
+    assert_eq!(
+        write_json_with_config(&json, config),
+        r#"{"a":{},"b":[1,-2,2.5],"c":[],"d":null,"e":false}"#
+    );
+}
+
+#[test]
+fn pretty_test() {
+    let json = crate::parse_ast_recursive(r#"{"b": [1, {"x": true}], "a": {}, "c": []}"#).unwrap();
+    assert_eq!(
+        write_json_with_config(&json, WriterConfig::pretty()),
+        r#"{
+  "b": [
+    1,
+    {
+      "x": true
+    }
+  ],
+  "a": {},
+  "c": []
+}
+"#
+    );
+
+    let config = WriterConfig {
+        indent: Some(4),
+        trailing_newline: false,
+        sort_keys: true,
+        escape_non_ascii: false,
+    };
+    assert_eq!(
+        write_json_with_config(&json, config),
+        r#"{
+    "a": {},
+    "b": [
+        1,
+        {
+            "x": true
+        }
+    ],
+    "c": []
+}"#
+    );
+
+    assert_eq!(
+        write_json_with_config(&Json::Int(1), WriterConfig::pretty()),
+        "1\n"
+    );
+}
+
+#[test]
+fn string_test() {
+    let json = Json::String(s("a\"\\/\u{8}\u{c}\n\r\t\u{0}\u{1f}é😀"));
+    assert_eq!(
+        write_json(&json),
+        "\"a\\\"\\\\/\\b\\f\\n\\r\\t\\u0000\\u001fé😀\""
+    );
+
+    let config = WriterConfig {
+        escape_non_ascii: true,
+        ..WriterConfig::default()
+    };
+    assert_eq!(
+        write_json_with_config(&json, config),
+        r#""a\"\\/\b\f\n\r\t\u0000\u001f\u00e9\ud83d\ude00""#
+    );
+}
+
+#[test]
+fn number_test() {
+    assert_eq!(write_json(&Json::Float(1.0)), "1.0");
+    assert_eq!(write_json(&Json::Float(-0.0)), "-0.0");
+    assert_eq!(write_json(&Json::Float(1e300)), "1e300");
+    assert_eq!(write_json(&Json::Float(f64::NAN)), "null");
+    assert_eq!(write_json(&Json::Float(f64::INFINITY)), "null");
+    assert_eq!(write_json(&Json::Int(u64::MAX)), "18446744073709551615");
+    assert_eq!(write_json(&Json::NegInt(i64::MIN)), "-9223372036854775808");
+
+    let big = "123456789012345678901234567890";
+    assert_eq!(write_json(&Json::RawNumber(s(big))), big);
+}
+
+#[test]
+fn round_trip_tests() {
+    for (str, ast) in crate::test_common::ast_tests() {
+        for config in all_configs() {
+            let output = write_json_with_config(&ast, config);
+            let expected = if config.sort_keys {
+                sort_keys(crate::parse_ast_recursive(&str).unwrap())
+            } else {
+                crate::parse_ast_recursive(&str).unwrap()
+            };
+            assert_eq!(
+                crate::parse_ast_recursive(&output).unwrap(),
+                expected,
+                "{:?} {:?}",
+                str,
+                config
+            );
+        }
+    }
+}
+
+#[test]
+fn round_trip_random_tests() {
+    for seed in 0..20 {
+        for input_size in [10, 100, 1_000, 10_000] {
+            let input = crate::gen_input_with_seed(input_size, seed);
+            let ast = crate::parse_ast_recursive(&input).unwrap();
+
+            // `gen_input` generates compact JSON.
+            assert_eq!(write_json(&ast), input);
+
+            for config in all_configs() {
+                let output = write_json_with_config(&ast, config);
+                let output_ast = crate::parse_ast_recursive(&output).unwrap();
+                if config.sort_keys {
+                    assert_eq!(
+                        output_ast,
+                        sort_keys(crate::parse_ast_recursive(&input).unwrap())
+                    );
+                } else {
+                    assert_eq!(output_ast, ast);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn round_trip_random_ast_tests() {
+    // `gen_input` only generates ASCII strings without escapes and `u64` integers, generate ASTs
+    // directly to test escaping and the other number types.
+    let parser_config = crate::ParserConfig {
+        int_overflow: crate::IntOverflow::Raw,
+        ..crate::ParserConfig::default()
+    };
+
+    for seed in 0..200 {
+        let ast = crate::input_gen::gen_json_with_seed(4, seed);
+        let compact = write_json(&ast);
+        let sorted_ast =
+            sort_keys(crate::parse_ast_recursive_with_config(&compact, parser_config).unwrap());
+
+        for config in all_configs() {
+            let output = write_json_with_config(&ast, config);
+            let output_ast = crate::parse_ast_recursive_with_config(&output, parser_config)
+                .unwrap_or_else(|err| panic!("{:?} {:?} {:?}", output, config, err));
+
+            if config.sort_keys {
+                assert_eq!(output_ast, sorted_ast, "{:?}", output);
+            } else {
+                assert_eq!(output_ast, ast, "{:?}", output);
+            }
+
+            if config.escape_non_ascii {
+                assert!(output.is_ascii(), "{:?}", output);
+            }
+
+            // Writing the parsed output with the default config gives the same compact output.
+            if !config.sort_keys {
+                assert_eq!(write_json(&output_ast), compact);
+            }
+        }
+    }
+}